@@ -91,7 +91,7 @@ fn scan_and_export_icons(apps_dir: &Path, output_dir: &Path) -> Result<(usize, u
         let path = entry.path();
         
         // 只处理 .app 结尾的应用
-        if !path.is_dir() || !path.extension().map_or(false, |ext| ext == "app") {
+        if !path.is_dir() || path.extension().is_none_or(|ext| ext != "app") {
             continue;
         }
         
@@ -127,7 +127,7 @@ fn export_app_icon(app_path: &Path, output_dir: &Path, app_name: &str) -> Result
     
     // 使用 plutil 读取 plist 文件
     let output = Command::new("plutil")
-        .args(&["-extract", "CFBundleIconFile", "raw", "-o", "-"])
+        .args(["-extract", "CFBundleIconFile", "raw", "-o", "-"])
         .arg(&info_plist)
         .output()
         .map_err(|e| format!("执行 plutil 失败: {}", e))?;
@@ -171,12 +171,12 @@ fn export_app_icon(app_path: &Path, output_dir: &Path, app_name: &str) -> Result
     let output_file = output_dir.join(format!("{}.png", safe_name));
     
     let status = Command::new("sips")
-        .args(&[
+        .args([
             "-s", "format", "png",
             "--resampleWidth", "512",  // 导出为 512x512
         ])
         .arg(&icon_file)
-        .args(&["--out"])
+        .args(["--out"])
         .arg(&output_file)
         .output()
         .map_err(|e| format!("执行 sips 失败: {}", e))?;
@@ -228,7 +228,7 @@ fn scan_and_export_icons(apps_dir: &Path, config: &ExportConfig) -> Result<(usiz
         let path = entry.path();
         
         // 只处理 .app 结尾的应用
-        if path.is_dir() && path.extension().map_or(false, |ext| ext == "app") {
+        if path.is_dir() && path.extension().is_some_and(|ext| ext == "app") {
             app_paths.push(path);
         }
     }
@@ -314,7 +314,7 @@ fn export_app_icon(
     
     // 使用 plutil 读取 plist 文件
     let output = Command::new("plutil")
-        .args(&["-extract", "CFBundleIconFile", "raw", "-o", "-"])
+        .args(["-extract", "CFBundleIconFile", "raw", "-o", "-"])
         .arg(&info_plist)
         .output()
         .map_err(|e| format!("执行 plutil 失败: {}", e))?;
@@ -358,12 +358,12 @@ fn export_app_icon(
     
     // 使用 sips 转换图标
     let status = Command::new("sips")
-        .args(&[
+        .args([
             "-s", "format", format,
             "--resampleWidth", &size.to_string(),
         ])
         .arg(&icon_file)
-        .args(&["--out"])
+        .args(["--out"])
         .arg(&output_file)
         .output()
         .map_err(|e| format!("执行 sips 失败: {}", e))?;
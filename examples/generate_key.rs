@@ -38,13 +38,13 @@ fn main() {
     let crypto = CryptoManager::new(&key).expect("创建加密管理器失败");
     let test_message = "Hello, Discord! 你好，Discord！";
     
-    match crypto.encrypt(test_message) {
+    match crypto.encrypt_field(test_message, "state", None) {
         Ok(encrypted) => {
             println!("✅ 加密测试成功");
             println!("   原文: {}", test_message);
             println!("   密文: {}...", &encrypted[..50.min(encrypted.len())]);
             
-            match crypto.decrypt(&encrypted) {
+            match crypto.decrypt_field(&encrypted, "state") {
                 Ok(decrypted) => {
                     if decrypted == test_message {
                         println!("✅ 解密测试成功");
@@ -0,0 +1,104 @@
+//! 模拟Discord IPC服务端
+//!
+//! 在没有Discord客户端的环境下调试主程序：
+//! 启动后将打印的套接字路径写入 `.env` 的 `DISCORD_IPC_PATH`，再运行主程序。
+//! 需要启用 `mock` 特性：`cargo run --example mock_discord --features mock`
+//!
+//! 运行中可在标准输入中输入命令，为下一条命令注入故障：
+//! - `error` - 以错误事件回复
+//! - `close` - 发送CLOSE帧并断开
+//! - `disconnect` - 直接断开
+//! - `ratelimit` - 以限流错误回复
+//! - `delay <毫秒>` - 延迟回复
+//! - `kick` - 立即断开所有连接
+
+use active_window_info_to_lanyard_lib::i18n::{self, Locale, Msg};
+use active_window_info_to_lanyard_lib::tr;
+
+#[cfg(unix)]
+fn main() {
+    use active_window_info_to_lanyard_lib::mock_ipc::{Fault, MockDiscordServer};
+    use std::io::BufRead;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    i18n::set_locale(Locale::from_system());
+
+    println!("{}", Msg::MockBanner.text());
+    println!();
+
+    // 可选参数：套接字所在目录
+    let server = match std::env::args().nth(1) {
+        Some(dir) => MockDiscordServer::start_in(&PathBuf::from(dir)),
+        None => MockDiscordServer::start(),
+    };
+    let server = match server {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::MockStartFailed, e));
+            return;
+        }
+    };
+
+    println!("{}", tr!(Msg::MockSocket, server.path().display()));
+    println!("{}", tr!(Msg::MockEnvHint, server.path().display()));
+    println!("{}", Msg::MockCommands.text());
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+
+    // 后台打印收到的握手和活动
+    {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let (mut handshakes, mut activities) = (0, 0);
+            loop {
+                for handshake in server.handshakes().iter().skip(handshakes) {
+                    println!("{}", tr!(Msg::MockHandshake, handshake));
+                    handshakes += 1;
+                }
+                for activity in server.activities().iter().skip(activities) {
+                    println!("📝 SET_ACTIVITY: {}", activity);
+                    activities += 1;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+    }
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let mut parts = line.split_whitespace();
+        let fault = match parts.next() {
+            Some("error") => Fault::Error { code: 4000, message: "mock error".to_string() },
+            Some("close") => Fault::Close { code: 1000, message: "mock close".to_string() },
+            Some("disconnect") => Fault::Disconnect,
+            Some("ratelimit") => Fault::RateLimited,
+            Some("delay") => match parts.next().and_then(|ms| ms.parse().ok()) {
+                Some(ms) => Fault::Delay(Duration::from_millis(ms)),
+                None => {
+                    println!("{}", Msg::MockDelayUsage.text());
+                    continue;
+                }
+            },
+            Some("kick") => {
+                server.disconnect_all();
+                println!("{}", Msg::MockKicked.text());
+                continue;
+            }
+            Some(other) => {
+                println!("{}", tr!(Msg::MockUnknownCommand, other));
+                continue;
+            }
+            None => continue,
+        };
+        println!("{}", tr!(Msg::MockFaultInjected, format!("{:?}", fault)));
+        server.inject_fault(fault);
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    i18n::set_locale(Locale::from_system());
+    eprintln!("{}", Msg::MockUnsupported.text());
+}
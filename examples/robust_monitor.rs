@@ -2,20 +2,20 @@
 //!
 //! 改进：
 //! - 添加心跳检测和健康检查
-//! - Discord RPC 断线自动重连
+//! - Discord RPC 断线自动重连（由 `DiscordManager` 内置的重连状态机完成）
 //! - 更详细的日志和错误处理
 //! - 防止长时间运行后卡住
 
-use active_window_info_to_lanyard_lib::{Config, DiscordManager, WindowInfo, WindowMonitor};
-use std::time::{Duration, Instant};
+use active_window_info_to_lanyard_lib::{
+    Config, ConnectionEvent, DiscordManager, UpdateResult, WindowInfo, WindowMonitor,
+};
+use std::time::Instant;
 use std::{fs::File, io::Read, thread};
 
 // 更新间隔（秒）
 const UPDATE_INTERVAL: u64 = 5;
 // 心跳间隔（秒）- 每隔这个时间打印一次状态
 const HEARTBEAT_INTERVAL: u64 = 60;
-// Discord 重连间隔（秒）
-const RECONNECT_INTERVAL: u64 = 30;
 
 fn main() {
     println!("╔════════════════════════════════════════════════╗");
@@ -44,24 +44,26 @@ fn main() {
     let mut window_monitor = WindowMonitor::new();
 
     // 主循环状态
-    let mut discord: Option<DiscordManager> = None;
     let mut last_successful_update = Instant::now();
     let mut last_heartbeat = Instant::now();
     let mut update_count = 0u64;
     let mut error_count = 0u64;
     let mut reconnect_count = 0u64;
 
-    // 初始连接
-    match connect_discord(&config) {
-        Ok(manager) => {
-            discord = Some(manager);
-            println!("✅ 已连接到Discord RPC\n");
-        }
+    // 初始连接；连接不上时由DiscordManager按指数退避自动重试
+    let mut discord = match DiscordManager::new(&config) {
+        Ok(manager) => manager,
         Err(e) => {
-            eprintln!("❌ 初始连接失败: {}", e);
-            eprintln!("   将在 {} 秒后重试...\n", RECONNECT_INTERVAL);
+            eprintln!("❌ 创建Discord管理器失败: {}", e);
+            return;
         }
+    };
+    if discord.is_connected() {
+        println!("✅ 已连接到Discord RPC\n");
+    } else {
+        eprintln!("❌ 初始连接失败，将在后台自动重试...\n");
     }
+    let events = discord.subscribe();
 
     println!("👀 开始监控活动窗口...\n");
 
@@ -78,26 +80,14 @@ fn main() {
             last_heartbeat = Instant::now();
         }
 
-        // 检查是否需要重连（超过重连间隔没有成功更新）
-        if discord.is_none() || last_successful_update.elapsed().as_secs() > RECONNECT_INTERVAL * 2 {
-            if discord.is_some() {
-                println!("⚠️  检测到可能的连接问题，尝试重新连接...");
-            }
-            
-            match connect_discord(&config) {
-                Ok(manager) => {
-                    discord = Some(manager);
-                    reconnect_count += 1;
-                    last_successful_update = Instant::now();
-                    println!("✅ Discord 重新连接成功（第 {} 次）\n", reconnect_count);
-                }
-                Err(e) => {
-                    eprintln!("❌ 重连失败: {}", e);
-                    discord = None;
-                    thread::sleep(Duration::from_secs(RECONNECT_INTERVAL));
-                    continue;
-                }
+        // 推进重连状态机，并打印连接状态变化
+        discord.poll();
+        for event in events.try_iter() {
+            if let ConnectionEvent::Connected { .. } = event {
+                reconnect_count += 1;
+                last_successful_update = Instant::now();
             }
+            println!("{}", event);
         }
 
         // 检查窗口变化
@@ -110,25 +100,21 @@ fn main() {
             // 解析窗口信息
             let window_info = WindowInfo::parse(&window_title);
 
-            // 尝试更新Discord状态
-            if let Some(ref mut discord_manager) = discord {
-                match discord_manager.update_activity(&window_info, &window_title) {
-                    Ok(_) => {
-                        update_count += 1;
-                        last_successful_update = Instant::now();
-                        println!("✅ Discord状态已更新（第 {} 次）", update_count);
-                    }
-                    Err(e) => {
-                        error_count += 1;
-                        eprintln!("⚠️  更新Discord失败: {}", e);
-                        eprintln!("   将在下次心跳时尝试重连");
-                        
-                        // 标记需要重连
-                        discord = None;
-                    }
+            // 尝试更新Discord状态；未连接时状态会在重连后自动发布
+            match discord.update_activity(&window_info, &window_title) {
+                UpdateResult::Success => {
+                    update_count += 1;
+                    last_successful_update = Instant::now();
+                    println!("✅ Discord状态已更新（第 {} 次）", update_count);
+                }
+                UpdateResult::Skipped => println!("⏭️  状态未变化，跳过更新"),
+                UpdateResult::Failed(e) if e.is_disconnect() => {
+                    eprintln!("⚠️  Discord未连接，重连后自动发布: {}", e);
+                }
+                UpdateResult::Failed(e) => {
+                    error_count += 1;
+                    eprintln!("⚠️  更新Discord失败: {}", e);
                 }
-            } else {
-                println!("⏸️  Discord未连接，跳过更新");
             }
         }
 
@@ -137,11 +123,6 @@ fn main() {
     }
 }
 
-/// 连接到Discord RPC
-fn connect_discord(config: &Config) -> Result<DiscordManager, String> {
-    DiscordManager::connect(config)
-}
-
 /// 创建配置
 fn create_config(discord_app_id: &str, encryption_key: Option<String>) -> Config {
     if let Some(key) = encryption_key {
@@ -201,10 +182,8 @@ fn read_env_config() -> (String, Option<String>) {
 
             match key {
                 "DISCORD_APP_ID" => app_id = Some(value.to_string()),
-                "ENCRYPTION_KEY" => {
-                    if !value.is_empty() {
-                        encryption_key = Some(value.to_string());
-                    }
+                "ENCRYPTION_KEY" if !value.is_empty() => {
+                    encryption_key = Some(value.to_string());
                 }
                 _ => {}
             }
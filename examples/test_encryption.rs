@@ -114,7 +114,7 @@ fn decrypt_data() {
     io::stdin().read_line(&mut encrypted).unwrap();
     let encrypted = encrypted.trim();
 
-    match crypto.decrypt(encrypted) {
+    match crypto.decrypt_field(encrypted, "state") {
         Ok(plaintext) => {
             println!("\n✅ 解密成功！");
             println!("原文: {}", plaintext);
@@ -132,7 +132,7 @@ fn encrypt_and_decrypt(crypto: &CryptoManager, message: &str) {
     }
 
     println!("\n📤 加密中...");
-    match crypto.encrypt(message) {
+    match crypto.encrypt_field(message, "state", None) {
         Ok(encrypted) => {
             println!("✅ 加密成功！");
             println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -144,7 +144,7 @@ fn encrypt_and_decrypt(crypto: &CryptoManager, message: &str) {
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
             println!("\n📥 解密中...");
-            match crypto.decrypt(&encrypted) {
+            match crypto.decrypt_field(&encrypted, "state") {
                 Ok(decrypted) => {
                     if decrypted == message {
                         println!("✅ 解密成功！原文匹配！");
@@ -65,7 +65,7 @@ fn main() {
             println!("   详情: {}", window_info.details);
 
             // 更新Discord状态
-            match discord.update_activity(&window_info, &window_title) {
+            match discord.update_activity(&window_info, &window_title).into_result() {
                 Ok(_) => {
                     if discord.is_encryption_enabled() {
                         println!("✅ Discord状态已更新（已加密）");
@@ -120,10 +120,8 @@ fn read_env_config() -> (String, Option<String>) {
 
             match key {
                 "DISCORD_APP_ID" => app_id = Some(value.to_string()),
-                "ENCRYPTION_KEY" => {
-                    if !value.is_empty() {
-                        encryption_key = Some(value.to_string());
-                    }
+                "ENCRYPTION_KEY" if !value.is_empty() => {
+                    encryption_key = Some(value.to_string());
                 }
                 _ => {}
             }
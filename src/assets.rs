@@ -0,0 +1,451 @@
+//! 图标资源模块
+//!
+//! 根据应用、类别和语言选择 `large_image` / `small_image` 资源键及其悬停文字。
+//! 资源键需要在Discord开发者后台的 Rich Presence → Art Assets 中上传同名图片。
+//!
+//! 大图标的选择顺序：
+//! 1. 针对规则、应用或类别的 `LARGE_IMAGE@...` 配置
+//! 2. 内置的应用图标表（如 `vscode`、`chrome`），其次是类别图标（如 `coding`）
+//! 3. 全局 `LARGE_IMAGE` 配置，未配置时为 `generic`
+//!
+//! 小图标用作状态叠加：离开时显示 `idle`，调试时显示 `debug`，启用加密时显示 `lock`。
+//! 悬停文字是模板（语法见 `template` 模块）。
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::category::{match_keyword, Category};
+use crate::fit::Field;
+use crate::i18n::{self, Locale, Msg};
+use crate::presence::Presence;
+use crate::rules::{RuleSet, Scope, Scoped, WindowContext};
+use crate::template::{Template, TemplateValues};
+use crate::tr;
+
+/// 未知应用使用的通用图标
+pub const GENERIC_ASSET: &str = "generic";
+
+/// 自定义“调试中”判断规则的名称（`RULE_DEBUGGING=...`）
+pub const DEBUGGING_RULE: &str = "DEBUGGING";
+
+/// 内置的应用图标表（关键字均为小写，按顺序匹配）
+const APP_ASSETS: &[(&str, &str)] = &[
+    ("visual studio code", "vscode"),
+    ("vscode", "vscode"),
+    ("code", "vscode"),
+    ("visual studio", "visualstudio"),
+    ("intellij", "intellij"),
+    ("pycharm", "pycharm"),
+    ("webstorm", "webstorm"),
+    ("goland", "goland"),
+    ("rustrover", "rustrover"),
+    ("clion", "clion"),
+    ("android studio", "androidstudio"),
+    ("xcode", "xcode"),
+    ("sublime", "sublime"),
+    ("zed", "zed"),
+    ("vim", "vim"),
+    ("emacs", "emacs"),
+    ("cursor", "cursor"),
+    ("chrome", "chrome"),
+    ("firefox", "firefox"),
+    ("edge", "edge"),
+    ("safari", "safari"),
+    ("opera", "opera"),
+    ("brave", "brave"),
+    ("arc", "arc"),
+    ("vivaldi", "vivaldi"),
+    ("discord", "discord"),
+    ("slack", "slack"),
+    ("telegram", "telegram"),
+    ("wechat", "wechat"),
+    ("微信", "wechat"),
+    ("qq", "qq"),
+    ("teams", "teams"),
+    ("zoom", "zoom"),
+    ("outlook", "outlook"),
+    ("spotify", "spotify"),
+    ("vlc", "vlc"),
+    ("youtube", "youtube"),
+    ("bilibili", "bilibili"),
+    ("steam", "steam"),
+    ("minecraft", "minecraft"),
+    ("word", "word"),
+    ("excel", "excel"),
+    ("powerpoint", "powerpoint"),
+    ("notion", "notion"),
+    ("obsidian", "obsidian"),
+    ("figma", "figma"),
+    ("photoshop", "photoshop"),
+    ("blender", "blender"),
+    ("iterm", "iterm"),
+    ("powershell", "powershell"),
+    ("windows terminal", "terminal"),
+    ("terminal", "terminal"),
+];
+
+/// 标题中表示正在调试的常见标记
+static DEBUGGING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\((debugging|debug|running)\)|\[debug(ging)?\]|\bdebugging\b").unwrap()
+});
+
+/// 可按语言区分的文字，未指定语言的版本用于所有语言
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalizedText {
+    /// 所有语言通用
+    pub any: Option<String>,
+    /// 英文
+    pub en: Option<String>,
+    /// 中文
+    pub zh: Option<String>,
+}
+
+impl LocalizedText {
+    /// 获取指定语言的文字
+    pub fn get(&self, locale: Locale) -> Option<&str> {
+        let specific = match locale {
+            Locale::En => &self.en,
+            Locale::Zh => &self.zh,
+        };
+        specific.as_deref().or(self.any.as_deref())
+    }
+
+    /// 设置某种语言的文字，`None` 表示通用
+    pub fn set(&mut self, locale: Option<Locale>, text: String) {
+        match locale {
+            None => self.any = Some(text),
+            Some(Locale::En) => self.en = Some(text),
+            Some(Locale::Zh) => self.zh = Some(text),
+        }
+    }
+}
+
+/// 小图标表示的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    /// 离开（没有活动窗口）
+    Idle,
+    /// 标题已加密
+    Encrypted,
+    /// 正在调试
+    Debugging,
+}
+
+impl Overlay {
+    /// 所有状态
+    pub const ALL: [Overlay; 3] = [Overlay::Idle, Overlay::Encrypted, Overlay::Debugging];
+
+    /// 配置名称（`OVERLAY_<名称>`）
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overlay::Idle => "IDLE",
+            Overlay::Encrypted => "ENCRYPTED",
+            Overlay::Debugging => "DEBUGGING",
+        }
+    }
+
+    /// 默认的资源键
+    pub fn default_key(&self) -> &'static str {
+        match self {
+            Overlay::Idle => "idle",
+            Overlay::Encrypted => "lock",
+            Overlay::Debugging => "debug",
+        }
+    }
+
+    /// 悬停文字
+    pub fn message(&self) -> Msg {
+        match self {
+            Overlay::Idle => Msg::OverlayIdle,
+            Overlay::Encrypted => Msg::OverlayEncrypted,
+            Overlay::Debugging => Msg::OverlayDebugging,
+        }
+    }
+}
+
+/// 图标配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetConfig {
+    /// 是否使用内置的应用和类别图标表
+    pub builtin: bool,
+    /// 大图标资源键
+    pub large_image: Scoped<String>,
+    /// 大图标悬停文字
+    pub large_text: Scoped<LocalizedText>,
+    /// 小图标资源键（没有状态叠加时使用）
+    pub small_image: Scoped<String>,
+    /// 小图标悬停文字
+    pub small_text: Scoped<LocalizedText>,
+    /// 各状态的小图标资源键，`None` 表示不显示该状态
+    pub overlays: Vec<(Overlay, Option<String>)>,
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            large_image: Scoped::default(),
+            large_text: Scoped::default(),
+            small_image: Scoped::default(),
+            small_text: Scoped::default(),
+            overlays: Overlay::ALL
+                .iter()
+                .map(|overlay| (*overlay, Some(overlay.default_key().to_string())))
+                .collect(),
+        }
+    }
+}
+
+impl AssetConfig {
+    /// 为当前窗口设置图标和悬停文字
+    ///
+    /// # 参数
+    /// * `presence` - 要修改的活动状态
+    /// * `context` - 当前窗口
+    /// * `rules` - 命名规则
+    /// * `values` - 悬停文字模板中占位符的值
+    /// * `encrypted` - 标题是否会被加密
+    pub fn apply(
+        &self,
+        presence: &mut Presence,
+        context: &WindowContext,
+        rules: &RuleSet,
+        values: &TemplateValues,
+        encrypted: bool,
+    ) {
+        let locale = i18n::locale();
+        presence.large_image = Some(self.large_image_for(context, rules));
+        presence.large_text = Some(self.large_text_for(context, rules, values, locale));
+        presence.small_image = self.small_image.resolve(context, rules).cloned();
+        presence.small_text = localized(&self.small_text, context, rules, values, locale);
+
+        if is_debugging(context, rules) {
+            self.apply_overlay(presence, Overlay::Debugging);
+        } else if encrypted {
+            self.apply_overlay(presence, Overlay::Encrypted);
+        }
+    }
+
+    /// 设置“离开”状态的图标
+    pub fn apply_away(&self, presence: &mut Presence) {
+        presence.large_image = Some(self.fallback_image());
+        self.apply_overlay(presence, Overlay::Idle);
+    }
+
+    /// 用状态图标覆盖小图标；该状态被关闭时不做修改
+    pub fn apply_overlay(&self, presence: &mut Presence, overlay: Overlay) {
+        if let Some(key) = self.overlay_key(overlay) {
+            presence.small_image = Some(key.to_string());
+            presence.small_text = Some(overlay.message().text().to_string());
+        }
+    }
+
+    /// 某个状态的小图标资源键
+    pub fn overlay_key(&self, overlay: Overlay) -> Option<&str> {
+        self.overlays
+            .iter()
+            .find(|(o, _)| *o == overlay)
+            .and_then(|(_, key)| key.as_deref())
+    }
+
+    /// 设置某个状态的小图标资源键
+    pub fn set_overlay(&mut self, overlay: Overlay, key: Option<String>) {
+        match self.overlays.iter_mut().find(|(o, _)| *o == overlay) {
+            Some(entry) => entry.1 = key,
+            None => self.overlays.push((overlay, key)),
+        }
+    }
+
+    /// 当前窗口的大图标资源键
+    pub fn large_image_for(&self, context: &WindowContext, rules: &RuleSet) -> String {
+        // 配置中针对规则、应用、类别的设置优先于内置表
+        let configured = self
+            .large_image
+            .resolve_where(context, rules, |scope, _| *scope != Scope::Global);
+        if let Some(key) = configured {
+            return key.clone();
+        }
+        if self.builtin {
+            if let Some(key) = match_keyword(&context.app_name, APP_ASSETS) {
+                return key.to_string();
+            }
+            if context.category != Category::Other {
+                return context.category.name().to_string();
+            }
+        }
+        self.fallback_image()
+    }
+
+    /// 当前窗口的大图标悬停文字：配置的文字，否则为类别名称（其他类别使用应用名称）
+    pub fn large_text_for(
+        &self,
+        context: &WindowContext,
+        rules: &RuleSet,
+        values: &TemplateValues,
+        locale: Locale,
+    ) -> String {
+        if let Some(text) = localized(&self.large_text, context, rules, values, locale) {
+            return text;
+        }
+        if context.category != Category::Other {
+            context.category.message().text_in(locale).to_string()
+        } else if !context.app_name.trim().is_empty() {
+            context.app_name.clone()
+        } else {
+            Msg::PresenceLargeText.text_in(locale).to_string()
+        }
+    }
+
+    /// 全局大图标，未配置时为通用图标
+    fn fallback_image(&self) -> String {
+        self.large_image.global().cloned().unwrap_or_else(|| GENERIC_ASSET.to_string())
+    }
+}
+
+/// 选出当前语言下生效的文字并渲染模板，渲染结果为空时返回 `None`
+fn localized(
+    scoped: &Scoped<LocalizedText>,
+    context: &WindowContext,
+    rules: &RuleSet,
+    values: &TemplateValues,
+    locale: Locale,
+) -> Option<String> {
+    let text = scoped
+        .resolve_where(context, rules, |_, text| text.get(locale).is_some())
+        .and_then(|text| text.get(locale))?;
+    // 配置加载时已校验过模板
+    let text = Template::parse(text).map_or_else(|_| text.to_string(), |t| t.render(values));
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// 当前窗口是否处于调试状态：定义了 `RULE_DEBUGGING` 时使用该规则，否则使用内置的标题标记
+pub fn is_debugging(context: &WindowContext, rules: &RuleSet) -> bool {
+    if rules.contains(DEBUGGING_RULE) {
+        rules.matches(DEBUGGING_RULE, &context.title)
+    } else {
+        DEBUGGING.is_match(&context.title)
+    }
+}
+
+/// 资源键校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetKeyError {
+    /// 资源键为空
+    Empty,
+    /// 超过Discord的长度限制（字节数）
+    TooLong(usize),
+    /// 包含空白字符
+    Whitespace,
+}
+
+impl fmt::Display for AssetKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr!(Msg::AssetKeyInvalid, Field::LargeImage.max_bytes()))
+    }
+}
+
+impl std::error::Error for AssetKeyError {}
+
+/// 校验资源键：不能为空，不能超过Discord的长度限制
+pub fn parse_asset_key(value: &str) -> Result<String, AssetKeyError> {
+    let key = value.trim();
+    if key.is_empty() {
+        return Err(AssetKeyError::Empty);
+    }
+    if key.len() > Field::LargeImage.max_bytes() {
+        return Err(AssetKeyError::TooLong(key.len()));
+    }
+    if key.contains(char::is_whitespace) {
+        return Err(AssetKeyError::Whitespace);
+    }
+    Ok(key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::WindowInfo;
+
+    fn context(title: &str) -> WindowContext {
+        WindowContext::new(title, &WindowInfo::parse(title))
+    }
+
+    #[test]
+    fn test_large_image_selection() {
+        let rules = RuleSet::default();
+        let mut config = AssetConfig::default();
+        assert_eq!(config.large_image_for(&context("a.rs - Visual Studio Code"), &rules), "vscode");
+        assert_eq!(config.large_image_for(&context("x - Neovide Vim"), &rules), "vim");
+        assert_eq!(config.large_image_for(&context("Doc - Pages"), &rules), "office");
+        assert_eq!(config.large_image_for(&context("Calculator"), &rules), GENERIC_ASSET);
+
+        config.large_image.set(Scope::Global, "windows".to_string());
+        let vscode = context("a.rs - Visual Studio Code");
+        config
+            .large_image
+            .set(Scope::App("Visual Studio Code".to_string()), "code-insiders".to_string());
+        assert_eq!(config.large_image_for(&vscode, &rules), "code-insiders");
+        assert_eq!(config.large_image_for(&context("Calculator"), &rules), "windows");
+
+        config.builtin = false;
+        assert_eq!(config.large_image_for(&context("Doc - Pages"), &rules), "windows");
+    }
+
+    #[test]
+    fn test_localized_text() {
+        let rules = RuleSet::default();
+        let mut config = AssetConfig::default();
+        let coding = context("a.rs - Visual Studio Code");
+        let values = TemplateValues::from_context(&coding, &[], None);
+        let text = |config: &AssetConfig, context: &WindowContext, locale| {
+            config.large_text_for(context, &rules, &values, locale)
+        };
+        assert_eq!(text(&config, &coding, Locale::En), "Coding");
+        assert_eq!(text(&config, &coding, Locale::Zh), "编程");
+        assert_eq!(text(&config, &context("Calculator"), Locale::En), "Calculator");
+
+        config
+            .large_text
+            .entry(Scope::Category(Category::Coding))
+            .set(Some(Locale::Zh), "写代码".to_string());
+        config.large_text.entry(Scope::Global).set(None, "Busy in {language}".to_string());
+        assert_eq!(text(&config, &coding, Locale::Zh), "写代码");
+        // 类别设置没有英文版本时回退到全局设置
+        assert_eq!(text(&config, &coding, Locale::En), "Busy in Rust");
+    }
+
+    #[test]
+    fn test_overlays() {
+        let rules = RuleSet::default();
+        let mut config = AssetConfig::default();
+        let mut presence = Presence::default();
+        let values = TemplateValues::default();
+        let debugging = context("main.rs (Debugging) - Visual Studio");
+        let editing = context("main.rs - Visual Studio");
+
+        config.apply(&mut presence, &debugging, &rules, &values, true);
+        assert_eq!(presence.small_image.as_deref(), Some("debug"));
+
+        config.apply(&mut presence, &editing, &rules, &values, true);
+        assert_eq!(presence.small_image.as_deref(), Some("lock"));
+
+        config.set_overlay(Overlay::Encrypted, None);
+        config.apply(&mut presence, &editing, &rules, &values, true);
+        assert_eq!(presence.small_image, None);
+
+        let mut away = Presence::default();
+        config.apply_away(&mut away);
+        assert_eq!(away.large_image.as_deref(), Some(GENERIC_ASSET));
+        assert_eq!(away.small_image.as_deref(), Some("idle"));
+    }
+
+    #[test]
+    fn test_debugging_rule_override() {
+        let mut rules = RuleSet::default();
+        rules.add(DEBUGGING_RULE, r"▶").unwrap();
+        assert!(is_debugging(&context("▶ app - Code"), &rules));
+        assert!(!is_debugging(&context("app (Debugging) - Code"), &rules));
+    }
+}
@@ -0,0 +1,121 @@
+//! 活动状态生成模块
+//!
+//! 根据窗口信息和配置生成与具体输出无关的活动状态（活动类型、时间戳、模板、按钮和图标），
+//! 生成的状态再交给各个输出发送
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::assets::AssetConfig;
+use crate::buttons::ButtonConfig;
+use crate::config::Config;
+use crate::parser::WindowInfo;
+use crate::presence::{ActivityType, Presence, PresenceSource};
+use crate::rules::{RuleSet, Scoped, WindowContext};
+use crate::template::{TemplateConfig, TemplateValues};
+use crate::timestamps::{ActivityClock, TimestampMode};
+
+/// 活动状态生成器
+#[derive(Debug, Clone)]
+pub struct PresenceBuilder {
+    start_time: u64,
+    encrypted: bool,
+    rules: RuleSet,
+    templates: TemplateConfig,
+    app_ids: Scoped<u64>,
+    timestamps: Scoped<TimestampMode>,
+    activity_types: Scoped<ActivityType>,
+    clock: ActivityClock,
+    buttons: ButtonConfig,
+    assets: AssetConfig,
+    project_dirs: Vec<PathBuf>,
+    /// 最近一次生成的状态所用的应用ID
+    current_app_id: Option<u64>,
+}
+
+impl PresenceBuilder {
+    /// 根据配置创建生成器，以当前时间作为会话开始时间
+    pub fn new(config: &Config) -> Self {
+        let start_time = unix_now();
+        Self {
+            start_time,
+            encrypted: config.is_encryption_enabled(),
+            rules: config.rules.clone(),
+            templates: config.templates.clone(),
+            app_ids: config.app_ids.clone(),
+            timestamps: config.timestamps.clone(),
+            activity_types: config.activity_types.clone(),
+            clock: ActivityClock::new(start_time),
+            buttons: config.buttons.clone(),
+            assets: config.assets.clone(),
+            project_dirs: config.project_dirs.clone(),
+            current_app_id: None,
+        }
+    }
+
+    /// 应用重新加载的配置，保留会话开始时间和窗口计时
+    pub fn reload(&mut self, config: &Config) {
+        *self = Self {
+            start_time: self.start_time,
+            clock: self.clock.clone(),
+            current_app_id: self.current_app_id,
+            ..Self::new(config)
+        };
+    }
+
+    /// 会话开始时间（Unix秒）
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    /// 生成当前窗口的活动状态
+    ///
+    /// 每次调用都会记录当前窗口，用于按窗口、类别或应用计时
+    ///
+    /// # 参数
+    /// * `full_title` - 完整的窗口标题（已脱敏）
+    /// * `window_info` - 窗口信息
+    pub fn presence_for(&mut self, full_title: &str, window_info: &WindowInfo) -> Presence {
+        let mut presence = Presence::new(&window_info.app_name, full_title, self.start_time);
+        let context = WindowContext::new(full_title, window_info);
+        let now = unix_now();
+        self.clock.observe(&context, now);
+        let mode = self.timestamps.resolve(&context, &self.rules).copied().unwrap_or_default();
+        let timestamps = self.clock.timestamps(mode, now);
+        presence.start_timestamp = timestamps.start;
+        presence.end_timestamp = timestamps.end;
+        presence.activity_type = self.activity_types.resolve(&context, &self.rules).copied();
+        presence.application_id = self.app_ids.resolve(&context, &self.rules).copied();
+        presence.source = Some(PresenceSource {
+            app: window_info.app_name.clone(),
+            category: context.category,
+            title: full_title.to_string(),
+        });
+        self.current_app_id = presence.application_id;
+
+        let elapsed = timestamps.start.map(|start| Duration::from_secs(now.saturating_sub(start)));
+        let values = TemplateValues::from_context(&context, &self.project_dirs, elapsed);
+        self.templates.apply(&mut presence, &context, &self.rules, &values);
+        presence.buttons = self.buttons.buttons_for(&context, &self.rules, &values);
+        self.assets.apply(&mut presence, &context, &self.rules, &values, self.encrypted);
+        presence
+    }
+
+    /// 没有活动窗口时显示的“离开”状态（带空闲状态图标），同时停止窗口计时
+    pub fn away_presence(&mut self) -> Presence {
+        self.clock.pause(unix_now());
+        let mut presence = Presence::away(self.start_time);
+        // 离开时留在当前应用，避免仅为显示离开而重新握手
+        presence.application_id = self.current_app_id;
+        self.assets.apply_away(&mut presence);
+        presence
+    }
+}
+
+/// 当前Unix时间（秒）
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
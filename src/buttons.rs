@@ -0,0 +1,331 @@
+//! 活动按钮模块
+//!
+//! 按钮通过 `BUTTON_1` / `BUTTON_2` 配置，格式为 `文字|链接`，可以带作用范围（见 `rules` 模块），
+//! 值为 `none` 表示在该范围内不显示此按钮。
+//!
+//! 按钮文字是一个模板（语法见 `template` 模块），渲染结果为空时不显示该按钮。
+//! 链接中只能使用简单占位符（如 `{repo_url}`、`{domain}`），某个占位符没有值时该按钮不显示，
+//! 普通文本值会进行URL编码。
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::fit::Field;
+use crate::i18n::Msg;
+use crate::presence::Button;
+use crate::rules::{RuleSet, Scoped, WindowContext};
+use crate::template::{Template, TemplateError, TemplateValues, PLACEHOLDERS};
+use crate::tr;
+
+/// 每个活动最多显示的按钮数
+pub const MAX_BUTTONS: usize = 2;
+
+/// 本身就是URL片段、插入链接时不需要编码的占位符
+const URL_PLACEHOLDERS: &[&str] = &["repo_url", "domain"];
+
+/// 看起来像域名、实际上是常见文件扩展名的后缀
+const FILE_EXTENSIONS: &[&str] = &[
+    "rs", "md", "py", "js", "ts", "tsx", "jsx", "txt", "json", "toml", "html", "css", "go", "java",
+    "kt", "c", "h", "cpp", "hpp", "cs", "sh", "yml", "yaml", "lock", "log", "pdf", "png", "jpg",
+    "jpeg", "gif", "svg", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "zip", "gz", "rb", "php",
+    "swift",
+];
+
+static DOMAIN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b((?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+([a-z]{2,63}))\b").unwrap()
+});
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([a-z_]+)\}").unwrap());
+
+/// 按钮配置解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ButtonError {
+    /// 缺少分隔文字和链接的 `|`
+    MissingSeparator,
+    /// 按钮文字为空
+    LabelEmpty,
+    /// 固定文字超过长度限制（字节数上限）
+    LabelTooLong(usize),
+    /// 按钮文字模板无效
+    Label(TemplateError),
+    /// 链接不是 `http(s)://` 开头或包含空白字符
+    BadUrl,
+    /// 链接中使用了未知的占位符
+    UnknownPlaceholder(String),
+}
+
+impl fmt::Display for ButtonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ButtonError::MissingSeparator => tr!(Msg::ButtonMissingSeparator),
+            ButtonError::LabelEmpty => tr!(Msg::ButtonLabelEmpty),
+            ButtonError::LabelTooLong(max) => tr!(Msg::ButtonLabelTooLong, max),
+            ButtonError::Label(e) => e.to_string(),
+            ButtonError::BadUrl => tr!(Msg::ButtonBadUrl),
+            ButtonError::UnknownPlaceholder(name) => tr!(Msg::ButtonUnknownPlaceholder, name),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ButtonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ButtonError::Label(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 按钮模板
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ButtonTemplate {
+    /// 按钮文字模板
+    pub label: Template,
+    /// 链接模板
+    pub url: String,
+}
+
+impl ButtonTemplate {
+    /// 解析 `文字|链接` 格式的按钮配置，`none` 表示不显示按钮
+    ///
+    /// 文字为空、固定文字超过Discord的长度限制、文字模板无效、链接不是 `http(s)://` 开头或
+    /// 使用了未知占位符时返回错误
+    pub fn parse(value: &str) -> Result<Option<Self>, ButtonError> {
+        if value.trim().eq_ignore_ascii_case("none") {
+            return Ok(None);
+        }
+        let (label, url) =
+            split_separator(value).ok_or(ButtonError::MissingSeparator)?;
+        let (label, url) = (label.trim(), url.trim());
+
+        if label.is_empty() {
+            return Err(ButtonError::LabelEmpty);
+        }
+        let template = Template::parse(label).map_err(ButtonError::Label)?;
+        let max_label = Field::ButtonLabel.max_bytes();
+        if !template.is_dynamic() && label.len() > max_label {
+            return Err(ButtonError::LabelTooLong(max_label));
+        }
+        if !(url.starts_with("http://") || url.starts_with("https://") || url.starts_with('{'))
+            || url.contains(char::is_whitespace)
+        {
+            return Err(ButtonError::BadUrl);
+        }
+        for name in PLACEHOLDER.captures_iter(url) {
+            if !PLACEHOLDERS.contains(&&name[1]) {
+                return Err(ButtonError::UnknownPlaceholder(name[0].to_string()));
+            }
+        }
+
+        Ok(Some(Self { label: template, url: url.to_string() }))
+    }
+
+    /// 文字和链接中用到的占位符
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = self.label.placeholders();
+        names.extend(PLACEHOLDER.captures_iter(&self.url).filter_map(|caps| caps.get(1)).map(|m| m.as_str()));
+        names
+    }
+
+    /// 用占位符的值生成按钮；文字为空、链接缺少某个值或生成的链接无效时返回 `None`
+    pub fn render(&self, values: &TemplateValues) -> Option<Button> {
+        let label = self.label.render(values);
+        let url = substitute(&self.url, values)?;
+        (!label.trim().is_empty() && is_valid_url(&url)).then_some(Button { label, url })
+    }
+}
+
+/// 按钮配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ButtonConfig {
+    /// 每个按钮位置的模板（`None` 表示在该范围内不显示）
+    pub slots: [Scoped<Option<ButtonTemplate>>; MAX_BUTTONS],
+}
+
+impl ButtonConfig {
+    /// 是否配置了任何按钮
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Scoped::is_empty)
+    }
+
+    /// 生成当前窗口的按钮
+    pub fn buttons_for(
+        &self,
+        context: &WindowContext,
+        rules: &RuleSet,
+        values: &TemplateValues,
+    ) -> Vec<Button> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.resolve(context, rules)?.as_ref())
+            .filter_map(|template| template.render(values))
+            .collect()
+    }
+}
+
+/// 在花括号之外的第一个 `|` 处拆分，文字模板中的过滤器也使用 `|`
+fn split_separator(value: &str) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => return Some((&value[..i], &value[i + 1..])),
+            _ => {},
+        }
+    }
+    None
+}
+
+/// 替换链接中的占位符，普通文本值会进行URL编码
+fn substitute(template: &str, values: &TemplateValues) -> Option<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut last = 0;
+    for caps in PLACEHOLDER.captures_iter(template) {
+        let whole = caps.get(0)?;
+        let name = &caps[1];
+        let value = values.get(name)?;
+        result.push_str(&template[last..whole.start()]);
+        if !URL_PLACEHOLDERS.contains(&name) {
+            result.push_str(&percent_encode(value));
+        } else {
+            result.push_str(value);
+        }
+        last = whole.end();
+    }
+    result.push_str(&template[last..]);
+    Some(result)
+}
+
+/// 对URL中的文本进行百分号编码（保留非保留字符）
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            },
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// 链接是否可以发送给Discord：`http(s)://`、有主机名、没有空白字符、不超过长度限制
+pub fn is_valid_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    !host.is_empty()
+        && url.len() <= Field::ButtonUrl.max_bytes()
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// 从标题中提取网站域名，跳过看起来像文件名的匹配
+///
+/// # 示例
+/// ```
+/// use active_window_info_to_lanyard_lib::buttons::extract_domain;
+///
+/// assert_eq!(extract_domain("Rust - docs.rs - Firefox").as_deref(), Some("docs.rs"));
+/// assert_eq!(extract_domain("main.rs - Visual Studio Code"), None);
+/// ```
+pub fn extract_domain(title: &str) -> Option<String> {
+    DOMAIN
+        .captures_iter(title)
+        .filter(|caps| {
+            let tld = caps[2].to_ascii_lowercase();
+            !FILE_EXTENSIONS.contains(&tld.as_str()) || is_known_host(&caps[1])
+        })
+        .map(|caps| caps[1].to_ascii_lowercase())
+        .next()
+}
+
+/// 少数使用文件扩展名作为顶级域的常见站点
+fn is_known_host(host: &str) -> bool {
+    ["docs.rs", "crates.io", "lib.rs"].contains(&host.to_ascii_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::parser::WindowInfo;
+    use crate::rules::Scope;
+
+    fn context(title: &str) -> WindowContext {
+        WindowContext::new(title, &WindowInfo::parse(title))
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let template = ButtonTemplate::parse("Open site | https://{domain}").unwrap().unwrap();
+        assert_eq!(template.label.source(), "Open site");
+        assert_eq!(template.url, "https://{domain}");
+        assert_eq!(ButtonTemplate::parse("none").unwrap(), None);
+
+        assert_eq!(ButtonTemplate::parse("no separator"), Err(ButtonError::MissingSeparator));
+        assert_eq!(ButtonTemplate::parse(" |https://a.b"), Err(ButtonError::LabelEmpty));
+        assert_eq!(ButtonTemplate::parse("Label|ftp://a.b"), Err(ButtonError::BadUrl));
+        assert_eq!(
+            ButtonTemplate::parse("Label|https://{unknown}"),
+            Err(ButtonError::UnknownPlaceholder("{unknown}".to_string()))
+        );
+        assert!(matches!(
+            ButtonTemplate::parse("{if app}Open|https://a.b"),
+            Err(ButtonError::Label(TemplateError::Unclosed(_)))
+        ));
+        assert_eq!(
+            ButtonTemplate::parse(&format!("{}|https://a.b", "x".repeat(33))),
+            Err(ButtonError::LabelTooLong(32))
+        );
+    }
+
+    #[test]
+    fn test_render_encodes_text_values() {
+        let template = ButtonTemplate::parse("Search {app}|https://example.com/?q={details}")
+            .unwrap()
+            .unwrap();
+        let values = TemplateValues::from_context(&context("a b&c - Notepad"), &[], None);
+        let button = template.render(&values).unwrap();
+        assert_eq!(button.label, "Search Notepad");
+        assert_eq!(button.url, "https://example.com/?q=a%20b%26c");
+
+        let template = ButtonTemplate::parse("{app|upper}|https://example.com").unwrap().unwrap();
+        assert_eq!(template.render(&values).unwrap().label, "NOTEPAD");
+    }
+
+    #[test]
+    fn test_missing_value_skips_button() {
+        let mut config = ButtonConfig::default();
+        let repo = ButtonTemplate::parse("View repo|{repo_url}").unwrap();
+        let site = ButtonTemplate::parse("Open site|https://{domain}").unwrap();
+        config.slots[0].set(Scope::Category(Category::Coding), repo);
+        config.slots[1].set(Scope::Global, site);
+
+        let rules = RuleSet::default();
+        let buttons_for = |title: &str| {
+            let context = context(title);
+            config.buttons_for(&context, &rules, &TemplateValues::from_context(&context, &[], None))
+        };
+        assert!(buttons_for("main.rs - app - Visual Studio Code").is_empty());
+
+        let buttons = buttons_for("Crates - crates.io - Firefox");
+        assert_eq!(buttons, vec![Button {
+            label: "Open site".to_string(),
+            url: "https://crates.io".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_is_valid_url() {
+        assert!(is_valid_url("https://github.com/a/b"));
+        assert!(!is_valid_url("https://"));
+        assert!(!is_valid_url("javascript:alert(1)"));
+        assert!(!is_valid_url("https://a.b/ c"));
+        assert!(!is_valid_url(&format!("https://a.b/{}", "x".repeat(512))));
+    }
+}
@@ -0,0 +1,217 @@
+//! 应用分类模块
+//!
+//! 根据应用名称将窗口归入编程、浏览、聊天等类别，类别名称通过消息目录本地化
+
+use crate::i18n::Msg;
+
+/// 应用类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Coding,
+    Browsing,
+    Communication,
+    Media,
+    Gaming,
+    Office,
+    Design,
+    Terminal,
+    Other,
+}
+
+/// 应用名称关键字与类别的对应关系（关键字均为小写，按顺序匹配）
+const KEYWORDS: &[(&str, Category)] = &[
+    ("visual studio", Category::Coding),
+    ("vscode", Category::Coding),
+    ("code", Category::Coding),
+    ("intellij", Category::Coding),
+    ("pycharm", Category::Coding),
+    ("webstorm", Category::Coding),
+    ("goland", Category::Coding),
+    ("rustrover", Category::Coding),
+    ("clion", Category::Coding),
+    ("android studio", Category::Coding),
+    ("xcode", Category::Coding),
+    ("sublime", Category::Coding),
+    ("zed", Category::Coding),
+    ("vim", Category::Coding),
+    ("emacs", Category::Coding),
+    ("cursor", Category::Coding),
+    ("chrome", Category::Browsing),
+    ("firefox", Category::Browsing),
+    ("edge", Category::Browsing),
+    ("safari", Category::Browsing),
+    ("opera", Category::Browsing),
+    ("brave", Category::Browsing),
+    ("arc", Category::Browsing),
+    ("vivaldi", Category::Browsing),
+    ("discord", Category::Communication),
+    ("slack", Category::Communication),
+    ("telegram", Category::Communication),
+    ("wechat", Category::Communication),
+    ("微信", Category::Communication),
+    ("qq", Category::Communication),
+    ("teams", Category::Communication),
+    ("zoom", Category::Communication),
+    ("outlook", Category::Communication),
+    ("mail", Category::Communication),
+    ("spotify", Category::Media),
+    ("music", Category::Media),
+    ("vlc", Category::Media),
+    ("mpv", Category::Media),
+    ("iina", Category::Media),
+    ("potplayer", Category::Media),
+    ("youtube", Category::Media),
+    ("netflix", Category::Media),
+    ("bilibili", Category::Media),
+    ("steam", Category::Gaming),
+    ("epic games", Category::Gaming),
+    ("minecraft", Category::Gaming),
+    ("battle.net", Category::Gaming),
+    ("word", Category::Office),
+    ("excel", Category::Office),
+    ("powerpoint", Category::Office),
+    ("onenote", Category::Office),
+    ("notion", Category::Office),
+    ("obsidian", Category::Office),
+    ("pages", Category::Office),
+    ("numbers", Category::Office),
+    ("keynote", Category::Office),
+    ("wps", Category::Office),
+    ("figma", Category::Design),
+    ("photoshop", Category::Design),
+    ("illustrator", Category::Design),
+    ("sketch", Category::Design),
+    ("blender", Category::Design),
+    ("gimp", Category::Design),
+    ("terminal", Category::Terminal),
+    ("iterm", Category::Terminal),
+    ("powershell", Category::Terminal),
+    ("cmd.exe", Category::Terminal),
+    ("命令提示符", Category::Terminal),
+    ("wezterm", Category::Terminal),
+    ("alacritty", Category::Terminal),
+    ("kitty", Category::Terminal),
+    ("warp", Category::Terminal),
+];
+
+/// 在关键字表中查找应用名称对应的值（关键字均为小写，按顺序匹配）
+///
+/// 单个ASCII单词的关键字按整词匹配，避免 "arc" 之类的关键字误匹配 "search"；
+/// 含空格或非ASCII字符的关键字按子串匹配
+pub(crate) fn match_keyword<T: Copy>(app_name: &str, table: &[(&str, T)]) -> Option<T> {
+    let app = app_name.to_lowercase();
+    let words: Vec<&str> = app
+        .split(|c: char| !c.is_alphanumeric() && c != '.')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    table
+        .iter()
+        .find(|(keyword, _)| {
+            if keyword.contains(' ') || !keyword.is_ascii() {
+                app.contains(keyword)
+            } else {
+                words.contains(keyword)
+            }
+        })
+        .map(|(_, value)| *value)
+}
+
+impl Category {
+    /// 所有类别
+    pub const ALL: [Category; 9] = [
+        Category::Coding,
+        Category::Browsing,
+        Category::Communication,
+        Category::Media,
+        Category::Gaming,
+        Category::Office,
+        Category::Design,
+        Category::Terminal,
+        Category::Other,
+    ];
+
+    /// 根据应用名称判断类别
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::category::Category;
+    ///
+    /// assert_eq!(Category::classify("Visual Studio Code"), Category::Coding);
+    /// assert_eq!(Category::classify("Calculator"), Category::Other);
+    /// ```
+    pub fn classify(app_name: &str) -> Self {
+        match_keyword(app_name, KEYWORDS).unwrap_or(Category::Other)
+    }
+
+    /// 类别的配置名称（小写英文，不随语言变化）
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Coding => "coding",
+            Category::Browsing => "browsing",
+            Category::Communication => "communication",
+            Category::Media => "media",
+            Category::Gaming => "gaming",
+            Category::Office => "office",
+            Category::Design => "design",
+            Category::Terminal => "terminal",
+            Category::Other => "other",
+        }
+    }
+
+    /// 从配置名称解析类别
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        Self::ALL.iter().copied().find(|c| c.name() == value)
+    }
+
+    /// 类别在当前语言下的显示名称
+    pub fn label(&self) -> &'static str {
+        self.message().text()
+    }
+
+    /// 类别对应的消息
+    pub fn message(&self) -> Msg {
+        match self {
+            Category::Coding => Msg::CategoryCoding,
+            Category::Browsing => Msg::CategoryBrowsing,
+            Category::Communication => Msg::CategoryCommunication,
+            Category::Media => Msg::CategoryMedia,
+            Category::Gaming => Msg::CategoryGaming,
+            Category::Office => Msg::CategoryOffice,
+            Category::Design => Msg::CategoryDesign,
+            Category::Terminal => Msg::CategoryTerminal,
+            Category::Other => Msg::CategoryOther,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Locale;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Category::classify("Visual Studio Code"), Category::Coding);
+        assert_eq!(Category::classify("Google Chrome"), Category::Browsing);
+        assert_eq!(Category::classify("Mozilla Firefox"), Category::Browsing);
+        assert_eq!(Category::classify("Spotify Premium"), Category::Media);
+        assert_eq!(Category::classify("微信"), Category::Communication);
+        assert_eq!(Category::classify("Research Notes"), Category::Other);
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        for category in Category::ALL {
+            assert_eq!(Category::parse(category.name()), Some(category));
+        }
+        assert_eq!(Category::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_labels_are_localised() {
+        assert_eq!(Category::Coding.message().text_in(Locale::En), "Coding");
+        assert_eq!(Category::Coding.message().text_in(Locale::Zh), "编程");
+    }
+}
@@ -1,16 +1,245 @@
 //! 应用程序配置管理模块
 
+use std::fmt;
+use std::net::{AddrParseError, SocketAddr};
+use std::num::ParseIntError;
+use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::assets::{parse_asset_key, AssetConfig, AssetKeyError, LocalizedText, Overlay};
+use crate::builder::unix_now;
+use crate::buttons::{ButtonConfig, ButtonError, ButtonTemplate, MAX_BUTTONS};
+use crate::discord::{ClientSelector, ClientSelectorError, ReconnectPolicy};
+use crate::fit::{Ellipsis, FitOptions, TruncateSide};
+use crate::i18n::{Locale, Msg};
+use crate::keyring::{KeyEntry, Keyring, KeyringError};
+use crate::lanyard::{KvConfig, KvPrefixError, DEFAULT_API_URL};
+use crate::lanyard_socket::DEFAULT_SOCKET_URL;
+use crate::presence::{ActivityType, ActivityTypeError};
+use crate::redact::{Detector, RedactAction, RedactError, RedactionConfig, UserPattern};
+use crate::rules::{parse_rules, parse_scoped, RuleError, RuleSet, Scoped, ScopedError};
+use crate::scheduler::RateLimit;
+use crate::script::ScriptConfig;
+use crate::signals::DEFAULT_SHUTDOWN_TIMEOUT_MS;
+use crate::template::{Template, TemplateConfig, TemplateError, TITLE_PLACEHOLDERS};
+use crate::timestamps::{TimestampMode, TimestampModeError};
+use crate::tr;
+
+/// 默认更新间隔（秒）
+pub const DEFAULT_UPDATE_INTERVAL_SECS: u64 = 5;
+
+/// 配置错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// 未设置必填项（键名）
+    MissingVar(&'static str),
+    /// Discord应用ID无法解析
+    BadAppId(ParseIntError),
+    /// `UPDATE_INTERVAL` 无法解析
+    BadInterval(ParseIntError),
+    /// Discord应用ID为0
+    AppIdZero,
+    /// 更新间隔小于1秒
+    IntervalTooShort,
+    /// 加密密钥长度不是64个字符
+    KeyLength,
+    /// 加密密钥包含非十六进制字符
+    KeyNotHex,
+    /// `ENCRYPTION_KEY_<ID>` 设置有误
+    Keyring(KeyringError),
+    /// 以毫秒为单位的时长无法解析
+    BadDuration { key: &'static str, source: ParseIntError },
+    /// 数值无法解析
+    BadNumber { key: &'static str, source: ParseIntError },
+    /// 布尔值无法解析
+    BadBool { key: &'static str, value: String },
+    /// 速率限制的更新次数为0
+    RateLimitZero(&'static str),
+    /// `SERVER_ADDR` 不是有效的地址
+    BadAddr(AddrParseError),
+    /// 设置了 `LANYARD_API_KEY` 但没有设置 `LANYARD_USER_ID`
+    KvNeedsUserId,
+    /// 设置了 `LANYARD_KV_TITLE` 但没有启用加密
+    KvTitleNeedsKey,
+    /// 启用加密时，`state` 以外的字段使用了来自窗口标题的占位符（键名和占位符）
+    TitleNotEncrypted { key: String, placeholder: &'static str },
+    /// `TRUNCATE_SIDE` 无法识别
+    UnknownTruncateSide(String),
+    /// `LANGUAGE` 无法识别
+    UnknownLanguage(String),
+    /// `REDACT_DETECTORS` 或 `REDACT_ACTION_<检测器>` 中的检测器无法识别
+    UnknownDetector(String),
+    /// 脱敏方式无法识别
+    UnknownRedactAction(String),
+    /// 自定义脱敏正则无效
+    Redaction(RedactError),
+    /// `DISCORD_CLIENT` 无法识别
+    Client(ClientSelectorError),
+    /// `LANYARD_KV_PREFIX` 无效
+    KvPrefix(KvPrefixError),
+    /// `RULE_<名称>` 的正则无效
+    Rule(RuleError),
+    /// `@` 之后的作用范围无法识别（完整键名）
+    UnknownScope(String),
+    /// 作用范围引用了未定义的规则（完整键名和规则名称）
+    UnknownRule { key: String, rule: String },
+    /// 带作用范围的 `DISCORD_APP_ID@<范围>` 无法解析
+    ScopedAppId { key: String, source: ParseIntError },
+    /// 带作用范围的 `DISCORD_APP_ID@<范围>` 为0（完整键名）
+    ScopedAppIdZero(String),
+    /// 按钮设置无效
+    Button { key: String, source: ButtonError },
+    /// 模板无效
+    Template { key: String, source: TemplateError },
+    /// `TIMESTAMP_MODE` 无效
+    Timestamp { key: String, source: TimestampModeError },
+    /// `ACTIVITY_TYPE` 无效
+    ActivityType { key: String, source: ActivityTypeError },
+    /// 图标资源键无效
+    Asset { key: String, source: AssetKeyError },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ConfigError::MissingVar(key) => tr!(Msg::ConfigMissingVar, key),
+            ConfigError::BadAppId(e) => tr!(Msg::ConfigBadAppId, e),
+            ConfigError::BadInterval(e) => tr!(Msg::ConfigBadInterval, e),
+            ConfigError::AppIdZero => tr!(Msg::ConfigAppIdZero),
+            ConfigError::IntervalTooShort => tr!(Msg::ConfigIntervalTooShort),
+            ConfigError::KeyLength => tr!(Msg::ConfigKeyLength),
+            ConfigError::KeyNotHex => tr!(Msg::ConfigKeyNotHex),
+            ConfigError::Keyring(e) => e.to_string(),
+            ConfigError::BadDuration { key, source } => tr!(Msg::ConfigBadDuration, key, source),
+            ConfigError::BadNumber { key, source } => tr!(Msg::ConfigBadNumber, key, source),
+            ConfigError::BadBool { key, value } => tr!(Msg::ConfigBadBool, key, value),
+            ConfigError::RateLimitZero(key) => tr!(Msg::ConfigRateLimitZero, key),
+            ConfigError::BadAddr(e) => tr!(Msg::ConfigBadValue, "SERVER_ADDR", e),
+            ConfigError::KvNeedsUserId => tr!(Msg::ConfigKvNeedsUserId),
+            ConfigError::KvTitleNeedsKey => tr!(Msg::ConfigKvTitleNeedsKey),
+            ConfigError::TitleNotEncrypted { key, placeholder } => {
+                tr!(Msg::ConfigTitleNotEncrypted, key, format!("{{{}}}", placeholder))
+            },
+            ConfigError::UnknownTruncateSide(value) => tr!(Msg::ConfigUnknownTruncateSide, value),
+            ConfigError::UnknownLanguage(value) => tr!(Msg::ConfigUnknownLanguage, value),
+            ConfigError::UnknownDetector(name) => tr!(Msg::ConfigUnknownDetector, name),
+            ConfigError::UnknownRedactAction(value) => tr!(Msg::ConfigUnknownRedactAction, value),
+            ConfigError::Redaction(e) => e.to_string(),
+            ConfigError::Client(e) => e.to_string(),
+            ConfigError::KvPrefix(e) => e.to_string(),
+            ConfigError::Rule(e) => e.to_string(),
+            ConfigError::UnknownScope(key) => tr!(Msg::ConfigUnknownScope, key),
+            ConfigError::UnknownRule { key, rule } => tr!(Msg::ConfigUnknownRule, key, rule),
+            ConfigError::ScopedAppId { key, source } => {
+                tr!(Msg::ConfigBadValue, key, tr!(Msg::ConfigBadAppId, source))
+            },
+            ConfigError::ScopedAppIdZero(key) => {
+                tr!(Msg::ConfigBadValue, key, Msg::ConfigAppIdZero.text())
+            },
+            ConfigError::Button { key, source } => tr!(Msg::ConfigBadValue, key, source),
+            ConfigError::Template { key, source } => tr!(Msg::ConfigBadValue, key, source),
+            ConfigError::Timestamp { key, source } => tr!(Msg::ConfigBadValue, key, source),
+            ConfigError::ActivityType { key, source } => tr!(Msg::ConfigBadValue, key, source),
+            ConfigError::Asset { key, source } => tr!(Msg::ConfigBadValue, key, source),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::BadAppId(e) | ConfigError::BadInterval(e) => Some(e),
+            ConfigError::BadDuration { source, .. } | ConfigError::BadNumber { source, .. } => {
+                Some(source)
+            },
+            ConfigError::ScopedAppId { source, .. } => Some(source),
+            ConfigError::Keyring(e) => Some(e),
+            ConfigError::BadAddr(e) => Some(e),
+            ConfigError::Redaction(e) => Some(e),
+            ConfigError::Client(e) => Some(e),
+            ConfigError::KvPrefix(e) => Some(e),
+            ConfigError::Rule(e) => Some(e),
+            ConfigError::Button { source, .. } => Some(source),
+            ConfigError::Template { source, .. } => Some(source),
+            ConfigError::Timestamp { source, .. } => Some(source),
+            ConfigError::ActivityType { source, .. } => Some(source),
+            ConfigError::Asset { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyringError> for ConfigError {
+    fn from(error: KeyringError) -> Self {
+        ConfigError::Keyring(error)
+    }
+}
+
+/// 转换带作用范围的设置的解析错误，值本身的错误由 `value` 包装（参数为完整键名和错误）
+fn scoped_error<E>(value: fn(String, E) -> ConfigError) -> impl Fn(ScopedError<E>) -> ConfigError {
+    move |error| match error {
+        ScopedError::UnknownScope(key) => ConfigError::UnknownScope(key),
+        ScopedError::UnknownRule { key, rule } => ConfigError::UnknownRule { key, rule },
+        ScopedError::Value { key, source } => value(key, source),
+    }
+}
+
 /// 应用程序配置
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Discord Application ID
     pub discord_app_id: u64,
+    /// 按类别、应用或规则使用的其他Discord应用ID（`DISCORD_APP_ID@<范围>`）
+    pub app_ids: Scoped<u64>,
     /// 状态更新间隔
     pub update_interval: Duration,
     /// 加密密钥（可选，32字节十六进制字符串）
     pub encryption_key: Option<String>,
+    /// 带ID的加密密钥（`ENCRYPTION_KEY_<ID>`），与 `encryption_key` 一起组成密钥环
+    pub encryption_keys: Keyring,
+    /// 隐私信息脱敏配置
+    pub redaction: RedactionConfig,
+    /// 字段长度适配选项
+    pub fit: FitOptions,
+    /// 界面语言（为空时根据系统环境变量选择）
+    pub locale: Option<Locale>,
+    /// 状态脚本配置（可选）
+    pub script: Option<ScriptConfig>,
+    /// 指定的Discord IPC套接字路径或所在目录（为空时自动查找）
+    pub ipc_path: Option<PathBuf>,
+    /// 同时运行多个Discord客户端时选择哪一个
+    pub discord_client: ClientSelector,
+    /// 断线重连策略
+    pub reconnect: ReconnectPolicy,
+    /// 活动状态更新的限流参数
+    pub rate_limit: RateLimit,
+    /// 退出时等待清除活动状态的时间上限
+    pub shutdown_timeout: Duration,
+    /// Lanyard追踪的Discord用户ID（为空时使用登录Discord客户端的用户）
+    pub lanyard_user_id: Option<String>,
+    /// Lanyard接口地址
+    pub lanyard_api_url: String,
+    /// Lanyard WebSocket地址（`verify` 命令使用）
+    pub lanyard_socket_url: String,
+    /// Lanyard兼容接口的监听地址（设置后启用）
+    pub server_addr: Option<SocketAddr>,
+    /// Lanyard KV输出（设置了API密钥时启用）
+    pub lanyard_kv: Option<KvConfig>,
+    /// 命名规则（`RULE_<名称>`），用于按规则区分的设置
+    pub rules: RuleSet,
+    /// `details` 和 `state` 模板
+    pub templates: TemplateConfig,
+    /// 时间戳模式
+    pub timestamps: Scoped<TimestampMode>,
+    /// 活动类型
+    pub activity_types: Scoped<ActivityType>,
+    /// 活动按钮
+    pub buttons: ButtonConfig,
+    /// 查找git仓库的项目目录
+    pub project_dirs: Vec<PathBuf>,
+    /// 图标资源
+    pub assets: AssetConfig,
 }
 
 impl Config {
@@ -22,8 +251,31 @@ impl Config {
     pub fn new(discord_app_id: u64, update_interval_secs: u64) -> Self {
         Self {
             discord_app_id,
+            app_ids: Scoped::default(),
             update_interval: Duration::from_secs(update_interval_secs),
             encryption_key: None,
+            encryption_keys: Keyring::default(),
+            redaction: RedactionConfig::default(),
+            fit: FitOptions::default(),
+            locale: None,
+            script: None,
+            ipc_path: None,
+            discord_client: ClientSelector::default(),
+            reconnect: ReconnectPolicy::default(),
+            rate_limit: RateLimit::default(),
+            shutdown_timeout: Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT_MS),
+            lanyard_user_id: None,
+            lanyard_api_url: DEFAULT_API_URL.to_string(),
+            lanyard_socket_url: DEFAULT_SOCKET_URL.to_string(),
+            server_addr: None,
+            lanyard_kv: None,
+            rules: RuleSet::default(),
+            templates: TemplateConfig::default(),
+            timestamps: Scoped::default(),
+            activity_types: Scoped::default(),
+            buttons: ButtonConfig::default(),
+            project_dirs: Vec::new(),
+            assets: AssetConfig::default(),
         }
     }
 
@@ -39,9 +291,8 @@ impl Config {
         encryption_key: String,
     ) -> Self {
         Self {
-            discord_app_id,
-            update_interval: Duration::from_secs(update_interval_secs),
             encryption_key: Some(encryption_key),
+            ..Self::new(discord_app_id, update_interval_secs)
         }
     }
 
@@ -53,43 +304,443 @@ impl Config {
     ///
     /// # 错误
     /// 如果Discord应用ID无法解析为u64，返回错误
-    pub fn from_str(discord_app_id: &str, update_interval_secs: u64) -> Result<Self, String> {
-        let app_id = discord_app_id
-            .parse::<u64>()
-            .map_err(|e| format!("无法解析Discord应用ID: {}", e))?;
+    pub fn from_str(discord_app_id: &str, update_interval_secs: u64) -> Result<Self, ConfigError> {
+        let app_id = discord_app_id.parse::<u64>().map_err(ConfigError::BadAppId)?;
 
         Ok(Self::new(app_id, update_interval_secs))
     }
 
+    /// 从.env格式的文本创建配置
+    ///
+    /// 支持的键：
+    /// * `DISCORD_APP_ID` - Discord应用ID（必填），可带作用范围，如 `DISCORD_APP_ID@coding`
+    /// * `ENCRYPTION_KEY` - 加密密钥（可选），在密钥环中的ID为0
+    /// * `ENCRYPTION_KEY_<ID>` - 带ID的加密密钥（`密钥|created=时间|expires=时间`），见 [`crate::keyring`]
+    /// * `UPDATE_INTERVAL` - 更新间隔（秒，可选）
+    /// * `REDACT_DETECTORS` - 启用的脱敏检测器，逗号分隔，`none` 表示全部关闭
+    /// * `REDACT_ACTION` - 默认处理方式（`mask` / `hash` / `remove`）
+    /// * `REDACT_ACTION_<检测器>` - 单个检测器的处理方式，如 `REDACT_ACTION_EMAIL=hash`
+    /// * `REDACT_RULE_<名称>` - 自定义正则，可用 `hash:` 等前缀指定处理方式
+    /// * `REDACT_HASH_SALT` - 哈希处理时使用的盐
+    /// * `TRUNCATE_ELLIPSIS` - 截断时的省略号（`unicode` / `ascii` / `none` / 自定义文本）
+    /// * `TRUNCATE_SIDE` - 截断位置（`end` / `start` / `middle`）
+    /// * `LANGUAGE` - 界面语言（`en` / `zh`），未设置时根据 `LANG` 选择
+    /// * `SCRIPT_PATH` - Rhai状态脚本路径（可选）
+    /// * `SCRIPT_TIMEOUT_MS` - 脚本单次执行时间上限（毫秒）
+    /// * `SCRIPT_MAX_OPERATIONS` - 脚本单次执行操作数上限
+    /// * `DISCORD_IPC_PATH` - 指定Discord IPC套接字路径或所在目录（可选，如连接模拟服务端）
+    /// * `DISCORD_CLIENT` - 选择Discord客户端（`any` / `stable` / `ptb` / `canary` / 用户ID / `all`）
+    /// * `RECONNECT_INITIAL_MS` - 断线后首次重连的等待时间（毫秒）
+    /// * `RECONNECT_MAX_MS` - 重连等待时间上限（毫秒）
+    /// * `RATE_LIMIT_UPDATES` / `RATE_LIMIT_WINDOW_MS` - 每个时间窗口内最多发送的更新次数和窗口长度
+    /// * `SHUTDOWN_TIMEOUT_MS` - 退出时等待清除活动状态的时间上限（毫秒）
+    /// * `LANYARD_USER_ID` - Lanyard追踪的Discord用户ID（可选，与登录账号不一致时警告）
+    /// * `LANYARD_API_URL` - Lanyard接口地址（默认 `https://api.lanyard.rest`）
+    /// * `LANYARD_SOCKET_URL` - Lanyard WebSocket地址（默认 `wss://api.lanyard.rest/socket`）
+    /// * `SERVER_ADDR` - 在该地址提供Lanyard兼容的接口（可选，如 `127.0.0.1:4001`）
+    /// * `LANYARD_API_KEY` - Lanyard API密钥，设置后把状态写入Lanyard KV（需要 `LANYARD_USER_ID`）
+    /// * `LANYARD_KV_PREFIX` - KV键名前缀（默认 `activity_`）
+    /// * `LANYARD_KV_TITLE` - 是否写入加密后的窗口标题（需要 `ENCRYPTION_KEY`）
+    /// * `LANYARD_KV_RATE_LIMIT_UPDATES` / `LANYARD_KV_RATE_LIMIT_WINDOW_MS` - KV写入的频率限制
+    /// * `RULE_<名称>` - 匹配窗口标题的正则，供 `KEY@rule:<名称>` 形式的设置使用
+    /// * `BUTTON_1` / `BUTTON_2` - 活动按钮（`文字|链接`），可带作用范围，如 `BUTTON_1@browsing`
+    /// * `PROJECT_DIRS` - 查找git仓库的项目目录，逗号分隔，支持 `~`
+    /// * `DETAILS_TEMPLATE` / `STATE_TEMPLATE` - `details` / `state` 模板，可带作用范围
+    /// * `TIMESTAMP_MODE` - 时间戳模式（`session` / `window` / `category` / `today` /
+    ///   `countdown:HH:MM` / `none`），可带作用范围
+    /// * `ACTIVITY_TYPE` - 活动类型（`playing` / `listening` / `watching` / `competing`），可带作用范围
+    /// * `LARGE_IMAGE` / `SMALL_IMAGE` - 大/小图标资源键，可带作用范围
+    /// * `LARGE_TEXT` / `SMALL_TEXT` - 图标悬停文字模板，可带作用范围，`_EN` / `_ZH` 后缀指定语言
+    /// * `BUILTIN_ASSETS` - 是否使用内置的应用和类别图标（默认 `true`）
+    /// * `OVERLAY_IDLE` / `OVERLAY_ENCRYPTED` / `OVERLAY_DEBUGGING` - 状态小图标，`none` 表示不显示
+    ///
+    /// # 错误
+    /// 缺少必填项或某个值无法解析时返回错误
+    pub fn from_env_str(contents: &str) -> Result<Self, ConfigError> {
+        let entries = parse_env(contents);
+        let get = |key: &str| {
+            entries
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+
+        let app_id = get("DISCORD_APP_ID").ok_or(ConfigError::MissingVar("DISCORD_APP_ID"))?;
+        let interval = match get("UPDATE_INTERVAL") {
+            Some(value) => value.parse::<u64>().map_err(ConfigError::BadInterval)?,
+            None => DEFAULT_UPDATE_INTERVAL_SECS,
+        };
+
+        let mut config = Self::from_str(app_id, interval)?;
+        config.encryption_key = get("ENCRYPTION_KEY")
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+        config.encryption_keys = Keyring::from_env(&entries)?;
+        config.redaction = parse_redaction(&entries)?;
+        if let Some(value) = get("TRUNCATE_ELLIPSIS") {
+            config.fit.ellipsis = Ellipsis::parse(value);
+        }
+        if let Some(value) = get("TRUNCATE_SIDE") {
+            config.fit.side = TruncateSide::parse(value)
+                .ok_or_else(|| ConfigError::UnknownTruncateSide(value.to_string()))?;
+        }
+        if let Some(path) = get("SCRIPT_PATH").filter(|v| !v.is_empty()) {
+            let mut script = ScriptConfig::new(path);
+            if let Some(value) = get("SCRIPT_TIMEOUT_MS") {
+                script.timeout = parse_millis("SCRIPT_TIMEOUT_MS", value)?;
+            }
+            if let Some(value) = get("SCRIPT_MAX_OPERATIONS") {
+                script.max_operations = parse_number("SCRIPT_MAX_OPERATIONS", value)?;
+            }
+            config.script = Some(script);
+        }
+        config.ipc_path = get("DISCORD_IPC_PATH")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+        if let Some(value) = get("DISCORD_CLIENT").filter(|v| !v.is_empty()) {
+            config.discord_client = ClientSelector::parse(value).map_err(ConfigError::Client)?;
+        }
+        if let Some(value) = get("RECONNECT_INITIAL_MS") {
+            config.reconnect.initial_delay = parse_millis("RECONNECT_INITIAL_MS", value)?;
+        }
+        if let Some(value) = get("RECONNECT_MAX_MS") {
+            config.reconnect.max_delay = parse_millis("RECONNECT_MAX_MS", value)?;
+        }
+        if let Some(value) = get("RATE_LIMIT_UPDATES") {
+            let updates = parse_number("RATE_LIMIT_UPDATES", value)?;
+            if updates == 0 {
+                return Err(ConfigError::RateLimitZero("RATE_LIMIT_UPDATES"));
+            }
+            config.rate_limit.updates = u32::try_from(updates).unwrap_or(u32::MAX);
+        }
+        if let Some(value) = get("RATE_LIMIT_WINDOW_MS") {
+            config.rate_limit.window = parse_millis("RATE_LIMIT_WINDOW_MS", value)?;
+        }
+        if let Some(value) = get("SHUTDOWN_TIMEOUT_MS") {
+            config.shutdown_timeout = parse_millis("SHUTDOWN_TIMEOUT_MS", value)?;
+        }
+        if let Some(value) = get("LANYARD_USER_ID").filter(|v| !v.is_empty()) {
+            parse_number("LANYARD_USER_ID", value)?;
+            config.lanyard_user_id = Some(value.to_string());
+        }
+        if let Some(value) = get("LANYARD_API_URL").filter(|v| !v.is_empty()) {
+            config.lanyard_api_url = value.to_string();
+        }
+        if let Some(value) = get("LANYARD_SOCKET_URL").filter(|v| !v.is_empty()) {
+            config.lanyard_socket_url = value.to_string();
+        }
+        if let Some(value) = get("SERVER_ADDR").filter(|v| !v.is_empty()) {
+            let addr = value.parse().map_err(ConfigError::BadAddr)?;
+            config.server_addr = Some(addr);
+        }
+        if let Some(api_key) = get("LANYARD_API_KEY").filter(|v| !v.is_empty()) {
+            let mut kv = KvConfig::new(api_key);
+            if let Some(value) = get("LANYARD_KV_PREFIX") {
+                kv.prefix = KvConfig::parse_prefix(value).map_err(ConfigError::KvPrefix)?;
+            }
+            if let Some(value) = get("LANYARD_KV_TITLE") {
+                kv.include_title = parse_bool("LANYARD_KV_TITLE", value)?;
+            }
+            if let Some(value) = get("LANYARD_KV_RATE_LIMIT_UPDATES") {
+                let updates = parse_number("LANYARD_KV_RATE_LIMIT_UPDATES", value)?;
+                if updates == 0 {
+                    return Err(ConfigError::RateLimitZero("LANYARD_KV_RATE_LIMIT_UPDATES"));
+                }
+                kv.rate_limit.updates = u32::try_from(updates).unwrap_or(u32::MAX);
+            }
+            if let Some(value) = get("LANYARD_KV_RATE_LIMIT_WINDOW_MS") {
+                kv.rate_limit.window = parse_millis("LANYARD_KV_RATE_LIMIT_WINDOW_MS", value)?;
+            }
+            config.lanyard_kv = Some(kv);
+        }
+        config.rules = parse_rules(&entries).map_err(ConfigError::Rule)?;
+        if let Some(value) = get("PROJECT_DIRS") {
+            config.project_dirs = parse_dirs(value);
+        }
+        for n in 0..MAX_BUTTONS {
+            let key = format!("BUTTON_{}", n + 1);
+            config.buttons.slots[n] =
+                parse_scoped(&entries, &key, &config.rules, ButtonTemplate::parse)
+                    .map_err(scoped_error(|key, source| ConfigError::Button { key, source }))?;
+        }
+        config.templates.details =
+            parse_scoped(&entries, "DETAILS_TEMPLATE", &config.rules, Template::parse)
+                .map_err(scoped_error(|key, source| ConfigError::Template { key, source }))?;
+        config.templates.state =
+            parse_scoped(&entries, "STATE_TEMPLATE", &config.rules, Template::parse)
+                .map_err(scoped_error(|key, source| ConfigError::Template { key, source }))?;
+        config.timestamps =
+            parse_scoped(&entries, "TIMESTAMP_MODE", &config.rules, TimestampMode::parse)
+                .map_err(scoped_error(|key, source| ConfigError::Timestamp { key, source }))?;
+        config.app_ids = parse_scoped(&entries, "DISCORD_APP_ID", &config.rules, str::parse::<u64>)
+            .map_err(scoped_error(|key, source| ConfigError::ScopedAppId { key, source }))?;
+        config.activity_types =
+            parse_scoped(&entries, "ACTIVITY_TYPE", &config.rules, ActivityType::parse)
+                .map_err(scoped_error(|key, source| ConfigError::ActivityType { key, source }))?;
+        config.assets = parse_assets(&entries, &config.rules)?;
+        if let Some(value) = get("LANGUAGE") {
+            config.locale = Some(
+                Locale::parse(value)
+                    .ok_or_else(|| ConfigError::UnknownLanguage(value.to_string()))?,
+            );
+        }
+
+        Ok(config)
+    }
+
     /// 验证配置是否有效
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), ConfigError> {
         if self.discord_app_id == 0 {
-            return Err("Discord应用ID不能为0".to_string());
+            return Err(ConfigError::AppIdZero);
+        }
+        if let Some((scope, _)) = self.app_ids.entries().find(|(_, id)| *id == 0) {
+            return Err(ConfigError::ScopedAppIdZero(scope.key("DISCORD_APP_ID")));
         }
 
         if self.update_interval.as_secs() < 1 {
-            return Err("更新间隔不能小于1秒".to_string());
+            return Err(ConfigError::IntervalTooShort);
         }
 
-        // 验证加密密钥格式（如果提供）
-        if let Some(ref key) = self.encryption_key {
-            if key.len() != 64 {
-                return Err("加密密钥必须是64个十六进制字符（32字节）".to_string());
+        if let Some(ref kv) = self.lanyard_kv {
+            if self.lanyard_user_id.is_none() {
+                return Err(ConfigError::KvNeedsUserId);
             }
-            if !key.chars().all(|c| c.is_ascii_hexdigit()) {
-                return Err("加密密钥必须只包含十六进制字符（0-9, a-f, A-F）".to_string());
+            if kv.include_title && !self.is_encryption_enabled() {
+                return Err(ConfigError::KvTitleNeedsKey);
             }
         }
 
+        // 验证加密密钥格式（如果提供）
+        if self.encryption_key.is_some() && self.encryption_keys.get(0).is_some() {
+            return Err(ConfigError::Keyring(KeyringError::DuplicateId(0)));
+        }
+        let keyring = self.keyring();
+        for entry in keyring.keys() {
+            check_hex_key(&entry.key)?;
+        }
+        if !keyring.is_empty() && keyring.active(unix_now()).is_none() {
+            return Err(ConfigError::Keyring(KeyringError::NoActiveKey));
+        }
+        if self.is_encryption_enabled() {
+            self.check_title_placeholders()?;
+        }
+
+        Ok(())
+    }
+
+    /// 只有 `state` 会被加密，其他模板不能使用来自窗口标题的占位符
+    fn check_title_placeholders(&self) -> Result<(), ConfigError> {
+        let check = |key: &str, names: Vec<&str>| {
+            match TITLE_PLACEHOLDERS.iter().find(|title| names.contains(title)) {
+                Some(placeholder) => Err(ConfigError::TitleNotEncrypted {
+                    key: key.to_string(),
+                    placeholder,
+                }),
+                None => Ok(()),
+            }
+        };
+
+        for (n, slot) in self.buttons.slots.iter().enumerate() {
+            for (_, button) in slot.entries() {
+                if let Some(button) = button {
+                    check(&format!("BUTTON_{}", n + 1), button.placeholders())?;
+                }
+            }
+        }
+        for (_, template) in self.templates.details.entries() {
+            check("DETAILS_TEMPLATE", template.placeholders())?;
+        }
+        for (key, texts) in
+            [("LARGE_TEXT", &self.assets.large_text), ("SMALL_TEXT", &self.assets.small_text)]
+        {
+            for (_, text) in texts.entries() {
+                for source in [&text.any, &text.en, &text.zh].into_iter().flatten() {
+                    // 解析时已经检查过，这里不会失败
+                    if let Ok(template) = Template::parse(source) {
+                        check(key, template.placeholders())?;
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
     /// 检查是否启用了加密
     pub fn is_encryption_enabled(&self) -> bool {
-        self.encryption_key.is_some()
+        self.encryption_key.is_some() || !self.encryption_keys.is_empty()
+    }
+
+    /// 所有加密密钥组成的密钥环，`ENCRYPTION_KEY` 的ID为0
+    pub fn keyring(&self) -> Keyring {
+        let mut keyring = self.encryption_keys.clone();
+        if let Some(ref key) = self.encryption_key {
+            keyring.insert(KeyEntry::new(0, key.as_str()));
+        }
+        keyring
+    }
+}
+
+/// 检查密钥是否为64个ASCII十六进制字符（32字节）
+pub fn check_hex_key(key: &str) -> Result<(), ConfigError> {
+    if key.len() != 64 {
+        return Err(ConfigError::KeyLength);
+    }
+    if !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ConfigError::KeyNotHex);
+    }
+    Ok(())
+}
+
+/// 解析.env格式文本为有序的键值对列表
+///
+/// 跳过空行和 `#` 开头的注释，键和值两侧的空白会被去除
+pub fn parse_env(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// 解析逗号分隔的目录列表，`~` 展开为用户主目录
+fn parse_dirs(value: &str) -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| match (dir.strip_prefix('~'), &home) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])),
+            _ => PathBuf::from(dir),
+        })
+        .collect()
+}
+
+/// 解析图标配置
+fn parse_assets(entries: &[(String, String)], rules: &RuleSet) -> Result<AssetConfig, ConfigError> {
+    let mut assets = AssetConfig::default();
+    let get = |key: &str| entries.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    if let Some(value) = get("BUILTIN_ASSETS") {
+        assets.builtin = parse_bool("BUILTIN_ASSETS", value)?;
+    }
+    let asset_error = scoped_error(|key, source| ConfigError::Asset { key, source });
+    assets.large_image =
+        parse_scoped(entries, "LARGE_IMAGE", rules, parse_asset_key).map_err(&asset_error)?;
+    assets.small_image =
+        parse_scoped(entries, "SMALL_IMAGE", rules, parse_asset_key).map_err(&asset_error)?;
+    assets.large_text = parse_localized(entries, "LARGE_TEXT", rules)?;
+    assets.small_text = parse_localized(entries, "SMALL_TEXT", rules)?;
+    for overlay in Overlay::ALL {
+        let key = format!("OVERLAY_{}", overlay.name());
+        if let Some(value) = get(&key) {
+            let asset = if value.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                Some(parse_asset_key(value).map_err(|source| ConfigError::Asset { key, source })?)
+            };
+            assets.set_overlay(overlay, asset);
+        }
+    }
+    Ok(assets)
+}
+
+/// 合并 `base`、`base_EN`、`base_ZH` 三组带作用范围的文字
+fn parse_localized(
+    entries: &[(String, String)],
+    base: &str,
+    rules: &RuleSet,
+) -> Result<Scoped<LocalizedText>, ConfigError> {
+    let mut merged: Scoped<LocalizedText> = Scoped::default();
+    for (suffix, locale) in [("", None), ("_EN", Some(Locale::En)), ("_ZH", Some(Locale::Zh))] {
+        let key = format!("{}{}", base, suffix);
+        let texts = parse_scoped(entries, &key, rules, |v| {
+            Template::parse(v).map(|_| v.to_string())
+        })
+        .map_err(scoped_error(|key, source| ConfigError::Template { key, source }))?;
+        for (scope, text) in texts.entries() {
+            merged.entry(scope.clone()).set(locale, text.clone());
+        }
+    }
+    Ok(merged)
+}
+
+/// 解析布尔配置项（`true` / `false` / `1` / `0` / `yes` / `no` / `on` / `off`）
+fn parse_bool(key: &'static str, value: &str) -> Result<bool, ConfigError> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(ConfigError::BadBool { key, value: value.to_string() }),
     }
 }
 
+/// 解析数值配置项
+fn parse_number(key: &'static str, value: &str) -> Result<u64, ConfigError> {
+    value.parse::<u64>().map_err(|source| ConfigError::BadNumber { key, source })
+}
+
+/// 解析以毫秒为单位的时长配置项
+fn parse_millis(key: &'static str, value: &str) -> Result<Duration, ConfigError> {
+    value
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|source| ConfigError::BadDuration { key, source })
+}
+
+/// 从键值对中解析脱敏配置
+fn parse_redaction(entries: &[(String, String)]) -> Result<RedactionConfig, ConfigError> {
+    let mut config = RedactionConfig::default();
+
+    for (key, value) in entries {
+        if key == "REDACT_DETECTORS" {
+            config.detectors.clear();
+            if value.eq_ignore_ascii_case("none") {
+                continue;
+            }
+            for name in value.split(',').filter(|n| !n.trim().is_empty()) {
+                let detector =
+                    Detector::parse(name)
+                    .ok_or_else(|| ConfigError::UnknownDetector(name.to_string()))?;
+                config.detectors.push((detector, None));
+            }
+        } else if key == "REDACT_ACTION" {
+            config.default_action = RedactAction::parse(value)
+                .ok_or_else(|| ConfigError::UnknownRedactAction(value.to_string()))?;
+        } else if key == "REDACT_HASH_SALT" {
+            config.hash_salt = value.clone();
+        } else if let Some(name) = key.strip_prefix("REDACT_RULE_") {
+            let (action, pattern) = match value.split_once(':') {
+                Some((prefix, rest)) if RedactAction::parse(prefix).is_some() => {
+                    (RedactAction::parse(prefix), rest.to_string())
+                },
+                _ => (None, value.clone()),
+            };
+            config.user_patterns.push(UserPattern {
+                name: name.to_string(),
+                pattern,
+                action,
+            });
+        }
+    }
+
+    // 单个检测器的处理方式在检测器列表确定后再应用
+    for (key, value) in entries {
+        if let Some(name) = key.strip_prefix("REDACT_ACTION_") {
+            let detector =
+                Detector::parse(name)
+                    .ok_or_else(|| ConfigError::UnknownDetector(name.to_string()))?;
+            let action = RedactAction::parse(value)
+                .ok_or_else(|| ConfigError::UnknownRedactAction(value.to_string()))?;
+            config.set_detector_action(detector, action);
+        }
+    }
+
+    Ok(config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +764,350 @@ mod tests {
         assert!(valid_config.validate().is_ok());
 
         let invalid_config = Config::new(0, 5);
-        assert!(invalid_config.validate().is_err());
+        assert_eq!(invalid_config.validate(), Err(ConfigError::AppIdZero));
+        let short_key = Config::new_with_encryption(1, 5, "abc".to_string());
+        assert_eq!(short_key.validate(), Err(ConfigError::KeyLength));
+    }
+
+    #[test]
+    fn test_config_from_env_str() {
+        let env = "# 注释\nDISCORD_APP_ID=123456789\nENCRYPTION_KEY=\nUPDATE_INTERVAL=3\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.discord_app_id, 123456789);
+        assert_eq!(config.update_interval, Duration::from_secs(3));
+        assert!(!config.is_encryption_enabled());
+        assert_eq!(config.redaction, RedactionConfig::default());
+
+        assert!(matches!(
+            Config::from_env_str("UPDATE_INTERVAL=3"),
+            Err(ConfigError::MissingVar("DISCORD_APP_ID"))
+        ));
+    }
+
+    #[test]
+    fn test_keyring_from_env_str() {
+        let key = "ab".repeat(32);
+        let env = format!(
+            "DISCORD_APP_ID=1\nENCRYPTION_KEY={}\nENCRYPTION_KEY_2={}|created=2020-01-01",
+            key, key
+        );
+        let config = Config::from_env_str(&env).unwrap();
+        assert!(config.is_encryption_enabled());
+        assert!(config.validate().is_ok());
+        let keyring = config.keyring();
+        assert_eq!(keyring.keys().len(), 2);
+        assert_eq!(keyring.active(crate::builder::unix_now()).unwrap().id, 2);
+
+        let only_ids = Config::from_env_str(&format!("DISCORD_APP_ID=1\nENCRYPTION_KEY_1={}", key)).unwrap();
+        assert!(only_ids.is_encryption_enabled());
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nENCRYPTION_KEY_one=abc"),
+            Err(ConfigError::Keyring(KeyringError::BadId(_)))
+        ));
+
+        let duplicate = format!("DISCORD_APP_ID=1\nENCRYPTION_KEY={}\nENCRYPTION_KEY_0={}", key, key);
+        assert_eq!(
+            Config::from_env_str(&duplicate).unwrap().validate(),
+            Err(ConfigError::Keyring(KeyringError::DuplicateId(0)))
+        );
+        let expired = format!("DISCORD_APP_ID=1\nENCRYPTION_KEY_1={}|expires=2020-01-01", key);
+        assert_eq!(
+            Config::from_env_str(&expired).unwrap().validate(),
+            Err(ConfigError::Keyring(KeyringError::NoActiveKey))
+        );
+        let bad = "DISCORD_APP_ID=1\nENCRYPTION_KEY_1=xyz|created=2020-01-01";
+        assert_eq!(Config::from_env_str(bad).unwrap().validate(), Err(ConfigError::KeyLength));
+    }
+
+    #[test]
+    fn test_templates_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nDETAILS_TEMPLATE@coding={if project}{project}{else}{app}{end}\n\
+                   STATE_TEMPLATE={details|truncate:40}\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.templates.details.entries().count(), 1);
+        assert_eq!(
+            config.templates.state.global().map(Template::source),
+            Some("{details|truncate:40}")
+        );
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nSTATE_TEMPLATE={if app}x"),
+            Err(ConfigError::Template { source: TemplateError::Unclosed(_), .. })
+        ));
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nLARGE_TEXT={nothing}"),
+            Err(ConfigError::Template { source: TemplateError::UnknownPlaceholder(_), .. })
+        ));
+
+        // 错误保留完整键名和原始错误
+        let error = Config::from_env_str("DISCORD_APP_ID=1\nSTATE_TEMPLATE@coding={app|shout}")
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ConfigError::Template {
+                key: "STATE_TEMPLATE@coding".to_string(),
+                source: TemplateError::UnknownFilter("shout".to_string()),
+            }
+        );
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), TemplateError::UnknownFilter("shout".to_string()).to_string());
+    }
+
+    #[test]
+    fn test_timestamps_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nTIMESTAMP_MODE=window\nTIMESTAMP_MODE@media=none\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.timestamps.global(), Some(&TimestampMode::Window));
+        assert_eq!(config.timestamps.entries().count(), 2);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nTIMESTAMP_MODE=forever"),
+            Err(ConfigError::Timestamp { source: TimestampModeError::UnknownMode(_), .. })
+        ));
+    }
+
+    #[test]
+    fn test_app_ids_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nDISCORD_APP_ID@coding=2\nDISCORD_APP_ID@app:Steam=3\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.discord_app_id, 1);
+        assert_eq!(config.app_ids.entries().count(), 3);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nDISCORD_APP_ID@gaming=x"),
+            Err(ConfigError::ScopedAppId { key, .. }) if key == "DISCORD_APP_ID@gaming"
+        ));
+    }
+
+    #[test]
+    fn test_scoped_app_id_zero() {
+        for (env, key) in [
+            ("DISCORD_APP_ID=1\nDISCORD_APP_ID@gaming=0", "DISCORD_APP_ID@gaming"),
+            ("DISCORD_APP_ID=1\nDISCORD_APP_ID@app:Steam=0", "DISCORD_APP_ID@app:Steam"),
+            ("DISCORD_APP_ID=1\nRULE_YT=YouTube\nDISCORD_APP_ID@rule:yt=0", "DISCORD_APP_ID@rule:YT"),
+        ] {
+            let config = Config::from_env_str(env).unwrap();
+            assert_eq!(config.validate(), Err(ConfigError::ScopedAppIdZero(key.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_activity_types_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nRULE_YOUTUBE=YouTube\nACTIVITY_TYPE@media=listening\n\
+                   ACTIVITY_TYPE@rule:YOUTUBE=watching\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.activity_types.entries().count(), 2);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nACTIVITY_TYPE=streaming"),
+            Err(ConfigError::ActivityType { source: ActivityTypeError::Unsupported(_), .. })
+        ));
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nACTIVITY_TYPE=dancing"),
+            Err(ConfigError::ActivityType { source: ActivityTypeError::Unknown(_), .. })
+        ));
+    }
+
+    #[test]
+    fn test_assets_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nBUILTIN_ASSETS=off\nLARGE_IMAGE=windows\n\
+                   LARGE_TEXT@coding=Coding\nLARGE_TEXT_ZH@coding=写代码\nOVERLAY_IDLE=none\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert!(!config.assets.builtin);
+        assert_eq!(config.assets.large_image.global().map(String::as_str), Some("windows"));
+        let (_, text) = config.assets.large_text.entries().next().unwrap();
+        assert_eq!(text.get(Locale::En), Some("Coding"));
+        assert_eq!(text.get(Locale::Zh), Some("写代码"));
+        assert_eq!(config.assets.overlay_key(Overlay::Idle), None);
+        assert_eq!(config.assets.overlay_key(Overlay::Encrypted), Some("lock"));
+
+        assert_eq!(
+            Config::from_env_str("DISCORD_APP_ID=1\nBUILTIN_ASSETS=maybe").err(),
+            Some(ConfigError::BadBool { key: "BUILTIN_ASSETS", value: "maybe".to_string() })
+        );
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nLARGE_IMAGE=two words"),
+            Err(ConfigError::Asset { source: AssetKeyError::Whitespace, .. })
+        ));
+    }
+
+    #[test]
+    fn test_fit_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nTRUNCATE_ELLIPSIS=ascii\nTRUNCATE_SIDE=middle\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.fit.ellipsis, Ellipsis::Ascii);
+        assert_eq!(config.fit.side, TruncateSide::Middle);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nTRUNCATE_SIDE=left"),
+            Err(ConfigError::UnknownTruncateSide(_))
+        ));
+    }
+
+    #[test]
+    fn test_script_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nSCRIPT_PATH=presence.rhai\nSCRIPT_TIMEOUT_MS=10\n";
+        let script = Config::from_env_str(env).unwrap().script.unwrap();
+        assert_eq!(script.path, std::path::PathBuf::from("presence.rhai"));
+        assert_eq!(script.timeout, Duration::from_millis(10));
+
+        assert!(Config::from_env_str("DISCORD_APP_ID=1\nSCRIPT_TIMEOUT_MS=10").unwrap().script.is_none());
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nSCRIPT_PATH=a\nSCRIPT_TIMEOUT_MS=x"),
+            Err(ConfigError::BadDuration { key: "SCRIPT_TIMEOUT_MS", .. })
+        ));
+    }
+
+    #[test]
+    fn test_ipc_path_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1\nDISCORD_IPC_PATH=/tmp/x/discord-ipc-0").unwrap();
+        assert_eq!(config.ipc_path, Some(PathBuf::from("/tmp/x/discord-ipc-0")));
+        assert!(Config::from_env_str("DISCORD_APP_ID=1\nDISCORD_IPC_PATH=").unwrap().ipc_path.is_none());
+    }
+
+    #[test]
+    fn test_reconnect_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nRECONNECT_INITIAL_MS=250\nRECONNECT_MAX_MS=10000";
+        let reconnect = Config::from_env_str(env).unwrap().reconnect;
+        assert_eq!(reconnect.initial_delay, Duration::from_millis(250));
+        assert_eq!(reconnect.max_delay, Duration::from_secs(10));
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nRECONNECT_MAX_MS=soon"),
+            Err(ConfigError::BadDuration { key: "RECONNECT_MAX_MS", .. })
+        ));
+    }
+
+    #[test]
+    fn test_discord_client_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1\nDISCORD_CLIENT=all").unwrap();
+        assert_eq!(config.discord_client, ClientSelector::All);
+        assert_eq!(Config::new(1, 5).discord_client, ClientSelector::Any);
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nDISCORD_CLIENT=beta"),
+            Err(ConfigError::Client(ClientSelectorError::Unknown(_)))
+        ));
+    }
+
+    #[test]
+    fn test_rate_limit_from_env_str() {
+        let env = "DISCORD_APP_ID=1\nRATE_LIMIT_UPDATES=3\nRATE_LIMIT_WINDOW_MS=15000";
+        let rate_limit = Config::from_env_str(env).unwrap().rate_limit;
+        assert_eq!(rate_limit.updates, 3);
+        assert_eq!(rate_limit.window, Duration::from_secs(15));
+        assert_eq!(Config::new(1, 5).rate_limit, RateLimit::default());
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nRATE_LIMIT_UPDATES=0"),
+            Err(ConfigError::RateLimitZero("RATE_LIMIT_UPDATES"))
+        ));
+    }
+
+    #[test]
+    fn test_shutdown_timeout_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1\nSHUTDOWN_TIMEOUT_MS=500").unwrap();
+        assert_eq!(config.shutdown_timeout, Duration::from_millis(500));
+        assert_eq!(Config::new(1, 5).shutdown_timeout, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_lanyard_user_id_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1\nLANYARD_USER_ID=94490510688792576").unwrap();
+        assert_eq!(config.lanyard_user_id.as_deref(), Some("94490510688792576"));
+        assert!(Config::from_env_str("DISCORD_APP_ID=1\nLANYARD_USER_ID=").unwrap().lanyard_user_id.is_none());
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nLANYARD_USER_ID=@me"),
+            Err(ConfigError::BadNumber { key: "LANYARD_USER_ID", .. })
+        ));
+    }
+
+    #[test]
+    fn test_lanyard_kv_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1").unwrap();
+        assert!(config.lanyard_kv.is_none());
+        assert_eq!(config.lanyard_api_url, DEFAULT_API_URL);
+        assert_eq!(config.lanyard_socket_url, DEFAULT_SOCKET_URL);
+
+        let env = "DISCORD_APP_ID=1\nLANYARD_USER_ID=2\nLANYARD_API_KEY=key\n\
+                   LANYARD_API_URL=http://127.0.0.1:8080\nLANYARD_KV_PREFIX=me_\n\
+                   LANYARD_SOCKET_URL=ws://127.0.0.1:8080/socket";
+        let config = Config::from_env_str(env).unwrap();
+        let kv = config.lanyard_kv.clone().unwrap();
+        assert_eq!((kv.api_key.as_str(), kv.prefix.as_str()), ("key", "me_"));
+        assert!(!kv.include_title);
+        assert_eq!(config.lanyard_api_url, "http://127.0.0.1:8080");
+        assert_eq!(config.lanyard_socket_url, "ws://127.0.0.1:8080/socket");
+        assert!(config.validate().is_ok());
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nLANYARD_API_KEY=k\nLANYARD_KV_PREFIX=a-b"),
+            Err(ConfigError::KvPrefix(KvPrefixError::InvalidChar(_)))
+        ));
+        let no_user = Config::from_env_str("DISCORD_APP_ID=1\nLANYARD_API_KEY=k").unwrap();
+        assert_eq!(no_user.validate(), Err(ConfigError::KvNeedsUserId));
+        let env = "DISCORD_APP_ID=1\nLANYARD_USER_ID=2\nLANYARD_API_KEY=k\nLANYARD_KV_TITLE=true";
+        assert_eq!(Config::from_env_str(env).unwrap().validate(), Err(ConfigError::KvTitleNeedsKey));
+    }
+
+    #[test]
+    fn test_buttons_from_env_str() {
+        let env = "DISCORD_APP_ID=1\n\
+                   RULE_GITHUB=GitHub\n\
+                   BUTTON_1=Open site|https://{domain}\n\
+                   BUTTON_1@rule:github=none\n\
+                   BUTTON_2@coding=View repo|{repo_url}\n\
+                   PROJECT_DIRS=/src, /work\n";
+        let config = Config::from_env_str(env).unwrap();
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.buttons.slots[0].entries().count(), 2);
+        assert_eq!(config.buttons.slots[1].entries().count(), 1);
+        assert_eq!(config.project_dirs, vec![PathBuf::from("/src"), PathBuf::from("/work")]);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nBUTTON_1=Label|ftp://x"),
+            Err(ConfigError::Button { source: ButtonError::BadUrl, .. })
+        ));
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nBUTTON_1@rule:nope=A|https://a.b"),
+            Err(ConfigError::UnknownRule { rule, .. }) if rule == "NOPE"
+        ));
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nRULE_X=("),
+            Err(ConfigError::Rule(RuleError::BadPattern { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_locale_from_env_str() {
+        let config = Config::from_env_str("DISCORD_APP_ID=1\nLANGUAGE=en").unwrap();
+        assert_eq!(config.locale, Some(Locale::En));
+
+        let config = Config::from_env_str("DISCORD_APP_ID=1").unwrap();
+        assert_eq!(config.locale, None);
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nLANGUAGE=xx"),
+            Err(ConfigError::UnknownLanguage(_))
+        ));
+    }
+
+    #[test]
+    fn test_redaction_from_env_str() {
+        let env = "DISCORD_APP_ID=1\n\
+                   REDACT_DETECTORS=email,phone\n\
+                   REDACT_ACTION=hash\n\
+                   REDACT_ACTION_PHONE=remove\n\
+                   REDACT_RULE_HOST=mask:[a-z]+\\.corp\n";
+        let redaction = Config::from_env_str(env).unwrap().redaction;
+        assert_eq!(
+            redaction.detectors,
+            vec![(Detector::Email, None), (Detector::Phone, Some(RedactAction::Remove))]
+        );
+        assert_eq!(redaction.default_action, RedactAction::Hash);
+        assert_eq!(redaction.user_patterns[0].pattern, "[a-z]+\\.corp");
+        assert_eq!(redaction.user_patterns[0].action, Some(RedactAction::Mask));
+
+        assert!(matches!(
+            Config::from_env_str("DISCORD_APP_ID=1\nREDACT_DETECTORS=bogus"),
+            Err(ConfigError::UnknownDetector(_))
+        ));
     }
 }
@@ -1,13 +1,41 @@
 /// 加密/解密模块
 ///
 /// 提供AES-256-GCM加密和解密功能
+///
+/// # 密文格式
+/// 密文为Base64编码的信封（当前版本为1）：
+///
+/// ```text
+/// [版本 1字节][密钥ID 2字节][标志 1字节][时间戳 8字节，可选][nonce 12字节][密文+认证标签]
+/// ```
+///
+/// 版本、密钥ID、标志、时间戳和字段名（如 `state`）一起作为附加认证数据（AAD），
+/// 因此密文不能被挪到其他字段，也不能篡改其中的时间戳。
+/// 早期版本的密文为 `base64(nonce || 密文)`，仍然可以解密，但不再生成
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     Aes256Gcm, Nonce,
 };
 use base64::{engine::general_purpose, Engine as _};
 use rand::RngCore;
 
+use crate::i18n::Msg;
+use crate::tr;
+
+/// nonce长度（字节）
+const NONCE_LEN: usize = 12;
+/// GCM认证标签长度（字节）
+const TAG_LEN: usize = 16;
+
+/// 当前的密文格式版本
+pub const ENVELOPE_VERSION: u8 = 1;
+/// 信封头长度（不含时间戳）：版本、密钥ID、标志
+const HEADER_LEN: usize = 4;
+/// 时间戳长度（字节）
+const TIMESTAMP_LEN: usize = 8;
+/// 标志位：带时间戳
+const FLAG_TIMESTAMP: u8 = 0x01;
+
 /// 加密错误类型
 #[derive(Debug)]
 pub enum CryptoError {
@@ -24,19 +52,33 @@ pub enum CryptoError {
 impl std::fmt::Display for CryptoError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CryptoError::EncryptionFailed(msg) => write!(f, "加密失败: {}", msg),
-            CryptoError::DecryptionFailed(msg) => write!(f, "解密失败: {}", msg),
-            CryptoError::InvalidKey(msg) => write!(f, "密钥无效: {}", msg),
-            CryptoError::Base64Error(msg) => write!(f, "Base64错误: {}", msg),
+            CryptoError::EncryptionFailed(msg) => write!(f, "{}", tr!(Msg::CryptoEncryptionFailed, msg)),
+            CryptoError::DecryptionFailed(msg) => write!(f, "{}", tr!(Msg::CryptoDecryptionFailed, msg)),
+            CryptoError::InvalidKey(msg) => write!(f, "{}", tr!(Msg::CryptoInvalidKey, msg)),
+            CryptoError::Base64Error(msg) => write!(f, "{}", tr!(Msg::CryptoBase64, msg)),
         }
     }
 }
 
 impl std::error::Error for CryptoError {}
 
+/// 解密得到的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opened {
+    /// 明文
+    pub plaintext: String,
+    /// 密文格式版本，早期格式为0
+    pub version: u8,
+    /// 加密使用的密钥ID，早期格式没有
+    pub key_id: Option<u16>,
+    /// 加密时附带的时间戳（Unix秒）
+    pub timestamp: Option<u64>,
+}
+
 /// 加密管理器
 pub struct CryptoManager {
     cipher: Aes256Gcm,
+    key_id: Option<u16>,
 }
 
 impl CryptoManager {
@@ -50,7 +92,20 @@ impl CryptoManager {
     /// * `Err(CryptoError)` - 密钥无效
     pub fn new(key: &[u8; 32]) -> Result<Self, CryptoError> {
         let cipher = Aes256Gcm::new(key.into());
-        Ok(Self { cipher })
+        Ok(Self { cipher, key_id: None })
+    }
+
+    /// 设置写入密文的密钥ID，设置后解密时只接受该ID的密文
+    ///
+    /// 未设置时写入0，解密时接受任何ID（由认证标签保证密钥正确）
+    pub fn with_key_id(mut self, key_id: u16) -> Self {
+        self.key_id = Some(key_id);
+        self
+    }
+
+    /// 密钥ID
+    pub fn key_id(&self) -> Option<u16> {
+        self.key_id
     }
 
     /// 从十六进制字符串创建加密管理器
@@ -63,15 +118,17 @@ impl CryptoManager {
     /// * `Err(CryptoError)` - 密钥格式无效
     pub fn from_hex(hex_key: &str) -> Result<Self, CryptoError> {
         if hex_key.len() != 64 {
-            return Err(CryptoError::InvalidKey(
-                "密钥必须是64个十六进制字符（32字节）".to_string(),
-            ));
+            return Err(CryptoError::InvalidKey(tr!(Msg::CryptoKeyLength)));
+        }
+        // 按字节切分前先排除非ASCII字符，避免切在多字节字符中间
+        if let Some(c) = hex_key.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(CryptoError::InvalidKey(tr!(Msg::CryptoKeyBadHex, c)));
         }
 
         let mut key = [0u8; 32];
         for i in 0..32 {
             key[i] = u8::from_str_radix(&hex_key[i * 2..i * 2 + 2], 16)
-                .map_err(|e| CryptoError::InvalidKey(format!("无效的十六进制字符: {}", e)))?;
+                .map_err(|e| CryptoError::InvalidKey(tr!(Msg::CryptoKeyBadHex, e)))?;
         }
 
         Self::new(&key)
@@ -100,28 +157,78 @@ impl CryptoManager {
             .collect::<String>()
     }
 
-    /// 加密数据
+    /// 计算在密文（Base64编码后）不超过指定字节数时，明文允许的最大字节数
+    ///
+    /// 按带时间戳的信封计算
+    ///
+    /// # 参数
+    /// * `ciphertext_limit` - 密文允许的最大字节数
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::CryptoManager;
+    ///
+    /// // Discord的state字段最多128字节
+    /// assert_eq!(CryptoManager::max_plaintext_len(128), 56);
+    /// ```
+    pub fn max_plaintext_len(ciphertext_limit: usize) -> usize {
+        // Base64每4个字符编码3个字节
+        (ciphertext_limit / 4 * 3)
+            .saturating_sub(HEADER_LEN + TIMESTAMP_LEN + NONCE_LEN + TAG_LEN)
+    }
+
+    /// 加密数据，密文不绑定字段，也不带时间戳
     ///
     /// # 参数
     /// * `plaintext` - 要加密的明文字符串
     ///
     /// # 返回值
-    /// * `Ok(String)` - Base64编码的加密数据（包含nonce）
+    /// * `Ok(String)` - Base64编码的信封
     /// * `Err(CryptoError)` - 加密失败
     pub fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        self.encrypt_field(plaintext, "", None)
+    }
+
+    /// 加密某个字段的数据
+    ///
+    /// # 参数
+    /// * `plaintext` - 要加密的明文字符串
+    /// * `field` - 密文所在的字段名（如 `state`），解密时必须相同
+    /// * `timestamp` - 写入信封的时间戳（Unix秒），解密时可以取出，不能被篡改
+    ///
+    /// # 返回值
+    /// * `Ok(String)` - Base64编码的信封
+    /// * `Err(CryptoError)` - 加密失败
+    pub fn encrypt_field(
+        &self,
+        plaintext: &str,
+        field: &str,
+        timestamp: Option<u64>,
+    ) -> Result<String, CryptoError> {
+        let mut header = vec![ENVELOPE_VERSION];
+        header.extend_from_slice(&self.key_id.unwrap_or(0).to_be_bytes());
+        match timestamp {
+            Some(timestamp) => {
+                header.push(FLAG_TIMESTAMP);
+                header.extend_from_slice(&timestamp.to_be_bytes());
+            }
+            None => header.push(0),
+        }
+
         // 生成随机nonce（12字节）
-        let mut nonce_bytes = [0u8; 12];
+        let mut nonce_bytes = [0u8; NONCE_LEN];
         OsRng.fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
 
-        // 加密数据
+        // 加密数据，信封头和字段名作为附加认证数据
+        let aad = [header.as_slice(), field.as_bytes()].concat();
         let ciphertext = self
             .cipher
-            .encrypt(nonce, plaintext.as_bytes())
+            .encrypt(nonce, Payload { msg: plaintext.as_bytes(), aad: &aad })
             .map_err(|e| CryptoError::EncryptionFailed(e.to_string()))?;
 
-        // 组合nonce和密文: [nonce(12 bytes)][ciphertext]
-        let mut result = Vec::with_capacity(12 + ciphertext.len());
+        // 组合: [信封头][nonce(12 bytes)][ciphertext]
+        let mut result = header;
         result.extend_from_slice(&nonce_bytes);
         result.extend_from_slice(&ciphertext);
 
@@ -129,7 +236,9 @@ impl CryptoManager {
         Ok(general_purpose::STANDARD.encode(&result))
     }
 
-    /// 解密数据
+    /// 解密 `encrypt` 生成的数据（或早期格式的密文）
+    ///
+    /// 用 `encrypt_field` 绑定了字段的密文需要用 `decrypt_field` 解密
     ///
     /// # 参数
     /// * `encrypted` - Base64编码的加密数据
@@ -138,36 +247,117 @@ impl CryptoManager {
     /// * `Ok(String)` - 解密后的明文字符串
     /// * `Err(CryptoError)` - 解密失败
     pub fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError> {
+        self.decrypt_field(encrypted, "")
+    }
+
+    /// 解密某个字段的数据
+    ///
+    /// # 参数
+    /// * `encrypted` - Base64编码的加密数据
+    /// * `field` - 密文所在的字段名，必须与加密时相同（早期格式不检查）
+    ///
+    /// # 返回值
+    /// * `Ok(String)` - 解密后的明文字符串
+    /// * `Err(CryptoError)` - 解密失败
+    pub fn decrypt_field(&self, encrypted: &str, field: &str) -> Result<String, CryptoError> {
+        self.open(encrypted, field).map(|opened| opened.plaintext)
+    }
+
+    /// 解密数据，同时取出信封中的版本、密钥ID和时间戳
+    ///
+    /// 早期格式没有版本字节，其第一个字节是随机的nonce，可能恰好等于版本号，
+    /// 因此按当前格式解密失败时再按早期格式尝试；认证标签保证不会错误地解密成功
+    pub fn open(&self, encrypted: &str, field: &str) -> Result<Opened, CryptoError> {
         // Base64解码
         let data = general_purpose::STANDARD
             .decode(encrypted)
             .map_err(|e| CryptoError::Base64Error(e.to_string()))?;
 
-        if data.len() < 12 {
-            return Err(CryptoError::DecryptionFailed(
-                "加密数据太短".to_string(),
-            ));
+        let envelope = match envelope_header(&data) {
+            Some((key_id, _, _)) if self.key_id.is_some_and(|id| id != key_id) => {
+                Err(CryptoError::DecryptionFailed(tr!(
+                    Msg::CryptoWrongKeyId,
+                    key_id,
+                    self.key_id.unwrap_or_default()
+                )))
+            }
+            Some((key_id, timestamp, header_len)) => {
+                let (header, rest) = data.split_at(header_len);
+                let aad = [header, field.as_bytes()].concat();
+                self.decrypt_bytes(rest, &aad).map(|plaintext| Opened {
+                    plaintext,
+                    version: ENVELOPE_VERSION,
+                    key_id: Some(key_id),
+                    timestamp,
+                })
+            }
+            None => Err(CryptoError::DecryptionFailed(tr!(Msg::CryptoDataTooShort))),
+        };
+        envelope.or_else(|e| {
+            self.decrypt_bytes(&data, &[])
+                .map(|plaintext| Opened { plaintext, version: 0, key_id: None, timestamp: None })
+                .map_err(|legacy| if data.first() == Some(&ENVELOPE_VERSION) { e } else { legacy })
+        })
+    }
+
+    /// 解密 `[nonce][密文+认证标签]`
+    fn decrypt_bytes(&self, data: &[u8], aad: &[u8]) -> Result<String, CryptoError> {
+        if data.len() < NONCE_LEN + TAG_LEN {
+            return Err(CryptoError::DecryptionFailed(tr!(Msg::CryptoDataTooShort)));
         }
 
         // 分离nonce和密文
-        let (nonce_bytes, ciphertext) = data.split_at(12);
+        let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
         let nonce = Nonce::from_slice(nonce_bytes);
 
         // 解密
         let plaintext = self
             .cipher
-            .decrypt(nonce, ciphertext)
+            .decrypt(nonce, Payload { msg: ciphertext, aad })
             .map_err(|e| CryptoError::DecryptionFailed(e.to_string()))?;
 
         String::from_utf8(plaintext)
-            .map_err(|e| CryptoError::DecryptionFailed(format!("无效的UTF-8数据: {}", e)))
+            .map_err(|e| CryptoError::DecryptionFailed(tr!(Msg::CryptoBadUtf8, e)))
     }
 }
 
+/// 读取Base64密文信封中的密钥ID，早期格式或无法识别时返回 `None`
+///
+/// 早期格式的第一个字节是随机的，可能被误认为信封，因此结果只能用于挑选密钥
+pub fn envelope_key_id(encrypted: &str) -> Option<u16> {
+    let data = general_purpose::STANDARD.decode(encrypted).ok()?;
+    envelope_header(&data).map(|(key_id, _, _)| key_id)
+}
+
+/// 解析信封头，返回密钥ID、时间戳和信封头长度
+fn envelope_header(data: &[u8]) -> Option<(u16, Option<u64>, usize)> {
+    if data.first() != Some(&ENVELOPE_VERSION) || data.len() < HEADER_LEN {
+        return None;
+    }
+    let key_id = u16::from_be_bytes([data[1], data[2]]);
+    if data[3] & FLAG_TIMESTAMP == 0 {
+        return Some((key_id, None, HEADER_LEN));
+    }
+    let bytes = data.get(HEADER_LEN..HEADER_LEN + TIMESTAMP_LEN)?;
+    let timestamp = u64::from_be_bytes(bytes.try_into().ok()?);
+    Some((key_id, Some(timestamp), HEADER_LEN + TIMESTAMP_LEN))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// 按早期格式 `base64(nonce || 密文)` 加密
+    fn legacy_encrypt(crypto: &CryptoManager, plaintext: &str) -> String {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let ciphertext = crypto
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+            .unwrap();
+        general_purpose::STANDARD.encode([nonce_bytes.as_slice(), &ciphertext].concat())
+    }
+
     #[test]
     fn test_encrypt_decrypt() {
         let key = CryptoManager::generate_key();
@@ -195,10 +385,118 @@ mod tests {
         assert_eq!(plaintext, decrypted);
     }
 
+    #[test]
+    fn test_encrypt_decrypt_field() {
+        let key = CryptoManager::generate_key();
+        let crypto1 = CryptoManager::new(&key).unwrap();
+        let crypto2 = CryptoManager::from_hex(&CryptoManager::key_to_hex(&key)).unwrap();
+
+        let plaintext = "Hello, World! 你好世界！";
+        let encrypted = crypto1.encrypt_field(plaintext, "state", None).unwrap();
+        assert_eq!(crypto2.decrypt_field(&encrypted, "state").unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_envelope_header() {
+        let crypto = CryptoManager::new(&CryptoManager::generate_key()).unwrap().with_key_id(0x0102);
+        let encrypted = crypto.encrypt_field("main.rs", "state", Some(1_700_000_000)).unwrap();
+        let data = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        assert_eq!(&data[..4], &[ENVELOPE_VERSION, 0x01, 0x02, FLAG_TIMESTAMP]);
+        assert_eq!(data.len(), HEADER_LEN + TIMESTAMP_LEN + NONCE_LEN + "main.rs".len() + TAG_LEN);
+        assert_eq!(envelope_key_id(&encrypted), Some(0x0102));
+
+        let opened = crypto.open(&encrypted, "state").unwrap();
+        assert_eq!(
+            opened,
+            Opened {
+                plaintext: "main.rs".to_string(),
+                version: ENVELOPE_VERSION,
+                key_id: Some(0x0102),
+                timestamp: Some(1_700_000_000),
+            }
+        );
+
+        let untimed = crypto.encrypt_field("main.rs", "state", None).unwrap();
+        assert_eq!(crypto.open(&untimed, "state").unwrap().timestamp, None);
+    }
+
+    #[test]
+    fn test_associated_data_is_authenticated() {
+        let crypto = CryptoManager::new(&CryptoManager::generate_key()).unwrap();
+        let encrypted = crypto.encrypt_field("secret.rs", "state", Some(1_700_000_000)).unwrap();
+
+        // 挪到其他字段
+        assert!(crypto.decrypt_field(&encrypted, "details").is_err());
+
+        // 篡改时间戳
+        let mut data = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        data[HEADER_LEN + TIMESTAMP_LEN - 1] ^= 1;
+        let tampered = general_purpose::STANDARD.encode(&data);
+        assert!(crypto.decrypt_field(&tampered, "state").is_err());
+
+        // 去掉时间戳标志
+        let mut data = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        data[3] = 0;
+        let tampered = general_purpose::STANDARD.encode(&data);
+        assert!(crypto.decrypt_field(&tampered, "state").is_err());
+    }
+
+    #[test]
+    fn test_wrong_key_id() {
+        let key = CryptoManager::generate_key();
+        let writer = CryptoManager::new(&key).unwrap().with_key_id(1);
+        let reader = CryptoManager::new(&key).unwrap().with_key_id(2);
+        let encrypted = writer.encrypt_field("main.rs", "state", None).unwrap();
+        let err = reader.decrypt_field(&encrypted, "state").unwrap_err();
+        assert!(err.to_string().contains('1') && err.to_string().contains('2'));
+    }
+
+    #[test]
+    fn test_decrypt_legacy_format() {
+        let crypto = CryptoManager::new(&CryptoManager::generate_key()).unwrap();
+        // 早期格式的第一个字节是随机的，多试几次以覆盖恰好等于版本号的情况
+        for _ in 0..64 {
+            let encrypted = legacy_encrypt(&crypto, "old.rs");
+            let opened = crypto.open(&encrypted, "state").unwrap();
+            assert_eq!(opened.plaintext, "old.rs");
+            assert_eq!((opened.version, opened.key_id, opened.timestamp), (0, None, None));
+        }
+    }
+
+    #[test]
+    fn test_max_plaintext_len() {
+        let key = CryptoManager::generate_key();
+        let crypto = CryptoManager::new(&key).unwrap();
+
+        let limit = 128;
+        let plaintext = "x".repeat(CryptoManager::max_plaintext_len(limit));
+        let timestamp = Some(u64::MAX);
+        assert!(crypto.encrypt_field(&plaintext, "state", timestamp).unwrap().len() <= limit);
+
+        let too_long = "x".repeat(CryptoManager::max_plaintext_len(limit) + 1);
+        assert!(crypto.encrypt_field(&too_long, "state", timestamp).unwrap().len() > limit);
+    }
+
+    #[test]
+    fn test_unbound_encrypt_decrypt() {
+        let crypto = CryptoManager::new(&CryptoManager::generate_key()).unwrap();
+        let encrypted = crypto.encrypt("main.rs").unwrap();
+        assert_eq!(crypto.decrypt(&encrypted).unwrap(), "main.rs");
+        assert!(crypto.decrypt_field(&encrypted, "state").is_err());
+
+        // 早期格式的密文也能解密
+        assert_eq!(crypto.decrypt(&legacy_encrypt(&crypto, "old.rs")).unwrap(), "old.rs");
+    }
+
     #[test]
     fn test_invalid_key() {
         let result = CryptoManager::from_hex("invalid");
         assert!(result.is_err());
+
+        // 非ASCII字符不能导致按字节切分时panic
+        let non_ascii = format!("é{}", "0".repeat(62));
+        assert_eq!(non_ascii.len(), 64);
+        assert!(matches!(CryptoManager::from_hex(&non_ascii), Err(CryptoError::InvalidKey(_))));
     }
 
     #[test]
@@ -209,5 +507,12 @@ mod tests {
         let result = crypto.decrypt("invalid_base64!");
         assert!(result.is_err());
     }
-}
 
+    #[test]
+    fn test_decrypt_field_invalid_data() {
+        let crypto = CryptoManager::new(&CryptoManager::generate_key()).unwrap();
+        assert!(crypto.decrypt_field("invalid_base64!", "state").is_err());
+        // 只有信封头、没有随机数和密文
+        assert!(crypto.decrypt_field("AQAAAA==", "state").is_err());
+    }
+}
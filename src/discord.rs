@@ -1,18 +1,490 @@
 /// Discord Rich Presence管理模块
 ///
-/// 提供与Discord RPC的连接和状态更新功能
-use discord_rpc_client::Client as DiscordClient;
-use std::time::{ SystemTime, UNIX_EPOCH };
+/// 提供与Discord RPC的连接和状态更新功能。
+/// 连接断开后按指数退避（带随机抖动）自动重连，重连成功后重新发布最近一次的活动状态，
+/// 连接状态的变化以 `ConnectionEvent` 的形式通知订阅者。
+use rand::Rng;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::builder::{unix_now, PresenceBuilder};
 use crate::config::Config;
-use crate::crypto::CryptoManager;
+use crate::crypto::{CryptoError, CryptoManager};
+use crate::keyring::KeyringCrypto;
+use crate::lanyard;
+use crate::error::Error;
+use crate::fit::{fit_presence, truncate, Field, FitOptions};
+use crate::i18n::Msg;
+use crate::ipc::{socket_candidates, socket_candidates_in, IpcClient, IpcError};
 use crate::parser::WindowInfo;
+use crate::presence::Presence;
+use crate::scheduler::RateLimit;
+use crate::sink::{PresenceSink, SinkResult};
+use crate::tr;
+
+/// 重连策略
+///
+/// 第 n 次重连前等待 `initial_delay * multiplier^(n-1)`，不超过 `max_delay`，
+/// 再乘以 `1 ± jitter` 范围内的随机系数，避免多个实例同时重连
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// 首次重连前的等待时间
+    pub initial_delay: Duration,
+    /// 等待时间上限
+    pub max_delay: Duration,
+    /// 每次失败后等待时间的倍数
+    pub multiplier: f64,
+    /// 随机抖动比例（0.2 表示 ±20%）
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次重连前的等待时间（不含抖动）
+    pub fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1).min(64) as i32);
+        Duration::try_from_secs_f64(self.initial_delay.as_secs_f64() * factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// 第 `attempt` 次重连前的等待时间（含抖动）
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.base_delay(attempt);
+        if self.jitter <= 0.0 {
+            return base;
+        }
+        let factor = 1.0 + rand::thread_rng().gen_range(-self.jitter..=self.jitter);
+        Duration::try_from_secs_f64(base.as_secs_f64() * factor.max(0.0)).unwrap_or(base)
+    }
+}
+
+/// 加密 `state` 时写入附加认证数据的字段名
+pub const STATE_FIELD: &str = "state";
+
+/// 当前登录Discord客户端的用户（来自READY事件）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscordUser {
+    /// 用户ID
+    pub id: String,
+    /// 用户名
+    pub username: String,
+    /// 显示名称
+    pub global_name: Option<String>,
+    /// 头像哈希
+    pub avatar: Option<String>,
+}
+
+impl DiscordUser {
+    /// 从READY事件的 `data` 中解析用户信息
+    pub fn from_ready(data: &Value) -> Option<Self> {
+        let user = data.get("user")?;
+        let text = |key: &str| {
+            user.get(key)
+                .and_then(Value::as_str)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        Some(Self {
+            id: text("id")?,
+            username: text("username").unwrap_or_default(),
+            global_name: text("global_name"),
+            avatar: text("avatar"),
+        })
+    }
+
+    /// 显示名称，未设置时使用用户名
+    pub fn display_name(&self) -> &str {
+        self.global_name.as_deref().unwrap_or(&self.username)
+    }
+
+    /// 头像地址
+    pub fn avatar_url(&self) -> Option<String> {
+        self.avatar
+            .as_ref()
+            .map(|hash| format!("https://cdn.discordapp.com/avatars/{}/{}.png", self.id, hash))
+    }
+
+    /// 该用户在Lanyard接口 `base_url`（如 `https://api.lanyard.rest`）上的状态地址
+    pub fn lanyard_url(&self, base_url: &str) -> String {
+        lanyard::user_url(base_url, &self.id)
+    }
+}
+
+/// Discord RPC错误
+#[derive(Debug)]
+pub enum DiscordError {
+    /// 未连接到Discord，正在等待重连
+    NotConnected,
+    /// 连接或握手失败（Discord未运行、应用ID无效等）
+    Connect(IpcError),
+    /// 设置活动状态失败（Discord拒绝了活动数据或连接断开）
+    SetActivity(IpcError),
+    /// 清除活动状态失败
+    ClearActivity(IpcError),
+    /// 退出时Discord没有在限定时间内响应
+    ShutdownTimeout(Duration),
+    /// 初始化加密管理器失败
+    CryptoInit(CryptoError),
+    /// 加密 `state` 失败
+    Encrypt(CryptoError),
+    /// 解密 `state` 失败
+    Decrypt(CryptoError),
+    /// 未启用加密
+    EncryptionDisabled,
+}
+
+impl DiscordError {
+    /// 底层的IPC错误
+    pub fn ipc_error(&self) -> Option<&IpcError> {
+        match self {
+            DiscordError::Connect(e) | DiscordError::SetActivity(e) | DiscordError::ClearActivity(e) => {
+                Some(e)
+            },
+            _ => None,
+        }
+    }
+
+    /// Discord是否未运行
+    pub fn is_not_running(&self) -> bool {
+        matches!(self.ipc_error(), Some(IpcError::NotRunning))
+    }
+
+    /// Discord是否拒绝了命令（例如活动数据无效），连接仍然可用
+    pub fn is_rejected(&self) -> bool {
+        matches!(self.ipc_error(), Some(IpcError::Rejected { .. }))
+    }
+
+    /// 重试是否也不会成功（Discord拒绝了活动数据、加密失败等）
+    pub fn is_permanent(&self) -> bool {
+        self.is_rejected()
+            || matches!(
+                self,
+                DiscordError::CryptoInit(_)
+                    | DiscordError::Encrypt(_)
+                    | DiscordError::Decrypt(_)
+                    | DiscordError::EncryptionDisabled
+            )
+    }
+
+    /// 是否因为没有连接而失败，重连后会自动发布最近的活动状态
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, DiscordError::NotConnected)
+            || self.ipc_error().is_some_and(IpcError::is_disconnect)
+    }
+}
+
+impl fmt::Display for DiscordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            DiscordError::NotConnected => tr!(Msg::DiscordNotConnected),
+            DiscordError::Connect(e) => e.to_string(),
+            DiscordError::SetActivity(e) => tr!(Msg::DiscordSetActivityFailed, e),
+            DiscordError::ClearActivity(e) => tr!(Msg::DiscordClearActivityFailed, e),
+            DiscordError::ShutdownTimeout(timeout) => {
+                tr!(Msg::DiscordShutdownTimeout, timeout.as_millis())
+            },
+            DiscordError::CryptoInit(e) => tr!(Msg::DiscordCryptoInitFailed, e),
+            DiscordError::Encrypt(e) => tr!(Msg::DiscordEncryptStateFailed, e),
+            DiscordError::Decrypt(e) => tr!(Msg::DiscordDecryptStateFailed, e),
+            DiscordError::EncryptionDisabled => tr!(Msg::DiscordEncryptionDisabled),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for DiscordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DiscordError::Connect(e) | DiscordError::SetActivity(e) | DiscordError::ClearActivity(e) => {
+                Some(e)
+            },
+            DiscordError::CryptoInit(e) | DiscordError::Encrypt(e) | DiscordError::Decrypt(e) => {
+                Some(e)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// `DISCORD_CLIENT` 解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientSelectorError {
+    /// 不是已知的客户端版本，也不是用户ID
+    Unknown(String),
+}
+
+impl fmt::Display for ClientSelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientSelectorError::Unknown(value) => f.write_str(&tr!(Msg::ConfigUnknownClient, value)),
+        }
+    }
+}
+
+impl std::error::Error for ClientSelectorError {}
+
+/// Discord客户端版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientBuild {
+    /// 正式版
+    Stable,
+    /// 公共测试版
+    Ptb,
+    /// 金丝雀版
+    Canary,
+    /// 无法识别
+    Unknown,
+}
+
+impl ClientBuild {
+    /// 根据READY事件中的 `config.api_endpoint` 识别客户端版本
+    pub fn from_ready(data: &Value) -> Self {
+        let endpoint = data
+            .pointer("/config/api_endpoint")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim_start_matches("https:")
+            .trim_start_matches("//");
+        let host = endpoint.split('/').next().unwrap_or_default();
+        match host {
+            "discord.com" | "discordapp.com" => ClientBuild::Stable,
+            "ptb.discord.com" | "ptb.discordapp.com" => ClientBuild::Ptb,
+            "canary.discord.com" | "canary.discordapp.com" => ClientBuild::Canary,
+            _ => ClientBuild::Unknown,
+        }
+    }
+
+    /// 配置名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientBuild::Stable => "stable",
+            ClientBuild::Ptb => "ptb",
+            ClientBuild::Canary => "canary",
+            ClientBuild::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for ClientBuild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 已连接的Discord客户端实例
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInstance {
+    /// IPC套接字路径
+    pub path: Option<PathBuf>,
+    /// 客户端版本
+    pub build: ClientBuild,
+    /// 登录的用户
+    pub user: Option<DiscordUser>,
+}
+
+impl ClientInstance {
+    fn from_client(client: &IpcClient) -> Self {
+        Self {
+            path: client.path().map(Path::to_path_buf),
+            build: ClientBuild::from_ready(client.ready_data()),
+            user: DiscordUser::from_ready(client.ready_data()),
+        }
+    }
+}
+
+/// 同时运行多个Discord客户端时选择哪一个（`DISCORD_CLIENT`）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClientSelector {
+    /// 第一个找到的客户端
+    #[default]
+    Any,
+    /// 优先选择指定版本，没有时使用第一个找到的客户端
+    Build(ClientBuild),
+    /// 优先选择登录了指定用户的客户端，没有时使用第一个找到的客户端
+    User(String),
+    /// 同时发布到所有客户端
+    All,
+}
+
+impl ClientSelector {
+    /// 解析配置值：`any`、`all`、`stable`、`ptb`、`canary` 或数字用户ID
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::discord::{ClientBuild, ClientSelector, ClientSelectorError};
+    ///
+    /// assert_eq!(ClientSelector::parse("Canary"), Ok(ClientSelector::Build(ClientBuild::Canary)));
+    /// assert_eq!(ClientSelector::parse("1234"), Ok(ClientSelector::User("1234".to_string())));
+    /// assert_eq!(ClientSelector::parse("beta"), Err(ClientSelectorError::Unknown("beta".to_string())));
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, ClientSelectorError> {
+        let value = value.trim();
+        match value.to_ascii_lowercase().as_str() {
+            "any" => Ok(ClientSelector::Any),
+            "all" => Ok(ClientSelector::All),
+            "stable" => Ok(ClientSelector::Build(ClientBuild::Stable)),
+            "ptb" => Ok(ClientSelector::Build(ClientBuild::Ptb)),
+            "canary" => Ok(ClientSelector::Build(ClientBuild::Canary)),
+            _ if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) => {
+                Ok(ClientSelector::User(value.to_string()))
+            },
+            _ => Err(ClientSelectorError::Unknown(value.to_string())),
+        }
+    }
+
+    /// 从已连接的客户端中选出要使用的，其余连接被关闭
+    pub fn select(&self, mut clients: Vec<IpcClient>) -> Vec<IpcClient> {
+        let preferred = |client: &IpcClient| match self {
+            ClientSelector::Build(build) => ClientBuild::from_ready(client.ready_data()) == *build,
+            ClientSelector::User(id) => {
+                DiscordUser::from_ready(client.ready_data()).is_some_and(|user| user.id == *id)
+            },
+            ClientSelector::Any | ClientSelector::All => true,
+        };
+        if *self == ClientSelector::All || clients.is_empty() {
+            return clients;
+        }
+        let index = clients.iter().position(preferred).unwrap_or(0);
+        let chosen = clients.swap_remove(index);
+        for client in clients {
+            let _ = client.close();
+        }
+        vec![chosen]
+    }
+}
+
+/// 连接状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// 已连接
+    Connected,
+    /// 连接断开，将在 `retry_at` 时进行第 `attempt` 次重连
+    Reconnecting { attempt: u32, retry_at: Instant },
+}
+
+/// 连接状态变化事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// 断开后重新连接成功，`attempts` 为本次共尝试的次数
+    Connected { attempts: u32 },
+    /// 连接断开
+    Disconnected { reason: String },
+    /// 重连失败，将在 `retry_in` 后再次尝试
+    ReconnectFailed { attempt: u32, error: String, retry_in: Duration },
+    /// 重连后已重新发布最近一次的活动状态
+    Republished,
+    /// 活动状态映射到了另一个Discord应用，已清除旧应用的状态并重新握手
+    ApplicationSwitched { from: u64, to: u64 },
+    /// 登录的Discord账号与配置的 `LANYARD_USER_ID` 不一致
+    UserMismatch { expected: String, actual: String },
+}
+
+impl fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ConnectionEvent::Connected { attempts } => tr!(Msg::DiscordEventConnected, attempts),
+            ConnectionEvent::Disconnected { reason } => tr!(Msg::DiscordEventDisconnected, reason),
+            ConnectionEvent::ReconnectFailed { attempt, error, retry_in } => tr!(
+                Msg::DiscordEventReconnectFailed,
+                attempt,
+                error,
+                format!("{:.1}", retry_in.as_secs_f64())
+            ),
+            ConnectionEvent::Republished => tr!(Msg::DiscordEventRepublished),
+            ConnectionEvent::ApplicationSwitched { from, to } => {
+                tr!(Msg::DiscordEventApplicationSwitched, from, to)
+            },
+            ConnectionEvent::UserMismatch { expected, actual } => {
+                tr!(Msg::DiscordUserMismatch, actual, expected)
+            },
+        };
+        f.write_str(&text)
+    }
+}
+
+/// 加密 `state` 并将所有字段适配到长度限制内，得到实际发布的状态
+///
+/// 其他按Discord格式发布状态的输出也使用它，保证内容与Discord中一致
+pub(crate) fn prepare_presence(
+    presence: &Presence,
+    crypto: Option<&KeyringCrypto>,
+    fit: &FitOptions,
+) -> Result<Presence, CryptoError> {
+    let mut presence = presence.clone();
+
+    // 密文会比明文长，先截断明文，保证密文不超过state字段的长度限制
+    if let (Some(crypto), Some(state)) = (crypto, &presence.state) {
+        let max_len = CryptoManager::max_plaintext_len(Field::State.max_bytes());
+        let plaintext = truncate(state, max_len, fit);
+        let now = unix_now();
+        presence.state = Some(crypto.encryptor(now)?.encrypt_field(&plaintext, STATE_FIELD, Some(now))?);
+    }
+
+    Ok(fit_presence(&presence, fit))
+}
+
+/// 根据配置中的密钥环创建加密管理器
+fn crypto_for(config: &Config) -> Result<Option<KeyringCrypto>, DiscordError> {
+    config.keyring().crypto().map_err(DiscordError::CryptoInit)
+}
+
+/// 打开IPC连接
+///
+/// 指定了套接字路径时只连接该路径；指定了目录时在其中查找 `discord-ipc-0..9`，否则自动查找。
+/// 选择第一个客户端时只连接找到的第一个，其他方式会先连接所有客户端再按 `selector` 选择
+fn open_clients(
+    app_id: u64,
+    ipc_path: Option<&Path>,
+    selector: &ClientSelector,
+) -> Result<Vec<IpcClient>, IpcError> {
+    let candidates = match ipc_path {
+        Some(path) if path.is_dir() => socket_candidates_in(path),
+        Some(path) => return IpcClient::connect_path(path, app_id).map(|client| vec![client]),
+        None if *selector == ClientSelector::Any => {
+            return IpcClient::connect(app_id).map(|client| vec![client]);
+        },
+        None => socket_candidates(),
+    };
+    IpcClient::connect_each(&candidates, app_id).map(|clients| selector.select(clients))
+}
 
 /// Discord RPC管理器
 pub struct DiscordManager {
-    client: DiscordClient,
-    start_time: u64,
-    crypto: Option<CryptoManager>,
+    clients: Vec<IpcClient>,
+    app_id: u64,
+    ipc_path: Option<PathBuf>,
+    selector: ClientSelector,
+    crypto: Option<KeyringCrypto>,
+    fit: FitOptions,
+    policy: ReconnectPolicy,
+    state: ConnectionState,
+    last_presence: Option<Presence>,
+    /// 当前已发送到Discord的活动状态，断开或切换应用后清空
+    published: Option<Presence>,
+    /// 最近一次成功发送的 `activity` 对象（加密并适配长度之后）
+    published_activity: Option<Value>,
+    subscribers: Vec<Sender<ConnectionEvent>>,
+    user: Option<DiscordUser>,
+    expected_user_id: Option<String>,
+    lanyard_api_url: String,
+    default_app_id: u64,
+    rate_limit: RateLimit,
+    builder: PresenceBuilder,
 }
 
 impl DiscordManager {
@@ -23,34 +495,213 @@ impl DiscordManager {
     ///
     /// # 返回值
     /// * `Ok(DiscordManager)` - 成功创建并连接
-    /// * `Err(String)` - 连接失败
-    pub fn connect(config: &Config) -> Result<Self, String> {
-        let mut client = DiscordClient::new(config.discord_app_id);
-
-        client.start();
-
-        let start_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| format!("获取系统时间失败: {}", e))?
-            .as_secs();
-
-        // 如果配置中有加密密钥，初始化加密管理器
-        let crypto = if let Some(ref key) = config.encryption_key {
-            Some(
-                CryptoManager::from_hex(key)
-                    .map_err(|e| format!("初始化加密管理器失败: {}", e))?
-            )
+    /// * `Err(DiscordError)` - 连接失败（Discord未运行、应用ID无效等）
+    pub fn connect(config: &Config) -> Result<Self, DiscordError> {
+        let clients =
+            open_clients(config.discord_app_id, config.ipc_path.as_deref(), &config.discord_client)
+                .map_err(DiscordError::Connect)?;
+        Self::with_clients(config, clients)
+    }
+
+    /// 创建Discord RPC管理器，连接失败时不报错，而是在之后的 `poll` 中按重连策略重试
+    ///
+    /// # 错误
+    /// 仅在加密管理器初始化失败等配置问题时返回错误
+    pub fn new(config: &Config) -> Result<Self, DiscordError> {
+        let clients =
+            open_clients(config.discord_app_id, config.ipc_path.as_deref(), &config.discord_client)
+                .unwrap_or_default();
+        Self::with_clients(config, clients)
+    }
+
+    fn with_clients(config: &Config, clients: Vec<IpcClient>) -> Result<Self, DiscordError> {
+        let crypto = crypto_for(config)?;
+
+        let user = clients.first().and_then(|c| DiscordUser::from_ready(c.ready_data()));
+        let policy = config.reconnect.clone();
+        let state = if !clients.is_empty() {
+            ConnectionState::Connected
         } else {
-            None
+            ConnectionState::Reconnecting { attempt: 1, retry_at: Instant::now() + policy.delay(1) }
         };
 
         Ok(Self {
-            client,
-            start_time,
+            clients,
+            app_id: config.discord_app_id,
+            ipc_path: config.ipc_path.clone(),
+            selector: config.discord_client.clone(),
             crypto,
+            fit: config.fit.clone(),
+            policy,
+            state,
+            last_presence: None,
+            published: None,
+            published_activity: None,
+            subscribers: Vec::new(),
+            user,
+            expected_user_id: config.lanyard_user_id.clone(),
+            lanyard_api_url: config.lanyard_api_url.clone(),
+            default_app_id: config.discord_app_id,
+            rate_limit: config.rate_limit,
+            builder: PresenceBuilder::new(config),
         })
     }
 
+    /// 应用重新加载的配置，保留当前连接、计时和最近的活动状态
+    ///
+    /// IPC路径或客户端选择变化时立即重新连接；默认应用ID变化时在下一次更新时切换
+    ///
+    /// # 错误
+    /// 新的加密密钥无效时返回错误，此时不应用任何设置
+    pub fn reload(&mut self, config: &Config) -> Result<(), DiscordError> {
+        self.crypto = crypto_for(config)?;
+        self.fit = config.fit.clone();
+        self.policy = config.reconnect.clone();
+        self.expected_user_id = config.lanyard_user_id.clone();
+        self.lanyard_api_url = config.lanyard_api_url.clone();
+        self.default_app_id = config.discord_app_id;
+        self.rate_limit = config.rate_limit;
+        self.builder.reload(config);
+
+        if self.ipc_path != config.ipc_path || self.selector != config.discord_client {
+            self.ipc_path = config.ipc_path.clone();
+            self.selector = config.discord_client.clone();
+            self.close_clients();
+            self.state = ConnectionState::Reconnecting { attempt: 1, retry_at: Instant::now() };
+        }
+        Ok(())
+    }
+
+    /// 退出前清除活动状态并关闭所有IPC连接，最多等待 `timeout`
+    ///
+    /// Discord可能迟迟不响应，因此在后台线程中进行；超时后直接返回，连接随进程退出关闭
+    pub fn shutdown(mut self, timeout: Duration) -> Result<(), DiscordError> {
+        let clients = std::mem::take(&mut self.clients);
+        if clients.is_empty() {
+            return Ok(());
+        }
+
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let mut result = Ok(());
+            for mut client in clients {
+                let cleared = client.set_activity(None).map(drop);
+                let closed = client.close();
+                if result.is_ok() {
+                    result = cleared.and(closed);
+                }
+            }
+            let _ = done.send(result);
+        });
+        match finished.recv_timeout(timeout) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(DiscordError::ClearActivity(e)),
+            Err(_) => Err(DiscordError::ShutdownTimeout(timeout)),
+        }
+    }
+
+    /// 订阅连接状态变化事件
+    pub fn subscribe(&mut self) -> Receiver<ConnectionEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// 当前连接状态
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// 是否已连接
+    pub fn is_connected(&self) -> bool {
+        self.state == ConnectionState::Connected
+    }
+
+    /// 最近一次成功发送给Discord的 `activity` 对象（加密并适配长度之后），清除后为 `None`
+    pub fn published_activity(&self) -> Option<&Value> {
+        self.published_activity.as_ref()
+    }
+
+    /// 当前握手使用的Discord应用ID
+    pub fn application_id(&self) -> u64 {
+        self.app_id
+    }
+
+    /// 当前连接的所有Discord客户端
+    pub fn instances(&self) -> Vec<ClientInstance> {
+        self.clients.iter().map(ClientInstance::from_client).collect()
+    }
+
+    /// 当前登录Discord客户端的用户（最近一次连接时READY事件中的信息，连接多个客户端时为第一个）
+    pub fn connected_user(&self) -> Option<&DiscordUser> {
+        self.user.as_ref()
+    }
+
+    /// Lanyard使用的用户ID：优先使用配置的 `LANYARD_USER_ID`，否则使用登录用户的ID
+    pub fn lanyard_user_id(&self) -> Option<&str> {
+        self.expected_user_id
+            .as_deref()
+            .or(self.user.as_ref().map(|user| user.id.as_str()))
+    }
+
+    /// 用户状态在配置的Lanyard接口（`LANYARD_API_URL`）上的地址
+    pub fn lanyard_url(&self) -> Option<String> {
+        self.lanyard_user_id().map(|id| lanyard::user_url(&self.lanyard_api_url, id))
+    }
+
+    /// 配置的用户ID与登录用户不一致时，返回 `(配置的ID, 登录用户的ID)`
+    pub fn user_mismatch(&self) -> Option<(&str, &str)> {
+        let expected = self.expected_user_id.as_deref()?;
+        let actual = self.user.as_ref()?.id.as_str();
+        (expected != actual).then_some((expected, actual))
+    }
+
+    /// 推进重连状态机，应在主循环中定期调用
+    ///
+    /// 断开状态下到达重连时间时尝试重连，成功后重新发布最近一次的活动状态
+    ///
+    /// # 返回值
+    /// 调用结束时是否已连接
+    pub fn poll(&mut self) -> bool {
+        let ConnectionState::Reconnecting { attempt, retry_at } = self.state else {
+            return true;
+        };
+        if Instant::now() < retry_at {
+            return false;
+        }
+
+        match open_clients(self.app_id, self.ipc_path.as_deref(), &self.selector) {
+            Ok(clients) => {
+                self.user = clients.first().and_then(|c| DiscordUser::from_ready(c.ready_data()));
+                self.clients = clients;
+                self.state = ConnectionState::Connected;
+                self.emit(ConnectionEvent::Connected { attempts: attempt });
+                if let Some((expected, actual)) = self.user_mismatch() {
+                    let (expected, actual) = (expected.to_string(), actual.to_string());
+                    self.emit(ConnectionEvent::UserMismatch { expected, actual });
+                }
+                if let Some(presence) = self.last_presence.clone()
+                    && self.send(Some(&presence)).is_ok()
+                {
+                    self.emit(ConnectionEvent::Republished);
+                }
+            },
+            Err(e) => {
+                let retry_in = self.policy.delay(attempt + 1);
+                self.state = ConnectionState::Reconnecting {
+                    attempt: attempt + 1,
+                    retry_at: Instant::now() + retry_in,
+                };
+                self.emit(ConnectionEvent::ReconnectFailed {
+                    attempt,
+                    error: e.to_string(),
+                    retry_in,
+                });
+            },
+        }
+        self.is_connected()
+    }
+
     /// 更新Discord Rich Presence状态
     ///
     /// # 参数
@@ -58,51 +709,153 @@ impl DiscordManager {
     /// * `full_title` - 完整的窗口标题
     ///
     /// # 返回值
-    /// * `Ok(())` - 更新成功
-    /// * `Err(String)` - 更新失败
+    /// 与 `update_presence` 相同
     pub fn update_activity(
         &mut self,
         window_info: &WindowInfo,
         full_title: &str
-    ) -> Result<(), String> {
-        // 如果启用了加密，加密state数据
-        let state_data = if let Some(ref crypto) = self.crypto {
-            crypto
-                .encrypt(full_title)
-                .map_err(|e| format!("加密state数据失败: {}", e))?
-        } else {
-            full_title.to_string()
-        };
+    ) -> UpdateResult {
+        let presence = self.presence_for(full_title, window_info);
+        self.update_presence(&presence)
+    }
 
-        self.client
-            .set_activity(|act| {
-                let mut activity = act
-                    .state(&state_data)
-                    .details(&window_info.app_name)
-                    .timestamps(|t| t.start(self.start_time));
+    /// 根据配置生成当前窗口的活动状态，见 [`PresenceBuilder::presence_for`]
+    pub fn presence_for(&mut self, full_title: &str, window_info: &WindowInfo) -> Presence {
+        self.builder.presence_for(full_title, window_info)
+    }
 
-                // 添加Windows图标（需要在Discord Developer Portal上传）
-                activity = activity.assets(|a| {
-                    a.large_image("windows").large_text("Windows Activity Monitor")
-                });
+    /// 没有活动窗口时显示的“离开”状态，见 [`PresenceBuilder::away_presence`]
+    pub fn away_presence(&mut self) -> Presence {
+        self.builder.away_presence()
+    }
 
-                activity
-            })
-            .map(|_| ())
-            .map_err(|e| format!("更新Discord状态失败: {}", e))
+    /// 将活动状态发送到Discord
+    ///
+    /// 如果启用了加密，`state` 字段会先被加密；发送前会将所有字段适配到Discord的长度限制内。
+    /// 未连接时返回 `DiscordError::NotConnected`，但活动状态会被记录下来，重连成功后自动发布。
+    /// 活动状态映射到另一个应用ID时，先切换应用再发送
+    ///
+    /// # 参数
+    /// * `presence` - 活动状态（明文）
+    ///
+    /// # 返回值
+    /// * `UpdateResult::Success` - 已发送
+    /// * `UpdateResult::Skipped` - 与当前已发布的状态相同，没有发送
+    /// * `UpdateResult::Failed` - 发送失败
+    pub fn update_presence(&mut self, presence: &Presence) -> UpdateResult {
+        self.switch_application(presence.application_id.unwrap_or(self.default_app_id));
+        let connected = self.poll();
+        if connected && self.published.as_ref() == Some(presence) {
+            return UpdateResult::Skipped;
+        }
+        self.last_presence = Some(presence.clone());
+        if !connected {
+            return UpdateResult::Failed(DiscordError::NotConnected);
+        }
+        self.send(Some(presence)).into()
     }
 
     /// 清除Discord Rich Presence状态
-    pub fn clear_activity(&mut self) -> Result<(), String> {
-        self.client
-            .clear_activity()
-            .map(|_| ())
-            .map_err(|e| format!("清除Discord状态失败: {}", e))
+    pub fn clear_activity(&mut self) -> UpdateResult {
+        let connected = self.poll();
+        self.last_presence = None;
+        if !connected {
+            return UpdateResult::Failed(DiscordError::NotConnected);
+        }
+        self.send(None).into()
+    }
+
+    /// 加密、适配并发送活动状态，`None` 表示清除；连接断开时切换到重连状态
+    fn send(&mut self, presence: Option<&Presence>) -> Result<(), DiscordError> {
+        let activity = match presence {
+            Some(presence) => Some(activity_payload(&self.prepare(presence)?)),
+            None => None,
+        };
+        if self.clients.is_empty() {
+            return Err(DiscordError::NotConnected);
+        }
+
+        // 连接多个客户端时全部发送；任意一个断开都会整体重连，重连后重新发布
+        let clearing = activity.is_none();
+        let mut failure = None;
+        for client in &mut self.clients {
+            if let Err(e) = client.set_activity(activity.clone()) {
+                let disconnected = e.is_disconnect();
+                failure.get_or_insert((e, disconnected));
+                if disconnected {
+                    break;
+                }
+            }
+        }
+        match failure {
+            None => {
+                self.published = presence.cloned();
+                self.published_activity = activity;
+                Ok(())
+            },
+            Some((e, disconnected)) => {
+                if disconnected {
+                    self.disconnect(e.to_string());
+                }
+                Err(if clearing {
+                    DiscordError::ClearActivity(e)
+                } else {
+                    DiscordError::SetActivity(e)
+                })
+            },
+        }
+    }
+
+    /// 加密 `state` 并将所有字段适配到长度限制内
+    fn prepare(&self, presence: &Presence) -> Result<Presence, DiscordError> {
+        prepare_presence(presence, self.crypto.as_ref(), &self.fit).map_err(DiscordError::Encrypt)
+    }
+
+    /// 切换到另一个Discord应用
+    ///
+    /// 应用ID在握手时确定，因此需要重新连接：先清除旧应用下的活动状态并关闭连接，
+    /// 避免旧状态残留，然后立即用新的应用ID重连
+    fn switch_application(&mut self, app_id: u64) {
+        if app_id == self.app_id {
+            return;
+        }
+        let from = std::mem::replace(&mut self.app_id, app_id);
+        self.close_clients();
+        self.last_presence = None;
+        self.state = ConnectionState::Reconnecting { attempt: 1, retry_at: Instant::now() };
+        self.emit(ConnectionEvent::ApplicationSwitched { from, to: app_id });
+    }
+
+    /// 清除活动状态并关闭所有连接，避免旧状态残留
+    fn close_clients(&mut self) {
+        self.published = None;
+        self.published_activity = None;
+        for mut client in self.clients.drain(..) {
+            let _ = client.set_activity(None);
+            let _ = client.close();
+        }
+    }
+
+    /// 丢弃当前连接并安排第一次重连
+    fn disconnect(&mut self, reason: String) {
+        self.clients.clear();
+        self.published = None;
+        self.published_activity = None;
+        self.state = ConnectionState::Reconnecting {
+            attempt: 1,
+            retry_at: Instant::now() + self.policy.delay(1),
+        };
+        self.emit(ConnectionEvent::Disconnected { reason });
+    }
+
+    /// 向所有订阅者发送事件，移除已关闭的订阅
+    fn emit(&mut self, event: ConnectionEvent) {
+        self.subscribers.retain(|sender| sender.send(event.clone()).is_ok());
     }
 
     /// 获取启动时间戳
     pub fn start_time(&self) -> u64 {
-        self.start_time
+        self.builder.start_time()
     }
 
     /// 检查是否启用了加密
@@ -117,27 +870,125 @@ impl DiscordManager {
     ///
     /// # 返回值
     /// * `Ok(String)` - 解密后的数据
-    /// * `Err(String)` - 解密失败或未启用加密
-    pub fn decrypt_state(&self, encrypted_data: &str) -> Result<String, String> {
+    /// * `Err(DiscordError)` - 解密失败或未启用加密
+    pub fn decrypt_state(&self, encrypted_data: &str) -> Result<String, DiscordError> {
         if let Some(ref crypto) = self.crypto {
-            crypto
-                .decrypt(encrypted_data)
-                .map_err(|e| format!("解密state数据失败: {}", e))
+            // 按密文中的密钥ID选择密钥，轮换前加密的内容同样可以解密
+            crypto.decrypt_field(encrypted_data, STATE_FIELD).map_err(DiscordError::Decrypt)
         } else {
-            Err("加密未启用".to_string())
+            Err(DiscordError::EncryptionDisabled)
         }
     }
 }
 
+impl PresenceSink for DiscordManager {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    fn rate_limit(&self) -> RateLimit {
+        self.rate_limit
+    }
+
+    fn poll(&mut self) {
+        DiscordManager::poll(self);
+    }
+
+    /// 连接已断开时放弃这次更新：`DiscordManager` 已记录该状态，重连后会自动发布；
+    /// Discord拒绝或加密失败时放弃这次更新，重试也不会成功；其他错误保留更新，稍后重试
+    fn publish(&mut self, presence: Option<&Presence>) -> SinkResult {
+        let result = match presence {
+            Some(presence) => self.update_presence(presence),
+            None => self.clear_activity(),
+        };
+        match result {
+            UpdateResult::Success => SinkResult::Sent,
+            UpdateResult::Skipped => SinkResult::Skipped,
+            UpdateResult::Failed(e) if e.is_disconnect() || e.is_permanent() => {
+                SinkResult::Dropped(e.into())
+            },
+            UpdateResult::Failed(e) => SinkResult::Retry(e.into()),
+        }
+    }
+
+    fn reload(&mut self, config: &Config) -> Result<(), Error> {
+        Ok(DiscordManager::reload(self, config)?)
+    }
+
+    fn shutdown(self: Box<Self>, timeout: Duration) -> Result<(), Error> {
+        Ok(DiscordManager::shutdown(*self, timeout)?)
+    }
+}
+
+/// 将活动状态转换为 `SET_ACTIVITY` 命令中的 `activity` 对象
+///
+/// 为空的字段不会出现在结果中
+pub fn activity_payload(presence: &Presence) -> Value {
+    fn insert(map: &mut Map<String, Value>, key: &str, value: &Option<String>) {
+        if let Some(value) = value {
+            map.insert(key.to_string(), Value::String(value.clone()));
+        }
+    }
+
+    let mut activity = Map::new();
+    if let Some(activity_type) = presence.activity_type {
+        activity.insert("type".to_string(), json!(activity_type.code()));
+    }
+    insert(&mut activity, "details", &presence.details);
+    insert(&mut activity, "state", &presence.state);
+
+    let mut timestamps = Map::new();
+    if let Some(start) = presence.start_timestamp {
+        timestamps.insert("start".to_string(), json!(start));
+    }
+    if let Some(end) = presence.end_timestamp {
+        timestamps.insert("end".to_string(), json!(end));
+    }
+    if !timestamps.is_empty() {
+        activity.insert("timestamps".to_string(), Value::Object(timestamps));
+    }
+
+    let mut assets = Map::new();
+    insert(&mut assets, "large_image", &presence.large_image);
+    insert(&mut assets, "large_text", &presence.large_text);
+    insert(&mut assets, "small_image", &presence.small_image);
+    insert(&mut assets, "small_text", &presence.small_text);
+    if !assets.is_empty() {
+        activity.insert("assets".to_string(), Value::Object(assets));
+    }
+
+    if !presence.buttons.is_empty() {
+        let buttons = presence
+            .buttons
+            .iter()
+            .map(|b| json!({ "label": b.label, "url": b.url }))
+            .collect();
+        activity.insert("buttons".to_string(), Value::Array(buttons));
+    }
+
+    activity.insert("instance".to_string(), Value::Bool(false));
+    Value::Object(activity)
+}
+
 /// Discord Rich Presence更新结果
 #[derive(Debug)]
+#[must_use]
 pub enum UpdateResult {
     /// 成功更新
     Success,
-    /// 跳过更新（窗口未变化）
+    /// 跳过更新（与当前已发布的状态相同）
     Skipped,
     /// 更新失败
-    Failed(String),
+    Failed(DiscordError),
+}
+
+impl From<Result<(), DiscordError>> for UpdateResult {
+    fn from(result: Result<(), DiscordError>) -> Self {
+        match result {
+            Ok(()) => UpdateResult::Success,
+            Err(e) => UpdateResult::Failed(e),
+        }
+    }
 }
 
 impl UpdateResult {
@@ -150,11 +1001,471 @@ impl UpdateResult {
     pub fn is_failed(&self) -> bool {
         matches!(self, UpdateResult::Failed(_))
     }
+
+    /// 转换为 `Result`，跳过的更新视为成功
+    pub fn into_result(self) -> Result<(), DiscordError> {
+        match self {
+            UpdateResult::Success | UpdateResult::Skipped => Ok(()),
+            UpdateResult::Failed(e) => Err(e),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::category::Category;
+    use crate::presence::ActivityType;
+    use crate::rules::Scope;
+
+    #[test]
+    fn test_activity_payload() {
+        let presence = Presence::new("Code", "main.rs", 1700000000);
+        let payload = activity_payload(&presence);
+        assert_eq!(payload["details"], "Code");
+        assert_eq!(payload["state"], "main.rs");
+        assert_eq!(payload["timestamps"], json!({ "start": 1700000000 }));
+        assert_eq!(payload["assets"]["large_image"], "windows");
+        assert!(payload.get("buttons").is_none());
+        assert!(payload.get("type").is_none());
+
+        let listening = Presence { activity_type: Some(ActivityType::Listening), ..presence };
+        assert_eq!(activity_payload(&listening)["type"], 2);
+
+        let empty = activity_payload(&Presence::default());
+        assert_eq!(empty, json!({ "instance": false }));
+    }
+
+    #[cfg(unix)]
+    fn connect_mock(server: &crate::mock_ipc::MockDiscordServer, key: Option<&str>) -> DiscordManager {
+        let mut config = Config::new(42, 5);
+        config.encryption_key = key.map(str::to_string);
+        config.ipc_path = Some(server.path().to_path_buf());
+        config.reconnect.initial_delay = Duration::ZERO;
+        DiscordManager::connect(&config).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_presence_with_mock_server() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        assert_eq!(server.handshakes()[0]["client_id"], "42");
+
+        let info = WindowInfo::parse("main.rs - Visual Studio Code");
+        discord.update_activity(&info, "main.rs - Visual Studio Code").into_result().unwrap();
+        let activity = server.last_activity().unwrap();
+        assert_eq!(activity["details"], info.app_name);
+        assert_eq!(activity["state"], "main.rs - Visual Studio Code");
+        assert_eq!(activity["timestamps"]["start"], discord.start_time());
+
+        discord.clear_activity().into_result().unwrap();
+        assert_eq!(server.last_activity(), Some(Value::Null));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_presence_encrypts_state() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let key = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, Some(key));
+
+        discord.update_presence(&Presence::new("Code", "secret.txt", 1)).into_result().unwrap();
+        let state = server.last_activity().unwrap()["state"].as_str().unwrap().to_string();
+        assert_ne!(state, "secret.txt");
+        assert_eq!(discord.decrypt_state(&state).unwrap(), "secret.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_presence_reports_mock_faults() {
+        use crate::mock_ipc::{Fault, MockDiscordServer};
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        let presence = Presence::new("Code", "main.rs", 1);
+
+        server.inject_fault(Fault::Error { code: 4000, message: "bad activity".into() });
+        let UpdateResult::Failed(err) = discord.update_presence(&presence) else {
+            panic!("rejected activity should fail");
+        };
+        assert!(err.is_rejected() && err.is_permanent() && !err.is_disconnect());
+        assert!(err.to_string().contains("bad activity"));
+
+        // 作为输出时放弃被拒绝的更新，不再重试
+        server.inject_fault(Fault::Error { code: 4000, message: "bad activity".into() });
+        let presence = Presence::new("Code", "lib.rs", 1);
+        assert!(matches!(PresenceSink::publish(&mut discord, Some(&presence)), SinkResult::Dropped(_)));
+
+        server.inject_fault(Fault::Disconnect);
+        assert!(discord.update_presence(&presence).is_failed());
+        assert!(!discord.is_connected());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_buttons_in_ipc_payload() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut config = Config::from_env_str(
+            "DISCORD_APP_ID=42\n\
+             BUTTON_1=Open site|https://{domain}\n\
+             BUTTON_2=Search|https://duckduckgo.com/?q={details}\n",
+        )
+        .unwrap();
+        config.ipc_path = Some(server.path().to_path_buf());
+        let mut discord = DiscordManager::connect(&config).unwrap();
+
+        let title = "Rust docs - docs.rs - Firefox";
+        discord.update_activity(&WindowInfo::parse(title), title).into_result().unwrap();
+        assert_eq!(
+            server.last_activity().unwrap(),
+            json!({
+                "details": "Firefox",
+                "state": title,
+                "timestamps": { "start": discord.start_time() },
+                "assets": { "large_image": "firefox", "large_text": Msg::CategoryBrowsing.text() },
+                "buttons": [
+                    { "label": "Open site", "url": "https://docs.rs" },
+                    { "label": "Search", "url": "https://duckduckgo.com/?q=Rust%20docs%20-%20docs.rs" },
+                ],
+                "instance": false,
+            })
+        );
+
+        // 没有域名时第一个按钮被跳过
+        let title = "notes.txt - Notepad";
+        discord.update_activity(&WindowInfo::parse(title), title).into_result().unwrap();
+        assert_eq!(
+            server.last_activity().unwrap()["buttons"],
+            json!([{ "label": "Search", "url": "https://duckduckgo.com/?q=notes.txt" }])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_title_placeholders_rejected_when_encrypted() {
+        use crate::config::ConfigError;
+        use crate::mock_ipc::MockDiscordServer;
+
+        let key = "11".repeat(32);
+        for (setting, field, placeholder) in [
+            ("BUTTON_2=Search|https://duckduckgo.com/?q={details}", "BUTTON_2", "details"),
+            ("BUTTON_1@browsing={domain}|https://a.b", "BUTTON_1", "domain"),
+            ("DETAILS_TEMPLATE@coding={if workspace}In {workspace}{end}", "DETAILS_TEMPLATE", "workspace"),
+            ("LARGE_TEXT_EN={title|truncate:20}", "LARGE_TEXT", "title"),
+        ] {
+            let env = format!("DISCORD_APP_ID=42\nENCRYPTION_KEY={}\n{}", key, setting);
+            assert_eq!(
+                Config::from_env_str(&env).unwrap().validate(),
+                Err(ConfigError::TitleNotEncrypted { key: field.to_string(), placeholder })
+            );
+            // 不加密时可以使用
+            let plain = format!("DISCORD_APP_ID=42\n{}", setting);
+            assert_eq!(Config::from_env_str(&plain).unwrap().validate(), Ok(()));
+        }
+
+        // state 和不来自标题的占位符不受限制
+        let env = format!(
+            "DISCORD_APP_ID=42\nENCRYPTION_KEY={}\nSTATE_TEMPLATE={{title}}\n\
+             BUTTON_1=Open {{app}}|https://example.com/{{app}}",
+            key
+        );
+        let config = Config::from_env_str(&env).unwrap();
+        assert_eq!(config.validate(), Ok(()));
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut config = config;
+        config.ipc_path = Some(server.path().to_path_buf());
+        let mut discord = DiscordManager::connect(&config).unwrap();
+        let title = "Rust docs - docs.rs - Firefox";
+        discord.update_activity(&WindowInfo::parse(title), title).into_result().unwrap();
+        let activity = server.last_activity().unwrap();
+        assert_ne!(activity["state"], title);
+        assert_eq!(
+            activity["buttons"],
+            json!([{ "label": "Open Firefox", "url": "https://example.com/Firefox" }])
+        );
+    }
+
+    #[test]
+    fn test_discord_user_from_ready() {
+        let ready = json!({
+            "v": 1,
+            "user": { "id": "123", "username": "neo", "global_name": null, "avatar": "abc" },
+        });
+        let user = DiscordUser::from_ready(&ready).unwrap();
+        assert_eq!(user.id, "123");
+        assert_eq!(user.display_name(), "neo");
+        assert_eq!(user.avatar_url().unwrap(), "https://cdn.discordapp.com/avatars/123/abc.png");
+        assert_eq!(
+            user.lanyard_url("https://api.lanyard.rest"),
+            "https://api.lanyard.rest/v1/users/123"
+        );
+        assert_eq!(
+            user.lanyard_url("http://127.0.0.1:4001/"),
+            "http://127.0.0.1:4001/v1/users/123"
+        );
+
+        assert!(DiscordUser::from_ready(&json!({ "v": 1 })).is_none());
+        assert!(DiscordUser::from_ready(&json!({ "user": { "username": "x" } })).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connected_user_and_mismatch() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        server.set_user(json!({ "id": "42", "username": "mock", "global_name": "Mock" }));
+
+        let mut config = Config::new(1, 5);
+        config.ipc_path = Some(server.path().to_path_buf());
+        let discord = DiscordManager::connect(&config).unwrap();
+        assert_eq!(discord.connected_user().unwrap().display_name(), "Mock");
+        assert_eq!(discord.lanyard_user_id(), Some("42"));
+        assert_eq!(discord.lanyard_url().unwrap(), "https://api.lanyard.rest/v1/users/42");
+        assert_eq!(discord.user_mismatch(), None);
+
+        config.lanyard_user_id = Some("7".to_string());
+        config.lanyard_api_url = "http://127.0.0.1:4001".to_string();
+        let discord = DiscordManager::connect(&config).unwrap();
+        assert_eq!(discord.lanyard_user_id(), Some("7"));
+        assert_eq!(discord.lanyard_url().unwrap(), "http://127.0.0.1:4001/v1/users/7");
+        assert_eq!(discord.user_mismatch(), Some(("7", "42")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_switch_application_per_category() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut config = Config::new(1, 5);
+        config.ipc_path = Some(server.path().to_path_buf());
+        config.app_ids.set(Scope::Category(Category::Coding), 2);
+        let mut discord = DiscordManager::connect(&config).unwrap();
+        let events = discord.subscribe();
+
+        let mut show = |title: &str| {
+            let presence = discord.presence_for(title, &WindowInfo::parse(title));
+            discord.update_presence(&presence).into_result().unwrap();
+        };
+        show("News - Firefox");
+        show("main.rs - Visual Studio Code");
+        show("lib.rs - Visual Studio Code");
+        assert_eq!(discord.application_id(), 2);
+
+        let client_ids: Vec<_> = server.handshakes().iter().map(|h| h["client_id"].clone()).collect();
+        assert_eq!(client_ids, vec![json!("1"), json!("2")]);
+        // 切换前先清除旧应用下的状态
+        let activities = server.activities();
+        assert_eq!(activities.len(), 4);
+        assert_eq!(activities[1], Value::Null);
+        assert_eq!(activities[2]["state"], "main.rs - Visual Studio Code");
+        assert!(events
+            .try_iter()
+            .any(|e| e == ConnectionEvent::ApplicationSwitched { from: 1, to: 2 }));
+
+        // 离开状态留在当前应用
+        let away = discord.away_presence();
+        discord.update_presence(&away).into_result().unwrap();
+        assert_eq!(server.handshakes().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shutdown_clears_presence() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        discord.update_presence(&Presence::new("Code", "main.rs", 1)).into_result().unwrap();
+        discord.shutdown(Duration::from_secs(2)).unwrap();
+        assert_eq!(server.last_activity(), Some(Value::Null));
+
+        // Discord不响应时在超时后返回
+        let discord = connect_mock(&server, None);
+        server.set_response_delay(Duration::from_secs(2));
+        let start = Instant::now();
+        assert!(discord.shutdown(Duration::from_millis(100)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reload_applies_new_settings() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        let info = WindowInfo::parse("main.rs - Visual Studio Code");
+
+        let env = format!(
+            "DISCORD_APP_ID=42\nDISCORD_IPC_PATH={}\nDETAILS_TEMPLATE=Editing {{title}}",
+            server.path().display()
+        );
+        discord.reload(&Config::from_env_str(&env).unwrap()).unwrap();
+        let presence = discord.presence_for(&info.app_name, &info);
+        assert_eq!(presence.details.as_deref(), Some("Editing Visual Studio Code"));
+        assert!(discord.is_connected());
+
+        // 无效的密钥不会改变当前设置
+        let mut config = Config::new(42, 5);
+        config.encryption_key = Some("xyz".to_string());
+        assert!(discord.reload(&config).is_err());
+        assert!(!discord.is_encryption_enabled());
+    }
+
+    #[test]
+    fn test_client_build_from_ready() {
+        let ready = |endpoint: &str| json!({ "config": { "api_endpoint": endpoint } });
+        assert_eq!(ClientBuild::from_ready(&ready("//discord.com/api")), ClientBuild::Stable);
+        assert_eq!(ClientBuild::from_ready(&ready("//ptb.discord.com/api")), ClientBuild::Ptb);
+        assert_eq!(ClientBuild::from_ready(&ready("https://canary.discord.com/api")), ClientBuild::Canary);
+        assert_eq!(ClientBuild::from_ready(&json!({ "v": 1 })), ClientBuild::Unknown);
+        assert_eq!(ClientSelector::parse("all"), Ok(ClientSelector::All));
+        assert_eq!(ClientSelector::parse(" any "), Ok(ClientSelector::Any));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_select_client_instance() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let stable = MockDiscordServer::start().unwrap();
+        stable.set_user(json!({ "id": "1", "username": "main" }));
+        let canary = MockDiscordServer::start_at(&stable.dir().join("discord-ipc-1")).unwrap();
+        canary.set_api_endpoint("//canary.discord.com/api");
+        canary.set_user(json!({ "id": "2", "username": "alt" }));
+
+        let mut config = Config::new(1, 5);
+        config.ipc_path = Some(stable.dir().to_path_buf());
+        let presence = Presence::new("Code", "main.rs", 1);
+        let publish = |selector: ClientSelector| {
+            stable.clear_records();
+            canary.clear_records();
+            let config = Config { discord_client: selector, ..config.clone() };
+            let mut discord = DiscordManager::connect(&config).unwrap();
+            discord.update_presence(&presence).into_result().unwrap();
+            let instances = discord.instances();
+            (instances, stable.activities().len(), canary.activities().len())
+        };
+
+        let (instances, to_stable, to_canary) = publish(ClientSelector::Build(ClientBuild::Canary));
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].build, ClientBuild::Canary);
+        assert_eq!(instances[0].path.as_deref(), Some(canary.path()));
+        assert_eq!((to_stable, to_canary), (0, 1));
+
+        let (instances, to_stable, to_canary) = publish(ClientSelector::User("1".to_string()));
+        assert_eq!(instances[0].user.as_ref().unwrap().id, "1");
+        assert_eq!((to_stable, to_canary), (1, 0));
+
+        // 没有匹配的客户端时使用第一个
+        let (instances, ..) = publish(ClientSelector::Build(ClientBuild::Ptb));
+        assert_eq!(instances[0].build, ClientBuild::Stable);
+
+        let (instances, to_stable, to_canary) = publish(ClientSelector::All);
+        assert_eq!(instances.len(), 2);
+        assert_eq!((to_stable, to_canary), (1, 1));
+    }
+
+    #[test]
+    fn test_reconnect_policy_backoff() {
+        let policy = ReconnectPolicy { jitter: 0.0, ..ReconnectPolicy::default() };
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(4), Duration::from_secs(8));
+        assert_eq!(policy.delay(10), Duration::from_secs(60));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(60));
+
+        let policy = ReconnectPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.delay(3);
+            assert!(delay >= Duration::from_millis(3200) && delay <= Duration::from_millis(4800));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reconnect_republishes_last_presence() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        let events = discord.subscribe();
+
+        discord.update_presence(&Presence::new("Code", "main.rs", 1)).into_result().unwrap();
+        server.disconnect_all();
+
+        // 发送时发现连接已断开，状态被记录，重连后自动发布
+        let presence = Presence::new("Code", "lib.rs", 1);
+        let _ = discord.update_presence(&presence);
+        assert!(discord.poll());
+
+        assert_eq!(server.handshakes().len(), 2);
+        assert_eq!(server.last_activity().unwrap()["state"], "lib.rs");
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(matches!(events[0], ConnectionEvent::Disconnected { .. }));
+        assert_eq!(events[1], ConnectionEvent::Connected { attempts: 1 });
+        assert_eq!(events[2], ConnectionEvent::Republished);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_new_without_discord_keeps_retrying() {
+        let mut config = Config::new(42, 5);
+        config.ipc_path = Some(std::path::PathBuf::from("/nonexistent/discord-ipc-0"));
+        config.reconnect = ReconnectPolicy { initial_delay: Duration::ZERO, jitter: 0.0, ..ReconnectPolicy::default() };
+        let mut discord = DiscordManager::new(&config).unwrap();
+        let events = discord.subscribe();
+        assert!(!discord.is_connected());
+
+        assert!(!discord.poll());
+        let result = discord.update_presence(&Presence::new("Code", "main.rs", 1));
+        assert!(matches!(result, UpdateResult::Failed(DiscordError::NotConnected)));
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(matches!(events[0], ConnectionEvent::ReconnectFailed { attempt: 1, .. }));
+        // update_presence 也会推进状态机，因此这里已经是第三次
+        assert!(matches!(discord.state(), ConnectionState::Reconnecting { attempt: 3, .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_presence_timestamps_follow_mode() {
+        let mut config = Config::from_env_str(
+            "DISCORD_APP_ID=42\nTIMESTAMP_MODE=window\nTIMESTAMP_MODE@media=none\n\
+             STATE_TEMPLATE@coding=for {elapsed}\n",
+        )
+        .unwrap();
+        config.ipc_path = Some(std::path::PathBuf::from("/nonexistent/discord-ipc-0"));
+        let mut discord = DiscordManager::new(&config).unwrap();
+
+        let title = "main.rs - Visual Studio Code";
+        let presence = discord.presence_for(title, &WindowInfo::parse(title));
+        assert!(presence.start_timestamp.unwrap() >= discord.start_time());
+        assert_eq!(presence.state.as_deref(), Some("for 0s"));
+
+        let title = "Song - Spotify";
+        let presence = discord.presence_for(title, &WindowInfo::parse(title));
+        assert_eq!((presence.start_timestamp, presence.end_timestamp), (None, None));
+        assert!(activity_payload(&presence).get("timestamps").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connect_fails_without_server() {
+        let mut config = Config::new(42, 5);
+        config.ipc_path = Some(std::path::PathBuf::from("/nonexistent/discord-ipc-0"));
+        let err = DiscordManager::connect(&config).err().unwrap();
+        assert!(err.is_not_running());
+    }
 
     #[test]
     fn test_update_result() {
@@ -162,8 +1473,27 @@ mod tests {
         assert!(success.is_success());
         assert!(!success.is_failed());
 
-        let failed = UpdateResult::Failed("test error".to_string());
+        let failed = UpdateResult::Failed(DiscordError::NotConnected);
         assert!(!failed.is_success());
         assert!(failed.is_failed());
+        assert!(failed.into_result().unwrap_err().is_disconnect());
+        assert!(UpdateResult::Skipped.into_result().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unchanged_presence_is_skipped() {
+        use crate::mock_ipc::MockDiscordServer;
+
+        let server = MockDiscordServer::start().unwrap();
+        let mut discord = connect_mock(&server, None);
+        let presence = Presence::new("Code", "main.rs", 1);
+        assert!(discord.update_presence(&presence).is_success());
+        assert!(matches!(discord.update_presence(&presence), UpdateResult::Skipped));
+        assert_eq!(server.activities().len(), 1);
+
+        // 清除后再次发送同样的状态不会被跳过
+        discord.clear_activity().into_result().unwrap();
+        assert!(discord.update_presence(&presence).is_success());
     }
 }
@@ -0,0 +1,156 @@
+//! 错误类型模块
+//!
+//! 各模块有自己的错误类型，`Error` 将它们汇总，方便在应用层用 `?` 统一传递；
+//! 通过 `source()` 可以取得底层错误，通过匹配变体区分错误来源
+
+use std::fmt;
+use std::io;
+
+use crate::config::ConfigError;
+use crate::crypto::CryptoError;
+use crate::discord::DiscordError;
+use crate::ipc::IpcError;
+use crate::lanyard::LanyardError;
+use crate::script::ScriptError;
+use crate::server::ServerError;
+use crate::viewer::ViewerError;
+use crate::window::WindowError;
+
+/// 库的统一错误类型
+#[derive(Debug)]
+pub enum Error {
+    /// 配置无效
+    Config(ConfigError),
+    /// Discord RPC错误
+    Discord(DiscordError),
+    /// Discord IPC协议错误
+    Ipc(IpcError),
+    /// Lanyard接口错误
+    Lanyard(LanyardError),
+    /// 状态服务错误
+    Server(ServerError),
+    /// 查看端解密错误
+    Viewer(ViewerError),
+    /// 加密/解密错误
+    Crypto(CryptoError),
+    /// 获取活动窗口失败
+    Window(WindowError),
+    /// 状态脚本错误
+    Script(ScriptError),
+    /// 读写文件失败
+    Io(io::Error),
+}
+
+/// 使用统一错误类型的 `Result`
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(e) => e.fmt(f),
+            Error::Discord(e) => e.fmt(f),
+            Error::Ipc(e) => e.fmt(f),
+            Error::Lanyard(e) => e.fmt(f),
+            Error::Server(e) => e.fmt(f),
+            Error::Viewer(e) => e.fmt(f),
+            Error::Crypto(e) => e.fmt(f),
+            Error::Window(e) => e.fmt(f),
+            Error::Script(e) => e.fmt(f),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(e) => Some(e),
+            Error::Discord(e) => Some(e),
+            Error::Ipc(e) => Some(e),
+            Error::Lanyard(e) => Some(e),
+            Error::Server(e) => Some(e),
+            Error::Viewer(e) => Some(e),
+            Error::Crypto(e) => Some(e),
+            Error::Window(e) => Some(e),
+            Error::Script(e) => Some(e),
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<DiscordError> for Error {
+    fn from(e: DiscordError) -> Self {
+        Error::Discord(e)
+    }
+}
+
+impl From<IpcError> for Error {
+    fn from(e: IpcError) -> Self {
+        Error::Ipc(e)
+    }
+}
+
+impl From<LanyardError> for Error {
+    fn from(e: LanyardError) -> Self {
+        Error::Lanyard(e)
+    }
+}
+
+impl From<ServerError> for Error {
+    fn from(e: ServerError) -> Self {
+        Error::Server(e)
+    }
+}
+
+impl From<ViewerError> for Error {
+    fn from(e: ViewerError) -> Self {
+        Error::Viewer(e)
+    }
+}
+
+impl From<CryptoError> for Error {
+    fn from(e: CryptoError) -> Self {
+        Error::Crypto(e)
+    }
+}
+
+impl From<WindowError> for Error {
+    fn from(e: WindowError) -> Self {
+        Error::Window(e)
+    }
+}
+
+impl From<ScriptError> for Error {
+    fn from(e: ScriptError) -> Self {
+        Error::Script(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_sources_are_preserved() {
+        let error = Error::from(DiscordError::Connect(IpcError::NotRunning));
+        assert!(matches!(error, Error::Discord(ref e) if e.is_not_running()));
+        let discord = error.source().unwrap();
+        assert!(discord.source().unwrap().downcast_ref::<IpcError>().is_some());
+
+        let error = Error::from(ConfigError::MissingVar("DISCORD_APP_ID"));
+        assert_eq!(error.to_string(), ConfigError::MissingVar("DISCORD_APP_ID").to_string());
+    }
+}
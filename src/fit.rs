@@ -0,0 +1,370 @@
+//! 字段长度适配模块
+//!
+//! Discord会拒绝超过长度上限或短于2个字符的字段，并且不会给出明确提示。
+//! 本模块在发送前按字素簇（grapheme cluster）边界截断过长的字段，
+//! 保证不会切断emoji或组合字符，并为过短的字段补齐长度。
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::presence::{Button, Presence};
+
+/// 文本字段的最小字符数
+pub const MIN_FIELD_CHARS: usize = 2;
+
+/// 过短字段的默认填充字符（零宽空格）
+pub const DEFAULT_PAD_CHAR: char = '\u{200b}';
+
+/// Rich Presence中有长度限制的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Details,
+    State,
+    LargeImage,
+    LargeText,
+    SmallImage,
+    SmallText,
+    ButtonLabel,
+    ButtonUrl,
+}
+
+impl Field {
+    /// 字段允许的最大字节数
+    pub fn max_bytes(&self) -> usize {
+        match self {
+            Field::Details | Field::State | Field::LargeText | Field::SmallText => 128,
+            Field::LargeImage | Field::SmallImage => 256,
+            Field::ButtonLabel => 32,
+            Field::ButtonUrl => 512,
+        }
+    }
+
+    /// 字段是否可以截断
+    ///
+    /// 资源键和链接截断后没有意义，超长时直接丢弃
+    pub fn is_truncatable(&self) -> bool {
+        !matches!(self, Field::LargeImage | Field::SmallImage | Field::ButtonUrl)
+    }
+}
+
+/// 截断时使用的省略号样式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ellipsis {
+    /// `…`
+    Unicode,
+    /// `...`
+    Ascii,
+    /// 不添加省略号
+    None,
+    /// 自定义文本
+    Custom(String),
+}
+
+impl Ellipsis {
+    /// 从配置字符串解析（不区分大小写），无法识别的值视为自定义文本
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "unicode" => Ellipsis::Unicode,
+            "ascii" => Ellipsis::Ascii,
+            "none" | "" => Ellipsis::None,
+            _ => Ellipsis::Custom(value.to_string()),
+        }
+    }
+
+    /// 省略号文本
+    pub fn as_str(&self) -> &str {
+        match self {
+            Ellipsis::Unicode => "…",
+            Ellipsis::Ascii => "...",
+            Ellipsis::None => "",
+            Ellipsis::Custom(s) => s,
+        }
+    }
+}
+
+/// 截断位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncateSide {
+    /// 保留开头，截掉结尾
+    End,
+    /// 保留结尾，截掉开头
+    Start,
+    /// 保留开头和结尾，截掉中间
+    Middle,
+}
+
+impl TruncateSide {
+    /// 从配置字符串解析（`end` / `start` / `middle`）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "end" => Some(TruncateSide::End),
+            "start" => Some(TruncateSide::Start),
+            "middle" => Some(TruncateSide::Middle),
+            _ => None,
+        }
+    }
+}
+
+/// 字段适配选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FitOptions {
+    /// 省略号样式
+    pub ellipsis: Ellipsis,
+    /// 截断位置
+    pub side: TruncateSide,
+    /// 过短字段的填充字符
+    pub pad_char: char,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        Self {
+            ellipsis: Ellipsis::Unicode,
+            side: TruncateSide::End,
+            pad_char: DEFAULT_PAD_CHAR,
+        }
+    }
+}
+
+/// 按字素簇边界将文本截断到指定字节数以内
+///
+/// # 示例
+/// ```
+/// use active_window_info_to_lanyard_lib::fit::{truncate, FitOptions};
+///
+/// let text = truncate("hello world", 8, &FitOptions::default());
+/// assert_eq!(text, "hello…");
+/// ```
+pub fn truncate(value: &str, max_bytes: usize, options: &FitOptions) -> String {
+    if value.len() <= max_bytes {
+        return value.to_string();
+    }
+
+    let mut ellipsis = options.ellipsis.as_str();
+    if ellipsis.len() >= max_bytes {
+        ellipsis = "";
+    }
+    let budget = max_bytes - ellipsis.len();
+    let graphemes: Vec<&str> = value.graphemes(true).collect();
+
+    match options.side {
+        TruncateSide::End => {
+            let head = take_front(&graphemes, budget);
+            format!("{}{}", head.trim_end(), ellipsis)
+        },
+        TruncateSide::Start => {
+            let tail = take_back(&graphemes, budget);
+            format!("{}{}", ellipsis, tail.trim_start())
+        },
+        TruncateSide::Middle => {
+            let head = take_front(&graphemes, budget - budget / 2);
+            let tail = take_back(&graphemes, budget - head.len());
+            format!("{}{}{}", head.trim_end(), ellipsis, tail.trim_start())
+        },
+    }
+}
+
+/// 从开头取尽可能多的字素簇，总字节数不超过 `budget`
+fn take_front(graphemes: &[&str], budget: usize) -> String {
+    let mut used = 0;
+    graphemes
+        .iter()
+        .take_while(|g| {
+            used += g.len();
+            used <= budget
+        })
+        .copied()
+        .collect()
+}
+
+/// 从结尾取尽可能多的字素簇，总字节数不超过 `budget`
+fn take_back(graphemes: &[&str], budget: usize) -> String {
+    let mut used = 0;
+    let mut tail: Vec<&str> = graphemes
+        .iter()
+        .rev()
+        .take_while(|g| {
+            used += g.len();
+            used <= budget
+        })
+        .copied()
+        .collect();
+    tail.reverse();
+    tail.concat()
+}
+
+/// 将单个字段适配到Discord的限制内
+///
+/// # 返回值
+/// * `Some(String)` - 适配后的值
+/// * `None` - 值为空，或者是无法截断的字段且超长，应省略该字段
+pub fn fit_field(field: Field, value: &str, options: &FitOptions) -> Option<String> {
+    if value.trim().is_empty() {
+        return None;
+    }
+
+    if value.len() > field.max_bytes() {
+        if !field.is_truncatable() {
+            return None;
+        }
+        return Some(pad(&truncate(value, field.max_bytes(), options), field.max_bytes(), options));
+    }
+
+    if field.is_truncatable() {
+        Some(pad(value, field.max_bytes(), options))
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// 为过短的文本补齐到最小字符数，补齐后不超过 `max_bytes`
+fn pad(value: &str, max_bytes: usize, options: &FitOptions) -> String {
+    let mut result = value.to_string();
+    while result.chars().count() < MIN_FIELD_CHARS
+        && result.len() + options.pad_char.len_utf8() <= max_bytes
+    {
+        result.push(options.pad_char);
+    }
+    result
+}
+
+/// 将活动状态的所有字段适配到Discord的限制内
+pub fn fit_presence(presence: &Presence, options: &FitOptions) -> Presence {
+    let fit = |field: Field, value: &Option<String>| {
+        value.as_deref().and_then(|v| fit_field(field, v, options))
+    };
+
+    Presence {
+        details: fit(Field::Details, &presence.details),
+        state: fit(Field::State, &presence.state),
+        large_image: fit(Field::LargeImage, &presence.large_image),
+        large_text: fit(Field::LargeText, &presence.large_text),
+        small_image: fit(Field::SmallImage, &presence.small_image),
+        small_text: fit(Field::SmallText, &presence.small_text),
+        buttons: presence
+            .buttons
+            .iter()
+            .filter_map(|button| {
+                Some(Button {
+                    label: fit_field(Field::ButtonLabel, &button.label, options)?,
+                    url: fit_field(Field::ButtonUrl, &button.url, options)?,
+                })
+            })
+            .take(2)
+            .collect(),
+        ..presence.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_keeps_short_values() {
+        let options = FitOptions::default();
+        assert_eq!(truncate("short", 128, &options), "short");
+    }
+
+    #[test]
+    fn test_truncate_on_grapheme_boundary() {
+        let options = FitOptions::default();
+        // 👨‍👩‍👧 是一个由多个码点组成的字素簇（18字节）
+        let family = "👨‍👩‍👧";
+        let text = format!("ab{}{}", family, family);
+        let result = truncate(&text, 25, &options);
+        assert_eq!(result, format!("ab{}…", family));
+        assert!(result.len() <= 25);
+
+        // 中文字符为3字节，不能被切断
+        let result = truncate("一二三四五", 10, &options);
+        assert_eq!(result, "一二…");
+    }
+
+    #[test]
+    fn test_truncate_sides_and_ellipsis() {
+        let mut options = FitOptions {
+            ellipsis: Ellipsis::Ascii,
+            side: TruncateSide::Start,
+            ..FitOptions::default()
+        };
+        assert_eq!(truncate("0123456789", 8, &options), "...56789");
+
+        options.side = TruncateSide::Middle;
+        assert_eq!(truncate("0123456789", 8, &options), "012...89");
+
+        options.ellipsis = Ellipsis::None;
+        options.side = TruncateSide::End;
+        assert_eq!(truncate("0123456789", 4, &options), "0123");
+    }
+
+    #[test]
+    fn test_fit_field_limits() {
+        let options = FitOptions::default();
+        let long = "x".repeat(300);
+
+        let state = fit_field(Field::State, &long, &options).unwrap();
+        assert_eq!(state.len(), 128);
+        assert!(state.ends_with('…'));
+
+        let label = fit_field(Field::ButtonLabel, &long, &options).unwrap();
+        assert!(label.len() <= 32);
+
+        assert_eq!(fit_field(Field::LargeImage, &long, &options), None);
+        assert_eq!(fit_field(Field::State, "   ", &options), None);
+    }
+
+    #[test]
+    fn test_fit_field_pads_short_values() {
+        let options = FitOptions::default();
+        let value = fit_field(Field::Details, "a", &options).unwrap();
+        assert_eq!(value.chars().count(), 2);
+        assert!(value.starts_with('a'));
+    }
+
+    #[test]
+    fn test_pad_stays_within_limit() {
+        let options = FitOptions::default();
+        // 补齐字符（3字节）放不下时保持原样
+        assert_eq!(pad("ab", 4, &options), "ab");
+        assert_eq!(pad("a", 3, &options), "a");
+        assert_eq!(pad("a", 4, &options), "a\u{200b}");
+
+        // 一个几乎占满上限的字素簇（大量组合符号）
+        let spam = format!("e{}", "\u{301}".repeat(200));
+        for field in [Field::State, Field::ButtonLabel] {
+            let value = fit_field(field, &spam, &options).unwrap();
+            assert!(value.len() <= field.max_bytes(), "{:?}: {}", field, value.len());
+        }
+    }
+
+    #[test]
+    fn test_ellipsis_parse() {
+        assert_eq!(Ellipsis::parse("Unicode"), Ellipsis::Unicode);
+        assert_eq!(Ellipsis::parse(" ASCII "), Ellipsis::Ascii);
+        assert_eq!(Ellipsis::parse("None"), Ellipsis::None);
+        assert_eq!(Ellipsis::parse(" "), Ellipsis::None);
+        assert_eq!(Ellipsis::parse(" ~"), Ellipsis::Custom(" ~".to_string()));
+    }
+
+    #[test]
+    fn test_fit_presence() {
+        let mut presence = Presence::new("X", &"y".repeat(200), 0);
+        presence.buttons = vec![
+            Button {
+                label: "Open a very long button label here".to_string(),
+                url: "https://example.com".to_string(),
+            },
+            Button {
+                label: "Bad".to_string(),
+                url: format!("https://example.com/{}", "a".repeat(600)),
+            },
+        ];
+
+        let fitted = fit_presence(&presence, &FitOptions::default());
+        assert_eq!(fitted.details.as_deref().map(|d| d.chars().count()), Some(2));
+        assert!(fitted.state.unwrap().len() <= 128);
+        assert_eq!(fitted.buttons.len(), 1);
+        assert!(fitted.buttons[0].label.len() <= 32);
+        assert_eq!(fitted.start_timestamp, Some(0));
+    }
+}
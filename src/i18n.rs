@@ -0,0 +1,691 @@
+//! 本地化模块
+//!
+//! 所有面向用户的文本（终端输出、错误信息、发送到Discord的固定文字）都集中在这里的消息目录中，
+//! 支持英文和中文两种语言。语言可以通过配置项 `LANGUAGE` 指定，
+//! 未指定时根据 `LC_ALL` / `LC_MESSAGES` / `LANG` 环境变量自动选择。
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    /// 英文
+    En,
+    /// 中文
+    Zh,
+}
+
+impl Locale {
+    /// 从语言代码解析，如 `en`、`zh`、`zh_CN.UTF-8`、`en-US`
+    pub fn parse(value: &str) -> Option<Self> {
+        let lang = value.trim().to_ascii_lowercase();
+        if lang.starts_with("zh") {
+            Some(Locale::Zh)
+        } else if lang.starts_with("en") || lang == "c" || lang.starts_with("c.") || lang == "posix" {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+
+    /// 根据环境变量检测系统语言
+    ///
+    /// 依次检查 `LC_ALL`、`LC_MESSAGES`、`LANG`，都无法识别时使用中文
+    pub fn from_system() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .filter(|value| !value.is_empty())
+            .find_map(|value| Self::parse(&value))
+            .unwrap_or(Locale::Zh)
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Locale::En => 0,
+            Locale::Zh => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Locale::En,
+            _ => Locale::Zh,
+        }
+    }
+}
+
+/// 当前语言（默认中文）
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(1);
+
+/// 设置当前语言
+pub fn set_locale(locale: Locale) {
+    CURRENT_LOCALE.store(locale.to_u8(), Ordering::Relaxed);
+}
+
+/// 获取当前语言
+pub fn locale() -> Locale {
+    Locale::from_u8(CURRENT_LOCALE.load(Ordering::Relaxed))
+}
+
+/// 定义消息目录：每条消息同时给出英文和中文文本，`{}` 为参数占位符
+macro_rules! catalog {
+    ($($(#[$doc:meta])* $name:ident => ($en:expr, $zh:expr),)*) => {
+        /// 消息目录中的消息
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Msg {
+            $($(#[$doc])* $name,)*
+        }
+
+        impl Msg {
+            /// 目录中的所有消息
+            pub const ALL: &'static [Msg] = &[$(Msg::$name,)*];
+
+            /// 获取指定语言的消息文本
+            pub fn text_in(self, locale: Locale) -> &'static str {
+                match (self, locale) {
+                    $(
+                        (Msg::$name, Locale::En) => $en,
+                        (Msg::$name, Locale::Zh) => $zh,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+catalog! {
+    // 启动与主循环
+    WelcomeBanner => (
+        "╔════════════════════════════════════════════════╗\n\
+         ║  Discord Activity Monitor                      ║\n\
+         ║  Active window → Discord Rich Presence         ║\n\
+         ║  Platforms: Windows & macOS                    ║\n\
+         ╚════════════════════════════════════════════════╝",
+        "╔════════════════════════════════════════════════╗\n\
+         ║  Discord Activity Monitor                     ║\n\
+         ║  活动窗口监控 → Discord Rich Presence         ║\n\
+         ║  支持: Windows & macOS                        ║\n\
+         ╚════════════════════════════════════════════════╝"
+    ),
+    WelcomeVersion => ("📝 Version: {}", "📝 版本: {}"),
+    WelcomeInterval => ("⏱️  Update interval: {} s", "⏱️  更新间隔: {} 秒"),
+    WelcomeAppId => ("🔧 Discord application ID: {}", "🔧 Discord应用ID: {}"),
+    WelcomeEncryptionOn => ("🔐 Encryption: enabled", "🔐 加密: 已启用"),
+    WelcomeEncryptionOff => ("🔓 Encryption: disabled", "🔓 加密: 未启用"),
+    WelcomeRedactionRules => ("🕶️  Redaction rules: {}", "🕶️  脱敏规则: {} 条"),
+    EncryptionEnabled => ("🔐 Encryption is enabled", "🔐 加密功能已启用"),
+    EncryptionDisabled => (
+        "⚠️  Encryption is disabled (titles are sent in plain text)\n   \
+         Hint: add ENCRYPTION_KEY to .env to enable encryption",
+        "⚠️  加密功能未启用（明文传输）\n   提示：在.env中添加ENCRYPTION_KEY可启用加密"
+    ),
+    EnvFileMissing => (
+        "❌ .env file not found\n   \
+         Please create a .env file in the project root\n   \
+         Format:\n   \
+         DISCORD_APP_ID=your application id\n   \
+         ENCRYPTION_KEY=your encryption key (optional)",
+        "❌ 未找到.env文件\n   \
+         请在项目根目录创建.env文件\n   \
+         格式:\n   \
+         DISCORD_APP_ID=你的应用ID\n   \
+         ENCRYPTION_KEY=你的加密密钥（可选）"
+    ),
+    EnvFileUnreadable => ("Unable to read .env file", "无法读取.env文件"),
+    ConfigLoadFailed => ("❌ Failed to load configuration: {}", "❌ 配置创建失败: {}"),
+    ConfigInvalid => ("❌ Invalid configuration: {}", "❌ 配置验证失败: {}"),
+    AppIdHint => (
+        "   Get an application ID at https://discord.com/developers/applications",
+        "   请在 https://discord.com/developers/applications 获取应用ID"
+    ),
+    RedactionInvalid => ("❌ Invalid redaction rule: {}", "❌ 脱敏规则无效: {}"),
+    DiscordConnected => ("✅ Connected to Discord RPC", "✅ 已连接到Discord RPC"),
+    DiscordInstance => ("   🖥️  Discord {} client: {} ({})", "   🖥️  Discord {} 客户端: {}（{}）"),
+    DiscordConnectFailed => ("❌ Failed to connect to Discord: {}", "❌ 连接Discord失败: {}"),
+    MonitoringStarted => ("👀 Watching the active window...\n", "👀 开始监控活动窗口...\n"),
+    WindowChanged => ("🔄 Window changed: {} [{}]", "🔄 窗口变化: {} [{}]"),
+    WindowLost => ("💤 No active window, showing as away", "💤 没有活动窗口，显示为离开"),
+    SinkUpdated => ("✅ {} presence updated", "✅ {}状态已更新"),
+    SinkUpdateFailed => ("⚠️  Failed to update {}: {}", "⚠️  更新{}失败: {}"),
+    SinkUpdateGaveUp => (
+        "⚠️  Gave up updating {} after {} attempts: {}",
+        "⚠️  更新{}失败 {} 次，放弃这次更新: {}"
+    ),
+    SinkUpdateDeferred => (
+        "⏳ {} is rate limited, the latest state will be sent in {} ms",
+        "⏳ {}已达到频率限制，最新状态将在 {} 毫秒后发送"
+    ),
+    SinkStats => ("📊 {} {}", "📊 {} {}"),
+    SinkReloadFailed => (
+        "⚠️  {} could not apply the new configuration: {}",
+        "⚠️  {}无法应用新配置: {}"
+    ),
+    DiscordShutdownTimeout => (
+        "Discord did not respond within {} ms while clearing the presence",
+        "清除活动状态时Discord在 {} 毫秒内没有响应"
+    ),
+    ShutdownStarted => ("👋 Shutting down, clearing the presence...", "👋 正在退出，清除活动状态..."),
+    ShutdownDone => ("✅ Presence cleared, bye", "✅ 状态已清除，再见"),
+    ShutdownFailed => ("⚠️  {} could not clear the presence cleanly: {}", "⚠️  {}未能正常清除状态: {}"),
+    SignalListenFailed => (
+        "⚠️  Cannot listen for signals ({}); Ctrl-C will not clear the presence",
+        "⚠️  无法监听系统信号（{}），Ctrl-C 退出时不会清除状态"
+    ),
+    ConfigReloaded => ("🔄 Configuration reloaded", "🔄 已重新加载配置"),
+    ConfigReloadFailed => (
+        "⚠️  Failed to reload configuration, keeping the current one: {}",
+        "⚠️  重新加载配置失败，继续使用当前配置: {}"
+    ),
+    SchedulerStats => (
+        "updates: {} submitted, {} sent, {} merged, {} dropped, {} failed",
+        "更新统计: 提交 {}，发送 {}，合并 {}，丢弃 {}，失败 {}"
+    ),
+
+    // 配置错误
+    ConfigMissingVar => ("{} is not set", "未设置{}"),
+    ConfigBadAppId => ("Unable to parse Discord application ID: {}", "无法解析Discord应用ID: {}"),
+    ConfigBadInterval => ("Unable to parse UPDATE_INTERVAL: {}", "无法解析UPDATE_INTERVAL: {}"),
+    ConfigAppIdZero => ("Discord application ID must not be 0", "Discord应用ID不能为0"),
+    ConfigIntervalTooShort => (
+        "Update interval must be at least 1 second",
+        "更新间隔不能小于1秒"
+    ),
+    ConfigKeyLength => (
+        "Encryption key must be 64 hex characters (32 bytes)",
+        "加密密钥必须是64个十六进制字符（32字节）"
+    ),
+    ConfigKeyNotHex => (
+        "Encryption key must only contain hex characters (0-9, a-f, A-F)",
+        "加密密钥必须只包含十六进制字符（0-9, a-f, A-F）"
+    ),
+    ConfigUnknownDetector => ("Unknown redaction detector: {}", "未知的脱敏检测器: {}"),
+    ConfigUnknownRedactAction => ("Unknown redaction action: {}", "未知的脱敏方式: {}"),
+    ConfigUnknownTruncateSide => ("Unknown truncation side: {}", "未知的截断位置: {}"),
+    ConfigUnknownLanguage => ("Unknown language: {}", "未知的语言: {}"),
+    ConfigBadNumber => ("Unable to parse {}: {}", "无法解析{}: {}"),
+    ConfigBadDuration => (
+        "{} must be a duration in milliseconds: {}",
+        "{} 应为以毫秒为单位的时长: {}"
+    ),
+    ConfigBadRule => ("Invalid regex in RULE_{}: {}", "RULE_{} 的正则无效: {}"),
+    ConfigUnknownScope => ("Unknown scope in {}", "{} 的作用范围无法识别"),
+    ConfigUnknownRule => ("{} refers to undefined rule {}", "{} 引用了未定义的规则 {}"),
+    ConfigBadBool => (
+        "{} must be true or false, got {}",
+        "{} 应为 true 或 false，实际为 {}"
+    ),
+    AssetKeyInvalid => (
+        "asset keys must be non-empty, contain no spaces and be at most {} bytes",
+        "资源键不能为空、不能包含空格，且不能超过 {} 字节"
+    ),
+    TemplateUnclosed => ("unclosed template tag {}", "模板标签 {} 未闭合"),
+    TemplateUnexpectedTag => ("unexpected {} without a matching {if}", "{} 没有对应的 {if}"),
+    TemplateUnknownPlaceholder => ("unknown placeholder {}", "未知的占位符 {}"),
+    TemplateUnknownFilter => ("unknown filter `{}`", "未知的过滤器 `{}`"),
+    TemplateBadArgument => ("invalid filter argument `{}`", "过滤器参数无效 `{}`"),
+    TimestampUnknownMode => (
+        "unknown timestamp mode `{}` (expected session, window, category, today, countdown:HH:MM or none)",
+        "未知的时间戳模式 `{}`（应为 session、window、category、today、countdown:HH:MM 或 none）"
+    ),
+    TimestampBadEndTime => ("invalid countdown end time `{}` (expected HH:MM)", "倒计时结束时间 `{}` 无效（应为 HH:MM）"),
+    ActivityTypeUnknown => (
+        "unknown activity type `{}` (expected playing, listening, watching or competing)",
+        "未知的活动类型 `{}`（应为 playing、listening、watching 或 competing）"
+    ),
+    ActivityTypeUnsupported => (
+        "activity type `{}` cannot be set over RPC and would be rejected by Discord",
+        "活动类型 `{}` 不能通过RPC设置，Discord会拒绝"
+    ),
+    ConfigUnknownClient => ("Unknown DISCORD_CLIENT: {} (expected any, stable, ptb, canary, all or a user ID)", "未知的 DISCORD_CLIENT：{}（应为 any、stable、ptb、canary、all 或用户ID）"),
+    ConfigRateLimitZero => ("{} must be at least 1", "{} 至少为 1"),
+    ConfigBadKvPrefix => (
+        "LANYARD_KV_PREFIX may only contain letters, digits and underscores: {}",
+        "LANYARD_KV_PREFIX 只能包含字母、数字和下划线: {}"
+    ),
+    ConfigKvNeedsUserId => (
+        "LANYARD_API_KEY requires LANYARD_USER_ID",
+        "设置 LANYARD_API_KEY 时必须同时设置 LANYARD_USER_ID"
+    ),
+    ConfigKvTitleNeedsKey => (
+        "LANYARD_KV_TITLE requires ENCRYPTION_KEY; window titles are never written in plain text",
+        "LANYARD_KV_TITLE 需要设置 ENCRYPTION_KEY，窗口标题不会以明文写入"
+    ),
+    ConfigBadValue => ("Invalid value for {}: {}", "{} 的值无效: {}"),
+    ConfigTitleNotEncrypted => (
+        "{} uses {}, which comes from the window title and would be sent unencrypted; only STATE_TEMPLATE is encrypted",
+        "{} 使用了来自窗口标题的 {}，该字段不会加密；只有 STATE_TEMPLATE 会被加密"
+    ),
+    ButtonMissingSeparator => ("expected `label|url`", "格式应为 `文字|链接`"),
+    ButtonLabelEmpty => ("button label is empty", "按钮文字为空"),
+    ButtonLabelTooLong => ("button label is longer than {} bytes", "按钮文字超过 {} 字节"),
+    ButtonBadUrl => (
+        "button URL must start with http:// or https://",
+        "按钮链接必须以 http:// 或 https:// 开头"
+    ),
+    ButtonUnknownPlaceholder => ("unknown placeholder {}", "未知的占位符 {}"),
+    RedactBadPattern => (
+        "Invalid regex in redaction rule {}: {}",
+        "脱敏规则 {} 的正则无效: {}"
+    ),
+
+    // 加密
+    CryptoEncryptionFailed => ("Encryption failed: {}", "加密失败: {}"),
+    CryptoDecryptionFailed => ("Decryption failed: {}", "解密失败: {}"),
+    CryptoInvalidKey => ("Invalid key: {}", "密钥无效: {}"),
+    CryptoBase64 => ("Base64 error: {}", "Base64错误: {}"),
+    CryptoKeyLength => (
+        "key must be 64 hex characters (32 bytes)",
+        "密钥必须是64个十六进制字符（32字节）"
+    ),
+    CryptoKeyBadHex => ("invalid hex character: {}", "无效的十六进制字符: {}"),
+    CryptoDataTooShort => ("encrypted data is too short", "加密数据太短"),
+    CryptoBadUtf8 => ("invalid UTF-8 data: {}", "无效的UTF-8数据: {}"),
+    CryptoWrongKeyId => (
+        "encrypted with key {}, but this key is {}",
+        "密文使用密钥 {} 加密，当前密钥为 {}"
+    ),
+
+    // 密钥环
+    KeyringBadId => (
+        "{}: key id must be a number from 0 to 65535",
+        "{}: 密钥ID必须是0到65535之间的数字"
+    ),
+    KeyringBadOption => (
+        "{}: unknown option {} (expected created= or expires=)",
+        "{}: 未知的选项 {}（应为 created= 或 expires=）"
+    ),
+    KeyringBadTime => (
+        "{}: invalid time {} (expected RFC 3339 or YYYY-MM-DD)",
+        "{}: 无效的时间 {}（应为RFC 3339或 YYYY-MM-DD）"
+    ),
+    KeyringDuplicateId => (
+        "key id {} is set more than once (ENCRYPTION_KEY counts as id 0)",
+        "密钥ID {} 重复设置（ENCRYPTION_KEY 视为ID 0）"
+    ),
+    KeyringNoActiveKey => (
+        "no encryption key is active (all keys have expired or are not yet valid)",
+        "没有可用的加密密钥（所有密钥都已过期或尚未启用）"
+    ),
+    KeyringUnknownKeyId => (
+        "encrypted with key {}, which is not in the keyring",
+        "密文使用密钥 {} 加密，密钥环中没有该密钥"
+    ),
+    KeyringIdsExhausted => ("no key id left for a new key", "没有可用于新密钥的ID"),
+
+    // Discord
+    DiscordCryptoInitFailed => (
+        "Failed to initialise encryption: {}",
+        "初始化加密管理器失败: {}"
+    ),
+    DiscordEncryptStateFailed => ("Failed to encrypt state: {}", "加密state数据失败: {}"),
+    DiscordDecryptStateFailed => ("Failed to decrypt state: {}", "解密state数据失败: {}"),
+    DiscordSetActivityFailed => (
+        "Failed to update Discord presence: {}",
+        "更新Discord状态失败: {}"
+    ),
+    DiscordClearActivityFailed => (
+        "Failed to clear Discord presence: {}",
+        "清除Discord状态失败: {}"
+    ),
+    DiscordEncryptionDisabled => ("Encryption is not enabled", "加密未启用"),
+    DiscordNotConnected => (
+        "not connected to Discord, waiting to reconnect",
+        "未连接到Discord，等待重连"
+    ),
+    DiscordWaiting => (
+        "⏳ Discord is not available yet, retrying in the background",
+        "⏳ 暂时无法连接Discord，将在后台自动重试"
+    ),
+    DiscordEventConnected => (
+        "✅ Reconnected to Discord (attempt {})",
+        "✅ 已重新连接到Discord（第 {} 次尝试）"
+    ),
+    DiscordEventDisconnected => (
+        "⚠️  Lost connection to Discord: {}",
+        "⚠️  与Discord的连接已断开: {}"
+    ),
+    DiscordEventReconnectFailed => (
+        "❌ Reconnect attempt {} failed: {} (retrying in {} s)",
+        "❌ 第 {} 次重连失败: {}（{} 秒后重试）"
+    ),
+    DiscordLoggedInAs => ("👤 Discord account: {} (ID: {})", "👤 Discord账号: {} (ID: {})"),
+    DiscordLanyardUrl => ("🔗 Lanyard: {}", "🔗 Lanyard: {}"),
+    DiscordUserMismatch => (
+        "⚠️  Discord is logged in as {}, but LANYARD_USER_ID is {}; Lanyard will not see this presence",
+        "⚠️  Discord当前登录的账号为 {}，与LANYARD_USER_ID {} 不一致，Lanyard将无法获取此状态"
+    ),
+    DiscordEventApplicationSwitched => (
+        "🔀 Switching Discord application {} → {}",
+        "🔀 切换Discord应用 {} → {}"
+    ),
+    DiscordEventRepublished => (
+        "🔁 Restored the last presence after reconnecting",
+        "🔁 重连后已恢复最近的状态"
+    ),
+
+    // Lanyard
+    LanyardRequestFailed => ("Lanyard request failed: {}", "Lanyard请求失败: {}"),
+    LanyardStatus => ("Lanyard returned HTTP {}: {}", "Lanyard返回 HTTP {}: {}"),
+    LanyardRateLimited => (
+        "rate limited by Lanyard, retrying in {} ms",
+        "被Lanyard限流，{} 毫秒后重试"
+    ),
+    LanyardEncryptTitleFailed => ("Failed to encrypt title: {}", "加密窗口标题失败: {}"),
+    LanyardKvEnabled => (
+        "📝 Writing {}* keys to Lanyard KV for user {}",
+        "📝 将 {}* 键写入用户 {} 的Lanyard KV"
+    ),
+    LanyardKvInitFailed => ("⚠️  Lanyard KV is disabled: {}", "⚠️  Lanyard KV未启用: {}"),
+    LanyardProtocol => ("unexpected Lanyard message: {}", "不符合Lanyard协议的消息: {}"),
+    LanyardNoHello => ("Lanyard did not send Hello", "Lanyard没有发送Hello"),
+    LanyardCleanupFailed => (
+        "failed to delete Lanyard KV keys: {}",
+        "删除Lanyard KV键失败: {}"
+    ),
+    LanyardVerifyTimeout => (
+        "the presence did not appear in Lanyard within {} s",
+        "{} 秒内没有在Lanyard中看到发布的状态"
+    ),
+
+    // 验证
+    VerifyProbeDetails => ("Verifying Lanyard", "正在验证Lanyard"),
+    VerifyProbeState => ("probe {}", "探测 {}"),
+    VerifyUsage => (
+        "Usage: main verify [timeout seconds]  (default {})",
+        "用法: main verify [超时秒数]（默认 {}）"
+    ),
+    VerifyWaitingForDiscord => ("⏳ Waiting for Discord...", "⏳ 正在等待Discord..."),
+    VerifyNoUser => (
+        "Cannot tell which user to watch: set LANYARD_USER_ID or log in to Discord",
+        "无法确定要订阅的用户：请设置 LANYARD_USER_ID 或登录Discord"
+    ),
+    VerifySubscribing => ("📡 Subscribing to {} on {}", "📡 在 {} 上订阅用户 {}"),
+    VerifyPublished => ("📤 Published probe: {}", "📤 已发布探测状态: {}"),
+    VerifySucceeded => (
+        "✅ Lanyard shows the published presence after {} ms",
+        "✅ Lanyard在 {} 毫秒后显示了发布的状态"
+    ),
+    VerifyFailed => ("❌ Verification failed: {}", "❌ 验证失败: {}"),
+
+    // 状态服务
+    ServerBindFailed => ("Unable to listen on {}: {}", "无法监听 {}: {}"),
+    ServerUserNotMonitored => ("User is not being monitored by Lanyard", "该用户不在Lanyard的监控范围内"),
+    ServerRouteNotFound => ("Route does not exist", "路由不存在"),
+    ServerTooManyConnections => ("Too many connections", "连接数过多"),
+    ServerListening => (
+        "🌐 Serving the Lanyard API at http://{}/v1/users/<id> and ws://{}/socket",
+        "🌐 Lanyard接口已启动: http://{}/v1/users/<id>，ws://{}/socket"
+    ),
+    ServerInitFailed => ("⚠️  Lanyard API server is disabled: {}", "⚠️  Lanyard接口服务未启动: {}"),
+    ServerOnly => (
+        "🔌 Server mode: not connecting to Discord",
+        "🔌 服务模式：不连接Discord"
+    ),
+
+    // 解密命令
+    ViewerBadJson => ("Invalid JSON: {}", "JSON无效: {}"),
+    ViewerNoEncryptedFields => (
+        "No field could be decrypted with this key",
+        "没有能用该密钥解密的字段"
+    ),
+    ViewerCiphertext => ("The ciphertext", "密文"),
+    ViewerStale => (
+        "{} was encrypted at {}, earlier than --max-age allows",
+        "{} 的加密时间为 {}，早于 --max-age 允许的时间"
+    ),
+    ViewerNoTimestamp => (
+        "{} has no encryption time, so --max-age cannot be checked",
+        "{} 没有加密时间，无法检查 --max-age"
+    ),
+    DecryptUsage => (
+        "Usage: main decrypt [--key <hex key>]... [--url <Lanyard API URL>] [--field <field, default state>] [--max-age <seconds>] <ciphertext | JSON | file | - | user id>",
+        "用法: main decrypt [--key <十六进制密钥>]... [--url <Lanyard接口地址>] [--field <字段名，默认 state>] [--max-age <秒数>] <密文 | JSON | 文件 | - | 用户ID>"
+    ),
+    DecryptEncryptedAt => ("{} (encrypted at {})", "{}（加密于 {}）"),
+    DecryptNoKey => (
+        "No key: pass --key or set ENCRYPTION_KEY / ENCRYPTION_KEY_<id> in .env",
+        "没有密钥：请使用 --key 或在.env中设置 ENCRYPTION_KEY / ENCRYPTION_KEY_<ID>"
+    ),
+    DecryptReadFailed => ("Unable to read {}: {}", "无法读取 {}: {}"),
+    DecryptFailed => ("❌ {}", "❌ {}"),
+
+    // 密钥轮换命令
+    RotateKeyUsage => (
+        "Usage: main rotate-key [--grace-days <days, default {}>]",
+        "用法: main rotate-key [--grace-days <天数，默认 {}>]"
+    ),
+    RotateKeyReadFailed => ("❌ Unable to read .env: {}", "❌ 无法读取.env: {}"),
+    RotateKeyWriteFailed => ("❌ Unable to write {}: {}", "❌ 无法写入 {}: {}"),
+    RotateKeyFailed => ("❌ Key rotation failed: {}", "❌ 密钥轮换失败: {}"),
+    RotateKeyBackup => ("💾 Previous .env saved to {}", "💾 原.env已保存到 {}"),
+    RotateKeyNew => (
+        "🔑 New key {} (ENCRYPTION_KEY_{}), used from {}:",
+        "🔑 新密钥 {}（ENCRYPTION_KEY_{}），从 {} 起使用:"
+    ),
+    RotateKeyRetired => ("⏳ Key {} expires at {}", "⏳ 密钥 {} 于 {} 过期"),
+    RotateKeyRemoved => ("🗑️  Removed expired key {}", "🗑️  已移除过期的密钥 {}"),
+    RotateKeyHint => (
+        "Share the new key with viewers before it takes effect; a running monitor picks it up after SIGHUP or a restart",
+        "请在新密钥生效前把它告诉查看状态的一方；正在运行的程序在收到SIGHUP或重启后读取新配置"
+    ),
+
+    // WebSocket
+    WsBadUrl => ("invalid WebSocket URL: {}", "无效的WebSocket地址: {}"),
+    WsIo => ("WebSocket I/O error: {}", "WebSocket读写错误: {}"),
+    WsTls => ("TLS error: {}", "TLS错误: {}"),
+    WsHandshakeFailed => ("WebSocket handshake failed: {}", "WebSocket握手失败: {}"),
+    WsBadAccept => ("Sec-WebSocket-Accept does not match", "Sec-WebSocket-Accept 不匹配"),
+    WsNotUpgrade => ("not a WebSocket upgrade request", "不是WebSocket升级请求"),
+    WsHeaderTooLarge => ("HTTP header too large", "HTTP头过大"),
+    WsProtocol => ("WebSocket protocol error: {}", "WebSocket协议错误: {}"),
+    WsMessageTooLarge => ("message too large ({} bytes)", "消息过大（{} 字节）"),
+    WsUnexpectedOpcode => ("unexpected opcode {}", "意外的操作码 {}"),
+    WsClosed => ("WebSocket connection closed", "WebSocket连接已关闭"),
+    WsClosedWithCode => ("WebSocket connection closed (code {})", "WebSocket连接已关闭（关闭码 {}）"),
+
+    // Discord IPC
+    IpcNotRunning => (
+        "Discord is not running (no IPC socket found)",
+        "Discord未运行（未找到IPC套接字）"
+    ),
+    IpcIo => ("IPC I/O error: {}", "IPC读写错误: {}"),
+    IpcProtocol => ("IPC protocol error: {}", "IPC协议错误: {}"),
+    IpcClosed => (
+        "Discord closed the connection ({}): {}",
+        "Discord关闭了连接（{}）: {}"
+    ),
+    IpcRejected => ("Discord rejected the request ({}): {}", "Discord拒绝了请求（{}）: {}"),
+    IpcBadOpcode => ("unknown opcode {}", "未知的操作码 {}"),
+    IpcFrameTooLarge => ("frame too large ({} bytes)", "数据帧过大（{} 字节）"),
+    IpcReadTimeout => ("Discord did not reply in time", "Discord没有及时回复"),
+    IpcUnexpectedHandshake => (
+        "unexpected reply to handshake",
+        "握手回复不符合预期"
+    ),
+
+    // 窗口监控
+    WindowUnsupportedPlatform => (
+        "⚠️  Window monitoring is only supported on Windows and macOS",
+        "⚠️  窗口监控仅在 Windows 和 macOS 平台上受支持"
+    ),
+    WindowNoActive => ("No active window", "没有活动窗口"),
+    WindowNoTitle => ("The active window has no title", "活动窗口没有标题"),
+    WindowQueryFailed => ("Failed to query the window list", "查询窗口列表失败"),
+    WindowQueryLockTimeout => ("Timed out waiting for the window query lock", "获取窗口查询锁超时"),
+    WindowLockTimeout => (
+        "[warn] Timed out waiting for the window query lock, skipping",
+        "[警告] 获取窗口查询锁超时，跳过此次查询"
+    ),
+    WindowThrottled => ("[debug] Query throttled, {}ms remaining", "[调试] 查询间隔限制，剩余 {}ms"),
+    WindowChangeDetected => ("[debug] Window changed: {} -> {}", "[调试] 检测到窗口变化: {} -> {}"),
+    WindowTitleUnavailable => (
+        "[debug] Unable to read window title, previous window: {}",
+        "[调试] 无法获取窗口标题，之前的窗口: {}"
+    ),
+
+    // 脚本
+    ScriptLoaded => ("📜 Loaded presence script: {}", "📜 已加载状态脚本: {}"),
+    ScriptLoadFailed => ("❌ Failed to load presence script: {}", "❌ 加载状态脚本失败: {}"),
+    ScriptSuppressed => ("🙈 Update suppressed by script", "🙈 脚本跳过了此次更新"),
+    ScriptFallback => (
+        "⚠️  {} (falling back to the default presence)",
+        "⚠️  {}（使用默认状态）"
+    ),
+    ScriptReadFailed => ("Unable to read script {}: {}", "无法读取脚本 {}: {}"),
+    ScriptCompileFailed => (
+        "Script syntax error (line {}): {}",
+        "脚本语法错误（第 {} 行）: {}"
+    ),
+    ScriptRuntimeError => ("Script error (line {}): {}", "脚本运行错误（第 {} 行）: {}"),
+    ScriptTimeout => (
+        "Script exceeded its time limit of {} ms",
+        "脚本执行超时（超过 {} 毫秒）"
+    ),
+    ScriptOperationLimit => (
+        "Script exceeded its limit of {} operations",
+        "脚本执行超过操作数上限（{} 次）"
+    ),
+    ScriptInvalidReturn => ("Invalid script return value: {}", "脚本返回值无效: {}"),
+    ScriptExpectedMap => (
+        "expected an object map or ()",
+        "应返回对象映射或 ()"
+    ),
+    ScriptFieldType => (
+        "field `{}` must be {}, got {}",
+        "字段 `{}` 应为 {}，实际为 {}"
+    ),
+
+    // 模拟Discord服务端（mock_discord 示例）
+    MockBanner => (
+        "╔════════════════════════════════════════════════╗\n\
+         ║  Mock Discord IPC Server                       ║\n\
+         ╚════════════════════════════════════════════════╝",
+        "╔════════════════════════════════════════════════╗\n\
+         ║  模拟 Discord IPC 服务端                      ║\n\
+         ║  Mock Discord IPC Server                     ║\n\
+         ╚════════════════════════════════════════════════╝"
+    ),
+    MockStartFailed => ("❌ Failed to start: {}", "❌ 启动失败: {}"),
+    MockSocket => ("🔌 Socket: {}", "🔌 套接字: {}"),
+    MockEnvHint => ("   Add to .env: DISCORD_IPC_PATH={}", "   在 .env 中添加: DISCORD_IPC_PATH={}"),
+    MockCommands => (
+        "⌨️  Commands: error / close / disconnect / ratelimit / delay <ms> / kick",
+        "⌨️  命令: error / close / disconnect / ratelimit / delay <毫秒> / kick"
+    ),
+    MockHandshake => ("🤝 Handshake: {}", "🤝 握手: {}"),
+    MockDelayUsage => ("⚠️  Usage: delay <ms>", "⚠️  用法: delay <毫秒>"),
+    MockKicked => ("🔌 Disconnected all clients", "🔌 已断开所有连接"),
+    MockUnknownCommand => ("⚠️  Unknown command: {}", "⚠️  未知命令: {}"),
+    MockFaultInjected => ("💉 The next command will get: {}", "💉 下一条命令将注入: {}"),
+    MockUnsupported => (
+        "❌ The mock server currently only supports Unix",
+        "❌ 模拟服务端目前仅支持Unix平台"
+    ),
+
+    // 发送到Discord的固定文字
+    PresenceLargeText => ("Windows Activity Monitor", "Windows 活动监视器"),
+    PresenceAway => ("Away", "离开"),
+    OverlayIdle => ("Idle", "空闲"),
+    OverlayEncrypted => ("Title encrypted", "标题已加密"),
+    OverlayDebugging => ("Debugging", "调试中"),
+    CategoryCoding => ("Coding", "编程"),
+    CategoryBrowsing => ("Browsing", "浏览网页"),
+    CategoryCommunication => ("Chatting", "聊天"),
+    CategoryMedia => ("Media", "影音"),
+    CategoryGaming => ("Gaming", "游戏"),
+    CategoryOffice => ("Office", "办公"),
+    CategoryDesign => ("Design", "设计"),
+    CategoryTerminal => ("Terminal", "终端"),
+    CategoryOther => ("Other", "其他"),
+}
+
+impl Msg {
+    /// 获取当前语言的消息文本
+    pub fn text(self) -> &'static str {
+        self.text_in(locale())
+    }
+}
+
+/// 使用参数填充消息中的 `{}` 占位符
+///
+/// 占位符多于参数时保留原样，参数多于占位符时忽略多余参数
+pub fn format_msg(template: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find("{}") {
+        result.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => result.push_str(&arg.to_string()),
+            None => result.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 按当前语言格式化消息
+///
+/// # 示例
+/// ```
+/// use active_window_info_to_lanyard_lib::i18n::{set_locale, Locale, Msg};
+/// use active_window_info_to_lanyard_lib::tr;
+///
+/// set_locale(Locale::En);
+/// assert_eq!(tr!(Msg::WelcomeVersion, "1.0"), "📝 Version: 1.0");
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($msg:expr) => {
+        $crate::i18n::Msg::text($msg).to_string()
+    };
+    ($msg:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::format_msg(
+            $crate::i18n::Msg::text($msg),
+            &[$(&$arg as &dyn ::std::fmt::Display),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_parse() {
+        assert_eq!(Locale::parse("zh_CN.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::parse("en-US"), Some(Locale::En));
+        assert_eq!(Locale::parse("C.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_text_in() {
+        assert_eq!(Msg::PresenceAway.text_in(Locale::En), "Away");
+        assert_eq!(Msg::PresenceAway.text_in(Locale::Zh), "离开");
+    }
+
+    #[test]
+    fn test_format_msg() {
+        let text = format_msg(Msg::WindowChangeDetected.text_in(Locale::En), &[&"a", &"b"]);
+        assert_eq!(text, "[debug] Window changed: a -> b");
+        assert_eq!(format_msg("{} and {}", &[&1]), "1 and {}");
+    }
+
+    #[test]
+    fn test_placeholders_match_between_locales() {
+        // 同一条消息在两种语言中的占位符数量必须一致
+        for &msg in Msg::ALL {
+            assert_eq!(
+                msg.text_in(Locale::En).matches("{}").count(),
+                msg.text_in(Locale::Zh).matches("{}").count(),
+                "{:?}",
+                msg
+            );
+        }
+    }
+}
@@ -0,0 +1,595 @@
+//! Discord IPC协议模块
+//!
+//! 直接实现Discord客户端的本地IPC协议，替代第三方RPC库，以便得到真实的连接错误。
+//!
+//! 每个数据帧的格式为 `[opcode: u32 LE][length: u32 LE][JSON payload]`。
+//! 连接建立后先发送HANDSHAKE帧，Discord回复READY事件；之后通过FRAME帧发送命令，
+//! 每个命令携带nonce，Discord的回复中带有相同的nonce。
+//!
+//! Unix平台上，Discord在以下目录创建 `discord-ipc-0` 至 `discord-ipc-9` 套接字：
+//! `$XDG_RUNTIME_DIR`、`$TMPDIR`、`$TMP`、`$TEMP`、`/tmp`，
+//! 以及这些目录下的 snap（`snap.discord`）和 flatpak（`app/com.discordapp.Discord`）子目录。
+//! Windows平台上使用命名管道 `\\.\pipe\discord-ipc-N`，读取时同样最多等待 `IPC_READ_TIMEOUT`。
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(windows)]
+use std::time::Instant;
+
+use rand::RngCore;
+use serde_json::{json, Value};
+
+use crate::i18n::Msg;
+use crate::tr;
+
+/// IPC套接字编号上限（`discord-ipc-0` 至 `discord-ipc-9`）
+pub const MAX_IPC_SOCKETS: usize = 10;
+
+/// 读取回复的超时时间
+pub const IPC_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 单个数据帧允许的最大长度，防止异常数据导致分配过多内存
+const MAX_FRAME_LEN: u32 = 64 * 1024;
+
+/// Windows平台上检查命名管道中是否有数据的间隔
+#[cfg(windows)]
+const PIPE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Unix平台上套接字所在目录下的子目录（snap / flatpak 安装的Discord）
+#[cfg(unix)]
+const SOCKET_SUBDIRS: &[&str] = &[
+    "",
+    "snap.discord",
+    "snap.discord-canary",
+    "snap.discord-ptb",
+    "app/com.discordapp.Discord",
+    "app/com.discordapp.DiscordCanary",
+    "app/com.discordapp.DiscordPTB",
+    ".flatpak/dev.vencord.Vesktop/xdg-run",
+];
+
+/// 数据帧操作码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Handshake = 0,
+    Frame = 1,
+    Close = 2,
+    Ping = 3,
+    Pong = 4,
+}
+
+impl Opcode {
+    /// 从数值解析操作码
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Opcode::Handshake),
+            1 => Some(Opcode::Frame),
+            2 => Some(Opcode::Close),
+            3 => Some(Opcode::Ping),
+            4 => Some(Opcode::Pong),
+            _ => None,
+        }
+    }
+}
+
+/// IPC错误
+#[derive(Debug)]
+pub enum IpcError {
+    /// 没有找到任何Discord IPC套接字（Discord未运行）
+    NotRunning,
+    /// 读写套接字失败
+    Io(io::Error),
+    /// 收到不符合协议的数据
+    Protocol(String),
+    /// Discord关闭了连接（例如应用ID无效）
+    Closed { code: i64, message: String },
+    /// Discord拒绝了命令（例如活动数据无效）
+    Rejected { code: i64, message: String },
+}
+
+impl IpcError {
+    /// 是否需要重新建立连接才能继续使用
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, IpcError::NotRunning | IpcError::Io(_) | IpcError::Closed { .. })
+    }
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            IpcError::NotRunning => tr!(Msg::IpcNotRunning),
+            IpcError::Io(e) => tr!(Msg::IpcIo, e),
+            IpcError::Protocol(message) => tr!(Msg::IpcProtocol, message),
+            IpcError::Closed { code, message } => tr!(Msg::IpcClosed, code, message),
+            IpcError::Rejected { code, message } => tr!(Msg::IpcRejected, code, message),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for IpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IpcError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IpcError {
+    fn from(e: io::Error) -> Self {
+        IpcError::Io(e)
+    }
+}
+
+/// 可读写的IPC连接
+pub trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
+/// 将一个数据帧写入连接
+pub fn write_frame<W: Write + ?Sized>(
+    writer: &mut W,
+    opcode: Opcode,
+    payload: &Value,
+) -> Result<(), IpcError> {
+    let body = serde_json::to_vec(payload).map_err(|e| IpcError::Protocol(e.to_string()))?;
+    let mut frame = Vec::with_capacity(8 + body.len());
+    frame.extend_from_slice(&(opcode as u32).to_le_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&body);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// 从连接读取一个数据帧
+pub fn read_frame<R: Read + ?Sized>(reader: &mut R) -> Result<(Opcode, Value), IpcError> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+
+    let opcode = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let opcode = Opcode::from_u32(opcode)
+        .ok_or_else(|| IpcError::Protocol(tr!(Msg::IpcBadOpcode, opcode)))?;
+    if len > MAX_FRAME_LEN {
+        return Err(IpcError::Protocol(tr!(Msg::IpcFrameTooLarge, len)));
+    }
+
+    let mut body = vec![0u8; len as usize];
+    reader.read_exact(&mut body)?;
+    let payload = serde_json::from_slice(&body).map_err(|e| IpcError::Protocol(e.to_string()))?;
+    Ok((opcode, payload))
+}
+
+/// 列出所有可能的IPC套接字路径（按优先级排序）
+#[cfg(unix)]
+pub fn socket_candidates() -> Vec<PathBuf> {
+    let mut bases: Vec<PathBuf> = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    bases.push(PathBuf::from("/tmp"));
+
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for base in bases {
+        if !unique.contains(&base) {
+            unique.push(base);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for base in &unique {
+        for subdir in SOCKET_SUBDIRS {
+            for n in 0..MAX_IPC_SOCKETS {
+                candidates.push(base.join(subdir).join(format!("discord-ipc-{}", n)));
+            }
+        }
+    }
+    candidates
+}
+
+/// 列出指定目录中的IPC套接字路径（`discord-ipc-0` 到 `discord-ipc-9`）
+pub fn socket_candidates_in(dir: &Path) -> Vec<PathBuf> {
+    (0..MAX_IPC_SOCKETS)
+        .map(|n| dir.join(format!("discord-ipc-{}", n)))
+        .collect()
+}
+
+/// 列出所有可能的IPC命名管道路径
+#[cfg(windows)]
+pub fn socket_candidates() -> Vec<PathBuf> {
+    (0..MAX_IPC_SOCKETS)
+        .map(|n| PathBuf::from(format!(r"\\.\pipe\discord-ipc-{}", n)))
+        .collect()
+}
+
+/// 打开指定路径的IPC连接
+#[cfg(unix)]
+fn open_transport(path: &Path) -> io::Result<Box<dyn Transport>> {
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(IPC_READ_TIMEOUT))?;
+    Ok(Box::new(stream))
+}
+
+/// 打开指定路径的IPC连接
+#[cfg(windows)]
+fn open_transport(path: &Path) -> io::Result<Box<dyn Transport>> {
+    let file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    Ok(Box::new(Pipe { file }))
+}
+
+/// 带读取超时的命名管道
+///
+/// 同步打开的管道上阻塞的读取无法取消，也会挡住同一句柄上的写入，
+/// 因此读取前用 `PeekNamedPipe` 等待数据到达，最多等待 `IPC_READ_TIMEOUT`
+#[cfg(windows)]
+struct Pipe {
+    file: std::fs::File,
+}
+
+#[cfg(windows)]
+impl Pipe {
+    /// 等待管道中有可读的数据，超时返回 `TimedOut`
+    fn wait_readable(&self) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::HANDLE;
+        use windows::Win32::System::Pipes::PeekNamedPipe;
+
+        let handle = HANDLE(self.file.as_raw_handle() as isize);
+        let deadline = Instant::now() + IPC_READ_TIMEOUT;
+        loop {
+            let mut available = 0u32;
+            let available_ptr = &mut available as *mut u32;
+            // SAFETY: 句柄在 `self.file` 存活期间有效，不读取数据，只写入 `available`
+            let peeked = unsafe { PeekNamedPipe(handle, None, 0, None, Some(available_ptr), None) };
+            if peeked.is_err() {
+                // 管道已断开
+                return Err(io::Error::last_os_error());
+            }
+            if available > 0 {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, tr!(Msg::IpcReadTimeout)));
+            }
+            std::thread::sleep(PIPE_POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.wait_readable()?;
+        self.file.read(buf)
+    }
+}
+
+#[cfg(windows)]
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 生成随机nonce
+fn new_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 从错误数据中提取错误码和信息
+fn error_details(data: &Value) -> (i64, String) {
+    let code = data.get("code").and_then(Value::as_i64).unwrap_or(0);
+    let message = data.get("message").and_then(Value::as_str).unwrap_or_default().to_string();
+    (code, message)
+}
+
+/// Discord IPC客户端
+pub struct IpcClient {
+    transport: Box<dyn Transport>,
+    path: Option<PathBuf>,
+    ready: Value,
+}
+
+impl IpcClient {
+    /// 依次尝试所有IPC套接字，连接到第一个可用的Discord客户端并完成握手
+    ///
+    /// # 参数
+    /// * `client_id` - Discord应用ID
+    ///
+    /// # 错误
+    /// * `IpcError::NotRunning` - 没有找到任何可连接的套接字
+    /// * 其他错误 - 所有套接字都握手失败，返回第一个握手错误
+    pub fn connect(client_id: u64) -> Result<Self, IpcError> {
+        Self::connect_first(&socket_candidates(), client_id)
+    }
+
+    /// 依次尝试 `paths`，返回第一个握手成功的连接；某个套接字握手失败时继续尝试后面的
+    ///
+    /// # 错误
+    /// 全部失败时返回第一个握手错误；没有找到任何套接字时返回 `IpcError::NotRunning`
+    pub fn connect_first(paths: &[PathBuf], client_id: u64) -> Result<Self, IpcError> {
+        let mut first_error = None;
+        for path in paths {
+            match Self::connect_path(path, client_id) {
+                Ok(client) => return Ok(client),
+                Err(IpcError::NotRunning) => {},
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                },
+            }
+        }
+        Err(first_error.unwrap_or(IpcError::NotRunning))
+    }
+
+    /// 连接到所有可用的IPC套接字（同时运行的多个Discord客户端）并完成握手
+    ///
+    /// # 错误
+    /// 一个都没有连接成功时，返回第一个握手错误；没有找到任何套接字时返回 `IpcError::NotRunning`
+    pub fn connect_each(paths: &[PathBuf], client_id: u64) -> Result<Vec<Self>, IpcError> {
+        let mut clients = Vec::new();
+        let mut first_error = None;
+        for path in paths {
+            match Self::connect_path(path, client_id) {
+                Ok(client) => clients.push(client),
+                Err(IpcError::NotRunning) => {},
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                },
+            }
+        }
+        match first_error {
+            Some(e) if clients.is_empty() => Err(e),
+            _ if clients.is_empty() => Err(IpcError::NotRunning),
+            _ => Ok(clients),
+        }
+    }
+
+    /// 连接到指定路径的IPC套接字并完成握手
+    pub fn connect_path(path: &Path, client_id: u64) -> Result<Self, IpcError> {
+        let transport = open_transport(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => IpcError::NotRunning,
+            _ => IpcError::Io(e),
+        })?;
+        let mut client = Self::handshake(transport, client_id)?;
+        client.path = Some(path.to_path_buf());
+        Ok(client)
+    }
+
+    /// 在已打开的连接上完成握手
+    ///
+    /// 发送HANDSHAKE帧并等待READY事件
+    pub fn handshake(mut transport: Box<dyn Transport>, client_id: u64) -> Result<Self, IpcError> {
+        let payload = json!({ "v": 1, "client_id": client_id.to_string() });
+        write_frame(&mut transport, Opcode::Handshake, &payload)?;
+
+        loop {
+            let (opcode, payload) = read_frame(&mut transport)?;
+            match opcode {
+                Opcode::Frame => {
+                    return match payload.get("evt").and_then(Value::as_str) {
+                        Some("READY") => Ok(Self {
+                            transport,
+                            path: None,
+                            ready: payload.get("data").cloned().unwrap_or(Value::Null),
+                        }),
+                        Some("ERROR") => {
+                            let (code, message) =
+                                error_details(payload.get("data").unwrap_or(&Value::Null));
+                            Err(IpcError::Rejected { code, message })
+                        },
+                        _ => Err(IpcError::Protocol(tr!(Msg::IpcUnexpectedHandshake))),
+                    };
+                },
+                Opcode::Close => {
+                    let (code, message) = error_details(&payload);
+                    return Err(IpcError::Closed { code, message });
+                },
+                Opcode::Ping => write_frame(&mut transport, Opcode::Pong, &payload)?,
+                _ => {},
+            }
+        }
+    }
+
+    /// 已连接的套接字路径
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// READY事件中的数据
+    pub fn ready_data(&self) -> &Value {
+        &self.ready
+    }
+
+    /// 发送命令并等待带有相同nonce的回复
+    ///
+    /// # 返回值
+    /// 回复中的 `data` 字段
+    pub fn send_command(&mut self, cmd: &str, args: Value) -> Result<Value, IpcError> {
+        let nonce = new_nonce();
+        let payload = json!({ "cmd": cmd, "args": args, "nonce": nonce });
+        write_frame(&mut self.transport, Opcode::Frame, &payload)?;
+
+        loop {
+            let (opcode, payload) = read_frame(&mut self.transport)?;
+            match opcode {
+                Opcode::Frame => {
+                    if payload.get("nonce").and_then(Value::as_str) != Some(nonce.as_str()) {
+                        // 其他事件（如订阅的推送），忽略
+                        continue;
+                    }
+                    let data = payload.get("data").cloned().unwrap_or(Value::Null);
+                    if payload.get("evt").and_then(Value::as_str) == Some("ERROR") {
+                        let (code, message) = error_details(&data);
+                        return Err(IpcError::Rejected { code, message });
+                    }
+                    return Ok(data);
+                },
+                Opcode::Close => {
+                    let (code, message) = error_details(&payload);
+                    return Err(IpcError::Closed { code, message });
+                },
+                Opcode::Ping => write_frame(&mut self.transport, Opcode::Pong, &payload)?,
+                _ => {},
+            }
+        }
+    }
+
+    /// 设置活动状态，`None` 表示清除
+    pub fn set_activity(&mut self, activity: Option<Value>) -> Result<Value, IpcError> {
+        let args = json!({
+            "pid": std::process::id(),
+            "activity": activity.unwrap_or(Value::Null),
+        });
+        self.send_command("SET_ACTIVITY", args)
+    }
+
+    /// 发送CLOSE帧，正常关闭连接
+    pub fn close(mut self) -> Result<(), IpcError> {
+        write_frame(&mut self.transport, Opcode::Close, &json!({}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut buffer = Vec::new();
+        let payload = json!({ "cmd": "SET_ACTIVITY", "nonce": "1" });
+        write_frame(&mut buffer, Opcode::Frame, &payload).unwrap();
+
+        assert_eq!(&buffer[0..4], &1u32.to_le_bytes());
+        let (opcode, decoded) = read_frame(&mut buffer.as_slice()).unwrap();
+        assert_eq!(opcode, Opcode::Frame);
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn test_read_frame_rejects_bad_data() {
+        let mut frame = 9u32.to_le_bytes().to_vec();
+        frame.extend_from_slice(&0u32.to_le_bytes());
+        assert!(matches!(read_frame(&mut frame.as_slice()), Err(IpcError::Protocol(_))));
+
+        let mut frame = 1u32.to_le_bytes().to_vec();
+        frame.extend_from_slice(&(MAX_FRAME_LEN + 1).to_le_bytes());
+        assert!(matches!(read_frame(&mut frame.as_slice()), Err(IpcError::Protocol(_))));
+
+        let frame = 1u32.to_le_bytes();
+        assert!(matches!(read_frame(&mut frame.as_slice()), Err(IpcError::Io(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_candidates_include_snap_and_flatpak() {
+        let candidates = socket_candidates();
+        assert!(candidates.contains(&PathBuf::from("/tmp/discord-ipc-0")));
+        assert!(candidates.contains(&PathBuf::from("/tmp/snap.discord/discord-ipc-0")));
+        assert!(candidates
+            .contains(&PathBuf::from("/tmp/app/com.discordapp.Discord/discord-ipc-9")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handshake_and_set_activity() {
+        use std::os::unix::net::UnixStream;
+
+        let (client_side, mut server) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            let (opcode, handshake) = read_frame(&mut server).unwrap();
+            assert_eq!(opcode, Opcode::Handshake);
+            assert_eq!(handshake["client_id"], "42");
+            let ready = json!({ "cmd": "DISPATCH", "evt": "READY", "data": { "v": 1 } });
+            write_frame(&mut server, Opcode::Frame, &ready).unwrap();
+
+            // 第一次SET_ACTIVITY：先发PING，再正常回复
+            let (_, command) = read_frame(&mut server).unwrap();
+            write_frame(&mut server, Opcode::Ping, &json!({ "n": 1 })).unwrap();
+            let (opcode, _) = read_frame(&mut server).unwrap();
+            assert_eq!(opcode, Opcode::Pong);
+            let reply = json!({ "cmd": "SET_ACTIVITY", "nonce": command["nonce"], "data": {} });
+            write_frame(&mut server, Opcode::Frame, &reply).unwrap();
+
+            // 第二次SET_ACTIVITY：返回错误
+            let (_, command) = read_frame(&mut server).unwrap();
+            let reply = json!({
+                "cmd": "SET_ACTIVITY",
+                "evt": "ERROR",
+                "nonce": command["nonce"],
+                "data": { "code": 4000, "message": "child \"activity\" fails" },
+            });
+            write_frame(&mut server, Opcode::Frame, &reply).unwrap();
+            command
+        });
+
+        let mut client = IpcClient::handshake(Box::new(client_side), 42).unwrap();
+        assert_eq!(client.ready_data()["v"], 1);
+        client.set_activity(Some(json!({ "state": "ok" }))).unwrap();
+        let err = client.set_activity(Some(json!({ "state": "x" }))).unwrap_err();
+        assert!(matches!(err, IpcError::Rejected { code: 4000, .. }));
+
+        let command = server.join().unwrap();
+        assert_eq!(command["cmd"], "SET_ACTIVITY");
+        assert_eq!(command["args"]["pid"], std::process::id());
+        assert_eq!(command["args"]["activity"]["state"], "x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handshake_close_is_reported() {
+        use std::os::unix::net::UnixStream;
+
+        let (client_side, mut server) = UnixStream::pair().unwrap();
+        std::thread::spawn(move || {
+            read_frame(&mut server).unwrap();
+            let close = json!({ "code": 4000, "message": "Invalid Client ID" });
+            write_frame(&mut server, Opcode::Close, &close).unwrap();
+        });
+
+        let err = IpcClient::handshake(Box::new(client_side), 1).err().unwrap();
+        assert!(matches!(err, IpcError::Closed { code: 4000, .. }));
+        assert!(err.is_disconnect());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connect_first_skips_failed_handshake() {
+        use crate::mock_ipc::{Fault, MockDiscordServer};
+
+        let first = MockDiscordServer::start().unwrap();
+        first.inject_handshake_fault(Fault::Close { code: 4000, message: "Invalid Client ID".into() });
+        let second = MockDiscordServer::start_at(&first.dir().join("discord-ipc-1")).unwrap();
+
+        let paths = socket_candidates_in(first.dir());
+        let client = IpcClient::connect_first(&paths, 1).unwrap();
+        assert_eq!(client.path(), Some(second.path()));
+
+        // 全部失败时返回第一个握手错误
+        first.inject_handshake_fault(Fault::Close { code: 4000, message: "Invalid Client ID".into() });
+        second.inject_handshake_fault(Fault::Disconnect);
+        let err = IpcClient::connect_first(&paths, 1).err().unwrap();
+        assert!(matches!(err, IpcError::Closed { code: 4000, .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_connect_path_missing_socket() {
+        let err = IpcClient::connect_path(Path::new("/nonexistent/discord-ipc-0"), 1).err().unwrap();
+        assert!(matches!(err, IpcError::NotRunning));
+    }
+}
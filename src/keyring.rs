@@ -0,0 +1,572 @@
+//! 加密密钥环模块
+//!
+//! 在.env中可以同时配置多个加密密钥，每个密钥有自己的ID、启用时间和过期时间：
+//!
+//! ```text
+//! ENCRYPTION_KEY_1=<64个十六进制字符>|expires=2024-07-01T00:00:00Z
+//! ENCRYPTION_KEY_2=<64个十六进制字符>|created=2024-07-01T00:00:00Z
+//! ```
+//!
+//! 已启用且未过期的密钥中最新的一个用于加密，密钥ID写入密文信封；解密时按信封中的ID
+//! 选择密钥，因此旧密钥加密的内容在它被移除之前都能解密。旧的 `ENCRYPTION_KEY` 视为ID为0的密钥。
+//!
+//! 轮换密钥（`rotate-key` 命令）时新密钥在宽限期结束后才启用，旧密钥同时过期，
+//! 查看状态的一方可以在宽限期内拿到新密钥；再次轮换时移除已过期的密钥。
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+use crate::crypto::{envelope_key_id, CryptoError, CryptoManager, Opened};
+use crate::i18n::Msg;
+use crate::tr;
+
+/// 旧的单密钥设置，视为ID为0的密钥
+pub const LEGACY_KEY_NAME: &str = "ENCRYPTION_KEY";
+/// 带ID的密钥设置前缀，如 `ENCRYPTION_KEY_1`
+pub const KEY_NAME_PREFIX: &str = "ENCRYPTION_KEY_";
+
+/// 密钥设置错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyringError {
+    /// `ENCRYPTION_KEY_` 后不是0到65535的数字（设置的键名）
+    BadId(String),
+    /// 未知的选项（设置的键名和选项）
+    BadOption { name: String, option: String },
+    /// 时间无法解析（设置的键名和时间）
+    BadTime { name: String, time: String },
+    /// 同一个ID设置了多次
+    DuplicateId(u16),
+    /// 没有已启用且未过期的密钥
+    NoActiveKey,
+    /// 没有可用于新密钥的ID
+    IdsExhausted,
+}
+
+impl fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            KeyringError::BadId(name) => tr!(Msg::KeyringBadId, name),
+            KeyringError::BadOption { name, option } => tr!(Msg::KeyringBadOption, name, option),
+            KeyringError::BadTime { name, time } => tr!(Msg::KeyringBadTime, name, time),
+            KeyringError::DuplicateId(id) => tr!(Msg::KeyringDuplicateId, id),
+            KeyringError::NoActiveKey => tr!(Msg::KeyringNoActiveKey),
+            KeyringError::IdsExhausted => tr!(Msg::KeyringIdsExhausted),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for KeyringError {}
+
+/// 密钥环中的一个密钥
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEntry {
+    /// 密钥ID，写入密文信封
+    pub id: u16,
+    /// 64个字符的十六进制密钥
+    pub key: String,
+    /// 启用时间（Unix秒），未设置时立即启用
+    pub created: Option<u64>,
+    /// 过期时间（Unix秒），过期后不再用于加密
+    pub expires: Option<u64>,
+}
+
+impl KeyEntry {
+    /// 创建立即启用、不会过期的密钥
+    pub fn new(id: u16, key: impl Into<String>) -> Self {
+        Self { id, key: key.into(), created: None, expires: None }
+    }
+
+    /// 解析 `<密钥>|created=<时间>|expires=<时间>`，时间为RFC 3339或 `YYYY-MM-DD`（UTC）
+    ///
+    /// `name` 为设置的键名，用于错误说明
+    pub fn parse(id: u16, name: &str, value: &str) -> Result<Self, KeyringError> {
+        let mut parts = value.split('|').map(str::trim);
+        let mut entry = Self::new(id, parts.next().unwrap_or_default());
+        for part in parts.filter(|part| !part.is_empty()) {
+            let (option, time) = part.split_once('=').unwrap_or((part, ""));
+            let time = time.trim();
+            let slot = match option.trim() {
+                "created" => &mut entry.created,
+                "expires" => &mut entry.expires,
+                _ => {
+                    return Err(KeyringError::BadOption {
+                        name: name.to_string(),
+                        option: part.to_string(),
+                    });
+                },
+            };
+            *slot = Some(parse_time(time).ok_or_else(|| KeyringError::BadTime {
+                name: name.to_string(),
+                time: time.to_string(),
+            })?);
+        }
+        Ok(entry)
+    }
+
+    /// 设置的键名，如 `ENCRYPTION_KEY_1`
+    pub fn env_name(&self) -> String {
+        format!("{}{}", KEY_NAME_PREFIX, self.id)
+    }
+
+    /// 按 [`KeyEntry::parse`] 的格式生成设置的值
+    pub fn env_value(&self) -> String {
+        let mut value = self.key.clone();
+        if let Some(created) = self.created {
+            value.push_str(&format!("|created={}", format_time(created)));
+        }
+        if let Some(expires) = self.expires {
+            value.push_str(&format!("|expires={}", format_time(expires)));
+        }
+        value
+    }
+
+    /// 在 `now` 时是否已启用且未过期
+    pub fn is_active(&self, now: u64) -> bool {
+        self.created.is_none_or(|created| created <= now) && !self.is_expired(now)
+    }
+
+    /// 在 `now` 时是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// 加密密钥环
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keyring {
+    keys: Vec<KeyEntry>,
+}
+
+impl Keyring {
+    /// 从.env键值对中解析 `ENCRYPTION_KEY_<ID>`，值为空的跳过，同一ID后出现的生效
+    ///
+    /// 不包含旧的 `ENCRYPTION_KEY`，需要时用 [`Keyring::insert`] 加入
+    pub fn from_env(entries: &[(String, String)]) -> Result<Self, KeyringError> {
+        let mut keyring = Self::default();
+        for (name, value) in entries {
+            let Some(id) = key_id_of(name)? else {
+                continue;
+            };
+            if value.is_empty() {
+                continue;
+            }
+            let entry = KeyEntry::parse(id, name, value)?;
+            keyring.keys.retain(|key| key.id != id);
+            keyring.keys.push(entry);
+        }
+        Ok(keyring)
+    }
+
+    /// 加入密钥，ID已存在时返回 `false` 且不加入
+    pub fn insert(&mut self, entry: KeyEntry) -> bool {
+        if self.get(entry.id).is_some() {
+            return false;
+        }
+        self.keys.push(entry);
+        true
+    }
+
+    /// 按ID查找密钥
+    pub fn get(&self, id: u16) -> Option<&KeyEntry> {
+        self.keys.iter().find(|key| key.id == id)
+    }
+
+    /// 所有密钥
+    pub fn keys(&self) -> &[KeyEntry] {
+        &self.keys
+    }
+
+    /// 是否没有任何密钥
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// `now` 时用于加密的密钥：已启用且未过期的密钥中启用时间最晚的，相同时取ID最大的
+    pub fn active(&self, now: u64) -> Option<&KeyEntry> {
+        self.keys
+            .iter()
+            .filter(|key| key.is_active(now))
+            .max_by_key(|key| (key.created.unwrap_or_default(), key.id))
+    }
+
+    /// 创建加密管理器，没有密钥时返回 `None`
+    ///
+    /// # 错误
+    /// 某个密钥无效时返回错误
+    pub fn crypto(&self) -> Result<Option<KeyringCrypto>, CryptoError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let keys = self
+            .keys
+            .iter()
+            .map(|entry| {
+                Ok(KeySlot {
+                    crypto: CryptoManager::from_hex(&entry.key)?.with_key_id(entry.id),
+                    created: entry.created,
+                    expires: entry.expires,
+                })
+            })
+            .collect::<Result<_, CryptoError>>()?;
+        Ok(Some(KeyringCrypto { keys }))
+    }
+}
+
+/// 密钥环中的密钥及其有效期
+struct KeySlot {
+    crypto: CryptoManager,
+    created: Option<u64>,
+    expires: Option<u64>,
+}
+
+/// 按密钥环加密和解密
+///
+/// 每次加密时按当前时间选择密钥，长时间运行时到了新密钥的启用时间会自动切换
+pub struct KeyringCrypto {
+    keys: Vec<KeySlot>,
+}
+
+impl KeyringCrypto {
+    /// 用没有有效期的密钥创建，如查看端通过命令行传入的密钥
+    pub fn new(keys: Vec<CryptoManager>) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|crypto| KeySlot { crypto, created: None, expires: None })
+            .collect();
+        Self { keys }
+    }
+
+    /// `now` 时用于加密的密钥，选择方式与 [`Keyring::active`] 相同
+    ///
+    /// # 错误
+    /// 所有密钥都已过期或尚未启用时返回错误，调用方不能退回明文
+    pub fn encryptor(&self, now: u64) -> Result<&CryptoManager, CryptoError> {
+        self.keys
+            .iter()
+            .filter(|slot| {
+                slot.created.is_none_or(|created| created <= now)
+                    && slot.expires.is_none_or(|expires| now < expires)
+            })
+            .max_by_key(|slot| (slot.created.unwrap_or_default(), slot.crypto.key_id()))
+            .map(|slot| &slot.crypto)
+            .ok_or_else(|| CryptoError::EncryptionFailed(tr!(Msg::KeyringNoActiveKey)))
+    }
+
+    /// 解密数据，取出信封中的版本、密钥ID和时间戳
+    ///
+    /// 先用信封中密钥ID对应的密钥，再尝试其他密钥（早期格式的密文没有密钥ID）。
+    /// 过期的密钥仍然用于解密
+    pub fn open(&self, encrypted: &str, field: &str) -> Result<Opened, CryptoError> {
+        let key_id = envelope_key_id(encrypted);
+        let (matching, others): (Vec<_>, Vec<_>) = self
+            .keys
+            .iter()
+            .map(|slot| &slot.crypto)
+            .partition(|crypto| key_id.is_some() && crypto.key_id() == key_id);
+
+        let mut first_error = None;
+        for crypto in matching.iter().chain(&others) {
+            match crypto.open(encrypted, field) {
+                Ok(opened) => return Ok(opened),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                },
+            }
+        }
+        let unknown = others.iter().all(|crypto| crypto.key_id().is_some());
+        match key_id {
+            Some(key_id) if matching.is_empty() && unknown => {
+                Err(CryptoError::DecryptionFailed(tr!(Msg::KeyringUnknownKeyId, key_id)))
+            },
+            _ => Err(first_error
+                .unwrap_or_else(|| CryptoError::DecryptionFailed(tr!(Msg::KeyringNoActiveKey)))),
+        }
+    }
+
+    /// 解密数据，只返回明文
+    pub fn decrypt_field(&self, encrypted: &str, field: &str) -> Result<String, CryptoError> {
+        self.open(encrypted, field).map(|opened| opened.plaintext)
+    }
+}
+
+/// 密钥轮换的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rotation {
+    /// 新密钥
+    pub new_key: KeyEntry,
+    /// 设置了过期时间的旧密钥
+    pub retired: Vec<KeyEntry>,
+    /// 已过期而被移除的密钥ID
+    pub removed: Vec<u16>,
+}
+
+/// 在.env文本中轮换密钥，返回新的文本
+///
+/// * 新密钥使用最大ID加1，启用时间为 `now + grace`（没有可用的旧密钥时为 `now`）
+/// * 未过期的旧密钥在新密钥启用时过期，已设置更早过期时间的保持不变；
+///   旧的 `ENCRYPTION_KEY` 改写为 `ENCRYPTION_KEY_0`
+/// * 已过期的密钥被移除
+///
+/// 其他行（包括注释）保持原样，新密钥写在最后一个密钥之后
+///
+/// # 错误
+/// 密钥设置无法解析、ID重复或没有可用的ID时返回错误
+pub fn rotate_env(
+    contents: &str,
+    new_key: &str,
+    now: u64,
+    grace: Duration,
+) -> Result<(String, Rotation), KeyringError> {
+    let starts = now.saturating_add(grace.as_secs());
+    let mut lines = Vec::new();
+    let mut seen = Vec::new();
+    let mut retired = Vec::new();
+    let mut removed = Vec::new();
+    let mut insert_at = None;
+
+    for line in contents.lines() {
+        let setting = line
+            .split_once('=')
+            .filter(|_| !line.trim_start().starts_with('#'))
+            .map(|(name, value)| (name.trim(), value.trim()));
+        let Some((name, value)) = setting.filter(|(_, value)| !value.is_empty()) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        let id = if name == LEGACY_KEY_NAME {
+            0
+        } else if let Some(id) = key_id_of(name)? {
+            id
+        } else {
+            lines.push(line.to_string());
+            continue;
+        };
+        if seen.contains(&id) {
+            return Err(KeyringError::DuplicateId(id));
+        }
+        seen.push(id);
+
+        let mut entry = KeyEntry::parse(id, name, value)?;
+        if entry.is_expired(now) {
+            removed.push(id);
+        } else {
+            entry.expires = Some(entry.expires.map_or(starts, |expires| expires.min(starts)));
+            lines.push(format!("{}={}", entry.env_name(), entry.env_value()));
+            insert_at = Some(lines.len());
+            retired.push(entry);
+        }
+    }
+
+    let id = match seen.iter().max() {
+        Some(id) => id.checked_add(1).ok_or(KeyringError::IdsExhausted)?,
+        None => 1,
+    };
+    let created = if retired.is_empty() { now } else { starts };
+    let new_key = KeyEntry { id, key: new_key.to_string(), created: Some(created), expires: None };
+    let line = format!("{}={}", new_key.env_name(), new_key.env_value());
+    match insert_at {
+        Some(index) => lines.insert(index, line),
+        None => lines.push(line),
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    Ok((text, Rotation { new_key, retired, removed }))
+}
+
+/// 设置的键名为 `ENCRYPTION_KEY_<ID>` 时返回ID
+fn key_id_of(name: &str) -> Result<Option<u16>, KeyringError> {
+    match name.strip_prefix(KEY_NAME_PREFIX) {
+        Some(suffix) => suffix
+            .parse()
+            .map(Some)
+            .map_err(|_| KeyringError::BadId(name.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// 解析RFC 3339时间或 `YYYY-MM-DD`（UTC零点），返回Unix秒
+pub fn parse_time(value: &str) -> Option<u64> {
+    let time = match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.timestamp(),
+        Err(_) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?
+            .and_utc()
+            .timestamp(),
+    };
+    u64::try_from(time).ok()
+}
+
+/// 把Unix秒格式化为RFC 3339（UTC）
+pub fn format_time(secs: u64) -> String {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| secs.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+    const KEY_B: &str = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+    /// 2024-01-01T00:00:00Z
+    const NOW: u64 = 1_704_067_200;
+    const DAY: u64 = 86_400;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_time_formats() {
+        assert_eq!(parse_time("2024-01-01"), Some(NOW));
+        assert_eq!(parse_time("2024-01-01T08:00:00+08:00"), Some(NOW));
+        assert_eq!(parse_time("2024-01-01T00:00:00Z"), Some(NOW));
+        assert_eq!(parse_time("next week"), None);
+        assert_eq!(format_time(NOW), "2024-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_parse_entries() {
+        let keyring = Keyring::from_env(&env(&[
+            ("ENCRYPTION_KEY_1", &format!("{}|expires=2024-01-08", KEY_A)),
+            ("ENCRYPTION_KEY_2", &format!("{} | created=2024-01-08T00:00:00Z", KEY_B)),
+            ("ENCRYPTION_KEY_3", ""),
+            ("ENCRYPTION_KEY", KEY_B),
+        ]))
+        .unwrap();
+        assert_eq!(keyring.keys().len(), 2);
+        let entry = keyring.get(1).unwrap();
+        assert_eq!(entry.expires, Some(NOW + 7 * DAY));
+        assert_eq!(
+            KeyEntry::parse(1, "ENCRYPTION_KEY_1", &entry.env_value()).unwrap(),
+            *entry
+        );
+        assert_eq!(keyring.get(2).unwrap().created, Some(NOW + 7 * DAY));
+
+        assert_eq!(
+            Keyring::from_env(&env(&[("ENCRYPTION_KEY_X", KEY_A)])),
+            Err(KeyringError::BadId("ENCRYPTION_KEY_X".to_string()))
+        );
+        assert!(matches!(
+            Keyring::from_env(&env(&[("ENCRYPTION_KEY_1", &format!("{}|until=2024", KEY_A))])),
+            Err(KeyringError::BadOption { option, .. }) if option == "until=2024"
+        ));
+        assert!(matches!(
+            Keyring::from_env(&env(&[("ENCRYPTION_KEY_1", &format!("{}|expires=soon", KEY_A))])),
+            Err(KeyringError::BadTime { time, .. }) if time == "soon"
+        ));
+    }
+
+    #[test]
+    fn test_active_key_follows_schedule() {
+        let mut keyring = Keyring::default();
+        assert!(keyring.insert(KeyEntry { expires: Some(NOW + DAY), ..KeyEntry::new(1, KEY_A) }));
+        assert!(keyring.insert(KeyEntry { created: Some(NOW + DAY), ..KeyEntry::new(2, KEY_B) }));
+        assert!(!keyring.insert(KeyEntry::new(2, KEY_A)));
+
+        assert_eq!(keyring.active(NOW).unwrap().id, 1);
+        assert_eq!(keyring.active(NOW + DAY).unwrap().id, 2);
+
+        let crypto = keyring.crypto().unwrap().unwrap();
+        assert_eq!(crypto.encryptor(NOW).unwrap().key_id(), Some(1));
+        assert_eq!(crypto.encryptor(NOW + DAY).unwrap().key_id(), Some(2));
+
+        let expired = Keyring::from_env(&env(&[("ENCRYPTION_KEY_1", &format!("{}|expires=2023-12-01", KEY_A))]))
+            .unwrap();
+        assert!(expired.active(NOW).is_none());
+        assert!(expired.crypto().unwrap().unwrap().encryptor(NOW).is_err());
+        assert!(Keyring::default().crypto().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decrypt_with_any_known_key() {
+        let mut keyring = Keyring::default();
+        keyring.insert(KeyEntry { expires: Some(NOW), ..KeyEntry::new(1, KEY_A) });
+        keyring.insert(KeyEntry::new(2, KEY_B));
+        let crypto = keyring.crypto().unwrap().unwrap();
+
+        let old = CryptoManager::from_hex(KEY_A).unwrap().with_key_id(1);
+        let new = crypto.encryptor(NOW).unwrap();
+        assert_eq!(new.key_id(), Some(2));
+        for (crypto_used, text) in [(&old, "old"), (new, "new")] {
+            let encrypted = crypto_used.encrypt_field(text, "state", Some(NOW)).unwrap();
+            assert_eq!(crypto.decrypt_field(&encrypted, "state").unwrap(), text);
+        }
+
+        let unknown = CryptoManager::from_hex(&"11".repeat(32))
+            .unwrap()
+            .with_key_id(9)
+            .encrypt_field("other", "state", None)
+            .unwrap();
+        let err = crypto.decrypt_field(&unknown, "state").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            CryptoError::DecryptionFailed(tr!(Msg::KeyringUnknownKeyId, 9)).to_string()
+        );
+
+        // 没有ID的密钥接受任何ID
+        let any = KeyringCrypto::new(vec![CryptoManager::from_hex(&"11".repeat(32)).unwrap()]);
+        assert_eq!(any.decrypt_field(&unknown, "state").unwrap(), "other");
+    }
+
+    #[test]
+    fn test_rotate_env() {
+        let contents = format!(
+            "# 设置\nDISCORD_APP_ID=1\nENCRYPTION_KEY={}\nENCRYPTION_KEY_3={}|expires=2023-12-31\nUPDATE_INTERVAL=5",
+            KEY_A, KEY_B
+        );
+        let grace = Duration::from_secs(7 * DAY);
+        let new_key = "22".repeat(32);
+        let (text, rotation) = rotate_env(&contents, &new_key, NOW, grace).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "# 设置\nDISCORD_APP_ID=1\nENCRYPTION_KEY_0={}|expires=2024-01-08T00:00:00Z\n\
+                 ENCRYPTION_KEY_4={}|created=2024-01-08T00:00:00Z\nUPDATE_INTERVAL=5\n",
+                KEY_A, new_key
+            )
+        );
+        assert_eq!(rotation.new_key.id, 4);
+        assert_eq!(rotation.retired.len(), 1);
+        assert_eq!(rotation.removed, [3]);
+
+        // 再次轮换：宽限期内的密钥保持更早的过期时间
+        let (text, rotation) = rotate_env(&text, &"33".repeat(32), NOW + DAY, grace).unwrap();
+        let keyring = Keyring::from_env(&crate::config::parse_env(&text)).unwrap();
+        assert_eq!(keyring.get(0).unwrap().expires, Some(NOW + 7 * DAY));
+        assert_eq!(keyring.get(4).unwrap().expires, Some(NOW + 8 * DAY));
+        assert_eq!(rotation.new_key.id, 5);
+        assert_eq!(keyring.active(NOW + DAY).unwrap().id, 0);
+        assert_eq!(keyring.active(NOW + 7 * DAY).unwrap().id, 4);
+        assert_eq!(keyring.active(NOW + 8 * DAY).unwrap().id, 5);
+    }
+
+    #[test]
+    fn test_rotate_env_without_keys() {
+        let (text, rotation) = rotate_env("DISCORD_APP_ID=1\nENCRYPTION_KEY=\n", KEY_A, NOW, Duration::from_secs(DAY)).unwrap();
+        assert_eq!(
+            text,
+            format!("DISCORD_APP_ID=1\nENCRYPTION_KEY=\nENCRYPTION_KEY_1={}|created=2024-01-01T00:00:00Z\n", KEY_A)
+        );
+        assert!(rotation.retired.is_empty());
+
+        let duplicate = format!("ENCRYPTION_KEY={}\nENCRYPTION_KEY_0={}\n", KEY_A, KEY_B);
+        assert!(matches!(
+            rotate_env(&duplicate, KEY_A, NOW, Duration::ZERO),
+            Err(KeyringError::DuplicateId(_))
+        ));
+        let full = format!("ENCRYPTION_KEY_65535={}\n", KEY_A);
+        assert_eq!(
+            rotate_env(&full, KEY_B, NOW, Duration::ZERO).err(),
+            Some(KeyringError::IdsExhausted)
+        );
+    }
+}
@@ -0,0 +1,700 @@
+//! Lanyard集成模块
+//!
+//! 通过WebSocket确认状态已到达Lanyard的部分见 `lanyard_socket` 模块。
+//!
+//! Lanyard除了转发Discord活动状态，还为每个用户提供一个键值存储（KV），
+//! 写入的键会出现在Lanyard返回的状态数据的 `kv` 字段中。`LanyardKvSink` 把当前应用、
+//! `details`、类别以及（可选的）加密窗口标题写入这些键：
+//!
+//! * 一次更新只发送有变化的键，用一个 `PATCH` 请求批量写入，不再需要的键逐个删除
+//! * Lanyard返回429时按 `Retry-After` 暂停，期间的更新留待之后重试
+//! * 退出时删除写入过的所有键
+//!
+//! 接口地址可以配置（`LANYARD_API_URL`），便于测试或使用自建的Lanyard实例。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde_json::{Map, Value};
+
+use crate::builder::unix_now;
+use crate::config::Config;
+use crate::crypto::CryptoError;
+use crate::keyring::KeyringCrypto;
+use crate::error::Error;
+use crate::i18n::Msg;
+use crate::presence::Presence;
+use crate::scheduler::RateLimit;
+use crate::sink::{PresenceSink, SinkResult};
+use crate::tr;
+use crate::ws::WsError;
+
+/// 默认的Lanyard接口地址
+pub const DEFAULT_API_URL: &str = "https://api.lanyard.rest";
+
+/// 默认的KV键名前缀
+pub const DEFAULT_KV_PREFIX: &str = "activity_";
+
+/// 默认时间窗口内允许写入KV的次数
+pub const DEFAULT_KV_RATE_LIMIT_UPDATES: u32 = 10;
+
+/// 默认KV限流时间窗口（毫秒）
+pub const DEFAULT_KV_RATE_LIMIT_WINDOW_MS: u64 = 60_000;
+
+/// 单个请求的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lanyard KV输出配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvConfig {
+    /// Lanyard API密钥（在Lanyard机器人私信中用 `.apikey` 获取）
+    pub api_key: String,
+    /// 键名前缀，写入的键为 `<前缀>app`、`<前缀>details`、`<前缀>category`、`<前缀>title`
+    pub prefix: String,
+    /// 是否写入加密后的窗口标题（需要设置加密密钥）
+    pub include_title: bool,
+    /// 写入频率限制
+    pub rate_limit: RateLimit,
+}
+
+impl KvConfig {
+    /// 使用默认前缀和限流参数创建配置
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            prefix: DEFAULT_KV_PREFIX.to_string(),
+            include_title: false,
+            rate_limit: RateLimit {
+                updates: DEFAULT_KV_RATE_LIMIT_UPDATES,
+                window: Duration::from_millis(DEFAULT_KV_RATE_LIMIT_WINDOW_MS),
+            },
+        }
+    }
+
+    /// 校验键名前缀：Lanyard的键只能包含字母、数字和下划线
+    pub fn parse_prefix(value: &str) -> Result<String, KvPrefixError> {
+        if value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            Ok(value.to_string())
+        } else {
+            Err(KvPrefixError::InvalidChar(value.to_string()))
+        }
+    }
+}
+
+/// `LANYARD_KV_PREFIX` 解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvPrefixError {
+    /// 包含字母、数字和下划线以外的字符
+    InvalidChar(String),
+}
+
+impl fmt::Display for KvPrefixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KvPrefixError::InvalidChar(prefix) => f.write_str(&tr!(Msg::ConfigBadKvPrefix, prefix)),
+        }
+    }
+}
+
+impl std::error::Error for KvPrefixError {}
+
+/// Lanyard接口错误
+#[derive(Debug)]
+pub enum LanyardError {
+    /// 请求没有完成（连接失败、超时等）
+    Request(reqwest::Error),
+    /// 接口返回了错误状态
+    Status {
+        /// HTTP状态码
+        status: u16,
+        /// Lanyard给出的错误说明
+        message: String,
+    },
+    /// 被限流，需要等待后再请求
+    RateLimited(Duration),
+    /// 初始化加密管理器失败
+    CryptoInit(CryptoError),
+    /// 加密窗口标题失败
+    Encrypt(CryptoError),
+    /// WebSocket连接出错
+    Socket(WsError),
+    /// 收到不符合Lanyard协议的消息
+    Protocol(String),
+    /// 在限定时间内没有在Lanyard中看到发布的状态
+    VerifyTimeout(Duration),
+    /// 退出时有KV键没能删除（键名和对应的错误）
+    Cleanup(Vec<(String, LanyardError)>),
+}
+
+impl LanyardError {
+    /// 稍后重试是否可能成功（网络错误、限流、服务端错误）
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LanyardError::Request(_) | LanyardError::RateLimited(_) | LanyardError::Socket(_) => true,
+            LanyardError::Status { status, .. } => *status >= 500,
+            LanyardError::Cleanup(errors) => errors.iter().all(|(_, e)| e.is_transient()),
+            LanyardError::CryptoInit(_)
+            | LanyardError::Encrypt(_)
+            | LanyardError::Protocol(_)
+            | LanyardError::VerifyTimeout(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for LanyardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            LanyardError::Request(e) => tr!(Msg::LanyardRequestFailed, e),
+            LanyardError::Status { status, message } => tr!(Msg::LanyardStatus, status, message),
+            LanyardError::RateLimited(wait) => tr!(Msg::LanyardRateLimited, wait.as_millis()),
+            LanyardError::CryptoInit(e) => tr!(Msg::DiscordCryptoInitFailed, e),
+            LanyardError::Encrypt(e) => tr!(Msg::LanyardEncryptTitleFailed, e),
+            LanyardError::Socket(e) => e.to_string(),
+            LanyardError::Protocol(message) => tr!(Msg::LanyardProtocol, message),
+            LanyardError::VerifyTimeout(timeout) => {
+                tr!(Msg::LanyardVerifyTimeout, timeout.as_secs_f64())
+            },
+            LanyardError::Cleanup(errors) => {
+                let details: Vec<String> =
+                    errors.iter().map(|(key, e)| format!("{}: {}", key, e)).collect();
+                tr!(Msg::LanyardCleanupFailed, details.join("; "))
+            },
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for LanyardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LanyardError::Request(e) => Some(e),
+            LanyardError::CryptoInit(e) | LanyardError::Encrypt(e) => Some(e),
+            LanyardError::Socket(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for LanyardError {
+    fn from(e: reqwest::Error) -> Self {
+        LanyardError::Request(e)
+    }
+}
+
+/// 把活动状态写入Lanyard KV
+pub struct LanyardKvSink {
+    client: Client,
+    base_url: String,
+    user_id: String,
+    kv: Option<KvConfig>,
+    crypto: Option<KeyringCrypto>,
+    /// 已写入Lanyard的键值
+    written: BTreeMap<String, String>,
+    /// 已写入的标题明文，标题不变时沿用原来的密文（每次加密的结果都不同）
+    written_title: Option<String>,
+    /// 被限流时，在此之前不再发送请求
+    retry_at: Option<Instant>,
+}
+
+impl LanyardKvSink {
+    /// 根据配置创建输出，没有配置API密钥或用户ID时返回 `None`
+    pub fn new(config: &Config) -> Result<Option<Self>, LanyardError> {
+        let (Some(kv), Some(user_id)) = (&config.lanyard_kv, &config.lanyard_user_id) else {
+            return Ok(None);
+        };
+        let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+        Ok(Some(Self {
+            client,
+            base_url: config.lanyard_api_url.trim_end_matches('/').to_string(),
+            user_id: user_id.clone(),
+            kv: Some(kv.clone()),
+            crypto: crypto_for(config)?,
+            written: BTreeMap::new(),
+            written_title: None,
+            retry_at: None,
+        }))
+    }
+
+    /// 写入的用户ID
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// 已写入Lanyard的键值
+    pub fn written(&self) -> &BTreeMap<String, String> {
+        &self.written
+    }
+
+    /// 活动状态对应的键值，`None` 表示清除所有键
+    fn entries_for(
+        &self,
+        presence: Option<&Presence>,
+    ) -> Result<BTreeMap<String, String>, LanyardError> {
+        let mut entries = BTreeMap::new();
+        let (Some(kv), Some(presence)) = (&self.kv, presence) else {
+            return Ok(entries);
+        };
+        let mut put = |name: &str, value: Option<String>| {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                entries.insert(format!("{}{}", kv.prefix, name), value);
+            }
+        };
+        let source = presence.source.as_ref();
+        put("app", source.map(|s| s.app.clone()));
+        put("details", presence.details.clone());
+        put("category", source.map(|s| s.category.name().to_string()));
+        if kv.include_title
+            && let (Some(crypto), Some(source)) = (&self.crypto, source)
+        {
+            let key = format!("{}title", kv.prefix);
+            let title = match self.written.get(&key) {
+                Some(written) if self.written_title.as_ref() == Some(&source.title) => {
+                    written.clone()
+                },
+                _ => {
+                    let now = unix_now();
+                    crypto
+                        .encryptor(now)
+                        .and_then(|crypto| crypto.encrypt_field(&source.title, &key, Some(now)))
+                        .map_err(LanyardError::Encrypt)?
+                },
+            };
+            put("title", Some(title));
+        }
+        Ok(entries)
+    }
+
+    /// 写入有变化的键，删除不再需要的键
+    fn sync(&mut self, entries: BTreeMap<String, String>) -> Result<bool, LanyardError> {
+        let changed: Map<String, Value> = entries
+            .iter()
+            .filter(|(key, value)| self.written.get(*key) != Some(value))
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect();
+        let removed: Vec<String> = self
+            .written
+            .keys()
+            .filter(|key| !entries.contains_key(*key))
+            .cloned()
+            .collect();
+        if changed.is_empty() && removed.is_empty() {
+            return Ok(false);
+        }
+        if let Some(retry_at) = self.retry_at {
+            let wait = retry_at.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                return Err(LanyardError::RateLimited(wait));
+            }
+            self.retry_at = None;
+        }
+
+        if !changed.is_empty() {
+            let request = self.client.patch(self.kv_url(None)).json(&changed);
+            self.send(request)?;
+            for (key, value) in changed {
+                if let Value::String(value) = value {
+                    self.written.insert(key, value);
+                }
+            }
+        }
+        for key in removed {
+            self.delete(&key, None)?;
+        }
+        Ok(true)
+    }
+
+    /// 删除一个键，`timeout` 为空时使用默认超时
+    fn delete(&mut self, key: &str, timeout: Option<Duration>) -> Result<(), LanyardError> {
+        let mut request = self.client.delete(self.kv_url(Some(key)));
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        self.send(request)?;
+        self.written.remove(key);
+        Ok(())
+    }
+
+    /// 发送请求，429时记录需要等待的时间
+    fn send(&mut self, request: RequestBuilder) -> Result<Response, LanyardError> {
+        let api_key = self.kv.as_ref().map_or("", |kv| kv.api_key.as_str());
+        let response = request.header("Authorization", api_key).send()?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let wait = retry_after(&response).unwrap_or_else(|| {
+                self.kv
+                    .as_ref()
+                    .map_or(Duration::ZERO, |kv| kv.rate_limit.window)
+            });
+            self.retry_at = Some(Instant::now() + wait);
+            return Err(LanyardError::RateLimited(wait));
+        }
+        if !status.is_success() {
+            return Err(LanyardError::Status {
+                status: status.as_u16(),
+                message: error_message(response),
+            });
+        }
+        Ok(response)
+    }
+
+    fn kv_url(&self, key: Option<&str>) -> String {
+        let url = format!("{}/v1/users/{}/kv", self.base_url, self.user_id);
+        match key {
+            Some(key) => format!("{}/{}", url, key),
+            None => url,
+        }
+    }
+}
+
+impl PresenceSink for LanyardKvSink {
+    fn name(&self) -> &str {
+        "Lanyard KV"
+    }
+
+    fn rate_limit(&self) -> RateLimit {
+        self.kv.as_ref().map(|kv| kv.rate_limit).unwrap_or_default()
+    }
+
+    /// 网络错误、限流和服务端错误保留更新稍后重试，其他错误（如API密钥无效）放弃这次更新
+    fn publish(&mut self, presence: Option<&Presence>) -> SinkResult {
+        let result = self
+            .entries_for(presence)
+            .and_then(|entries| self.sync(entries));
+        if result.is_ok() {
+            let has_title = self
+                .kv
+                .as_ref()
+                .is_some_and(|kv| self.written.contains_key(&format!("{}title", kv.prefix)));
+            self.written_title = presence
+                .and_then(|p| p.source.as_ref())
+                .map(|source| source.title.clone())
+                .filter(|_| has_title);
+        }
+        match result {
+            Ok(true) => SinkResult::Sent,
+            Ok(false) => SinkResult::Skipped,
+            Err(e) if e.is_transient() => SinkResult::Retry(e.into()),
+            Err(e) => SinkResult::Dropped(e.into()),
+        }
+    }
+
+    /// 前缀改变或不再写入KV时，先删除按旧配置写入的键
+    fn reload(&mut self, config: &Config) -> Result<(), Error> {
+        let crypto = crypto_for(config)?;
+        let prefix_changed = self.kv.as_ref().map(|kv| &kv.prefix)
+            != config.lanyard_kv.as_ref().map(|kv| &kv.prefix);
+        let user_changed = config.lanyard_user_id.as_ref() != Some(&self.user_id);
+        if prefix_changed || user_changed {
+            self.sync(BTreeMap::new())?;
+        }
+        self.kv = config
+            .lanyard_kv
+            .clone()
+            .filter(|_| config.lanyard_user_id.is_some());
+        if let Some(user_id) = &config.lanyard_user_id {
+            self.user_id = user_id.clone();
+        }
+        self.base_url = config.lanyard_api_url.trim_end_matches('/').to_string();
+        self.crypto = crypto;
+        Ok(())
+    }
+
+    fn shutdown(mut self: Box<Self>, timeout: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        let keys: Vec<String> = self.written.keys().cloned().collect();
+        // 某个键删除失败时继续删除其余的键，最后一并报告
+        let mut errors = Vec::new();
+        for key in keys {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if let Err(e) = self.delete(&key, Some(remaining.max(Duration::from_millis(1)))) {
+                errors.push((key, e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(LanyardError::Cleanup(errors).into())
+        }
+    }
+}
+
+/// 从Lanyard获取用户的状态数据（响应中的 `data` 对象）
+///
+/// # 参数
+/// * `base_url` - 接口地址，如 `https://api.lanyard.rest`
+/// * `user_id` - Discord用户ID
+pub fn fetch_presence(base_url: &str, user_id: &str) -> Result<Value, LanyardError> {
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let response = client.get(user_url(base_url, user_id)).send()?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(LanyardError::Status { status: status.as_u16(), message: error_message(response) });
+    }
+    let mut body: Value = response.json()?;
+    Ok(body["data"].take())
+}
+
+/// 用户状态在Lanyard接口 `base_url` 上的地址
+pub fn user_url(base_url: &str, user_id: &str) -> String {
+    format!("{}/v1/users/{}", base_url.trim_end_matches('/'), user_id)
+}
+
+/// 根据配置中的密钥环创建加密管理器
+fn crypto_for(config: &Config) -> Result<Option<KeyringCrypto>, LanyardError> {
+    config.keyring().crypto().map_err(LanyardError::CryptoInit)
+}
+
+/// 解析 `Retry-After`（秒）
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get("Retry-After")?.to_str().ok()?;
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// 取出Lanyard错误响应中的说明（`{"success": false, "error": {"message": ...}}`）
+fn error_message(response: Response) -> String {
+    let text = response.text().unwrap_or_default();
+    serde_json::from_str::<Value>(&text)
+        .ok()
+        .and_then(|body| body["error"]["message"].as_str().map(str::to_string))
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::crypto::CryptoManager;
+    use crate::presence::PresenceSource;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// 收到的请求：方法、路径、Authorization、请求体
+    type Request = (String, String, String, String);
+
+    /// 按顺序用 `responses` 应答的HTTP服务端，收到的请求通过通道返回
+    fn serve(responses: Vec<&'static str>) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+                let (mut length, mut auth) = (0, String::new());
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    let (name, value) = header.split_once(':').unwrap();
+                    match name.to_ascii_lowercase().as_str() {
+                        "content-length" => length = value.trim().parse().unwrap(),
+                        "authorization" => auth = value.trim().to_string(),
+                        _ => {},
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                sender
+                    .send((method, path, auth, String::from_utf8(body).unwrap()))
+                    .unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    const OK: &str =
+        "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\n{\"success\":true}";
+    const NO_CONTENT: &str = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+    const TOO_MANY: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const UNAUTHORIZED: &str = "HTTP/1.1 401 Unauthorized\r\nContent-Length: 67\r\nConnection: close\r\n\r\n{\"success\":false,\"error\":{\"code\":\"invalid_api_key\",\"message\":\"no\"}}";
+
+    fn sink(url: &str, include_title: bool) -> LanyardKvSink {
+        let mut config = Config::new_with_encryption(1, 5, "00".repeat(32));
+        config.lanyard_api_url = format!("{}/", url);
+        config.lanyard_user_id = Some("94490510688792576".to_string());
+        config.lanyard_kv = Some(KvConfig {
+            include_title,
+            ..KvConfig::new("secret")
+        });
+        LanyardKvSink::new(&config).unwrap().unwrap()
+    }
+
+    fn presence(app: &str, title: &str) -> Presence {
+        let mut presence = Presence::new(app, title, 0);
+        presence.source = Some(PresenceSource {
+            app: app.to_string(),
+            category: Category::classify(app),
+            title: title.to_string(),
+        });
+        presence
+    }
+
+    #[test]
+    fn test_not_configured_without_key() {
+        let mut config = Config::new(1, 5);
+        config.lanyard_user_id = Some("1".to_string());
+        assert!(LanyardKvSink::new(&config).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_writes_changed_keys_in_one_batch() {
+        let (url, requests) = serve(vec![OK, OK, NO_CONTENT, NO_CONTENT]);
+        let mut sink = sink(&url, false);
+
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "main.rs"))),
+            SinkResult::Sent
+        ));
+        let (method, path, auth, body) = requests.recv().unwrap();
+        assert_eq!((method.as_str(), auth.as_str()), ("PATCH", "secret"));
+        assert_eq!(path, "/v1/users/94490510688792576/kv");
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["activity_app"], "Code");
+        assert_eq!(body["activity_details"], "Code");
+        assert_eq!(body["activity_category"], Category::classify("Code").name());
+        assert!(body.get("activity_title").is_none());
+
+        // 没有变化的键不再发送
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "lib.rs"))),
+            SinkResult::Skipped
+        ));
+
+        // 离开时只保留 details，删除其他键
+        let mut away = Presence::away(0);
+        away.details = Some("Code".to_string());
+        let mut next = presence("Firefox", "x");
+        next.details = Some("Code".to_string());
+        assert!(matches!(sink.publish(Some(&next)), SinkResult::Sent));
+        let (_, _, _, body) = requests.recv().unwrap();
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body.as_object().unwrap().len(), 2);
+        assert!(matches!(sink.publish(Some(&away)), SinkResult::Sent));
+        let deleted: Vec<String> = requests
+            .iter()
+            .map(|(method, path, _, _)| {
+                assert_eq!(method, "DELETE");
+                path.rsplit('/').next().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(deleted, vec!["activity_app", "activity_category"]);
+        assert_eq!(
+            sink.written().keys().collect::<Vec<_>>(),
+            vec!["activity_details"]
+        );
+    }
+
+    #[test]
+    fn test_title_is_encrypted() {
+        let (url, requests) = serve(vec![OK]);
+        let mut sink = sink(&url, true);
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "secret.rs"))),
+            SinkResult::Sent
+        ));
+        let (_, _, _, body) = requests.recv().unwrap();
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let title = body["activity_title"].as_str().unwrap();
+        let crypto = CryptoManager::from_hex(&"00".repeat(32)).unwrap();
+        assert_eq!(crypto.decrypt_field(title, "activity_title").unwrap(), "secret.rs");
+        assert!(crypto.decrypt_field(title, "state").is_err());
+
+        // 标题不变时沿用原来的密文，不重复写入
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "secret.rs"))),
+            SinkResult::Skipped
+        ));
+    }
+
+    #[test]
+    fn test_rate_limited_update_is_retried() {
+        let (url, requests) = serve(vec![TOO_MANY]);
+        let mut sink = sink(&url, false);
+        let result = sink.publish(Some(&presence("Code", "a")));
+        assert!(
+            matches!(result, SinkResult::Retry(Error::Lanyard(LanyardError::RateLimited(wait)))
+            if wait == Duration::from_secs(30))
+        );
+        requests.recv().unwrap();
+        // 等待期间不再发送请求
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "a"))),
+            SinkResult::Retry(_)
+        ));
+        assert!(requests.try_recv().is_err());
+        assert!(sink.written().is_empty());
+    }
+
+    #[test]
+    fn test_rejected_update_is_dropped() {
+        let (url, _requests) = serve(vec![UNAUTHORIZED]);
+        let mut sink = sink(&url, false);
+        let result = sink.publish(Some(&presence("Code", "a")));
+        let SinkResult::Dropped(Error::Lanyard(LanyardError::Status { status, message })) = result
+        else {
+            panic!("unexpected result: {:?}", result);
+        };
+        assert_eq!((status, message.as_str()), (401, "no"));
+    }
+
+    #[test]
+    fn test_shutdown_deletes_written_keys() {
+        let (url, requests) = serve(vec![OK, NO_CONTENT, NO_CONTENT, NO_CONTENT]);
+        let mut sink = sink(&url, false);
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "a"))),
+            SinkResult::Sent
+        ));
+        requests.recv().unwrap();
+        Box::new(sink).shutdown(Duration::from_secs(5)).unwrap();
+        let deleted: Vec<String> = requests
+            .iter()
+            .map(|(method, path, _, _)| {
+                assert_eq!(method, "DELETE");
+                path.rsplit('/').next().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(
+            deleted,
+            vec!["activity_app", "activity_category", "activity_details"]
+        );
+    }
+
+    #[test]
+    fn test_shutdown_tries_every_key() {
+        let (url, requests) = serve(vec![OK, NO_CONTENT, UNAUTHORIZED, NO_CONTENT]);
+        let mut sink = sink(&url, false);
+        assert!(matches!(
+            sink.publish(Some(&presence("Code", "a"))),
+            SinkResult::Sent
+        ));
+        requests.recv().unwrap();
+        let result = Box::new(sink).shutdown(Duration::from_secs(5));
+        let Err(Error::Lanyard(LanyardError::Cleanup(errors))) = result else {
+            panic!("unexpected result: {:?}", result);
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "activity_category");
+        assert!(matches!(errors[0].1, LanyardError::Status { status: 401, .. }));
+        // 失败之后仍然删除了剩下的键
+        assert_eq!(requests.iter().count(), 3);
+    }
+}
@@ -0,0 +1,443 @@
+//! Lanyard WebSocket模块
+//!
+//! 实现Lanyard的WebSocket协议，用于确认发布到Discord的活动状态是否真的出现在Lanyard中：
+//!
+//! 1. 连接后服务端发送 `Hello`（op 1），带心跳间隔
+//! 2. 客户端发送 `Initialize`（op 2），用 `subscribe_to_id` 订阅一个用户
+//! 3. 服务端发送 `INIT_STATE` 事件（op 0）给出当前状态，之后每次变化发送 `PRESENCE_UPDATE`
+//! 4. 客户端按心跳间隔发送 `Heartbeat`（op 3），否则服务端会断开连接
+//!
+//! 地址可以配置（`LANYARD_SOCKET_URL`），便于在测试中连接本地的替代服务。
+
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::discord::{DiscordError, DiscordManager};
+use crate::error::Error;
+use crate::i18n::Msg;
+use crate::lanyard::LanyardError;
+use crate::presence::Presence;
+use crate::tr;
+use crate::ws::{self, Message, Transport, WebSocket};
+
+/// 默认的Lanyard WebSocket地址
+pub const DEFAULT_SOCKET_URL: &str = "wss://api.lanyard.rest/socket";
+
+const OP_EVENT: u64 = 0;
+const OP_HELLO: u64 = 1;
+const OP_INITIALIZE: u64 = 2;
+const OP_HEARTBEAT: u64 = 3;
+
+/// Lanyard推送的状态事件，内容为Lanyard格式的状态数据
+#[derive(Debug, Clone, PartialEq)]
+pub enum LanyardEvent {
+    /// 订阅后的初始状态
+    InitState(Value),
+    /// 状态变化
+    PresenceUpdate(Value),
+}
+
+impl LanyardEvent {
+    /// 事件中的状态数据
+    pub fn presence(&self) -> &Value {
+        match self {
+            LanyardEvent::InitState(presence) | LanyardEvent::PresenceUpdate(presence) => presence,
+        }
+    }
+
+    /// 查找来自 `application_id`，且 `details`、`state`、`type` 与 `activity` 一致的活动
+    ///
+    /// `activity` 为发送给Discord的 `activity` 对象；图标在Lanyard中会被替换为资源ID，因此不比较
+    pub fn find_activity(&self, application_id: u64, activity: &Value) -> Option<&Value> {
+        let application_id = application_id.to_string();
+        self.presence()["activities"]
+            .as_array()?
+            .iter()
+            .find(|candidate| {
+                candidate["application_id"].as_str() == Some(application_id.as_str())
+                    && candidate["details"] == activity["details"]
+                    && candidate["state"] == activity["state"]
+                    && candidate["type"].as_u64().unwrap_or(0)
+                        == activity["type"].as_u64().unwrap_or(0)
+            })
+    }
+}
+
+/// 订阅了一个用户的Lanyard WebSocket连接
+pub struct LanyardSocket {
+    socket: WebSocket<Box<dyn Transport>>,
+    heartbeat_interval: Duration,
+    next_heartbeat: Instant,
+}
+
+impl LanyardSocket {
+    /// 连接到 `url` 并订阅 `user_id`，`timeout` 用于建立连接和等待 `Hello`
+    pub fn connect(url: &str, user_id: &str, timeout: Duration) -> Result<Self, LanyardError> {
+        let mut socket = ws::connect(url, timeout).map_err(LanyardError::Socket)?;
+        let hello = match socket.read(Some(timeout)).map_err(LanyardError::Socket)? {
+            Some(Message::Text(text)) => parse(&text)?,
+            Some(_) | None => return Err(LanyardError::Protocol(tr!(Msg::LanyardNoHello))),
+        };
+        if hello["op"].as_u64() != Some(OP_HELLO) {
+            return Err(LanyardError::Protocol(tr!(Msg::LanyardNoHello)));
+        }
+        let interval = hello["d"]["heartbeat_interval"].as_u64().unwrap_or(30_000);
+        let heartbeat_interval = Duration::from_millis(interval.max(1));
+
+        let initialize = json!({ "op": OP_INITIALIZE, "d": { "subscribe_to_id": user_id } });
+        socket
+            .send_text(&initialize.to_string())
+            .map_err(LanyardError::Socket)?;
+        Ok(Self {
+            socket,
+            heartbeat_interval,
+            next_heartbeat: Instant::now() + heartbeat_interval,
+        })
+    }
+
+    /// 服务端要求的心跳间隔
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.heartbeat_interval
+    }
+
+    /// 等待下一个状态事件，期间按时发送心跳；`timeout` 内没有事件时返回 `None`
+    pub fn next_event(&mut self, timeout: Duration) -> Result<Option<LanyardEvent>, LanyardError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let now = Instant::now();
+            if now >= self.next_heartbeat {
+                let heartbeat = json!({ "op": OP_HEARTBEAT }).to_string();
+                self.socket
+                    .send_text(&heartbeat)
+                    .map_err(LanyardError::Socket)?;
+                self.next_heartbeat = now + self.heartbeat_interval;
+            }
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            let wait = deadline.min(self.next_heartbeat) - now;
+            match self.socket.read(Some(wait)).map_err(LanyardError::Socket)? {
+                Some(Message::Text(text)) => {
+                    if let Some(event) = event(&parse(&text)?) {
+                        return Ok(Some(event));
+                    }
+                },
+                Some(Message::Close(code)) => {
+                    return Err(LanyardError::Socket(ws::WsError::Closed(code)));
+                },
+                Some(Message::Binary(_)) | None => {},
+            }
+        }
+    }
+
+    /// 关闭连接
+    pub fn close(mut self) {
+        let _ = self.socket.close(1000);
+    }
+}
+
+/// 验证结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    /// 从发布到Lanyard推送该状态的时间
+    pub latency: Duration,
+    /// Lanyard中对应的活动
+    pub activity: Value,
+}
+
+/// 通过 `discord` 发布 `presence`，并等待它出现在Lanyard中
+///
+/// `socket` 应已订阅登录Discord的用户。`presence` 应与当前状态不同（例如带随机内容），
+/// 否则无法区分Lanyard中已有的旧状态
+///
+/// # 错误
+/// 发布失败、连接出错或 `timeout` 内没有看到该状态时返回错误
+pub fn verify(
+    discord: &mut DiscordManager,
+    socket: &mut LanyardSocket,
+    presence: &Presence,
+    timeout: Duration,
+) -> Result<Verification, Error> {
+    discord.update_presence(presence).into_result()?;
+    let published = Instant::now();
+    let activity = discord
+        .published_activity()
+        .cloned()
+        .ok_or(DiscordError::NotConnected)?;
+    let application_id = discord.application_id();
+
+    let deadline = published + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Some(event) = socket.next_event(remaining)? else {
+            return Err(LanyardError::VerifyTimeout(timeout).into());
+        };
+        if let Some(found) = event.find_activity(application_id, &activity) {
+            return Ok(Verification {
+                latency: published.elapsed(),
+                activity: found.clone(),
+            });
+        }
+    }
+}
+
+/// 生成用于验证的活动状态，`state` 中带随机标记以区别于已有状态
+pub fn probe_presence(start_timestamp: u64) -> Presence {
+    let marker: u32 = rand::random();
+    Presence::new(
+        Msg::VerifyProbeDetails.text(),
+        &tr!(Msg::VerifyProbeState, format!("{:08x}", marker)),
+        start_timestamp,
+    )
+}
+
+fn parse(text: &str) -> Result<Value, LanyardError> {
+    serde_json::from_str(text).map_err(|e| LanyardError::Protocol(e.to_string()))
+}
+
+fn event(message: &Value) -> Option<LanyardEvent> {
+    if message["op"].as_u64() != Some(OP_EVENT) {
+        return None;
+    }
+    let data = message["d"].clone();
+    match message["t"].as_str()? {
+        "INIT_STATE" => Some(LanyardEvent::InitState(data)),
+        "PRESENCE_UPDATE" => Some(LanyardEvent::PresenceUpdate(data)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    /// 在本地端口上运行模拟的Lanyard WebSocket服务，`script` 处理唯一的一个连接
+    fn serve<T: Send + 'static>(
+        script: impl FnOnce(WebSocket<TcpStream>) -> T + Send + 'static,
+    ) -> (String, JoinHandle<T>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/socket", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (socket, path) = WebSocket::accept(stream).unwrap();
+            assert_eq!(path, "/socket");
+            script(socket)
+        });
+        (url, handle)
+    }
+
+    fn hello(socket: &mut WebSocket<TcpStream>, interval_ms: u64) -> Value {
+        let hello = json!({ "op": OP_HELLO, "d": { "heartbeat_interval": interval_ms } });
+        socket.send_text(&hello.to_string()).unwrap();
+        receive(socket)
+    }
+
+    fn receive(socket: &mut WebSocket<TcpStream>) -> Value {
+        match socket.read(Some(Duration::from_secs(5))).unwrap() {
+            Some(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    fn send_event(socket: &mut WebSocket<TcpStream>, kind: &str, data: Value) {
+        let event = json!({ "op": OP_EVENT, "t": kind, "seq": 1, "d": data });
+        socket.send_text(&event.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_subscribe_and_receive_events() {
+        let (url, server) = serve(|mut socket| {
+            let initialize = hello(&mut socket, 30_000);
+            send_event(&mut socket, "INIT_STATE", json!({ "activities": [] }));
+            socket
+                .send_text(r#"{"op":0,"t":"UNKNOWN","d":{}}"#)
+                .unwrap();
+            send_event(
+                &mut socket,
+                "PRESENCE_UPDATE",
+                json!({ "discord_status": "online" }),
+            );
+            initialize
+        });
+
+        let mut socket =
+            LanyardSocket::connect(&url, "94490510688792576", Duration::from_secs(5)).unwrap();
+        assert_eq!(socket.heartbeat_interval(), Duration::from_secs(30));
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            socket.next_event(timeout).unwrap(),
+            Some(LanyardEvent::InitState(json!({ "activities": [] })))
+        );
+        assert_eq!(
+            socket.next_event(timeout).unwrap(),
+            Some(LanyardEvent::PresenceUpdate(
+                json!({ "discord_status": "online" })
+            ))
+        );
+        socket.close();
+
+        let initialize = server.join().unwrap();
+        assert_eq!(
+            initialize,
+            json!({ "op": 2, "d": { "subscribe_to_id": "94490510688792576" } })
+        );
+    }
+
+    #[test]
+    fn test_heartbeats_while_waiting() {
+        let (url, server) = serve(|mut socket| {
+            hello(&mut socket, 20);
+            let heartbeat = receive(&mut socket);
+            send_event(&mut socket, "PRESENCE_UPDATE", json!({}));
+            heartbeat
+        });
+
+        let mut socket = LanyardSocket::connect(&url, "1", Duration::from_secs(5)).unwrap();
+        let event = socket.next_event(Duration::from_secs(5)).unwrap();
+        assert_eq!(event, Some(LanyardEvent::PresenceUpdate(json!({}))));
+        assert_eq!(server.join().unwrap(), json!({ "op": 3 }));
+    }
+
+    #[test]
+    fn test_next_event_times_out() {
+        let (url, server) = serve(|mut socket| {
+            hello(&mut socket, 30_000);
+            // 保持连接直到客户端关闭
+            let _ = socket.read(Some(Duration::from_secs(5)));
+        });
+
+        let mut socket = LanyardSocket::connect(&url, "1", Duration::from_secs(5)).unwrap();
+        assert_eq!(socket.next_event(Duration::from_millis(50)).unwrap(), None);
+        socket.close();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_connect_requires_hello() {
+        let (url, server) = serve(|mut socket| {
+            send_event(&mut socket, "INIT_STATE", json!({}));
+        });
+
+        let err = LanyardSocket::connect(&url, "1", Duration::from_secs(5))
+            .err()
+            .unwrap();
+        assert!(matches!(err, LanyardError::Protocol(_)));
+        assert!(!err.is_transient());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_find_activity() {
+        let event = LanyardEvent::PresenceUpdate(json!({
+            "activities": [
+                { "application_id": "7", "details": "Code", "state": "main.rs", "type": 0 },
+                { "application_id": "42", "details": "Code", "state": "lib.rs", "type": 0 },
+                { "application_id": "42", "details": "Code", "state": "main.rs", "type": 0 },
+            ]
+        }));
+        let activity = json!({ "details": "Code", "state": "main.rs" });
+        let found = event.find_activity(42, &activity).unwrap();
+        assert_eq!(found["application_id"], "42");
+        assert_eq!(found["state"], "main.rs");
+
+        assert!(
+            event
+                .find_activity(
+                    42,
+                    &json!({ "details": "Code", "state": "main.rs", "type": 3 })
+                )
+                .is_none()
+        );
+        assert!(event.find_activity(1, &activity).is_none());
+        assert!(
+            LanyardEvent::InitState(json!({}))
+                .find_activity(42, &activity)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_probe_presence_is_unique() {
+        let first = probe_presence(1);
+        let second = probe_presence(1);
+        assert_eq!(
+            first.details.as_deref(),
+            Some(Msg::VerifyProbeDetails.text())
+        );
+        assert_ne!(first.state, second.state);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify_with_mock_discord() {
+        use crate::config::Config;
+        use crate::mock_ipc::MockDiscordServer;
+
+        let discord_server = MockDiscordServer::start().unwrap();
+        let mut config = Config::new(42, 5);
+        config.ipc_path = Some(discord_server.path().to_path_buf());
+        let mut discord = DiscordManager::connect(&config).unwrap();
+
+        // 模拟Lanyard先推送无关的状态，再推送与发布内容一致的活动
+        let presence = probe_presence(discord.start_time());
+        let (details, state) = (presence.details.clone(), presence.state.clone());
+        let (url, server) = serve(move |mut socket| {
+            hello(&mut socket, 30_000);
+            let other = json!({ "application_id": "42", "details": "Code", "state": "main.rs" });
+            send_event(
+                &mut socket,
+                "PRESENCE_UPDATE",
+                json!({ "activities": [other] }),
+            );
+            let probe =
+                json!({ "application_id": "42", "details": details, "state": state, "type": 0 });
+            send_event(
+                &mut socket,
+                "PRESENCE_UPDATE",
+                json!({ "activities": [probe] }),
+            );
+            let _ = socket.read(Some(Duration::from_secs(5)));
+        });
+
+        let mut socket = LanyardSocket::connect(&url, "1", Duration::from_secs(5)).unwrap();
+        let verification =
+            verify(&mut discord, &mut socket, &presence, Duration::from_secs(5)).unwrap();
+        assert_eq!(verification.activity["state"], json!(presence.state));
+        assert_eq!(
+            discord_server.last_activity().unwrap()["state"],
+            json!(presence.state)
+        );
+        assert!(verification.latency < Duration::from_secs(5));
+        socket.close();
+        server.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify_times_out() {
+        use crate::config::Config;
+        use crate::mock_ipc::MockDiscordServer;
+
+        let discord_server = MockDiscordServer::start().unwrap();
+        let mut config = Config::new(42, 5);
+        config.ipc_path = Some(discord_server.path().to_path_buf());
+        let mut discord = DiscordManager::connect(&config).unwrap();
+
+        let (url, server) = serve(|mut socket| {
+            hello(&mut socket, 30_000);
+            send_event(&mut socket, "PRESENCE_UPDATE", json!({ "activities": [] }));
+            let _ = socket.read(Some(Duration::from_secs(5)));
+        });
+
+        let mut socket = LanyardSocket::connect(&url, "1", Duration::from_secs(5)).unwrap();
+        let timeout = Duration::from_millis(100);
+        let err = verify(&mut discord, &mut socket, &probe_presence(1), timeout).unwrap_err();
+        assert!(matches!(err, Error::Lanyard(LanyardError::VerifyTimeout(t)) if t == timeout));
+        socket.close();
+        server.join().unwrap();
+    }
+}
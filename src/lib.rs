@@ -4,22 +4,99 @@
 ///
 /// # 模块
 /// * `config` - 应用配置管理
+/// * `error` - 统一错误类型
+/// * `i18n` - 本地化消息目录
 /// * `window` - Windows窗口监控
+/// * `builder` - 活动状态生成
 /// * `parser` - 窗口标题解析
+/// * `category` - 应用分类
+/// * `rules` - 设置的作用范围与命名规则
+/// * `template` - 字段模板
+/// * `timestamps` - 时间戳模式
+/// * `buttons` - 活动按钮
+/// * `assets` - 图标资源选择
+/// * `project` - 工作区与git仓库识别
+/// * `redact` - 隐私信息脱敏
 /// * `discord` - Discord RPC集成
+/// * `ipc` - Discord IPC协议
+/// * `lanyard` - Lanyard KV输出
+/// * `lanyard_socket` - Lanyard WebSocket客户端与状态验证
+/// * `ws` - WebSocket协议
+/// * `scheduler` - 更新合并与限流
+/// * `server` - Lanyard兼容的状态服务
+/// * `signals` - 退出与重新加载信号
+/// * `sink` - 状态输出与分发
+/// * `mock_ipc` - 用于测试的模拟Discord IPC服务端（仅Unix，需启用 `mock` 特性）
+/// * `presence` - 活动状态数据
+/// * `fit` - 字段长度适配
 /// * `crypto` - 加密/解密功能
+/// * `keyring` - 加密密钥环与密钥轮换
+/// * `viewer` - 查看端解密
+/// * `script` - Rhai脚本扩展
+pub mod assets;
+pub mod builder;
+pub mod buttons;
+pub mod category;
 pub mod config;
 pub mod crypto;
 pub mod discord;
+pub mod error;
+pub mod fit;
+pub mod i18n;
+pub mod ipc;
+pub mod keyring;
+pub mod lanyard;
+pub mod lanyard_socket;
+#[cfg(all(unix, any(test, feature = "mock")))]
+pub mod mock_ipc;
 pub mod parser;
+pub mod presence;
+pub mod project;
+pub mod redact;
+pub mod rules;
+pub mod scheduler;
+pub mod script;
+pub mod server;
+pub mod signals;
+pub mod sink;
+pub mod template;
+pub mod timestamps;
+pub mod viewer;
 pub mod window;
+pub mod ws;
 
 // 重新导出常用类型，方便使用
-pub use config::Config;
-pub use crypto::{CryptoError, CryptoManager};
-pub use discord::{DiscordManager, UpdateResult};
+pub use assets::{AssetConfig, AssetKeyError, LocalizedText, Overlay};
+pub use builder::PresenceBuilder;
+pub use buttons::{ButtonConfig, ButtonError, ButtonTemplate};
+pub use category::Category;
+pub use config::{Config, ConfigError};
+pub use crypto::{CryptoError, CryptoManager, Opened};
+pub use discord::{
+    ClientBuild, ClientInstance, ClientSelector, ClientSelectorError, ConnectionEvent,
+    ConnectionState, DiscordError, DiscordManager, DiscordUser, ReconnectPolicy, UpdateResult,
+};
+pub use error::Error;
+pub use fit::{fit_presence, FitOptions};
+pub use i18n::{Locale, Msg};
+pub use ipc::{IpcClient, IpcError};
+pub use keyring::{KeyEntry, Keyring, KeyringCrypto, KeyringError, Rotation};
+pub use lanyard::{KvConfig, KvPrefixError, LanyardError, LanyardKvSink};
+pub use lanyard_socket::{LanyardEvent, LanyardSocket, Verification};
 pub use parser::{extract_app_name, sanitize_title, WindowInfo};
-pub use window::{get_active_window_title, WindowMonitor};
+pub use presence::{ActivityType, ActivityTypeError, Button, Presence, PresenceSource};
+pub use redact::{RedactAction, RedactError, RedactionConfig, Redactor};
+pub use rules::{RuleError, RuleSet, Scope, Scoped, ScopedError, WindowContext};
+pub use scheduler::{FlushOutcome, RateLimit, RetryPolicy, SchedulerStats, UpdateScheduler};
+pub use script::{ScriptConfig, ScriptError, ScriptHook};
+pub use server::{LanyardServer, ServerError};
+pub use signals::ControlSignal;
+pub use sink::{Fanout, PresenceSink, SinkResult};
+pub use template::{Template, TemplateConfig, TemplateError, TemplateValues};
+pub use timestamps::{ActivityClock, TimestampMode, TimestampModeError};
+pub use viewer::{DecryptInput, DecryptedField, ViewerError};
+pub use window::{active_window_title, get_active_window_title, WindowError, WindowMonitor};
+pub use ws::{WebSocket, WsError};
 
 /// 库版本
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
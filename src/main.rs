@@ -1,178 +1,647 @@
-use active_window_info_to_lanyard_lib::{ Config, DiscordManager, WindowInfo, WindowMonitor };
+use active_window_info_to_lanyard_lib::config::{check_hex_key, parse_env};
+use active_window_info_to_lanyard_lib::discord::STATE_FIELD;
+use active_window_info_to_lanyard_lib::i18n::{self, Locale, Msg};
+use active_window_info_to_lanyard_lib::keyring::{self, LEGACY_KEY_NAME};
+use active_window_info_to_lanyard_lib::lanyard::DEFAULT_API_URL;
+use active_window_info_to_lanyard_lib::server::DEFAULT_SERVER_ADDR;
+use active_window_info_to_lanyard_lib::signals;
+use active_window_info_to_lanyard_lib::viewer::{self, DecryptInput, ViewerError};
+use active_window_info_to_lanyard_lib::{
+    lanyard_socket, tr, Category, Config, ConfigError, ConnectionEvent, ControlSignal,
+    CryptoManager, DiscordError, DiscordManager, Error, Fanout, FlushOutcome, KeyEntry, Keyring,
+    KeyringCrypto, LanyardKvSink, LanyardServer, LanyardSocket, PresenceBuilder, Redactor,
+    ScriptHook, WindowMonitor,
+};
 /// 跨平台 Discord Activity Monitor - 主入口
 ///
 /// 监控活动窗口并将其同步到Discord Rich Presence
 /// 支持 Windows 和 macOS 平台
-use std::{ fs::File, io::Read, thread, time::Duration };
+use std::{ fs::{File, OpenOptions}, io::{Read, Write}, path::Path, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH} };
 
-// 更新间隔（秒）
-const UPDATE_INTERVAL: u64 = 5;
+/// 设置了 `SERVER_ADDR` 时启动Lanyard兼容接口，返回是否在运行
+fn add_server(config: &Config, sinks: &mut Fanout) -> bool {
+    match LanyardServer::new(config) {
+        Ok(Some(mut server)) => {
+            if let Some(addr) = server.local_addr() {
+                println!("{}", tr!(Msg::ServerListening, addr, addr));
+            }
+            server.set_user(sinks.get::<DiscordManager>().and_then(|d| d.connected_user().cloned()));
+            sinks.add(server);
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::ServerInitFailed, e));
+            false
+        }
+    }
+}
+
+/// `verify` 命令默认等待Lanyard的时间（秒）
+const DEFAULT_VERIFY_TIMEOUT_SECS: u64 = 30;
+
+/// `rotate-key` 命令默认的宽限期（天）
+const DEFAULT_GRACE_DAYS: u64 = 7;
 
 fn main() {
+    // 在读取配置之前先按系统语言输出
+    i18n::set_locale(Locale::from_system());
+
+    // 解密命令供查看状态的一方使用，不需要完整的.env
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("decrypt") {
+        if !run_decrypt(&args[1..]) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // 读取并解析.env文件
-    let (discord_app_id, encryption_key) = read_env_config();
-    
-    // 打印欢迎信息
-    print_welcome(&discord_app_id, encryption_key.is_some());
-    
-    // 创建配置
-    let config = if let Some(key) = encryption_key {
-        println!("🔐 加密功能已启用");
-        println!();
-        
-        let cfg = Config::new_with_encryption(
-            discord_app_id.parse().expect("无效的Discord应用ID"),
-            UPDATE_INTERVAL,
-            key,
-        );
-        
-        if let Err(e) = cfg.validate() {
-            eprintln!("❌ 配置验证失败: {}", e);
+    let mut config = match Config::from_env_str(&read_env_file()) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::ConfigLoadFailed, e));
             return;
         }
-        cfg
+    };
+    if let Some(locale) = config.locale {
+        i18n::set_locale(locale);
+    }
+
+    // 子命令
+    if args.first().map(String::as_str) == Some("verify") {
+        if !run_verify(&config, &args[1..]) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.first().map(String::as_str) == Some("rotate-key") {
+        if !run_rotate_key(&args[1..]) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    // `serve` 只提供Lanyard兼容接口，不连接Discord
+    let serve_only = args.first().map(String::as_str) == Some("serve");
+    if serve_only && config.server_addr.is_none() {
+        config.server_addr = DEFAULT_SERVER_ADDR.parse().ok();
+    }
+
+    // 打印欢迎信息
+    print_welcome(&config);
+
+    if config.is_encryption_enabled() {
+        println!("{}", Msg::EncryptionEnabled.text());
     } else {
-        println!("⚠️  加密功能未启用（明文传输）");
-        println!("   提示：在.env中添加ENCRYPTION_KEY可启用加密");
-        println!();
-        
-        match Config::from_str(&discord_app_id, UPDATE_INTERVAL) {
-            Ok(cfg) => {
-                if let Err(e) = cfg.validate() {
-                    eprintln!("❌ 配置验证失败: {}", e);
-                    eprintln!("   请在 https://discord.com/developers/applications 获取应用ID");
-                    return;
-                }
-                cfg
+        println!("{}", Msg::EncryptionDisabled.text());
+    }
+    println!();
+
+    if let Err(e) = config.validate() {
+        eprintln!("{}", tr!(Msg::ConfigInvalid, e));
+        eprintln!("{}", Msg::AppIdHint.text());
+        return;
+    }
+
+    // 创建脱敏器
+    let mut redactor = match Redactor::new(&config.redaction) {
+        Ok(redactor) => redactor,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::RedactionInvalid, e));
+            return;
+        }
+    };
+
+    // 加载状态脚本（可选）
+    let mut script = match config.script {
+        Some(ref script_config) => match ScriptHook::from_config(script_config) {
+            Ok(hook) => {
+                println!("{}", tr!(Msg::ScriptLoaded, script_config.path.display()));
+                Some(hook)
             }
             Err(e) => {
-                eprintln!("❌ 配置创建失败: {}", e);
+                eprintln!("{}", tr!(Msg::ScriptLoadFailed, e));
                 return;
             }
-        }
+        },
+        None => None,
     };
 
-    // 连接到Discord RPC
-    let mut discord = match DiscordManager::connect(&config) {
-        Ok(manager) => {
-            println!("✅ 已连接到Discord RPC");
-            manager
+    // 生成与输出无关的活动状态，再分发给每个输出；
+    // 每个输出单独合并快速切换产生的更新、遵守各自的频率限制并处理自己的错误
+    let mut builder = PresenceBuilder::new(&config);
+    let mut sinks = Fanout::new();
+
+    // 连接到Discord RPC；暂时连接不上时在后台按退避策略重试
+    let connection_events = if serve_only {
+        println!("{}", Msg::ServerOnly.text());
+        None
+    } else {
+        let mut discord = match DiscordManager::new(&config) {
+            Ok(manager) => manager,
+            Err(e) => {
+                eprintln!("{}", tr!(Msg::DiscordConnectFailed, e));
+                return;
+            }
+        };
+        if discord.is_connected() {
+            println!("{}", Msg::DiscordConnected.text());
+            print_discord_user(&discord);
+        } else {
+            println!("{}", Msg::DiscordWaiting.text());
         }
+        let events = discord.subscribe();
+        sinks.add(discord);
+        Some(events)
+    };
+    add_lanyard_kv(&config, &mut sinks);
+    if !add_server(&config, &mut sinks) && serve_only {
+        return;
+    }
+
+    // Ctrl-C / SIGTERM 时清除状态后退出，SIGHUP 时重新加载配置
+    let signals = match signals::listen() {
+        Ok(receiver) => Some(receiver),
         Err(e) => {
-            eprintln!("❌ 连接Discord失败: {}", e);
-            return;
+            eprintln!("{}", tr!(Msg::SignalListenFailed, e));
+            None
         }
     };
 
     // 创建窗口监控器
     let mut window_monitor = WindowMonitor::new();
+    let mut away = false;
 
-    println!("👀 开始监控活动窗口...\n");
+    println!("{}", Msg::MonitoringStarted.text());
 
     // 主循环
     loop {
+        sinks.poll();
+
         if let Some(window_title) = window_monitor.check_for_change() {
-            println!("🔄 窗口变化: {}", window_title);
-
-            // 解析窗口信息
-            let window_info = WindowInfo::parse(&window_title);
-
-            // 更新Discord状态
-            let mut attempts = 0;
-            let mut updated = false;
-            while attempts < 3 && !updated {
-                attempts += 1;
-                match discord.update_activity(&window_info, &window_title) {
-                    Ok(_) => {
-                        println!("✅ Discord状态已更新 (尝试 {} 次)", attempts);
-                        updated = true;
-                    }
-                    Err(e) => {
-                        eprintln!("⚠️  更新Discord失败 (第 {} 次尝试): {}", attempts, e);
-                        if attempts >= 3 {
-                            // 回滚窗口记录，下一轮循环会再次检测到变化并重试
-                            window_monitor.revert_last_change();
-                        } else {
-                            // 短暂等待后重试
-                            thread::sleep(Duration::from_millis(300));
-                        }
+            away = false;
+
+            // 解析窗口信息，并在发送前脱敏
+            let (window_title, window_info) = redactor.redact_title(&window_title);
+            let category = Category::classify(&window_info.app_name);
+            println!("{}", tr!(Msg::WindowChanged, window_title, category.label()));
+
+            // 生成活动状态，并交给脚本做最终变换；脚本返回空时不发送，其余流程照常进行
+            let mut presence = Some(builder.presence_for(&window_title, &window_info));
+            if let (Some(hook), Some(built)) = (&script, &presence) {
+                match hook.transform(&window_title, &window_info, built) {
+                    Ok(transformed) => presence = transformed,
+                    Err(e) => eprintln!("{}", tr!(Msg::ScriptFallback, e)),
+                }
+                if presence.is_none() {
+                    println!("{}", Msg::ScriptSuppressed.text());
+                }
+            }
+
+            if let Some(presence) = presence {
+                sinks.submit(&presence);
+            }
+        } else if window_monitor.last_title().is_empty() && !away {
+            // 没有活动窗口（如锁屏），显示为离开
+            println!("{}", Msg::WindowLost.text());
+            sinks.submit(&builder.away_presence());
+            away = true;
+        }
+
+        // 令牌足够时立即发送；失败时由各输出决定退避后重试还是放弃
+        for (name, outcome) in sinks.flush() {
+            match outcome {
+                FlushOutcome::Sent => println!("{}", tr!(Msg::SinkUpdated, name)),
+                FlushOutcome::Deferred(wait) => {
+                    println!("{}", tr!(Msg::SinkUpdateDeferred, name, wait.as_millis()))
+                }
+                FlushOutcome::Failed(e) => eprintln!("{}", tr!(Msg::SinkUpdateFailed, name, e)),
+                FlushOutcome::GaveUp { attempts, error } => {
+                    eprintln!("{}", tr!(Msg::SinkUpdateGaveUp, name, attempts, error))
+                }
+                FlushOutcome::Idle => {}
+            }
+        }
+
+        for event in connection_events.iter().flat_map(|events| events.try_iter()) {
+            println!("{}", event);
+            if let ConnectionEvent::Connected { .. } = event
+                && let Some(discord) = sinks.get::<DiscordManager>()
+            {
+                print_discord_user(discord);
+                let user = discord.connected_user().cloned();
+                if let Some(server) = sinks.get_mut::<LanyardServer>() {
+                    server.set_user(user);
+                }
+            }
+        }
+
+        // 等待指定时间后再次检查；有被限流的更新时提前醒来发送，收到信号时立即处理
+        let wait = sinks
+            .next_flush_in(Instant::now())
+            .map_or(config.update_interval, |wait| wait.min(config.update_interval));
+        match signals::wait(signals.as_ref(), wait) {
+            Some(ControlSignal::Shutdown) => {
+                // 退出时直接清除状态，尚未发送的更新不再需要
+                println!("{}", Msg::ShutdownStarted.text());
+                for (name, stats) in sinks.stats() {
+                    println!("{}", tr!(Msg::SinkStats, name, stats));
+                }
+                let failures = sinks.shutdown(config.shutdown_timeout);
+                for (name, e) in &failures {
+                    eprintln!("{}", tr!(Msg::ShutdownFailed, name, e));
+                }
+                if failures.is_empty() {
+                    println!("{}", Msg::ShutdownDone.text());
+                }
+                return;
+            }
+            Some(ControlSignal::Reload) => {
+                match reload(&mut builder, &mut sinks) {
+                    Ok((new_config, new_redactor, new_script)) => {
+                        (config, redactor, script) = (new_config, new_redactor, new_script);
+                        // 让当前窗口按新配置重新生成一次状态
+                        window_monitor = WindowMonitor::new();
+                        away = false;
+                        println!("{}", Msg::ConfigReloaded.text());
                     }
+                    Err(e) => eprintln!("{}", tr!(Msg::ConfigReloadFailed, e)),
                 }
             }
+            None => {}
         }
+    }
+}
+
+/// 重新读取.env并应用到状态生成器和各个输出（SIGHUP）
+///
+/// 配置无效时不修改任何设置；个别输出无法应用新配置时只给出警告，该输出继续使用原来的配置
+fn reload(
+    builder: &mut PresenceBuilder,
+    sinks: &mut Fanout,
+) -> Result<(Config, Redactor, Option<ScriptHook>), Error> {
+    let contents = std::fs::read_to_string(".env")?;
+    let config = Config::from_env_str(&contents)?;
+    config.validate()?;
+    let redactor = Redactor::new(&config.redaction).map_err(ConfigError::Redaction)?;
+    let script = config.script.as_ref().map(ScriptHook::from_config).transpose()?;
+
+    builder.reload(&config);
+    for (name, e) in sinks.reload(&config) {
+        eprintln!("{}", tr!(Msg::SinkReloadFailed, name, e));
+    }
+    if sinks.get::<LanyardKvSink>().is_none() {
+        add_lanyard_kv(&config, sinks);
+    }
+    if sinks.get::<LanyardServer>().is_none() {
+        add_server(&config, sinks);
+    }
+    if let Some(locale) = config.locale {
+        i18n::set_locale(locale);
+    }
+    Ok((config, redactor, script))
+}
 
-        // 等待指定时间后再次检查
-        thread::sleep(config.update_interval);
+/// 配置了Lanyard API密钥时，把状态同时写入Lanyard KV
+fn add_lanyard_kv(config: &Config, sinks: &mut Fanout) {
+    match LanyardKvSink::new(config) {
+        Ok(Some(sink)) => {
+            let prefix = config.lanyard_kv.as_ref().map_or("", |kv| kv.prefix.as_str());
+            println!("{}", tr!(Msg::LanyardKvEnabled, prefix, sink.user_id()));
+            sinks.add(sink);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("{}", tr!(Msg::LanyardKvInitFailed, e)),
+    }
+}
+
+/// `verify` 命令：发布一条探测状态，确认它出现在Lanyard中并报告延迟，之后清除状态
+///
+/// 返回是否验证成功
+fn run_verify(config: &Config, args: &[String]) -> bool {
+    let timeout = match args.first().map(|value| value.parse::<u64>()) {
+        None => Duration::from_secs(DEFAULT_VERIFY_TIMEOUT_SECS),
+        Some(Ok(secs)) if secs > 0 => Duration::from_secs(secs),
+        Some(_) => {
+            eprintln!("{}", tr!(Msg::VerifyUsage, DEFAULT_VERIFY_TIMEOUT_SECS));
+            return false;
+        }
+    };
+    if let Err(e) = config.validate() {
+        eprintln!("{}", tr!(Msg::ConfigInvalid, e));
+        return false;
+    }
+
+    let mut discord = match DiscordManager::new(config) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::DiscordConnectFailed, e));
+            return false;
+        }
+    };
+    // Discord暂时连接不上时在超时时间内等待后台重连
+    let deadline = Instant::now() + timeout;
+    if !discord.is_connected() {
+        println!("{}", Msg::VerifyWaitingForDiscord.text());
+    }
+    while !discord.poll() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+    }
+    if !discord.is_connected() {
+        eprintln!("{}", tr!(Msg::VerifyFailed, DiscordError::NotConnected));
+        return false;
+    }
+    print_discord_user(&discord);
+
+    let Some(user_id) = discord.lanyard_user_id().map(str::to_string) else {
+        eprintln!("{}", Msg::VerifyNoUser.text());
+        return false;
+    };
+    println!("{}", tr!(Msg::VerifySubscribing, user_id, config.lanyard_socket_url));
+
+    let result = LanyardSocket::connect(&config.lanyard_socket_url, &user_id, timeout)
+        .map_err(Error::from)
+        .and_then(|mut socket| {
+            // 先等待订阅后的初始状态，避免把它误认为新状态
+            socket.next_event(timeout)?;
+            let presence = lanyard_socket::probe_presence(discord.start_time());
+            println!("{}", tr!(Msg::VerifyPublished, presence.state.as_deref().unwrap_or_default()));
+            let verification = lanyard_socket::verify(&mut discord, &mut socket, &presence, timeout);
+            socket.close();
+            verification
+        });
+    let verified = match result {
+        Ok(verification) => {
+            println!("{}", tr!(Msg::VerifySucceeded, verification.latency.as_millis()));
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::VerifyFailed, e));
+            false
+        }
+    };
+
+    if let Err(e) = discord.shutdown(config.shutdown_timeout) {
+        eprintln!("{}", tr!(Msg::ShutdownFailed, "Discord", e));
+    }
+    verified
+}
+
+/// `rotate-key` 命令：生成新密钥写入.env，旧密钥在宽限期结束、新密钥启用时过期
+///
+/// 已过期的密钥被移除，原文件保存为仅所有者可读写的 `.env.<时间戳>.bak`（不覆盖之前的备份），
+/// 新内容先写入同目录下的临时文件再替换.env。返回是否成功
+fn run_rotate_key(args: &[String]) -> bool {
+    let grace_days = match args {
+        [] => Some(DEFAULT_GRACE_DAYS),
+        [flag, days] if flag == "--grace-days" => days.parse::<u64>().ok(),
+        _ => None,
+    };
+    let Some(grace_days) = grace_days else {
+        eprintln!("{}", tr!(Msg::RotateKeyUsage, DEFAULT_GRACE_DAYS));
+        return false;
+    };
+    let contents = match std::fs::read_to_string(".env") {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::RotateKeyReadFailed, e));
+            return false;
+        }
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let grace = Duration::from_secs(grace_days.saturating_mul(24 * 60 * 60));
+    let new_key = CryptoManager::key_to_hex(&CryptoManager::generate_key());
+    let (text, rotation) = match keyring::rotate_env(&contents, &new_key, now, grace) {
+        Ok(rotated) => rotated,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::RotateKeyFailed, e));
+            return false;
+        }
+    };
+    let backup = format!(".env.{}.bak", now);
+    if let Err(e) = write_private(Path::new(&backup), &contents) {
+        eprintln!("{}", tr!(Msg::RotateKeyWriteFailed, backup, e));
+        return false;
+    }
+    if let Err(e) = replace_file(Path::new(".env"), &text) {
+        eprintln!("{}", tr!(Msg::RotateKeyWriteFailed, ".env", e));
+        return false;
+    }
+
+    println!("{}", tr!(Msg::RotateKeyBackup, backup));
+    for id in &rotation.removed {
+        println!("{}", tr!(Msg::RotateKeyRemoved, id));
+    }
+    for entry in &rotation.retired {
+        let expires = entry.expires.map(keyring::format_time).unwrap_or_default();
+        println!("{}", tr!(Msg::RotateKeyRetired, entry.id, expires));
+    }
+    let new_key = &rotation.new_key;
+    let created = new_key.created.map(keyring::format_time).unwrap_or_default();
+    println!("{}", tr!(Msg::RotateKeyNew, new_key.id, new_key.id, created));
+    println!("{}", new_key.key);
+    println!();
+    println!("{}", Msg::RotateKeyHint.text());
+    true
+}
+
+/// 新建一个仅所有者可读写（0600）的文件，文件已存在时失败
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// 原子地替换文件内容：写入同目录下的临时文件，沿用原文件的权限后重命名覆盖原文件
+fn replace_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let temp = path.with_file_name(format!("{}.{}.tmp", name, std::process::id()));
+    let result = write_private(&temp, contents)
+        .and_then(|()| match std::fs::metadata(path) {
+            Ok(metadata) => std::fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|()| std::fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// `decrypt` 命令：解密单条密文、Lanyard JSON（参数、文件或 `-` 表示标准输入）或某个用户在Lanyard上的状态
+///
+/// `--key` 可以重复指定；没有 `--key` / `--url` 时使用.env中的密钥环（`ENCRYPTION_KEY` 和
+/// `ENCRYPTION_KEY_<ID>`）/ `LANYARD_API_URL`（如果有）。输出中附带密文的加密时间，
+/// 指定 `--max-age <秒数>` 时拒绝更早加密的内容。返回是否成功
+fn run_decrypt(args: &[String]) -> bool {
+    let env = std::fs::read_to_string(".env").map(|contents| parse_env(&contents)).unwrap_or_default();
+    let from_env = |name: &str| {
+        env.iter().rev().find(|(key, value)| key == name && !value.is_empty()).map(|(_, value)| value.clone())
+    };
+    let mut base_url = from_env("LANYARD_API_URL");
+    let mut keys = Vec::new();
+    let mut field = None;
+    let mut max_age = None;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => keys.extend(args.next().cloned()),
+            "--url" => base_url = args.next().cloned(),
+            "--field" => field = args.next().cloned(),
+            "--max-age" => match args.next().and_then(|secs| secs.parse::<u64>().ok()) {
+                Some(secs) => max_age = Some(secs),
+                None => {
+                    eprintln!("{}", Msg::DecryptUsage.text());
+                    return false;
+                }
+            },
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    let [input] = inputs.as_slice() else {
+        eprintln!("{}", Msg::DecryptUsage.text());
+        return false;
+    };
+    // 命令行传入的密钥没有ID，可以解密任何密钥ID的密文
+    let crypto = if keys.is_empty() {
+        let mut keyring = match Keyring::from_env(&env) {
+            Ok(keyring) => keyring,
+            Err(e) => {
+                eprintln!("{}", tr!(Msg::DecryptFailed, e));
+                return false;
+            }
+        };
+        if let Some(key) = from_env(LEGACY_KEY_NAME) {
+            keyring.insert(KeyEntry::new(0, key));
+        }
+        if let Some(Err(e)) = keyring.keys().iter().map(|entry| check_hex_key(&entry.key)).find(Result::is_err) {
+            eprintln!("{}", tr!(Msg::DecryptFailed, e));
+            return false;
+        }
+        keyring.crypto()
+    } else {
+        if let Some(Err(e)) = keys.iter().map(|key| check_hex_key(key)).find(Result::is_err) {
+            eprintln!("{}", tr!(Msg::DecryptFailed, e));
+            return false;
+        }
+        keys.iter()
+            .map(|key| CryptoManager::from_hex(key))
+            .collect::<Result<_, _>>()
+            .map(|keys| Some(KeyringCrypto::new(keys)))
+    };
+    let crypto = match crypto {
+        Ok(Some(crypto)) => crypto,
+        Ok(None) => {
+            eprintln!("{}", Msg::DecryptNoKey.text());
+            return false;
+        }
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::DecryptFailed, ViewerError::Key(e)));
+            return false;
+        }
+    };
+
+    // 参数为 `-` 或已存在的文件时读取其内容
+    let text = if input == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map(|_| text)
+    } else if std::path::Path::new(input).is_file() {
+        std::fs::read_to_string(input)
+    } else {
+        Ok(input.to_string())
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::DecryptReadFailed, input, e));
+            return false;
+        }
+    };
+
+    let base_url = base_url.unwrap_or_else(|| DEFAULT_API_URL.to_string());
+    // 单条密文默认按 `state` 字段解密，其他字段（如KV中的标题）用 `--field` 指定键名
+    let field = field.as_deref().unwrap_or(STATE_FIELD);
+    let input = DecryptInput::parse(&text);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let fields = input.and_then(|input| viewer::decrypt_with(&crypto, &input, field, &base_url)).and_then(|fields| {
+        match max_age {
+            Some(max_age) => viewer::check_max_age(&fields, now, max_age).map(|()| fields),
+            None => Ok(fields),
+        }
+    });
+    match fields {
+        Ok(fields) => {
+            for field in fields {
+                let text = match field.timestamp {
+                    Some(timestamp) => tr!(Msg::DecryptEncryptedAt, field.plaintext, keyring::format_time(timestamp)),
+                    None => field.plaintext,
+                };
+                if field.path.is_empty() {
+                    println!("{}", text);
+                } else {
+                    println!("{}: {}", field.path, text);
+                }
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", tr!(Msg::DecryptFailed, e));
+            false
+        }
     }
 }
 
 /// 打印欢迎信息
-fn print_welcome(discord_app_id: &str, encryption_enabled: bool) {
-    println!("╔════════════════════════════════════════════════╗");
-    println!("║  Discord Activity Monitor                     ║");
-    println!("║  活动窗口监控 → Discord Rich Presence         ║");
-    println!("║  支持: Windows & macOS                        ║");
-    println!("╚════════════════════════════════════════════════╝");
+fn print_welcome(config: &Config) {
+    println!("{}", Msg::WelcomeBanner.text());
     println!();
-    println!("📝 版本: {}", active_window_info_to_lanyard_lib::VERSION);
-    println!("⏱️  更新间隔: {} 秒", UPDATE_INTERVAL);
-    println!("🔧 Discord应用ID: {}", discord_app_id);
-    if encryption_enabled {
-        println!("🔐 加密: 已启用");
+    println!("{}", tr!(Msg::WelcomeVersion, active_window_info_to_lanyard_lib::VERSION));
+    println!("{}", tr!(Msg::WelcomeInterval, config.update_interval.as_secs()));
+    println!("{}", tr!(Msg::WelcomeAppId, config.discord_app_id));
+    if config.is_encryption_enabled() {
+        println!("{}", Msg::WelcomeEncryptionOn.text());
     } else {
-        println!("🔓 加密: 未启用");
+        println!("{}", Msg::WelcomeEncryptionOff.text());
     }
+    let redaction_rules = config.redaction.detectors.len() + config.redaction.user_patterns.len();
+    println!("{}", tr!(Msg::WelcomeRedactionRules, redaction_rules));
     println!();
 }
 
-/// 从.env文件读取配置
-fn read_env_config() -> (String, Option<String>) {
+/// 打印登录的Discord账号和对应的Lanyard地址，账号与配置不一致时给出警告
+fn print_discord_user(discord: &DiscordManager) {
+    for instance in discord.instances() {
+        let path = instance.path.map(|path| path.display().to_string()).unwrap_or_default();
+        let user = instance.user.map(|user| user.display_name().to_string()).unwrap_or_default();
+        println!("{}", tr!(Msg::DiscordInstance, instance.build, user, path));
+    }
+    if let Some(user) = discord.connected_user() {
+        println!("{}", tr!(Msg::DiscordLoggedInAs, user.display_name(), user.id));
+    }
+    if let Some(url) = discord.lanyard_url() {
+        println!("{}", tr!(Msg::DiscordLanyardUrl, url));
+    }
+    if let Some((expected, actual)) = discord.user_mismatch() {
+        eprintln!("{}", tr!(Msg::DiscordUserMismatch, actual, expected));
+    }
+}
+
+/// 读取.env文件内容
+fn read_env_file() -> String {
     let mut file = File::open(".env").unwrap_or_else(|_| {
-        eprintln!("❌ 未找到.env文件");
-        eprintln!("   请在项目根目录创建.env文件");
-        eprintln!("   格式:");
-        eprintln!("   DISCORD_APP_ID=你的应用ID");
-        eprintln!("   ENCRYPTION_KEY=你的加密密钥（可选）");
+        eprintln!("{}", Msg::EnvFileMissing.text());
         std::process::exit(1);
     });
 
     let mut contents = String::new();
     file.read_to_string(&mut contents)
-        .expect("无法读取.env文件");
-
-    let mut app_id = None;
-    let mut encryption_key = None;
-
-    // 逐行解析
-    for line in contents.lines() {
-        let line = line.trim();
-        
-        // 跳过空行和注释
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        // 解析键值对
-        if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim();
-            let value = value.trim();
-
-            match key {
-                "DISCORD_APP_ID" => app_id = Some(value.to_string()),
-                "ENCRYPTION_KEY" => {
-                    if !value.is_empty() {
-                        encryption_key = Some(value.to_string());
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-
-    let app_id = app_id.unwrap_or_else(|| {
-        eprintln!("❌ .env文件中未设置DISCORD_APP_ID");
-        std::process::exit(1);
-    });
+        .unwrap_or_else(|_| panic!("{}", Msg::EnvFileUnreadable.text()));
 
-    (app_id, encryption_key)
+    contents
 }
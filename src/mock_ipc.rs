@@ -0,0 +1,427 @@
+//! 模拟Discord IPC服务端模块
+//!
+//! 在临时目录中监听 `discord-ipc-0` 套接字，按真实Discord客户端的方式完成握手、回复命令，
+//! 并记录收到的 `SET_ACTIVITY` 负载，用于在没有Discord的环境下测试 `DiscordManager`。
+//!
+//! 可以为下一次握手或下一条命令注入故障（错误回复、关闭、断开、延迟、限流），
+//! 故障按注入顺序逐个消耗。目前仅支持Unix平台。
+
+use std::collections::VecDeque;
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+use serde_json::{json, Value};
+
+use crate::ipc::{read_frame, write_frame, Opcode};
+
+/// 模拟限流时使用的错误码（与Discord RPC的 `RATE_LIMITED` 关闭码一致）
+pub const RATE_LIMIT_CODE: i64 = 4002;
+
+/// 注入的故障
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// 以ERROR事件回复，连接保持
+    Error { code: i64, message: String },
+    /// 发送CLOSE帧后断开连接
+    Close { code: i64, message: String },
+    /// 不回复，直接断开连接
+    Disconnect,
+    /// 等待指定时间后正常回复
+    Delay(Duration),
+    /// 以限流错误回复，连接保持
+    RateLimited,
+}
+
+/// 服务端共享状态
+#[derive(Default)]
+struct State {
+    handshakes: Vec<Value>,
+    activities: Vec<Value>,
+    handshake_faults: VecDeque<Fault>,
+    command_faults: VecDeque<Fault>,
+    response_delay: Duration,
+    user: Option<Value>,
+    api_endpoint: Option<String>,
+    connections: Vec<(u64, UnixStream)>,
+    next_connection: u64,
+}
+
+/// 模拟的Discord IPC服务端
+///
+/// 被丢弃时关闭所有连接并删除临时目录
+pub struct MockDiscordServer {
+    dir: PathBuf,
+    owns_dir: bool,
+    path: PathBuf,
+    state: Arc<Mutex<State>>,
+    stopped: Arc<AtomicBool>,
+    accept_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MockDiscordServer {
+    /// 在新建的临时目录中启动服务端
+    pub fn start() -> io::Result<Self> {
+        let mut bytes = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let dir = std::env::temp_dir().join(format!("mock-discord-{}", name));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut server = Self::start_in(&dir)?;
+        server.owns_dir = true;
+        Ok(server)
+    }
+
+    /// 在指定目录中启动服务端，套接字为 `<dir>/discord-ipc-0`
+    ///
+    /// 目录中残留的同名套接字会被替换
+    pub fn start_in(dir: &Path) -> io::Result<Self> {
+        Self::start_at(&dir.join("discord-ipc-0"))
+    }
+
+    /// 在指定的套接字路径启动服务端（用于模拟同时运行的多个Discord客户端）
+    ///
+    /// 残留的同名套接字会被替换
+    pub fn start_at(path: &Path) -> io::Result<Self> {
+        let path = path.to_path_buf();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let accept_thread = {
+            let state = Arc::clone(&state);
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || accept_loop(listener, state, stopped))
+        };
+
+        Ok(Self {
+            dir,
+            owns_dir: false,
+            path,
+            state,
+            stopped,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// 套接字路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 套接字所在目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 收到的所有握手负载
+    pub fn handshakes(&self) -> Vec<Value> {
+        self.lock().handshakes.clone()
+    }
+
+    /// 收到的所有 `SET_ACTIVITY` 中的 `activity`（清除状态时为 `null`）
+    pub fn activities(&self) -> Vec<Value> {
+        self.lock().activities.clone()
+    }
+
+    /// 最近一次收到的 `activity`
+    pub fn last_activity(&self) -> Option<Value> {
+        self.lock().activities.last().cloned()
+    }
+
+    /// 清空已记录的握手和活动
+    pub fn clear_records(&self) {
+        let mut state = self.lock();
+        state.handshakes.clear();
+        state.activities.clear();
+    }
+
+    /// 为之后的某次握手注入故障
+    pub fn inject_handshake_fault(&self, fault: Fault) {
+        self.lock().handshake_faults.push_back(fault);
+    }
+
+    /// 为之后的某条命令注入故障
+    pub fn inject_fault(&self, fault: Fault) {
+        self.lock().command_faults.push_back(fault);
+    }
+
+    /// 设置每次回复前的固定延迟
+    pub fn set_response_delay(&self, delay: Duration) {
+        self.lock().response_delay = delay;
+    }
+
+    /// 设置READY事件中的用户信息
+    pub fn set_user(&self, user: Value) {
+        self.lock().user = Some(user);
+    }
+
+    /// 设置READY事件中的 `config.api_endpoint`，用于模拟PTB、Canary等客户端版本
+    pub fn set_api_endpoint(&self, endpoint: &str) {
+        self.lock().api_endpoint = Some(endpoint.to_string());
+    }
+
+    /// 当前打开的连接数
+    pub fn connection_count(&self) -> usize {
+        self.lock().connections.len()
+    }
+
+    /// 断开所有已建立的连接，服务端继续监听
+    pub fn disconnect_all(&self) {
+        for (_, stream) in self.lock().connections.drain(..) {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// 等待直到记录到至少 `count` 个活动，超时返回 `false`
+    pub fn wait_for_activities(&self, count: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.lock().activities.len() >= count {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        self.lock().activities.len() >= count
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockDiscordServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // 连接一次以唤醒阻塞在accept上的线程
+        let _ = UnixStream::connect(&self.path);
+        if let Some(handle) = self.accept_thread.take() {
+            let _ = handle.join();
+        }
+        self.disconnect_all();
+        let _ = std::fs::remove_file(&self.path);
+        if self.owns_dir {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// 接受连接，每个连接由单独的线程处理
+fn accept_loop(listener: UnixListener, state: Arc<Mutex<State>>, stopped: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let id = {
+            let mut state = lock(&state);
+            let id = state.next_connection;
+            state.next_connection += 1;
+            if let Ok(clone) = stream.try_clone() {
+                state.connections.push((id, clone));
+            }
+            id
+        };
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            let _ = serve(stream, &state);
+            lock(&state).connections.retain(|(other, _)| *other != id);
+        });
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 按故障处理一次回复（`Delay` 由调用方处理）；返回 `false` 表示连接已结束
+fn apply_fault(stream: &mut UnixStream, fault: &Fault, nonce: &Value) -> io::Result<bool> {
+    match fault {
+        Fault::Error { code, message } => {
+            write_error(stream, nonce, *code, message)?;
+            Ok(true)
+        },
+        Fault::RateLimited => {
+            write_error(stream, nonce, RATE_LIMIT_CODE, "You are being rate limited.")?;
+            Ok(true)
+        },
+        Fault::Close { code, message } => {
+            let payload = json!({ "code": code, "message": message });
+            let _ = write_frame(stream, Opcode::Close, &payload);
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+            Ok(false)
+        },
+        Fault::Disconnect => {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+            Ok(false)
+        },
+        Fault::Delay(_) => Ok(true),
+    }
+}
+
+fn write_error(stream: &mut UnixStream, nonce: &Value, code: i64, message: &str) -> io::Result<()> {
+    let payload = json!({
+        "cmd": "SET_ACTIVITY",
+        "evt": "ERROR",
+        "nonce": nonce,
+        "data": { "code": code, "message": message },
+    });
+    write_frame(stream, Opcode::Frame, &payload).map_err(io::Error::other)
+}
+
+/// 处理单个连接：握手，然后循环回复命令
+fn serve(mut stream: UnixStream, state: &Mutex<State>) -> io::Result<()> {
+    let (opcode, handshake) = read_frame(&mut stream).map_err(io::Error::other)?;
+    if opcode != Opcode::Handshake {
+        return Ok(());
+    }
+
+    let (fault, delay, user, api_endpoint) = {
+        let mut state = lock(state);
+        state.handshakes.push(handshake);
+        (
+            state.handshake_faults.pop_front(),
+            state.response_delay,
+            state.user.clone(),
+            state.api_endpoint.clone(),
+        )
+    };
+    thread::sleep(delay);
+    match fault {
+        Some(Fault::Delay(extra)) => thread::sleep(extra),
+        Some(fault) => {
+            // 握手阶段的故障都会结束这次连接
+            apply_fault(&mut stream, &fault, &Value::Null)?;
+            return Ok(());
+        },
+        None => {},
+    }
+
+    let user = user.unwrap_or_else(|| {
+        json!({ "id": "1", "username": "mock", "discriminator": "0", "global_name": "Mock" })
+    });
+    let ready = json!({
+        "cmd": "DISPATCH",
+        "evt": "READY",
+        "data": {
+            "v": 1,
+            "config": { "api_endpoint": api_endpoint.as_deref().unwrap_or("//discord.com/api") },
+            "user": user,
+        },
+    });
+    write_frame(&mut stream, Opcode::Frame, &ready).map_err(io::Error::other)?;
+
+    loop {
+        let (opcode, payload) = match read_frame(&mut stream) {
+            Ok(frame) => frame,
+            Err(_) => return Ok(()),
+        };
+        match opcode {
+            Opcode::Frame => {},
+            Opcode::Ping => {
+                write_frame(&mut stream, Opcode::Pong, &payload).map_err(io::Error::other)?;
+                continue;
+            },
+            Opcode::Close => return Ok(()),
+            _ => continue,
+        }
+
+        let cmd = payload.get("cmd").cloned().unwrap_or(Value::Null);
+        let nonce = payload.get("nonce").cloned().unwrap_or(Value::Null);
+        let (fault, delay) = {
+            let mut state = lock(state);
+            if cmd == "SET_ACTIVITY" {
+                let activity = payload["args"].get("activity").cloned().unwrap_or(Value::Null);
+                state.activities.push(activity);
+            }
+            (state.command_faults.pop_front(), state.response_delay)
+        };
+
+        thread::sleep(delay);
+        match fault {
+            Some(Fault::Delay(extra)) => thread::sleep(extra),
+            Some(fault) => {
+                if !apply_fault(&mut stream, &fault, &nonce)? {
+                    return Ok(());
+                }
+                continue;
+            },
+            None => {},
+        }
+
+        let data = match payload["args"].get("activity") {
+            Some(activity) if cmd == "SET_ACTIVITY" => activity.clone(),
+            _ => json!({}),
+        };
+        let reply = json!({ "cmd": cmd, "evt": Value::Null, "nonce": nonce, "data": data });
+        write_frame(&mut stream, Opcode::Frame, &reply).map_err(io::Error::other)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{IpcClient, IpcError};
+
+    #[test]
+    fn test_records_activities() {
+        let server = MockDiscordServer::start().unwrap();
+        let mut client = IpcClient::connect_path(server.path(), 42).unwrap();
+        assert_eq!(server.handshakes()[0]["client_id"], "42");
+        assert_eq!(client.ready_data()["user"]["username"], "mock");
+
+        client.set_activity(Some(json!({ "state": "a" }))).unwrap();
+        client.set_activity(None).unwrap();
+        assert_eq!(server.activities(), vec![json!({ "state": "a" }), Value::Null]);
+    }
+
+    #[test]
+    fn test_injected_faults() {
+        let server = MockDiscordServer::start().unwrap();
+        let mut client = IpcClient::connect_path(server.path(), 1).unwrap();
+
+        server.inject_fault(Fault::RateLimited);
+        let err = client.set_activity(Some(json!({}))).unwrap_err();
+        assert!(matches!(err, IpcError::Rejected { code: RATE_LIMIT_CODE, .. }));
+
+        server.inject_fault(Fault::Delay(Duration::from_millis(20)));
+        let started = Instant::now();
+        client.set_activity(Some(json!({}))).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        server.inject_fault(Fault::Disconnect);
+        assert!(client.set_activity(Some(json!({}))).unwrap_err().is_disconnect());
+
+        // 服务端仍在监听，可以重新连接
+        let mut client = IpcClient::connect_path(server.path(), 1).unwrap();
+        client.set_activity(Some(json!({ "state": "back" }))).unwrap();
+        assert_eq!(server.activities().len(), 4);
+    }
+
+    #[test]
+    fn test_handshake_fault() {
+        let server = MockDiscordServer::start().unwrap();
+        server.inject_handshake_fault(Fault::Close { code: 4000, message: "Invalid Client ID".into() });
+        let err = IpcClient::connect_path(server.path(), 1).err().unwrap();
+        assert!(matches!(err, IpcError::Closed { code: 4000, .. }));
+        assert!(IpcClient::connect_path(server.path(), 1).is_ok());
+    }
+
+    #[test]
+    fn test_drop_removes_socket() {
+        let server = MockDiscordServer::start().unwrap();
+        let dir = server.dir().to_path_buf();
+        drop(server);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,192 @@
+//! Rich Presence数据模块
+//!
+//! 定义与具体输出无关的活动状态结构，由窗口信息构建，再交给Discord等输出发送
+
+use std::fmt;
+
+use crate::category::Category;
+use crate::i18n::Msg;
+use crate::tr;
+
+/// 活动按钮
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Button {
+    /// 按钮文字
+    pub label: String,
+    /// 点击后打开的链接
+    pub url: String,
+}
+
+/// 活动类型解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActivityTypeError {
+    /// 未知的类型
+    Unknown(String),
+    /// Discord不允许通过RPC设置的类型（`streaming`、`custom`）
+    Unsupported(String),
+}
+
+impl fmt::Display for ActivityTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ActivityTypeError::Unknown(value) => tr!(Msg::ActivityTypeUnknown, value),
+            ActivityTypeError::Unsupported(value) => tr!(Msg::ActivityTypeUnsupported, value),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ActivityTypeError {}
+
+/// 活动类型，决定Discord显示的前缀（“正在玩”、“正在听”等）
+///
+/// 直播（1）需要直播链接、自定义状态（4）不能由RPC客户端设置，因此不支持
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityType {
+    /// 正在玩
+    Playing,
+    /// 正在听
+    Listening,
+    /// 正在看
+    Watching,
+    /// 正在竞技
+    Competing,
+}
+
+impl ActivityType {
+    /// 所有支持的类型
+    pub const ALL: [ActivityType; 4] = [
+        ActivityType::Playing,
+        ActivityType::Listening,
+        ActivityType::Watching,
+        ActivityType::Competing,
+    ];
+
+    /// IPC中 `type` 字段的值
+    pub fn code(&self) -> u8 {
+        match self {
+            ActivityType::Playing => 0,
+            ActivityType::Listening => 2,
+            ActivityType::Watching => 3,
+            ActivityType::Competing => 5,
+        }
+    }
+
+    /// 配置名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActivityType::Playing => "playing",
+            ActivityType::Listening => "listening",
+            ActivityType::Watching => "watching",
+            ActivityType::Competing => "competing",
+        }
+    }
+
+    /// 解析配置名称或IPC数值，Discord会拒绝的类型返回错误
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::presence::{ActivityType, ActivityTypeError};
+    ///
+    /// assert_eq!(ActivityType::parse("Listening"), Ok(ActivityType::Listening));
+    /// assert_eq!(ActivityType::parse("3"), Ok(ActivityType::Watching));
+    /// assert_eq!(
+    ///     ActivityType::parse("streaming"),
+    ///     Err(ActivityTypeError::Unsupported("streaming".to_string()))
+    /// );
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, ActivityTypeError> {
+        let value = value.trim().to_ascii_lowercase();
+        if let Some(activity_type) = ActivityType::ALL
+            .into_iter()
+            .find(|t| t.name() == value || t.code().to_string() == value)
+        {
+            return Ok(activity_type);
+        }
+        match value.as_str() {
+            "streaming" | "custom" | "1" | "4" => Err(ActivityTypeError::Unsupported(value)),
+            _ => Err(ActivityTypeError::Unknown(value)),
+        }
+    }
+}
+
+impl fmt::Display for ActivityType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// 活动状态
+///
+/// 所有文本字段为 `None` 时表示不发送该字段
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Presence {
+    /// 第一行文字
+    pub details: Option<String>,
+    /// 第二行文字
+    pub state: Option<String>,
+    /// 大图标资源键
+    pub large_image: Option<String>,
+    /// 大图标悬停文字
+    pub large_text: Option<String>,
+    /// 小图标资源键
+    pub small_image: Option<String>,
+    /// 小图标悬停文字
+    pub small_text: Option<String>,
+    /// 开始时间戳（Unix秒）
+    pub start_timestamp: Option<u64>,
+    /// 结束时间戳（Unix秒）
+    pub end_timestamp: Option<u64>,
+    /// 按钮（最多两个）
+    pub buttons: Vec<Button>,
+    /// 活动类型，为 `None` 时由Discord按“正在玩”显示
+    pub activity_type: Option<ActivityType>,
+    /// 发布到哪个Discord应用（“正在玩”后显示的名称），为 `None` 时使用 `DISCORD_APP_ID`
+    pub application_id: Option<u64>,
+    /// 生成该状态的窗口，不发送到Discord，供其他输出使用；“离开”状态为 `None`
+    pub source: Option<PresenceSource>,
+}
+
+/// 活动状态对应的窗口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresenceSource {
+    /// 应用名称
+    pub app: String,
+    /// 应用类别
+    pub category: Category,
+    /// 完整的窗口标题（已脱敏）
+    pub title: String,
+}
+
+impl Presence {
+    /// 根据窗口信息创建默认的活动状态
+    ///
+    /// # 参数
+    /// * `app_name` - 应用名称，显示在 `details`
+    /// * `state` - 显示在 `state` 的内容（完整标题或其密文）
+    /// * `start_timestamp` - 开始时间戳
+    pub fn new(app_name: &str, state: &str, start_timestamp: u64) -> Self {
+        Self {
+            details: Some(app_name.to_string()),
+            state: Some(state.to_string()),
+            large_image: Some("windows".to_string()),
+            large_text: Some(Msg::PresenceLargeText.text().to_string()),
+            start_timestamp: Some(start_timestamp),
+            ..Self::default()
+        }
+    }
+
+    /// 没有活动窗口时显示的“离开”状态
+    ///
+    /// # 参数
+    /// * `start_timestamp` - 开始时间戳
+    pub fn away(start_timestamp: u64) -> Self {
+        Self {
+            details: Some(Msg::PresenceAway.text().to_string()),
+            large_image: Some("windows".to_string()),
+            large_text: Some(Msg::PresenceLargeText.text().to_string()),
+            start_timestamp: Some(start_timestamp),
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! 隐私信息脱敏模块
+//!
+//! 在窗口信息离开本机之前（发送到Discord等外部服务），识别并处理标题中的敏感信息。
+//! 内置邮箱、家目录路径、URL令牌、电话号码和工单号检测器（工单号默认关闭），并支持用户自定义正则。
+
+use std::fmt;
+
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+
+use crate::i18n::Msg;
+use crate::parser::{sanitize_title, WindowInfo};
+use crate::tr;
+
+/// 敏感信息匹配后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactAction {
+    /// 替换为标签，例如 `[email]`
+    Mask,
+    /// 替换为标签加短哈希，例如 `[email:1a2b3c4d]`，相同内容得到相同结果
+    Hash,
+    /// 直接移除
+    Remove,
+}
+
+impl RedactAction {
+    /// 从配置字符串解析处理方式（`mask` / `hash` / `remove`）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mask" => Some(RedactAction::Mask),
+            "hash" => Some(RedactAction::Hash),
+            "remove" => Some(RedactAction::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// 内置敏感信息检测器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detector {
+    /// 邮箱地址
+    Email,
+    /// 用户家目录下的完整路径（`/home/x/...`、`/Users/x/...`、`C:\Users\x\...`、`~/...`）
+    HomePath,
+    /// URL查询参数中的令牌（`token=`、`access_token=`、`key=` 等）
+    UrlToken,
+    /// 电话号码
+    Phone,
+    /// 工单号（如 `PROJ-123`），容易误判 `UTF-8`、`SHA-256` 等，需要手动启用
+    TicketId,
+}
+
+impl Detector {
+    /// 默认启用的内置检测器（不含工单号）
+    pub const DEFAULT: [Detector; 4] =
+        [Detector::Email, Detector::HomePath, Detector::UrlToken, Detector::Phone];
+
+    /// 所有内置检测器
+    pub const ALL: [Detector; 5] = [
+        Detector::Email,
+        Detector::HomePath,
+        Detector::UrlToken,
+        Detector::Phone,
+        Detector::TicketId,
+    ];
+
+    /// 从配置字符串解析检测器名称
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "email" => Some(Detector::Email),
+            "home_path" | "path" => Some(Detector::HomePath),
+            "url_token" | "token" => Some(Detector::UrlToken),
+            "phone" => Some(Detector::Phone),
+            "ticket_id" | "ticket" => Some(Detector::TicketId),
+            _ => None,
+        }
+    }
+
+    /// 检测器名称，同时用作掩码标签
+    pub fn name(&self) -> &'static str {
+        match self {
+            Detector::Email => "email",
+            Detector::HomePath => "path",
+            Detector::UrlToken => "token",
+            Detector::Phone => "phone",
+            Detector::TicketId => "ticket",
+        }
+    }
+
+    /// 检测器使用的正则表达式
+    ///
+    /// 如果正则中包含名为 `secret` 的捕获组，则只处理该组，其余部分保持原样
+    fn pattern(&self) -> &'static str {
+        match self {
+            Detector::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+            Detector::HomePath => {
+                r"(?:[A-Za-z]:\\(?i:Users)\\[^\\\s]+(?:\\[^\\\s]+)*\\?|/(?:home|Users)/[^/\s]+(?:/[^/\s]+)*/?|~(?:/[^/\s]+)+/?)"
+            },
+            Detector::UrlToken => {
+                r"(?i)[?&#](?:access_token|id_token|refresh_token|token|api_key|apikey|key|secret|client_secret|sig|signature|auth|password|passwd|session|sid|code)=(?P<secret>[^&#\s]+)"
+            },
+            Detector::Phone => {
+                r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[\s.-]\d{3,4}[\s.-]\d{3,4}\b|\+\d{8,15}\b|\b1[3-9]\d{9}\b"
+            },
+            Detector::TicketId => r"\b[A-Z][A-Z0-9]{1,9}-[1-9]\d*\b",
+        }
+    }
+}
+
+/// 用户自定义的脱敏规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserPattern {
+    /// 规则名称，用作掩码标签
+    pub name: String,
+    /// 正则表达式
+    pub pattern: String,
+    /// 处理方式（为空时使用默认处理方式）
+    pub action: Option<RedactAction>,
+}
+
+/// 脱敏配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionConfig {
+    /// 启用的内置检测器及其处理方式（为空时使用默认处理方式）
+    pub detectors: Vec<(Detector, Option<RedactAction>)>,
+    /// 用户自定义规则
+    pub user_patterns: Vec<UserPattern>,
+    /// 默认处理方式
+    pub default_action: RedactAction,
+    /// 计算哈希时使用的盐
+    pub hash_salt: String,
+}
+
+impl RedactionConfig {
+    /// 不做任何脱敏的配置
+    pub fn disabled() -> Self {
+        Self {
+            detectors: Vec::new(),
+            user_patterns: Vec::new(),
+            default_action: RedactAction::Mask,
+            hash_salt: String::new(),
+        }
+    }
+
+    /// 设置某个内置检测器的处理方式（检测器未启用时不生效）
+    pub fn set_detector_action(&mut self, detector: Detector, action: RedactAction) {
+        for (d, a) in self.detectors.iter_mut() {
+            if *d == detector {
+                *a = Some(action);
+            }
+        }
+    }
+}
+
+impl Default for RedactionConfig {
+    /// 默认启用工单号以外的内置检测器，并使用掩码处理
+    fn default() -> Self {
+        Self {
+            detectors: Detector::DEFAULT.iter().map(|d| (*d, None)).collect(),
+            ..Self::disabled()
+        }
+    }
+}
+
+/// 编译后的单条规则
+#[derive(Debug)]
+struct Rule {
+    label: String,
+    regex: Regex,
+    action: RedactAction,
+}
+
+/// 脱敏配置错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedactError {
+    /// 自定义正则无法编译（规则名称和错误）
+    BadPattern { name: String, source: regex::Error },
+}
+
+impl fmt::Display for RedactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedactError::BadPattern { name, source } => {
+                f.write_str(&tr!(Msg::RedactBadPattern, name, source))
+            },
+        }
+    }
+}
+
+impl std::error::Error for RedactError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RedactError::BadPattern { source, .. } => Some(source),
+        }
+    }
+}
+
+/// 脱敏器
+///
+/// 按顺序应用内置检测器和用户规则
+#[derive(Debug)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+    hash_salt: String,
+}
+
+impl Redactor {
+    /// 根据配置创建脱敏器
+    ///
+    /// # 错误
+    /// 如果用户自定义正则无法编译，返回错误
+    pub fn new(config: &RedactionConfig) -> Result<Self, RedactError> {
+        let mut rules = Vec::new();
+
+        for (detector, action) in &config.detectors {
+            rules.push(Rule {
+                label: detector.name().to_string(),
+                regex: Regex::new(detector.pattern()).expect("内置正则必须有效"),
+                action: action.unwrap_or(config.default_action),
+            });
+        }
+
+        for user in &config.user_patterns {
+            let regex = Regex::new(&user.pattern).map_err(|source| RedactError::BadPattern {
+                name: user.name.clone(),
+                source,
+            })?;
+            rules.push(Rule {
+                label: user.name.to_lowercase(),
+                regex,
+                action: user.action.unwrap_or(config.default_action),
+            });
+        }
+
+        Ok(Self {
+            rules,
+            hash_salt: config.hash_salt.clone(),
+        })
+    }
+
+    /// 是否没有任何规则
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 对文本进行脱敏
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::redact::{RedactionConfig, Redactor};
+    ///
+    /// let redactor = Redactor::new(&RedactionConfig::default()).unwrap();
+    /// assert_eq!(redactor.redact("Inbox - me@example.com"), "Inbox - [email]");
+    /// ```
+    pub fn redact(&self, text: &str) -> String {
+        if self.rules.is_empty() {
+            return text.to_string();
+        }
+
+        let mut result = text.to_string();
+        for rule in &self.rules {
+            result = rule
+                .regex
+                .replace_all(&result, |caps: &Captures| self.replace(rule, caps))
+                .into_owned();
+        }
+
+        if self.rules.iter().any(|r| r.action == RedactAction::Remove) {
+            sanitize_title(&result)
+        } else {
+            result
+        }
+    }
+
+    /// 对窗口信息的应用名称和详细信息分别脱敏
+    pub fn redact_info(&self, info: &WindowInfo) -> WindowInfo {
+        WindowInfo::new(self.redact(&info.app_name), self.redact(&info.details))
+    }
+
+    /// 解析原始窗口标题后脱敏，返回脱敏后的标题和窗口信息
+    ///
+    /// 先解析再脱敏，标题只处理一次，脱敏也不会改变应用名称和详细信息的分界；
+    /// 返回的标题由脱敏后的两部分重新拼接
+    pub fn redact_title(&self, title: &str) -> (String, WindowInfo) {
+        let info = self.redact_info(&WindowInfo::parse(title));
+        let title = if info.details.is_empty() {
+            info.app_name.clone()
+        } else {
+            format!("{} - {}", info.details, info.app_name)
+        };
+        (title, info)
+    }
+
+    /// 生成单个匹配的替换文本
+    fn replace(&self, rule: &Rule, caps: &Captures) -> String {
+        let whole = caps.get(0).expect("匹配总是包含第0组");
+        let Some(secret) = caps.name("secret") else {
+            return self.substitute(rule, whole.as_str());
+        };
+
+        // 只替换secret组，保留前后内容（如URL参数名）
+        let start = secret.start() - whole.start();
+        let end = secret.end() - whole.start();
+        let matched = whole.as_str();
+        format!(
+            "{}{}{}",
+            &matched[..start],
+            self.substitute(rule, secret.as_str()),
+            &matched[end..]
+        )
+    }
+
+    fn substitute(&self, rule: &Rule, matched: &str) -> String {
+        match rule.action {
+            RedactAction::Mask => format!("[{}]", rule.label),
+            RedactAction::Hash => format!("[{}:{}]", rule.label, self.short_hash(matched)),
+            RedactAction::Remove => String::new(),
+        }
+    }
+
+    /// 计算加盐SHA-256，取前8个十六进制字符
+    fn short_hash(&self, value: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.hash_salt.as_bytes());
+        hasher.update(value.as_bytes());
+        hasher.finalize()[..4]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new(&RedactionConfig::default()).expect("默认脱敏配置必须有效")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(detector: Detector, action: RedactAction) -> Redactor {
+        Redactor::new(&RedactionConfig {
+            detectors: vec![(detector, Some(action))],
+            ..RedactionConfig::disabled()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_email_detector() {
+        let r = only(Detector::Email, RedactAction::Mask);
+        assert_eq!(r.redact("Inbox (3) - john.doe+x@mail.example.co.uk - Mail"), "Inbox (3) - [email] - Mail");
+        assert_eq!(r.redact("no address here"), "no address here");
+    }
+
+    #[test]
+    fn test_home_path_detector() {
+        let r = only(Detector::HomePath, RedactAction::Mask);
+        assert_eq!(r.redact("main.rs - /home/alice/code/app/src - VSCode"), "main.rs - [path] - VSCode");
+        assert_eq!(r.redact("/Users/bob/Documents/x.txt"), "[path]");
+        assert_eq!(r.redact(r"C:\Users\carol\Desktop\notes.txt - Notepad"), "[path] - Notepad");
+        assert_eq!(r.redact("~/projects/demo - zsh"), "[path] - zsh");
+        assert_eq!(r.redact("/usr/bin/env"), "/usr/bin/env");
+    }
+
+    #[test]
+    fn test_url_token_detector() {
+        let r = only(Detector::UrlToken, RedactAction::Mask);
+        assert_eq!(
+            r.redact("https://example.com/cb?state=ok&access_token=abc123&x=1 - Firefox"),
+            "https://example.com/cb?state=ok&access_token=[token]&x=1 - Firefox"
+        );
+        assert_eq!(r.redact("https://example.com/?page=2"), "https://example.com/?page=2");
+    }
+
+    #[test]
+    fn test_phone_detector() {
+        let r = only(Detector::Phone, RedactAction::Mask);
+        assert_eq!(r.redact("Call +1 (555) 123-4567 now"), "Call [phone] now");
+        assert_eq!(r.redact("555-123-4567"), "[phone]");
+        assert_eq!(r.redact("联系 13812345678"), "联系 [phone]");
+        assert_eq!(r.redact("+8613812345678"), "[phone]");
+        // 日期和版本号不应被误判
+        assert_eq!(r.redact("2024-12-25 v1.2.3"), "2024-12-25 v1.2.3");
+    }
+
+    #[test]
+    fn test_ticket_id_detector() {
+        let r = only(Detector::TicketId, RedactAction::Mask);
+        assert_eq!(r.redact("PROJ-1234: Fix login - Jira"), "[ticket]: Fix login - Jira");
+        assert_eq!(r.redact("proj-1234 lowercase"), "proj-1234 lowercase");
+    }
+
+    #[test]
+    fn test_hash_action_is_stable() {
+        let r = only(Detector::Email, RedactAction::Hash);
+        let a = r.redact("a@example.com");
+        let b = r.redact("a@example.com");
+        let c = r.redact("b@example.com");
+        assert!(a.starts_with("[email:") && a.len() == "[email:]".len() + 8);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_remove_action_collapses_whitespace() {
+        let r = only(Detector::Email, RedactAction::Remove);
+        assert_eq!(r.redact("Inbox  a@example.com  Mail"), "Inbox Mail");
+    }
+
+    #[test]
+    fn test_user_pattern() {
+        let config = RedactionConfig {
+            user_patterns: vec![UserPattern {
+                name: "HOST".to_string(),
+                pattern: r"[a-z]+\.corp\.internal".to_string(),
+                action: None,
+            }],
+            ..RedactionConfig::disabled()
+        };
+        let r = Redactor::new(&config).unwrap();
+        assert_eq!(r.redact("ssh build.corp.internal"), "ssh [host]");
+    }
+
+    #[test]
+    fn test_invalid_user_pattern() {
+        let config = RedactionConfig {
+            user_patterns: vec![UserPattern {
+                name: "BAD".to_string(),
+                pattern: "(".to_string(),
+                action: None,
+            }],
+            ..RedactionConfig::disabled()
+        };
+        assert!(matches!(
+            Redactor::new(&config),
+            Err(RedactError::BadPattern { name, .. }) if name == "BAD"
+        ));
+    }
+
+    #[test]
+    fn test_redact_info() {
+        let r = Redactor::default();
+        let info = WindowInfo::parse("me@example.com - Outlook");
+        let redacted = r.redact_info(&info);
+        assert_eq!(redacted.app_name, "Outlook");
+        assert_eq!(redacted.details, "[email]");
+    }
+
+    #[test]
+    fn test_redact_title_parses_before_redacting() {
+        let r = only(Detector::Email, RedactAction::Remove);
+        let (title, info) = r.redact_title("me@example.com - Outlook");
+        assert_eq!(info, WindowInfo::new("Outlook".to_string(), String::new()));
+        assert_eq!(title, "Outlook");
+
+        let (title, info) = Redactor::default().redact_title("~/notes/a.md - me@example.com - Code");
+        assert_eq!(info.app_name, "Code");
+        assert_eq!(info.details, "[path] - [email]");
+        assert_eq!(title, "[path] - [email] - Code");
+    }
+
+    #[test]
+    fn test_default_keeps_ticket_like_terms() {
+        let r = Redactor::default();
+        for title in ["UTF-8 - Notepad", "SHA-256 checksum", "GPT-4 - Chrome", "PROJ-1234: Fix login"] {
+            assert_eq!(r.redact(title), title);
+        }
+        assert!(!RedactionConfig::default().detectors.iter().any(|(d, _)| *d == Detector::TicketId));
+    }
+}
@@ -0,0 +1,414 @@
+//! 作用范围与规则模块
+//!
+//! 许多设置（按钮、模板、图标等）可以针对全局、某个类别、某个应用或某条规则分别配置。
+//! 配置键通过 `@` 后缀指定范围：
+//!
+//! * `KEY` - 全局
+//! * `KEY@coding` - 某个类别（类别的配置名称，见 `Category::name`）
+//! * `KEY@app:Visual Studio Code` - 某个应用（与解析出的应用名称比较，不区分大小写）
+//! * `KEY@rule:GITHUB` - 某条规则，规则由 `RULE_<名称>=正则` 定义，匹配完整窗口标题
+//!
+//! 查找时优先级为：规则 > 应用 > 类别 > 全局；多条规则同时匹配时按配置顺序取第一条。
+
+use std::fmt;
+
+use regex::Regex;
+
+use crate::category::Category;
+use crate::i18n::Msg;
+use crate::parser::WindowInfo;
+use crate::tr;
+
+/// 当前窗口的上下文，用于选择生效的设置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowContext {
+    /// 完整窗口标题（已脱敏）
+    pub title: String,
+    /// 应用名称
+    pub app_name: String,
+    /// 详细信息
+    pub details: String,
+    /// 应用类别
+    pub category: Category,
+}
+
+impl WindowContext {
+    /// 根据窗口标题和解析结果创建上下文
+    pub fn new(title: &str, info: &WindowInfo) -> Self {
+        Self {
+            title: title.to_string(),
+            app_name: info.app_name.clone(),
+            details: info.details.clone(),
+            category: Category::classify(&info.app_name),
+        }
+    }
+}
+
+/// 设置的作用范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    /// 全局
+    Global,
+    /// 某个类别
+    Category(Category),
+    /// 某个应用
+    App(String),
+    /// 某条规则
+    Rule(String),
+}
+
+impl Scope {
+    /// 解析 `@` 之后的范围描述
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(app) = value.strip_prefix("app:") {
+            Some(Scope::App(app.trim().to_string()))
+        } else if let Some(rule) = value.strip_prefix("rule:") {
+            Some(Scope::Rule(rule.trim().to_ascii_uppercase()))
+        } else {
+            Category::parse(value).map(Scope::Category)
+        }
+    }
+
+    /// 还原带此范围的完整配置键，如 `DISCORD_APP_ID@app:Steam`
+    pub fn key(&self, base: &str) -> String {
+        match self {
+            Scope::Global => base.to_string(),
+            Scope::Category(category) => format!("{}@{}", base, category.name()),
+            Scope::App(app) => format!("{}@app:{}", base, app),
+            Scope::Rule(rule) => format!("{}@rule:{}", base, rule),
+        }
+    }
+
+    /// 优先级，数值越大越优先
+    fn priority(&self) -> u8 {
+        match self {
+            Scope::Global => 0,
+            Scope::Category(_) => 1,
+            Scope::App(_) => 2,
+            Scope::Rule(_) => 3,
+        }
+    }
+}
+
+/// 将配置键拆分为基础键和作用范围
+///
+/// # 示例
+/// ```
+/// use active_window_info_to_lanyard_lib::rules::{split_scope, Scope};
+/// use active_window_info_to_lanyard_lib::Category;
+///
+/// assert_eq!(split_scope("BUTTON_1"), Some(("BUTTON_1", Scope::Global)));
+/// assert_eq!(split_scope("BUTTON_1@coding"), Some(("BUTTON_1", Scope::Category(Category::Coding))));
+/// assert_eq!(split_scope("BUTTON_1@unknown"), None);
+/// ```
+pub fn split_scope(key: &str) -> Option<(&str, Scope)> {
+    match key.split_once('@') {
+        Some((base, scope)) => Scope::parse(scope).map(|scope| (base.trim(), scope)),
+        None => Some((key, Scope::Global)),
+    }
+}
+
+/// 规则定义错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    /// 正则无法编译（规则名称和错误）
+    BadPattern { name: String, source: regex::Error },
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::BadPattern { name, source } => {
+                f.write_str(&tr!(Msg::ConfigBadRule, name, source))
+            },
+        }
+    }
+}
+
+impl std::error::Error for RuleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RuleError::BadPattern { source, .. } => Some(source),
+        }
+    }
+}
+
+/// 带作用范围的设置的解析错误，`E` 为值本身的解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopedError<E> {
+    /// 范围无法识别（完整键名）
+    UnknownScope(String),
+    /// 引用了未定义的规则（完整键名和规则名称）
+    UnknownRule { key: String, rule: String },
+    /// 值无法解析（完整键名和错误）
+    Value { key: String, source: E },
+}
+
+impl<E: fmt::Display> fmt::Display for ScopedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ScopedError::UnknownScope(key) => tr!(Msg::ConfigUnknownScope, key),
+            ScopedError::UnknownRule { key, rule } => tr!(Msg::ConfigUnknownRule, key, rule),
+            ScopedError::Value { key, source } => tr!(Msg::ConfigBadValue, key, source),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ScopedError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScopedError::Value { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// 命名规则集合（`RULE_<名称>=正则`）
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<(String, Regex)>,
+}
+
+impl RuleSet {
+    /// 添加一条规则，名称不区分大小写
+    pub fn add(&mut self, name: &str, pattern: &str) -> Result<(), RuleError> {
+        let regex = Regex::new(pattern)
+            .map_err(|source| RuleError::BadPattern { name: name.to_string(), source })?;
+        self.rules.push((name.to_ascii_uppercase(), regex));
+        Ok(())
+    }
+
+    /// 是否定义了指定名称的规则
+    pub fn contains(&self, name: &str) -> bool {
+        self.rules.iter().any(|(rule, _)| rule.eq_ignore_ascii_case(name))
+    }
+
+    /// 指定规则是否匹配窗口标题
+    pub fn matches(&self, name: &str, title: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|(rule, regex)| rule.eq_ignore_ascii_case(name) && regex.is_match(title))
+    }
+
+    /// 规则数量
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// 是否没有任何规则
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// 按作用范围区分的设置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scoped<T> {
+    entries: Vec<(Scope, T)>,
+}
+
+impl<T> Default for Scoped<T> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+impl<T> Scoped<T> {
+    /// 设置某个范围的值，同一范围的旧值会被替换
+    pub fn set(&mut self, scope: Scope, value: T) {
+        match self.entries.iter_mut().find(|(existing, _)| *existing == scope) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((scope, value)),
+        }
+    }
+
+    /// 获取某个范围的值，不存在时插入默认值
+    pub fn entry(&mut self, scope: Scope) -> &mut T
+    where
+        T: Default,
+    {
+        let index = match self.entries.iter().position(|(existing, _)| *existing == scope) {
+            Some(index) => index,
+            None => {
+                self.entries.push((scope, T::default()));
+                self.entries.len() - 1
+            },
+        };
+        &mut self.entries[index].1
+    }
+
+    /// 全局值
+    pub fn global(&self) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|(scope, _)| *scope == Scope::Global)
+            .map(|(_, value)| value)
+    }
+
+    /// 所有范围及其值
+    pub fn entries(&self) -> impl Iterator<Item = &(Scope, T)> {
+        self.entries.iter()
+    }
+
+    /// 是否没有任何设置
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 选出对当前窗口生效的值
+    pub fn resolve(&self, context: &WindowContext, rules: &RuleSet) -> Option<&T> {
+        self.resolve_where(context, rules, |_, _| true)
+    }
+
+    /// 在满足 `accept` 的范围和值中选出对当前窗口生效的值
+    pub fn resolve_where(
+        &self,
+        context: &WindowContext,
+        rules: &RuleSet,
+        accept: impl Fn(&Scope, &T) -> bool,
+    ) -> Option<&T> {
+        let mut best: Option<&(Scope, T)> = None;
+        for entry in self.entries.iter().filter(|(scope, value)| accept(scope, value)) {
+            let applies = match &entry.0 {
+                Scope::Global => true,
+                Scope::Category(category) => *category == context.category,
+                Scope::App(app) => app.eq_ignore_ascii_case(&context.app_name),
+                Scope::Rule(rule) => rules.matches(rule, &context.title),
+            };
+            // 同优先级时保留先出现的（规则按配置顺序）
+            if applies && best.is_none_or(|(scope, _)| entry.0.priority() > scope.priority()) {
+                best = Some(entry);
+            }
+        }
+        best.map(|(_, value)| value)
+    }
+}
+
+/// 解析带作用范围的设置：收集键为 `base` 或 `base@<范围>` 的所有条目
+///
+/// 范围无法识别、引用了未定义的规则或值无法解析时返回错误
+pub fn parse_scoped<T, E>(
+    entries: &[(String, String)],
+    base: &str,
+    rules: &RuleSet,
+    mut parse: impl FnMut(&str) -> Result<T, E>,
+) -> Result<Scoped<T>, ScopedError<E>> {
+    let mut scoped = Scoped::default();
+    for (key, value) in entries {
+        // 只接受 `base` 本身或 `base@...`，避免 BUTTON_1 匹配到 BUTTON_10
+        match key.strip_prefix(base) {
+            Some(rest) if rest.is_empty() || rest.starts_with('@') => {},
+            _ => continue,
+        }
+        let (_, scope) =
+            split_scope(key).ok_or_else(|| ScopedError::UnknownScope(key.to_string()))?;
+        if let Scope::Rule(ref name) = scope
+            && !rules.contains(name)
+        {
+            return Err(ScopedError::UnknownRule { key: key.to_string(), rule: name.clone() });
+        }
+        let value =
+            parse(value).map_err(|source| ScopedError::Value { key: key.to_string(), source })?;
+        scoped.set(scope, value);
+    }
+    Ok(scoped)
+}
+
+/// 从键值对中解析规则定义（`RULE_<名称>=正则`）
+pub fn parse_rules(entries: &[(String, String)]) -> Result<RuleSet, RuleError> {
+    let mut rules = RuleSet::default();
+    for (key, value) in entries {
+        if let Some(name) = key.strip_prefix("RULE_") {
+            rules.add(name, value)?;
+        }
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(title: &str) -> WindowContext {
+        WindowContext::new(title, &WindowInfo::parse(title))
+    }
+
+    #[test]
+    fn test_scope_parse() {
+        assert_eq!(Scope::parse("Browsing"), Some(Scope::Category(Category::Browsing)));
+        assert_eq!(Scope::parse("app:Google Chrome"), Some(Scope::App("Google Chrome".into())));
+        assert_eq!(Scope::parse("rule:github"), Some(Scope::Rule("GITHUB".into())));
+        assert_eq!(Scope::parse("nothing"), None);
+    }
+
+    #[test]
+    fn test_resolve_priority() {
+        let mut rules = RuleSet::default();
+        rules.add("github", r"GitHub").unwrap();
+
+        let mut scoped = Scoped::default();
+        scoped.set(Scope::Global, "global");
+        scoped.set(Scope::Category(Category::Browsing), "browsing");
+        scoped.set(Scope::App("google chrome".into()), "chrome");
+        scoped.set(Scope::Rule("GITHUB".into()), "github");
+
+        assert_eq!(scoped.resolve(&context("Notes - Notepad"), &rules), Some(&"global"));
+        assert_eq!(scoped.resolve(&context("News - Firefox"), &rules), Some(&"browsing"));
+        assert_eq!(scoped.resolve(&context("News - Google Chrome"), &rules), Some(&"chrome"));
+        assert_eq!(scoped.resolve(&context("GitHub - Google Chrome"), &rules), Some(&"github"));
+
+        scoped.set(Scope::Global, "replaced");
+        assert_eq!(scoped.global(), Some(&"replaced"));
+        assert_eq!(Scoped::<u8>::default().resolve(&context("x"), &rules), None);
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let entries = vec![("RULE_WORK".to_string(), r"\bJIRA\b".to_string())];
+        let rules = parse_rules(&entries).unwrap();
+        assert!(rules.contains("work"));
+        assert!(rules.matches("WORK", "PROJ-1 - JIRA - Firefox"));
+        assert!(!rules.matches("WORK", "jira"));
+
+        let entries = vec![("RULE_BAD".to_string(), "(".to_string())];
+        assert!(matches!(
+            parse_rules(&entries),
+            Err(RuleError::BadPattern { name, .. }) if name == "BAD"
+        ));
+    }
+
+    #[test]
+    fn test_parse_scoped() {
+        let mut rules = RuleSet::default();
+        rules.add("WORK", "JIRA").unwrap();
+        let entries: Vec<(String, String)> = [
+            ("KEY", "1"),
+            ("KEY@media", "2"),
+            ("KEY@rule:work", "3"),
+            ("KEY_OTHER", "4"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let scoped = parse_scoped(&entries, "KEY", &rules, str::parse::<u8>).unwrap();
+        assert_eq!(scoped.entries().count(), 3);
+        assert_eq!(scoped.global(), Some(&1));
+
+        let bad = |key: &str, value: &str| {
+            let entries = vec![(key.to_string(), value.to_string())];
+            parse_scoped(&entries, "KEY", &rules, str::parse::<u8>)
+        };
+        assert_eq!(bad("KEY@nowhere", "1"), Err(ScopedError::UnknownScope("KEY@nowhere".to_string())));
+        assert_eq!(
+            bad("KEY@rule:missing", "1"),
+            Err(ScopedError::UnknownRule {
+                key: "KEY@rule:missing".to_string(),
+                rule: "MISSING".to_string()
+            })
+        );
+        assert!(matches!(bad("KEY", "x"), Err(ScopedError::Value { key, .. }) if key == "KEY"));
+    }
+}
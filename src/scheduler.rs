@@ -0,0 +1,501 @@
+//! 更新调度模块
+//!
+//! Discord对 `SET_ACTIVITY` 限流（约20秒5次）。每个输出（`PresenceSink`）前都有一个 `UpdateScheduler`：
+//! 新的活动状态先进入待发送槽，只保留最新的一个；令牌桶有余量时立即发送，
+//! 否则等到有令牌时再发送。与当前已发送状态相同的更新直接丢弃。
+//! 发送失败需要重试时按 `RetryPolicy` 退避等待，重试次数用完后放弃这次更新。
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::i18n::Msg;
+use crate::presence::Presence;
+use crate::sink::{PresenceSink, SinkResult};
+use crate::tr;
+
+/// 默认时间窗口内允许的更新次数
+pub const DEFAULT_RATE_LIMIT_UPDATES: u32 = 5;
+
+/// 默认限流时间窗口（毫秒）
+pub const DEFAULT_RATE_LIMIT_WINDOW_MS: u64 = 20_000;
+
+/// 默认的最多重试次数
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// 默认的首次重试等待时间（毫秒），之后每次加倍
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 1_000;
+
+/// 重试等待时间的上限
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 限流参数：每个时间窗口内最多发送 `updates` 次
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// 时间窗口内允许的更新次数（令牌桶容量）
+    pub updates: u32,
+    /// 时间窗口
+    pub window: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            updates: DEFAULT_RATE_LIMIT_UPDATES,
+            window: Duration::from_millis(DEFAULT_RATE_LIMIT_WINDOW_MS),
+        }
+    }
+}
+
+/// 重试策略：一次更新最多重试 `max_retries` 次，第n次重试前等待 `backoff * 2^(n-1)`（最多60秒）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 放弃前最多重试的次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            backoff: Duration::from_millis(DEFAULT_RETRY_BACKOFF_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `retry` 次（从1开始）重试前的等待时间
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(MAX_RETRY_BACKOFF)
+    }
+}
+
+/// 令牌桶：容量为 `updates`，每 `window / updates` 补充一个令牌
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_interval: Duration,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// 创建装满令牌的令牌桶
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        let capacity = f64::from(limit.updates.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_interval: limit.window.div_f64(capacity),
+            last_refill: now,
+        }
+    }
+
+    /// 当前可用的令牌数
+    pub fn available(&mut self, now: Instant) -> f64 {
+        self.refill(now);
+        self.tokens
+    }
+
+    /// 尝试取出一个令牌
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 退还一个令牌（更新没有真正发送出去时）
+    pub fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(self.capacity);
+    }
+
+    /// 距离下一个令牌可用还需等待的时间
+    pub fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            self.refill_interval.mul_f64(1.0 - self.tokens)
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        if self.refill_interval.is_zero() {
+            self.tokens = self.capacity;
+        } else {
+            let added = elapsed.as_secs_f64() / self.refill_interval.as_secs_f64();
+            self.tokens = (self.tokens + added).min(self.capacity);
+        }
+        self.last_refill = now;
+    }
+}
+
+/// 待发送的更新，`None` 表示清除活动状态
+type Update = Option<Presence>;
+
+/// 调度计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedulerStats {
+    /// 提交的更新数
+    pub submitted: u64,
+    /// 实际发送的更新数
+    pub sent: u64,
+    /// 尚未发送就被更新的状态替换掉的更新数
+    pub merged: u64,
+    /// 与已发送状态相同而被丢弃的更新数
+    pub dropped: u64,
+    /// 发送失败的次数
+    pub failed: u64,
+}
+
+impl fmt::Display for SchedulerStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            tr!(
+                Msg::SchedulerStats,
+                self.submitted,
+                self.sent,
+                self.merged,
+                self.dropped,
+                self.failed
+            )
+        )
+    }
+}
+
+/// 一次 `flush` 的结果
+#[derive(Debug)]
+pub enum FlushOutcome {
+    /// 没有待发送的更新
+    Idle,
+    /// 已发送
+    Sent,
+    /// 令牌不足或正在退避，需等待一段时间后再发送
+    Deferred(Duration),
+    /// 发送失败
+    Failed(Error),
+    /// 重试次数用完，放弃这次更新
+    GaveUp {
+        /// 一共尝试发送的次数
+        attempts: u32,
+        /// 最后一次的错误
+        error: Error,
+    },
+}
+
+/// 合并更新并按令牌桶限流发送
+#[derive(Debug, Clone)]
+pub struct UpdateScheduler {
+    bucket: TokenBucket,
+    pending: Option<Update>,
+    last_sent: Option<Update>,
+    stats: SchedulerStats,
+    retry: RetryPolicy,
+    retries: u32,
+    retry_at: Option<Instant>,
+}
+
+impl UpdateScheduler {
+    /// 创建调度器
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: TokenBucket::new(limit, Instant::now()),
+            pending: None,
+            last_sent: None,
+            stats: SchedulerStats::default(),
+            retry: RetryPolicy::default(),
+            retries: 0,
+            retry_at: None,
+        }
+    }
+
+    /// 设置重试策略
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    /// 更换限流参数（重新加载配置时），令牌桶重新装满
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        self.bucket = TokenBucket::new(limit, Instant::now());
+    }
+
+    /// 提交新的活动状态，替换尚未发送的更新
+    pub fn submit(&mut self, presence: Presence) {
+        self.push(Some(presence));
+    }
+
+    /// 提交清除活动状态的请求
+    pub fn submit_clear(&mut self) {
+        self.push(None);
+    }
+
+    /// 是否有待发送的更新
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// 调度计数
+    pub fn stats(&self) -> SchedulerStats {
+        self.stats
+    }
+
+    /// 距离可以发送待发送更新还需等待的时间，没有待发送更新时返回 `None`
+    pub fn next_flush_in(&mut self, now: Instant) -> Option<Duration> {
+        self.pending.as_ref()?;
+        Some(self.bucket.wait_time(now).max(self.backoff_remaining(now)))
+    }
+
+    /// 通过输出发送待发送的更新
+    pub fn flush(&mut self, sink: &mut dyn PresenceSink) -> FlushOutcome {
+        self.flush_with(Instant::now(), |update| sink.publish(update))
+    }
+
+    /// 令牌足够时用 `send` 发送待发送的更新（`None` 表示清除活动状态）
+    ///
+    /// `send` 跳过的更新（与已发布的状态相同）不消耗令牌；
+    /// 返回 `Retry` 时退还令牌并保留待发送的更新，按重试策略退避后重试，重试次数用完后放弃；
+    /// 返回 `Dropped` 时退还令牌并放弃该更新
+    pub fn flush_with(
+        &mut self,
+        now: Instant,
+        send: impl FnOnce(Option<&Presence>) -> SinkResult,
+    ) -> FlushOutcome {
+        let Some(update) = &self.pending else {
+            return FlushOutcome::Idle;
+        };
+        let backoff = self.backoff_remaining(now);
+        if !backoff.is_zero() {
+            return FlushOutcome::Deferred(backoff);
+        }
+        if !self.bucket.try_take(now) {
+            return FlushOutcome::Deferred(self.bucket.wait_time(now));
+        }
+
+        let result = send(update.as_ref());
+        if !matches!(result, SinkResult::Retry(_)) {
+            self.retries = 0;
+            self.retry_at = None;
+        }
+        match result {
+            SinkResult::Sent => {
+                self.stats.sent += 1;
+                self.last_sent = self.pending.take();
+                FlushOutcome::Sent
+            },
+            SinkResult::Skipped => {
+                self.bucket.refund();
+                self.stats.dropped += 1;
+                self.last_sent = self.pending.take();
+                FlushOutcome::Idle
+            },
+            SinkResult::Retry(e) => {
+                self.bucket.refund();
+                self.stats.failed += 1;
+                if self.retries >= self.retry.max_retries {
+                    let attempts = self.retries + 1;
+                    self.retries = 0;
+                    self.retry_at = None;
+                    self.pending = None;
+                    return FlushOutcome::GaveUp { attempts, error: e };
+                }
+                self.retries += 1;
+                self.retry_at = Some(now + self.retry.delay(self.retries));
+                FlushOutcome::Failed(e)
+            },
+            SinkResult::Dropped(e) => {
+                self.bucket.refund();
+                self.stats.failed += 1;
+                self.pending = None;
+                FlushOutcome::Failed(e)
+            },
+        }
+    }
+
+    /// 距离退避结束还需等待的时间
+    fn backoff_remaining(&self, now: Instant) -> Duration {
+        self.retry_at.map_or(Duration::ZERO, |at| at.saturating_duration_since(now))
+    }
+
+    /// 新的更新重新计算重试次数，但仍需等待之前的退避结束
+    fn push(&mut self, update: Update) {
+        self.stats.submitted += 1;
+        self.retries = 0;
+        if self.pending.take().is_some() {
+            self.stats.merged += 1;
+        }
+        if self.last_sent.as_ref() == Some(&update) {
+            self.stats.dropped += 1;
+        } else {
+            self.pending = Some(update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord::{DiscordError, DiscordManager};
+
+    fn limit(updates: u32, window_ms: u64) -> RateLimit {
+        RateLimit { updates, window: Duration::from_millis(window_ms) }
+    }
+
+    fn presence(state: &str) -> Presence {
+        Presence::new("App", state, 0)
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(limit(5, 20_000), start);
+        for _ in 0..5 {
+            assert!(bucket.try_take(start));
+        }
+        assert!(!bucket.try_take(start));
+        assert_eq!(bucket.wait_time(start), Duration::from_secs(4));
+
+        let later = start + Duration::from_secs(4);
+        assert!(bucket.try_take(later));
+        assert!(!bucket.try_take(later));
+
+        // 补充的令牌不超过容量
+        assert_eq!(bucket.available(later + Duration::from_secs(600)), 5.0);
+    }
+
+    #[test]
+    fn test_coalesces_to_latest_state() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::new(limit(1, 1000));
+        scheduler.bucket = TokenBucket::new(limit(1, 1000), start);
+        let mut sent = Vec::new();
+
+        scheduler.submit(presence("a"));
+        let outcome = scheduler.flush_with(start, |p| {
+            sent.push(p.cloned());
+            SinkResult::Sent
+        });
+        assert!(matches!(outcome, FlushOutcome::Sent));
+
+        // 令牌用完后的快速切换只保留最后一个
+        scheduler.submit(presence("b"));
+        scheduler.submit(presence("c"));
+        let outcome = scheduler.flush_with(start, |_| unreachable!());
+        assert!(matches!(outcome, FlushOutcome::Deferred(wait) if wait == Duration::from_secs(1)));
+        let later = start + Duration::from_secs(1);
+        assert_eq!(scheduler.next_flush_in(later), Some(Duration::ZERO));
+        scheduler.flush_with(later, |p| {
+            sent.push(p.cloned());
+            SinkResult::Sent
+        });
+        assert_eq!(sent, vec![Some(presence("a")), Some(presence("c"))]);
+
+        // 与已发送状态相同的更新被丢弃
+        scheduler.submit(presence("c"));
+        assert!(!scheduler.has_pending());
+        assert!(matches!(scheduler.flush_with(later, |_| unreachable!()), FlushOutcome::Idle));
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.submitted, stats.sent, stats.merged, stats.dropped), (4, 2, 1, 1));
+    }
+
+    #[test]
+    fn test_failed_update_stays_pending() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::new(limit(5, 1000));
+        scheduler.submit_clear();
+        let outcome = scheduler.flush_with(start, |_| {
+            SinkResult::Retry(DiscordError::NotConnected.into())
+        });
+        assert!(matches!(outcome, FlushOutcome::Failed(Error::Discord(DiscordError::NotConnected))));
+        assert!(scheduler.has_pending());
+        assert_eq!(scheduler.stats().failed, 1);
+
+        // 退避期间不再发送
+        let wait = Duration::from_millis(DEFAULT_RETRY_BACKOFF_MS);
+        assert_eq!(scheduler.next_flush_in(start), Some(wait));
+        let outcome = scheduler.flush_with(start, |_| unreachable!());
+        assert!(matches!(outcome, FlushOutcome::Deferred(w) if w == wait));
+
+        let outcome = scheduler.flush_with(start + wait, |p| {
+            assert!(p.is_none());
+            SinkResult::Sent
+        });
+        assert!(matches!(outcome, FlushOutcome::Sent));
+    }
+
+    #[test]
+    fn test_gives_up_after_max_retries() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::new(limit(1, 60_000));
+        scheduler.bucket = TokenBucket::new(limit(1, 60_000), start);
+        scheduler.set_retry_policy(RetryPolicy { max_retries: 2, backoff: Duration::from_secs(1) });
+        scheduler.submit(presence("a"));
+
+        let mut now = start;
+        let mut delays = Vec::new();
+        for _ in 0..2 {
+            let outcome = scheduler.flush_with(now, |_| SinkResult::Retry(DiscordError::NotConnected.into()));
+            assert!(matches!(outcome, FlushOutcome::Failed(_)));
+            let wait = scheduler.next_flush_in(now).unwrap();
+            delays.push(wait);
+            now += wait;
+        }
+        // 退避时间逐次加倍，失败的发送退还令牌
+        assert_eq!(delays, vec![Duration::from_secs(1), Duration::from_secs(2)]);
+        assert_eq!(scheduler.bucket.available(now), 1.0);
+
+        let outcome = scheduler.flush_with(now, |_| SinkResult::Retry(DiscordError::NotConnected.into()));
+        assert!(matches!(outcome, FlushOutcome::GaveUp { attempts: 3, .. }));
+        assert!(!scheduler.has_pending());
+        assert_eq!(scheduler.stats().failed, 3);
+
+        // 新的更新重新开始计算
+        scheduler.submit(presence("b"));
+        assert!(matches!(scheduler.flush_with(now, |_| SinkResult::Sent), FlushOutcome::Sent));
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(40), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_skipped_update_keeps_token() {
+        let start = Instant::now();
+        let mut scheduler = UpdateScheduler::new(limit(1, 60_000));
+        scheduler.bucket = TokenBucket::new(limit(1, 60_000), start);
+        scheduler.submit(presence("a"));
+        assert!(matches!(scheduler.flush_with(start, |_| SinkResult::Skipped), FlushOutcome::Idle));
+        assert!(!scheduler.has_pending());
+        assert_eq!(scheduler.stats().dropped, 1);
+        assert_eq!(scheduler.bucket.available(start), 1.0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disconnected_flush_refunds_token() {
+        let mut config = crate::config::Config::new(42, 5);
+        config.ipc_path = Some(std::path::PathBuf::from("/nonexistent/discord-ipc-0"));
+        let mut discord = DiscordManager::new(&config).unwrap();
+        let mut scheduler = UpdateScheduler::new(limit(1, 60_000));
+
+        scheduler.submit(presence("a"));
+        assert!(matches!(scheduler.flush(&mut discord), FlushOutcome::Failed(_)));
+        // 断线时由 DiscordManager 在重连后发布，调度器不再保留
+        assert!(!scheduler.has_pending());
+        assert_eq!(scheduler.bucket.available(Instant::now()), 1.0);
+    }
+}
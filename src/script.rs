@@ -0,0 +1,469 @@
+//! 脚本扩展模块
+//!
+//! 对于正则规则难以表达的动态变换（例如从分支名推导Jira编号、按标签页数量显示不同文字），
+//! 可以编写一个 [Rhai](https://rhai.rs) 脚本，在每次窗口变化时运行。
+//!
+//! 脚本需要定义 `transform(window, presence)` 函数：
+//! * `window` - 窗口数据：`title`、`app_name`、`details`、`category`、`category_label`
+//! * `presence` - 默认生成的活动状态：`details`、`state`、`large_image`、`large_text`、
+//!   `small_image`、`small_text`、`start`、`end`、`buttons`（`[#{label, url}]`）、
+//!   `type`（`playing` / `listening` / `watching` / `competing`）、`app_id`（Discord应用ID）
+//!
+//! 函数返回最终的活动状态（对象映射），返回 `()` 则不发送此次更新。
+//!
+//! ```rhai
+//! fn transform(window, presence) {
+//!     if window.category == "media" { return (); }
+//!     presence.state = window.details.sub_string(0, 20);
+//!     presence
+//! }
+//! ```
+//!
+//! 脚本运行在沙箱中：无法访问文件和模块，执行时间和操作数都有上限。
+
+use std::cell::Cell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::category::Category;
+use crate::i18n::Msg;
+use crate::parser::WindowInfo;
+use crate::presence::{ActivityType, Button, Presence};
+use crate::tr;
+
+/// 脚本入口函数名
+pub const TRANSFORM_FN: &str = "transform";
+
+/// 默认执行时间上限（毫秒）
+pub const DEFAULT_SCRIPT_TIMEOUT_MS: u64 = 50;
+
+/// 默认操作数上限
+pub const DEFAULT_SCRIPT_MAX_OPERATIONS: u64 = 100_000;
+
+/// 脚本配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptConfig {
+    /// 脚本文件路径
+    pub path: PathBuf,
+    /// 单次执行时间上限
+    pub timeout: Duration,
+    /// 单次执行操作数上限
+    pub max_operations: u64,
+}
+
+impl ScriptConfig {
+    /// 使用默认限制创建脚本配置
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            timeout: Duration::from_millis(DEFAULT_SCRIPT_TIMEOUT_MS),
+            max_operations: DEFAULT_SCRIPT_MAX_OPERATIONS,
+        }
+    }
+}
+
+/// 脚本错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// 无法读取脚本文件
+    Io { path: PathBuf, message: String },
+    /// 脚本语法错误
+    Compile { line: Option<usize>, message: String },
+    /// 脚本运行时错误
+    Runtime { line: Option<usize>, message: String },
+    /// 执行超时
+    Timeout(Duration),
+    /// 超过操作数上限
+    OperationLimit(u64),
+    /// 返回值不是有效的活动状态
+    InvalidReturn(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = |line: &Option<usize>| line.map_or("?".to_string(), |l| l.to_string());
+        let text = match self {
+            ScriptError::Io { path, message } => {
+                tr!(Msg::ScriptReadFailed, path.display(), message)
+            },
+            ScriptError::Compile { line: l, message } => {
+                tr!(Msg::ScriptCompileFailed, line(l), message)
+            },
+            ScriptError::Runtime { line: l, message } => {
+                tr!(Msg::ScriptRuntimeError, line(l), message)
+            },
+            ScriptError::Timeout(limit) => tr!(Msg::ScriptTimeout, limit.as_millis()),
+            ScriptError::OperationLimit(limit) => tr!(Msg::ScriptOperationLimit, limit),
+            ScriptError::InvalidReturn(message) => tr!(Msg::ScriptInvalidReturn, message),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// 脚本钩子
+///
+/// 持有编译后的脚本，每次窗口变化时调用 [`ScriptHook::transform`]
+pub struct ScriptHook {
+    engine: Engine,
+    ast: AST,
+    timeout: Duration,
+    max_operations: u64,
+    /// 本次执行的开始时间，由进度回调读取
+    started: Rc<Cell<Instant>>,
+}
+
+impl ScriptHook {
+    /// 从配置中的脚本文件创建钩子
+    pub fn from_config(config: &ScriptConfig) -> Result<Self, ScriptError> {
+        let source = read_script(&config.path)?;
+        Self::from_source(&source, config.timeout, config.max_operations)
+    }
+
+    /// 从脚本源码创建钩子
+    ///
+    /// # 参数
+    /// * `source` - 脚本源码
+    /// * `timeout` - 单次执行时间上限
+    /// * `max_operations` - 单次执行操作数上限
+    pub fn from_source(
+        source: &str,
+        timeout: Duration,
+        max_operations: u64,
+    ) -> Result<Self, ScriptError> {
+        let started = Rc::new(Cell::new(Instant::now()));
+        let engine = sandboxed_engine(timeout, max_operations, started.clone());
+
+        let ast = engine.compile(source).map_err(|e| ScriptError::Compile {
+            line: e.1.line(),
+            message: e.0.to_string(),
+        })?;
+
+        Ok(Self {
+            engine,
+            ast,
+            timeout,
+            max_operations,
+            started,
+        })
+    }
+
+    /// 对活动状态运行脚本
+    ///
+    /// # 参数
+    /// * `window_title` - 完整窗口标题（已脱敏）
+    /// * `window_info` - 解析后的窗口信息（已脱敏）
+    /// * `presence` - 默认生成的活动状态
+    ///
+    /// # 返回值
+    /// * `Ok(Some(Presence))` - 脚本返回的最终活动状态
+    /// * `Ok(None)` - 脚本要求跳过此次更新
+    /// * `Err(ScriptError)` - 脚本出错
+    pub fn transform(
+        &self,
+        window_title: &str,
+        window_info: &WindowInfo,
+        presence: &Presence,
+    ) -> Result<Option<Presence>, ScriptError> {
+        let window = window_to_map(window_title, window_info);
+        let source = presence.source.clone();
+        let presence = presence_to_map(presence);
+
+        self.started.set(Instant::now());
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, TRANSFORM_FN, (window, presence))
+            .map_err(|e| self.runtime_error(*e))?;
+
+        if result.is_unit() {
+            return Ok(None);
+        }
+
+        let map = result
+            .try_cast::<Map>()
+            .ok_or_else(|| ScriptError::InvalidReturn(tr!(Msg::ScriptExpectedMap)))?;
+        // 脚本不能修改状态对应的窗口
+        map_to_presence(&map).map(|presence| Some(Presence { source, ..presence }))
+    }
+
+    fn runtime_error(&self, mut error: EvalAltResult) -> ScriptError {
+        // 报告脚本函数内部实际出错的位置
+        while let EvalAltResult::ErrorInFunctionCall(_, _, inner, _) = error {
+            error = *inner;
+        }
+        match error {
+            EvalAltResult::ErrorTerminated(..) => return ScriptError::Timeout(self.timeout),
+            EvalAltResult::ErrorTooManyOperations(..) => {
+                return ScriptError::OperationLimit(self.max_operations);
+            },
+            _ => {},
+        }
+        let line = error.take_position().line();
+        ScriptError::Runtime {
+            line,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// 读取脚本文件
+fn read_script(path: &Path) -> Result<String, ScriptError> {
+    std::fs::read_to_string(path).map_err(|e| ScriptError::Io {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+/// 创建受限的脚本引擎
+fn sandboxed_engine(timeout: Duration, max_operations: u64, started: Rc<Cell<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+
+    // 禁止加载模块和动态执行代码
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+
+    // 限制资源占用
+    engine.set_max_operations(max_operations);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(4096);
+    engine.set_max_array_size(256);
+    engine.set_max_map_size(256);
+
+    // 超过时间上限时终止脚本
+    engine.on_progress(move |_| {
+        if started.get().elapsed() > timeout {
+            Some(Dynamic::UNIT)
+        } else {
+            None
+        }
+    });
+
+    engine.on_print(|text| println!("📜 {}", text));
+    engine.on_debug(|text, _, pos| println!("📜 [{:?}] {}", pos, text));
+
+    engine
+}
+
+/// 将窗口信息转换为脚本中的对象映射
+fn window_to_map(window_title: &str, window_info: &WindowInfo) -> Map {
+    let category = Category::classify(&window_info.app_name);
+    let mut map = Map::new();
+    map.insert("title".into(), window_title.into());
+    map.insert("app_name".into(), window_info.app_name.clone().into());
+    map.insert("details".into(), window_info.details.clone().into());
+    map.insert("category".into(), category.name().into());
+    map.insert("category_label".into(), category.label().into());
+    map
+}
+
+/// 将活动状态转换为脚本中的对象映射，空字段为 `()`
+fn presence_to_map(presence: &Presence) -> Map {
+    let text = |value: &Option<String>| value.clone().map_or(Dynamic::UNIT, Dynamic::from);
+    let time = |value: Option<u64>| value.map_or(Dynamic::UNIT, |v| Dynamic::from(v as i64));
+
+    let buttons: Array = presence
+        .buttons
+        .iter()
+        .map(|button| {
+            let mut map = Map::new();
+            map.insert("label".into(), button.label.clone().into());
+            map.insert("url".into(), button.url.clone().into());
+            Dynamic::from_map(map)
+        })
+        .collect();
+
+    let mut map = Map::new();
+    map.insert("details".into(), text(&presence.details));
+    map.insert("state".into(), text(&presence.state));
+    map.insert("large_image".into(), text(&presence.large_image));
+    map.insert("large_text".into(), text(&presence.large_text));
+    map.insert("small_image".into(), text(&presence.small_image));
+    map.insert("small_text".into(), text(&presence.small_text));
+    map.insert("start".into(), time(presence.start_timestamp));
+    map.insert("end".into(), time(presence.end_timestamp));
+    map.insert("buttons".into(), buttons.into());
+    map.insert(
+        "type".into(),
+        presence.activity_type.map_or(Dynamic::UNIT, |t| Dynamic::from(t.name().to_string())),
+    );
+    map.insert("app_id".into(), time(presence.application_id));
+    map
+}
+
+/// 将脚本返回的对象映射转换为活动状态
+fn map_to_presence(map: &Map) -> Result<Presence, ScriptError> {
+    let text = |key: &str| -> Result<Option<String>, ScriptError> {
+        match map.get(key) {
+            None => Ok(None),
+            Some(value) if value.is_unit() => Ok(None),
+            Some(value) if value.is_string() => Ok(Some(value.to_string())),
+            Some(value) => Err(ScriptError::InvalidReturn(tr!(
+                Msg::ScriptFieldType,
+                key,
+                "string",
+                value.type_name()
+            ))),
+        }
+    };
+    let number = |key: &str| -> Result<Option<u64>, ScriptError> {
+        match map.get(key) {
+            None => Ok(None),
+            Some(value) if value.is_unit() => Ok(None),
+            Some(value) => value
+                .as_int()
+                .ok()
+                .and_then(|v| u64::try_from(v).ok())
+                .map(Some)
+                .ok_or_else(|| {
+                    ScriptError::InvalidReturn(tr!(
+                        Msg::ScriptFieldType,
+                        key,
+                        "int",
+                        value.type_name()
+                    ))
+                }),
+        }
+    };
+
+    let mut buttons = Vec::new();
+    if let Some(value) = map.get("buttons").filter(|v| !v.is_unit()) {
+        let array = value.clone().try_cast::<Array>().ok_or_else(|| {
+            ScriptError::InvalidReturn(tr!(Msg::ScriptFieldType, "buttons", "array", value.type_name()))
+        })?;
+        for item in array {
+            let type_name = item.type_name();
+            let button = item.try_cast::<Map>().ok_or_else(|| {
+                ScriptError::InvalidReturn(tr!(Msg::ScriptFieldType, "buttons[]", "map", type_name))
+            })?;
+            let field = |key: &str| {
+                button.get(key).filter(|v| v.is_string()).map(|v| v.to_string()).ok_or_else(
+                    || ScriptError::InvalidReturn(tr!(Msg::ScriptFieldType, key, "string", "()")),
+                )
+            };
+            buttons.push(Button {
+                label: field("label")?,
+                url: field("url")?,
+            });
+        }
+    }
+
+    Ok(Presence {
+        details: text("details")?,
+        state: text("state")?,
+        large_image: text("large_image")?,
+        large_text: text("large_text")?,
+        small_image: text("small_image")?,
+        small_text: text("small_text")?,
+        start_timestamp: number("start")?,
+        end_timestamp: number("end")?,
+        buttons,
+        activity_type: text("type")?
+            .map(|value| ActivityType::parse(&value))
+            .transpose()
+            .map_err(|e| ScriptError::InvalidReturn(e.to_string()))?,
+        application_id: number("app_id")?,
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(source: &str) -> Result<ScriptHook, ScriptError> {
+        ScriptHook::from_source(source, Duration::from_millis(200), DEFAULT_SCRIPT_MAX_OPERATIONS)
+    }
+
+    fn run(source: &str, title: &str) -> Result<Option<Presence>, ScriptError> {
+        let info = WindowInfo::parse(title);
+        let presence = Presence::new(&info.app_name, title, 100);
+        hook(source)?.transform(title, &info, &presence)
+    }
+
+    #[test]
+    fn test_transform_modifies_presence() {
+        let source = r#"
+            fn transform(window, presence) {
+                let key = window.details.split("/")[1];
+                presence.state = "Working on " + key.to_upper();
+                presence.buttons = [#{ label: "Open", url: "https://example.com" }];
+                presence.type = "watching";
+                presence
+            }
+        "#;
+        let presence = run(source, "feature/proj-42 - Terminal").unwrap().unwrap();
+        assert_eq!(presence.state.as_deref(), Some("Working on PROJ-42"));
+        assert_eq!(presence.details.as_deref(), Some("Terminal"));
+        assert_eq!(presence.start_timestamp, Some(100));
+        assert_eq!(presence.buttons[0].label, "Open");
+        assert_eq!(presence.activity_type, Some(ActivityType::Watching));
+    }
+
+    #[test]
+    fn test_transform_can_suppress() {
+        let source = r#"
+            fn transform(window, presence) {
+                if window.category == "terminal" { return (); }
+                presence
+            }
+        "#;
+        assert_eq!(run(source, "zsh - Terminal").unwrap(), None);
+        assert!(run(source, "a.txt - Notepad").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_compile_error_reports_line() {
+        let err = hook("fn transform(window, presence) {\n  let x = ;\n}").err().unwrap();
+        assert!(matches!(err, ScriptError::Compile { line: Some(2), .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_runtime_error_reports_line() {
+        let source = "fn transform(window, presence) {\n  let x = 1;\n  undefined_fn(x)\n}";
+        let err = run(source, "x - y").unwrap_err();
+        assert!(matches!(err, ScriptError::Runtime { line: Some(3), .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let source = "fn transform(window, presence) { loop { } }";
+        let hook = ScriptHook::from_source(source, Duration::from_millis(20), u64::MAX).unwrap();
+        let info = WindowInfo::parse("x - y");
+        let err = hook.transform("x - y", &info, &Presence::default()).unwrap_err();
+        assert_eq!(err, ScriptError::Timeout(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_operation_limit() {
+        let source = "fn transform(window, presence) { loop { } }";
+        let hook = ScriptHook::from_source(source, Duration::from_secs(60), 1_000).unwrap();
+        let info = WindowInfo::parse("x - y");
+        let err = hook.transform("x - y", &info, &Presence::default()).unwrap_err();
+        assert_eq!(err, ScriptError::OperationLimit(1_000));
+    }
+
+    #[test]
+    fn test_invalid_return() {
+        let err = run("fn transform(window, presence) { 42 }", "x - y").unwrap_err();
+        assert!(matches!(err, ScriptError::InvalidReturn(_)));
+
+        let err = run("fn transform(window, presence) { #{ state: 1 } }", "x - y").unwrap_err();
+        assert!(matches!(err, ScriptError::InvalidReturn(_)));
+
+        let err = run(r#"fn transform(window, presence) { #{ type: "custom" } }"#, "x - y").unwrap_err();
+        assert!(matches!(err, ScriptError::InvalidReturn(_)));
+    }
+
+    #[test]
+    fn test_sandbox_blocks_import() {
+        let source = r#"import "os" as os; fn transform(window, presence) { presence }"#;
+        assert!(run(source, "x - y").is_err());
+    }
+}
@@ -0,0 +1,879 @@
+//! Lanyard兼容的状态服务模块
+//!
+//! 在本机提供与Lanyard相同格式的接口，网站上现有的Lanyard组件只需把地址指向这里即可使用，
+//! 不经过Discord或Lanyard：
+//!
+//! * `GET /v1/users/:id` - 返回 `{ "success": true, "data": <状态> }`
+//! * `/socket` - WebSocket，协议与Lanyard相同（`Hello`、`Initialize`、心跳），
+//!   订阅后推送 `INIT_STATE` 和 `PRESENCE_UPDATE`
+//!
+//! 作为 `PresenceSink` 接入，与Discord使用同一份活动状态；`state` 的加密和长度适配也与Discord一致。
+//! 设置了 `LANYARD_USER_ID` 时只响应该用户，否则使用登录Discord的用户，两者都没有时响应任何用户ID。
+//! 同时处理的连接数有上限，超出时直接返回503。
+
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value, json};
+
+use crate::builder::unix_now;
+use crate::config::Config;
+use crate::crypto::CryptoError;
+use crate::keyring::KeyringCrypto;
+use crate::discord::{DiscordUser, activity_payload, prepare_presence};
+use crate::error::Error;
+use crate::fit::FitOptions;
+use crate::i18n::{Locale, Msg};
+use crate::presence::Presence;
+use crate::scheduler::RateLimit;
+use crate::sink::{PresenceSink, SinkResult};
+use crate::tr;
+use crate::ws::{self, Message, WebSocket};
+
+/// `main serve` 未设置 `SERVER_ADDR` 时监听的地址
+pub const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:4001";
+
+/// 要求客户端发送心跳的间隔
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+const OP_EVENT: u64 = 0;
+const OP_HELLO: u64 = 1;
+const OP_INITIALIZE: u64 = 2;
+const OP_HEARTBEAT: u64 = 3;
+
+/// 服务关闭时使用的关闭码
+const CLOSE_GOING_AWAY: u16 = 1001;
+/// 客户端发送了无效消息（与Lanyard相同）
+const CLOSE_INVALID_PAYLOAD: u16 = 4005;
+/// 客户端没有按时发送心跳
+const CLOSE_HEARTBEAT_TIMEOUT: u16 = 4006;
+
+/// 检查新连接和新消息的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 读取HTTP请求头的时间上限
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 同时处理的连接数上限（包括保持中的WebSocket连接）
+const MAX_CONNECTIONS: usize = 64;
+
+/// 状态服务错误
+#[derive(Debug)]
+pub enum ServerError {
+    /// 无法监听地址
+    Bind(SocketAddr, io::Error),
+    /// 加密管理器初始化失败
+    CryptoInit(CryptoError),
+    /// 加密 `state` 失败
+    Encrypt(CryptoError),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ServerError::Bind(addr, e) => tr!(Msg::ServerBindFailed, addr, e),
+            ServerError::CryptoInit(e) => tr!(Msg::DiscordCryptoInitFailed, e),
+            ServerError::Encrypt(e) => tr!(Msg::DiscordEncryptStateFailed, e),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServerError::Bind(_, e) => Some(e),
+            ServerError::CryptoInit(e) | ServerError::Encrypt(e) => Some(e),
+        }
+    }
+}
+
+/// 各连接共享的状态
+#[derive(Default)]
+struct State {
+    /// 当前活动（Lanyard格式），没有活动时为 `None`
+    activity: Option<Value>,
+    /// `online` / `idle` / `offline`
+    status: &'static str,
+    /// 配置的用户ID
+    user_id: Option<String>,
+    /// 登录Discord的用户
+    user: Option<DiscordUser>,
+    /// 每个已订阅的WebSocket连接一个通知通道
+    subscribers: Vec<Sender<()>>,
+}
+
+impl State {
+    /// 是否响应 `id` 的请求
+    fn monitors(&self, id: &str) -> bool {
+        match self
+            .user_id
+            .as_deref()
+            .or(self.user.as_ref().map(|user| user.id.as_str()))
+        {
+            Some(user_id) => user_id == id,
+            None => true,
+        }
+    }
+
+    /// 用户 `id` 的Lanyard格式状态数据
+    fn presence_data(&self, id: &str) -> Value {
+        let user = self.user.as_ref().filter(|user| user.id == id);
+        let online = self.status != "offline";
+        json!({
+            "discord_user": {
+                "id": id,
+                "username": user.map_or("", |user| user.username.as_str()),
+                "global_name": user.and_then(|user| user.global_name.clone()),
+                "display_name": user.and_then(|user| user.global_name.clone()),
+                "avatar": user.and_then(|user| user.avatar.clone()),
+                "avatar_decoration_data": null,
+                "discriminator": "0",
+                "bot": false,
+                "public_flags": 0,
+            },
+            "discord_status": self.status,
+            "activities": self.activity.iter().collect::<Vec<_>>(),
+            "active_on_discord_desktop": online,
+            "active_on_discord_mobile": false,
+            "active_on_discord_web": false,
+            "active_on_discord_embedded": false,
+            "listening_to_spotify": false,
+            "spotify": null,
+            "kv": {},
+        })
+    }
+
+    /// 通知所有订阅者状态已变化，移除已断开的连接
+    fn notify(&mut self) {
+        self.subscribers.retain(|sender| sender.send(()).is_ok());
+    }
+}
+
+/// 正在运行的监听线程
+struct Listener {
+    /// 配置中的地址（端口可能为0）
+    requested: SocketAddr,
+    /// 实际监听的地址
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Listener {
+    fn start(requested: SocketAddr, state: Arc<Mutex<State>>) -> Result<Self, ServerError> {
+        let bind = |e| ServerError::Bind(requested, e);
+        let listener = TcpListener::bind(requested).map_err(bind)?;
+        listener.set_nonblocking(true).map_err(bind)?;
+        let addr = listener.local_addr().map_err(bind)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || accept_loop(listener, state, stop))
+        };
+        Ok(Self { requested, addr, stop, handle })
+    }
+
+    /// 停止接受新连接，并让已有的WebSocket连接关闭
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.handle.join();
+    }
+}
+
+/// Lanyard兼容的状态服务
+pub struct LanyardServer {
+    listener: Option<Listener>,
+    state: Arc<Mutex<State>>,
+    crypto: Option<KeyringCrypto>,
+    fit: FitOptions,
+    default_app_id: u64,
+}
+
+impl LanyardServer {
+    /// 根据配置启动服务，没有设置 `SERVER_ADDR` 时返回 `None`
+    pub fn new(config: &Config) -> Result<Option<Self>, ServerError> {
+        let Some(addr) = config.server_addr else {
+            return Ok(None);
+        };
+        let state = Arc::new(Mutex::new(State {
+            status: "online",
+            user_id: config.lanyard_user_id.clone(),
+            ..State::default()
+        }));
+        Ok(Some(Self {
+            listener: Some(Listener::start(addr, Arc::clone(&state))?),
+            state,
+            crypto: crypto_for(config)?,
+            fit: config.fit.clone(),
+            default_app_id: config.discord_app_id,
+        }))
+    }
+
+    /// 实际监听的地址（配置的端口为0时由系统分配）
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.as_ref().map(|listener| listener.addr)
+    }
+
+    /// 设置登录Discord的用户，用于返回的 `discord_user` 和未配置用户ID时确定响应哪个用户
+    pub fn set_user(&mut self, user: Option<DiscordUser>) {
+        let mut state = lock(&self.state);
+        state.user = user;
+        state.notify();
+    }
+
+    /// 更新当前状态并推送给订阅者
+    fn set_activity(&self, activity: Option<Value>, status: &'static str) {
+        let mut state = lock(&self.state);
+        if state.activity == activity && state.status == status {
+            return;
+        }
+        state.activity = activity;
+        state.status = status;
+        state.notify();
+    }
+}
+
+impl PresenceSink for LanyardServer {
+    fn name(&self) -> &str {
+        "Lanyard API"
+    }
+
+    /// 本地推送没有外部限制，只合并极短时间内的连续更新
+    fn rate_limit(&self) -> RateLimit {
+        RateLimit {
+            updates: 10,
+            window: Duration::from_secs(1),
+        }
+    }
+
+    /// 没有活动窗口时（“离开”状态）显示为 `idle`
+    fn publish(&mut self, presence: Option<&Presence>) -> SinkResult {
+        let Some(presence) = presence else {
+            self.set_activity(None, "online");
+            return SinkResult::Sent;
+        };
+        match prepare_presence(presence, self.crypto.as_ref(), &self.fit) {
+            Ok(prepared) => {
+                let application_id = presence.application_id.unwrap_or(self.default_app_id);
+                let activity = lanyard_activity(&prepared, application_id);
+                let status = if presence.source.is_some() {
+                    "online"
+                } else {
+                    "idle"
+                };
+                self.set_activity(Some(activity), status);
+                SinkResult::Sent
+            },
+            Err(e) => SinkResult::Dropped(ServerError::Encrypt(e).into()),
+        }
+    }
+
+    /// 监听地址改变时先在新地址上启动，成功后再停止旧的监听
+    /// 地址改变时在新地址上监听（成功后才停止旧的监听），删除 `SERVER_ADDR` 时停止服务
+    fn reload(&mut self, config: &Config) -> Result<(), Error> {
+        let crypto = crypto_for(config)?;
+        match config.server_addr {
+            None => {
+                if let Some(old) = self.listener.take() {
+                    old.stop();
+                }
+            },
+            Some(addr) if self.listener.as_ref().is_none_or(|listener| listener.requested != addr) => {
+                let listener = Listener::start(addr, Arc::clone(&self.state))?;
+                if let Some(old) = self.listener.replace(listener) {
+                    old.stop();
+                }
+            },
+            Some(_) => {},
+        }
+        self.crypto = crypto;
+        self.fit = config.fit.clone();
+        self.default_app_id = config.discord_app_id;
+        let mut state = lock(&self.state);
+        state.user_id = config.lanyard_user_id.clone();
+        state.notify();
+        Ok(())
+    }
+
+    /// 推送离线状态后关闭所有连接
+    fn shutdown(mut self: Box<Self>, _timeout: Duration) -> Result<(), Error> {
+        self.set_activity(None, "offline");
+        if let Some(listener) = self.listener.take() {
+            listener.stop();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LanyardServer {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            listener.stop();
+        }
+    }
+}
+
+/// 把发送给Discord的活动转换为Lanyard中的格式：时间戳为毫秒，按钮只有文字
+fn lanyard_activity(presence: &Presence, application_id: u64) -> Value {
+    let mut activity = match activity_payload(presence) {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+    activity.remove("instance");
+    activity.insert(
+        "type".to_string(),
+        json!(presence.activity_type.map_or(0, |t| t.code())),
+    );
+    let name = presence.source.as_ref().map(|source| source.app.as_str());
+    activity.insert(
+        "name".to_string(),
+        json!(name.or(presence.details.as_deref()).unwrap_or_default()),
+    );
+    activity.insert(
+        "application_id".to_string(),
+        json!(application_id.to_string()),
+    );
+    activity.insert("created_at".to_string(), json!(unix_now() * 1000));
+    if let Some(Value::Object(timestamps)) = activity.get_mut("timestamps") {
+        for value in timestamps.values_mut() {
+            *value = json!(value.as_u64().unwrap_or(0) * 1000);
+        }
+    }
+    if !presence.buttons.is_empty() {
+        let labels = presence
+            .buttons
+            .iter()
+            .map(|button| json!(button.label))
+            .collect();
+        activity.insert("buttons".to_string(), Value::Array(labels));
+    }
+    Value::Object(activity)
+}
+
+/// 根据配置中的密钥环创建加密管理器
+fn crypto_for(config: &Config) -> Result<Option<KeyringCrypto>, ServerError> {
+    config.keyring().crypto().map_err(ServerError::CryptoInit)
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// 接受连接，每个连接在单独的线程中处理；已有 `MAX_CONNECTIONS` 个连接时直接返回503并关闭
+fn accept_loop(listener: TcpListener, state: Arc<Mutex<State>>, stop: Arc<AtomicBool>) {
+    let active = Arc::new(AtomicUsize::new(0));
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((mut stream, _)) if active.load(Ordering::SeqCst) >= MAX_CONNECTIONS => {
+                let body = error_body("too_many_connections", Msg::ServerTooManyConnections);
+                let _ = respond(&mut stream, "503 Service Unavailable", Some(body));
+            },
+            Ok((stream, _)) => {
+                active.fetch_add(1, Ordering::SeqCst);
+                let (state, stop, active) = (Arc::clone(&state), Arc::clone(&stop), Arc::clone(&active));
+                thread::spawn(move || {
+                    let _ = handle_connection(stream, &state, &stop);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// 处理一个连接：WebSocket升级请求进入推送循环，其他请求按HTTP接口响应后关闭
+fn handle_connection(
+    mut stream: TcpStream,
+    state: &Mutex<State>,
+    stop: &AtomicBool,
+) -> Result<(), ws::WsError> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let head = ws::read_head(&mut stream)?;
+    let mut request = head.split_whitespace();
+    let method = request.next().unwrap_or_default();
+    let target = request.next().unwrap_or("/");
+    let path = target
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+
+    let upgrade = ws::header(&head, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if let (true, "/socket", Some(key)) = (upgrade, path, ws::header(&head, "Sec-WebSocket-Key")) {
+        stream.write_all(ws::upgrade_response(key).as_bytes())?;
+        return serve_socket(WebSocket::server(stream), state, stop);
+    }
+
+    let (status, body) = match (method, path.strip_prefix("/v1/users/")) {
+        ("OPTIONS", _) => ("204 No Content", None),
+        ("GET", Some(id)) => {
+            let state = lock(state);
+            if state.monitors(id) {
+                (
+                    "200 OK",
+                    Some(json!({ "success": true, "data": state.presence_data(id) })),
+                )
+            } else {
+                (
+                    "404 Not Found",
+                    Some(error_body(
+                        "user_not_monitored",
+                        Msg::ServerUserNotMonitored,
+                    )),
+                )
+            }
+        },
+        _ => (
+            "404 Not Found",
+            Some(error_body("not_found", Msg::ServerRouteNotFound)),
+        ),
+    };
+    respond(&mut stream, status, body)?;
+    Ok(())
+}
+
+/// 发送HTTP响应（JSON内容，允许跨域），之后关闭连接
+fn respond(stream: &mut TcpStream, status: &str, body: Option<Value>) -> io::Result<()> {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, OPTIONS\r\n\
+         Access-Control-Allow-Headers: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+/// 与Lanyard相同格式的错误响应（错误信息固定为英文）
+fn error_body(code: &str, message: Msg) -> Value {
+    json!({ "success": false, "error": { "code": code, "message": message.text_in(Locale::En) } })
+}
+
+/// 订阅的用户
+enum Subscription {
+    /// `subscribe_to_id`：事件内容为该用户的状态
+    One(String),
+    /// `subscribe_to_ids` / `subscribe_to_all`：事件内容为用户ID到状态的映射，更新中带 `user_id`
+    Many(Option<Vec<String>>),
+}
+
+impl Subscription {
+    fn parse(data: &Value) -> Option<Self> {
+        if let Some(id) = data["subscribe_to_id"].as_str() {
+            return Some(Subscription::One(id.to_string()));
+        }
+        if let Some(ids) = data["subscribe_to_ids"].as_array() {
+            let ids = ids
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect();
+            return Some(Subscription::Many(Some(ids)));
+        }
+        data["subscribe_to_all"]
+            .as_bool()
+            .filter(|all| *all)
+            .map(|_| Subscription::Many(None))
+    }
+
+    /// 订阅中本服务响应的用户ID，与HTTP接口一样跳过不监控的用户
+    fn ids(&self, state: &State) -> Vec<String> {
+        let ids = match self {
+            Subscription::One(id) => vec![id.clone()],
+            Subscription::Many(Some(ids)) => ids.clone(),
+            Subscription::Many(None) => state
+                .user_id
+                .clone()
+                .or(state.user.as_ref().map(|user| user.id.clone()))
+                .into_iter()
+                .collect(),
+        };
+        ids.into_iter().filter(|id| state.monitors(id)).collect()
+    }
+
+    /// 与Lanyard相同：不监控的用户不出现在 `INIT_STATE` 中，`subscribe_to_id` 时为空对象
+    fn init_state(&self, state: &State) -> Value {
+        match self {
+            Subscription::One(_) => self
+                .ids(state)
+                .first()
+                .map_or_else(|| json!({}), |id| state.presence_data(id)),
+            Subscription::Many(_) => self
+                .ids(state)
+                .into_iter()
+                .map(|id| {
+                    let data = state.presence_data(&id);
+                    (id, data)
+                })
+                .collect::<Map<_, _>>()
+                .into(),
+        }
+    }
+
+    fn updates(&self, state: &State) -> Vec<Value> {
+        match self {
+            Subscription::One(_) => self
+                .ids(state)
+                .iter()
+                .map(|id| state.presence_data(id))
+                .collect(),
+            Subscription::Many(_) => self
+                .ids(state)
+                .into_iter()
+                .map(|id| {
+                    let mut data = state.presence_data(&id);
+                    data["user_id"] = json!(id);
+                    data
+                })
+                .collect(),
+        }
+    }
+}
+
+/// WebSocket连接：发送 `Hello`，等待 `Initialize` 后推送状态，直到连接关闭、心跳超时或服务停止
+fn serve_socket(
+    mut socket: WebSocket<TcpStream>,
+    state: &Mutex<State>,
+    stop: &AtomicBool,
+) -> Result<(), ws::WsError> {
+    let hello = json!({ "op": OP_HELLO, "d": { "heartbeat_interval": HEARTBEAT_INTERVAL.as_millis() as u64 } });
+    socket.send_text(&hello.to_string())?;
+
+    let mut subscription: Option<(Subscription, Receiver<()>)> = None;
+    let mut seq = 0u64;
+    let mut send_event = |socket: &mut WebSocket<TcpStream>, kind: &str, data: Value| {
+        seq += 1;
+        let event = json!({ "op": OP_EVENT, "seq": seq, "t": kind, "d": data });
+        socket.send_text(&event.to_string())
+    };
+    let mut last_heartbeat = Instant::now();
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return socket.close(CLOSE_GOING_AWAY);
+        }
+        if last_heartbeat.elapsed() > HEARTBEAT_INTERVAL * 2 {
+            return socket.close(CLOSE_HEARTBEAT_TIMEOUT);
+        }
+
+        match socket.read(Some(POLL_INTERVAL))? {
+            Some(Message::Text(text)) => {
+                let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                    return socket.close(CLOSE_INVALID_PAYLOAD);
+                };
+                match message["op"].as_u64() {
+                    Some(OP_HEARTBEAT) => last_heartbeat = Instant::now(),
+                    Some(OP_INITIALIZE) => {
+                        let Some(parsed) = Subscription::parse(&message["d"]) else {
+                            return socket.close(CLOSE_INVALID_PAYLOAD);
+                        };
+                        let (sender, receiver) = mpsc::channel();
+                        let data = {
+                            let mut state = lock(state);
+                            state.subscribers.push(sender);
+                            parsed.init_state(&state)
+                        };
+                        send_event(&mut socket, "INIT_STATE", data)?;
+                        subscription = Some((parsed, receiver));
+                    },
+                    _ => return socket.close(CLOSE_INVALID_PAYLOAD),
+                }
+            },
+            Some(Message::Close(_)) => return Ok(()),
+            Some(Message::Binary(_)) | None => {},
+        }
+
+        if let Some((parsed, receiver)) = &subscription
+            && receiver.try_iter().count() > 0
+        {
+            let updates = parsed.updates(&lock(state));
+            for data in updates {
+                send_event(&mut socket, "PRESENCE_UPDATE", data)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::crypto::CryptoManager;
+    use crate::lanyard_socket::{LanyardEvent, LanyardSocket};
+    use crate::presence::{Button, PresenceSource};
+
+    fn start(user_id: Option<&str>, key: Option<&str>) -> (LanyardServer, String) {
+        let mut config = Config::new(42, 5);
+        config.server_addr = Some("127.0.0.1:0".parse().unwrap());
+        config.lanyard_user_id = user_id.map(str::to_string);
+        config.encryption_key = key.map(str::to_string);
+        let server = LanyardServer::new(&config).unwrap().unwrap();
+        let addr = server.local_addr().unwrap();
+        (server, addr.to_string())
+    }
+
+    fn coding(title: &str) -> Presence {
+        let mut presence = Presence::new("Visual Studio Code", title, 1_700_000_000);
+        presence.source = Some(PresenceSource {
+            app: "Visual Studio Code".to_string(),
+            category: Category::Coding,
+            title: title.to_string(),
+        });
+        presence.buttons = vec![Button {
+            label: "Repo".to_string(),
+            url: "https://example.com".to_string(),
+        }];
+        presence
+    }
+
+    fn get(url: &str) -> (u16, Value) {
+        let response = reqwest::blocking::get(url).unwrap();
+        let status = response.status().as_u16();
+        assert_eq!(
+            response.headers()["access-control-allow-origin"]
+                .to_str()
+                .unwrap(),
+            "*"
+        );
+        (status, response.json().unwrap())
+    }
+
+    #[test]
+    fn test_rest_returns_lanyard_shape() {
+        let (mut server, addr) = start(Some("94490510688792576"), None);
+        assert!(matches!(
+            server.publish(Some(&coding("main.rs"))),
+            SinkResult::Sent
+        ));
+
+        let (status, body) = get(&format!("http://{}/v1/users/94490510688792576", addr));
+        assert_eq!(status, 200);
+        assert_eq!(body["success"], true);
+        let data = &body["data"];
+        assert_eq!(data["discord_user"]["id"], "94490510688792576");
+        assert_eq!(data["discord_status"], "online");
+        assert_eq!(data["active_on_discord_desktop"], true);
+        assert_eq!(data["kv"], json!({}));
+        let activity = &data["activities"][0];
+        assert_eq!(activity["application_id"], "42");
+        assert_eq!(activity["name"], "Visual Studio Code");
+        assert_eq!(activity["details"], "Visual Studio Code");
+        assert_eq!(activity["state"], "main.rs");
+        assert_eq!(activity["type"], 0);
+        assert_eq!(activity["timestamps"]["start"], 1_700_000_000_000u64);
+        assert_eq!(activity["buttons"], json!(["Repo"]));
+        assert!(activity.get("instance").is_none());
+
+        let (status, body) = get(&format!("http://{}/v1/users/1", addr));
+        assert_eq!(status, 404);
+        assert_eq!(body["error"]["code"], "user_not_monitored");
+        let (status, body) = get(&format!("http://{}/v2/other", addr));
+        assert_eq!(status, 404);
+        assert_eq!(body["error"]["code"], "not_found");
+    }
+
+    #[test]
+    fn test_rest_without_user_id_serves_any_user() {
+        let (mut server, addr) = start(None, None);
+        let (_, body) = get(&format!("http://{}/v1/users/7", addr));
+        assert_eq!(body["data"]["activities"], json!([]));
+
+        // 已知登录Discord的用户时只响应该用户，并带上用户名
+        server.set_user(Some(DiscordUser {
+            id: "7".to_string(),
+            username: "someone".to_string(),
+            global_name: Some("Someone".to_string()),
+            avatar: None,
+        }));
+        let (_, body) = get(&format!("http://{}/v1/users/7/", addr));
+        assert_eq!(body["data"]["discord_user"]["username"], "someone");
+        assert_eq!(body["data"]["discord_user"]["display_name"], "Someone");
+        assert_eq!(get(&format!("http://{}/v1/users/8", addr)).0, 404);
+    }
+
+    #[test]
+    fn test_state_is_encrypted_like_discord() {
+        let key = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+        let (mut server, addr) = start(Some("1"), Some(key));
+        server.publish(Some(&coding("secret.rs")));
+
+        let (_, body) = get(&format!("http://{}/v1/users/1", addr));
+        let state = body["data"]["activities"][0]["state"].as_str().unwrap();
+        assert_ne!(state, "secret.rs");
+        assert_eq!(
+            CryptoManager::from_hex(key)
+                .unwrap()
+                .decrypt_field(state, "state")
+                .unwrap(),
+            "secret.rs"
+        );
+    }
+
+    #[test]
+    fn test_socket_pushes_presence_updates() {
+        let (mut server, addr) = start(Some("1"), None);
+        let url = format!("ws://{}/socket", addr);
+        let mut socket = LanyardSocket::connect(&url, "1", Duration::from_secs(5)).unwrap();
+        assert_eq!(socket.heartbeat_interval(), HEARTBEAT_INTERVAL);
+
+        let timeout = Duration::from_secs(5);
+        let Some(LanyardEvent::InitState(data)) = socket.next_event(timeout).unwrap() else {
+            panic!("expected INIT_STATE");
+        };
+        assert_eq!(data["activities"], json!([]));
+
+        let presence = coding("lib.rs");
+        server.publish(Some(&presence));
+        let event = socket.next_event(timeout).unwrap().unwrap();
+        assert!(matches!(event, LanyardEvent::PresenceUpdate(_)));
+        let sent = activity_payload(&presence);
+        assert_eq!(event.find_activity(42, &sent).unwrap()["state"], "lib.rs");
+
+        // 离开状态显示为 idle，退出时推送离线状态
+        server.publish(Some(&Presence::away(1)));
+        let event = socket.next_event(timeout).unwrap().unwrap();
+        assert_eq!(event.presence()["discord_status"], "idle");
+        Box::new(server).shutdown(timeout).unwrap();
+        let event = socket.next_event(timeout).unwrap().unwrap();
+        assert_eq!(event.presence()["discord_status"], "offline");
+        assert!(socket.next_event(timeout).is_err());
+    }
+
+    fn read(socket: &mut WebSocket<Box<dyn ws::Transport>>) -> Value {
+        match socket.read(Some(Duration::from_secs(5))).unwrap() {
+            Some(Message::Text(text)) => serde_json::from_str::<Value>(&text).unwrap(),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_socket_subscribe_to_ids() {
+        let (mut server, addr) = start(Some("1"), None);
+        let mut socket =
+            ws::connect(&format!("ws://{}/socket", addr), Duration::from_secs(5)).unwrap();
+        assert_eq!(read(&mut socket)["op"], OP_HELLO);
+
+        socket
+            .send_text(r#"{"op":2,"d":{"subscribe_to_ids":["1","2"]}}"#)
+            .unwrap();
+        let init = read(&mut socket);
+        assert_eq!(init["t"], "INIT_STATE");
+        let users: Vec<&String> = init["d"].as_object().unwrap().keys().collect();
+        assert_eq!(users, ["1"]);
+
+        server.publish(Some(&coding("a.rs")));
+        let update = read(&mut socket);
+        assert_eq!(update["t"], "PRESENCE_UPDATE");
+        assert_eq!(update["d"]["user_id"], "1");
+        assert!(update["seq"].as_u64().unwrap() > init["seq"].as_u64().unwrap());
+
+        socket.send_text("not json").unwrap();
+        assert_eq!(
+            socket.read(Some(Duration::from_secs(5))).unwrap(),
+            Some(Message::Close(Some(CLOSE_INVALID_PAYLOAD)))
+        );
+    }
+
+    #[test]
+    fn test_socket_ignores_unmonitored_id() {
+        let (mut server, addr) = start(Some("1"), None);
+        let mut socket =
+            ws::connect(&format!("ws://{}/socket", addr), Duration::from_secs(5)).unwrap();
+        assert_eq!(read(&mut socket)["op"], OP_HELLO);
+
+        // 与HTTP接口返回404一致，不暴露其他用户ID下的状态
+        socket.send_text(r#"{"op":2,"d":{"subscribe_to_id":"2"}}"#).unwrap();
+        let init = read(&mut socket);
+        assert_eq!(init["t"], "INIT_STATE");
+        assert_eq!(init["d"], json!({}));
+
+        server.publish(Some(&coding("a.rs")));
+        assert_eq!(socket.read(Some(Duration::from_millis(300))).unwrap(), None);
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let (_server, addr) = start(Some("1"), None);
+        // 占满连接数：这些连接不发送请求，处理线程一直等待请求头
+        let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(&addr).unwrap())
+            .collect();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let status = loop {
+            let (status, body) = get(&format!("http://{}/v1/users/1", addr));
+            if status == 503 || Instant::now() > deadline {
+                assert_eq!(body["error"]["code"], "too_many_connections");
+                break status;
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        assert_eq!(status, 503);
+
+        drop(idle);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while get(&format!("http://{}/v1/users/1", addr)).0 != 200 {
+            assert!(Instant::now() < deadline, "connections were not released");
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_reload_moves_and_stops_listener() {
+        // 先找一个空闲端口，以固定端口启动
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut config = Config::new(42, 5);
+        config.server_addr = Some(SocketAddr::from(([127, 0, 0, 1], port)));
+        let mut server = LanyardServer::new(&config).unwrap().unwrap();
+        let first = server.local_addr().unwrap();
+        assert!(TcpStream::connect(first).is_ok());
+
+        // 相同的配置不会重新监听
+        server.reload(&config).unwrap();
+        assert_eq!(server.local_addr(), Some(first));
+
+        // 改为端口0时在新端口上监听，旧端口不再接受连接
+        config.server_addr = Some("127.0.0.1:0".parse().unwrap());
+        server.reload(&config).unwrap();
+        let second = server.local_addr().unwrap();
+        assert_ne!(second, first);
+        assert!(TcpStream::connect(second).is_ok());
+        assert!(TcpStream::connect(first).is_err());
+        server.reload(&config).unwrap();
+        assert_eq!(server.local_addr(), Some(second));
+
+        // 删除 SERVER_ADDR 后停止服务
+        config.server_addr = None;
+        server.reload(&config).unwrap();
+        assert_eq!(server.local_addr(), None);
+        assert!(TcpStream::connect(second).is_err());
+
+        // 重新设置后恢复
+        config.server_addr = Some("127.0.0.1:0".parse().unwrap());
+        server.reload(&config).unwrap();
+        assert!(TcpStream::connect(server.local_addr().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_bind_failure() {
+        let (server, addr) = start(None, None);
+        let mut config = Config::new(42, 5);
+        config.server_addr = Some(addr.parse().unwrap());
+        let err = LanyardServer::new(&config).err().unwrap();
+        assert!(matches!(err, ServerError::Bind(..)));
+        drop(server);
+    }
+}
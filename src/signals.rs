@@ -0,0 +1,138 @@
+//! 进程信号模块
+//!
+//! 在后台线程中监听系统信号，并转换为主循环可以处理的控制信号：
+//!
+//! * `SIGINT` / `SIGTERM`（Windows上为 Ctrl-C、Ctrl-Break 和关闭控制台）- 清除活动状态后退出
+//! * `SIGHUP` - 重新加载配置
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// 默认的退出等待时间（清除活动状态并关闭连接）
+pub const DEFAULT_SHUTDOWN_TIMEOUT_MS: u64 = 3_000;
+
+/// 主循环需要处理的控制信号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlSignal {
+    /// 清除活动状态并退出
+    Shutdown,
+    /// 重新加载配置
+    Reload,
+}
+
+/// 开始监听系统信号
+///
+/// 信号处理在一个单线程tokio运行时中完成，收到的信号通过通道发送给调用方；
+/// 接收端被丢弃后监听线程随之退出
+///
+/// # 错误
+/// 无法创建运行时或注册信号处理时返回错误
+pub fn listen() -> io::Result<Receiver<ControlSignal>> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let mut signals = runtime.block_on(async { Signals::register() })?;
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("signals".to_string())
+        .spawn(move || runtime.block_on(signals.forward(sender)))?;
+    Ok(receiver)
+}
+
+/// 等待 `timeout`，期间收到控制信号时提前返回
+pub fn wait(signals: Option<&Receiver<ControlSignal>>, timeout: Duration) -> Option<ControlSignal> {
+    match signals {
+        Some(receiver) => receiver.recv_timeout(timeout).ok(),
+        None => {
+            thread::sleep(timeout);
+            None
+        },
+    }
+}
+
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    hangup: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn register() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    async fn forward(&mut self, sender: Sender<ControlSignal>) {
+        loop {
+            let signal = tokio::select! {
+                _ = self.interrupt.recv() => ControlSignal::Shutdown,
+                _ = self.terminate.recv() => ControlSignal::Shutdown,
+                _ = self.hangup.recv() => ControlSignal::Reload,
+            };
+            if sender.send(signal).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+struct Signals {
+    ctrl_c: tokio::signal::windows::CtrlC,
+    ctrl_break: tokio::signal::windows::CtrlBreak,
+    ctrl_close: tokio::signal::windows::CtrlClose,
+}
+
+#[cfg(windows)]
+impl Signals {
+    fn register() -> io::Result<Self> {
+        use tokio::signal::windows::{ctrl_break, ctrl_c, ctrl_close};
+
+        Ok(Self { ctrl_c: ctrl_c()?, ctrl_break: ctrl_break()?, ctrl_close: ctrl_close()? })
+    }
+
+    async fn forward(&mut self, sender: Sender<ControlSignal>) {
+        loop {
+            tokio::select! {
+                _ = self.ctrl_c.recv() => {},
+                _ = self.ctrl_break.recv() => {},
+                _ = self.ctrl_close.recv() => {},
+            }
+            if sender.send(ControlSignal::Shutdown).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_without_listener_sleeps() {
+        let start = std::time::Instant::now();
+        assert_eq!(wait(None, Duration::from_millis(20)), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hangup_requests_reload() {
+        let signals = listen().unwrap();
+        // 只向本进程发送SIGHUP，tokio已接管其处理，不会终止测试进程
+        let status = std::process::Command::new("kill")
+            .args(["-HUP", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(wait(Some(&signals), Duration::from_secs(5)), Some(ControlSignal::Reload));
+    }
+}
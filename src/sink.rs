@@ -0,0 +1,311 @@
+//! 状态输出模块
+//!
+//! `PresenceSink` 是活动状态的一个输出（Discord RPC、文件、Webhook、Lanyard KV等）。
+//! 状态由 `PresenceBuilder` 统一生成，`Fanout` 再分发给所有输出：
+//! 每个输出有自己的 `UpdateScheduler`（合并与限流），发送失败时由输出自己决定重试还是放弃，
+//! 一个输出失败不会影响其他输出。
+
+use std::any::Any;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::presence::Presence;
+use crate::scheduler::{FlushOutcome, RateLimit, RetryPolicy, SchedulerStats, UpdateScheduler};
+
+/// 一次发送的结果
+#[derive(Debug)]
+pub enum SinkResult {
+    /// 已发送
+    Sent,
+    /// 与已发布的状态相同，没有发送
+    Skipped,
+    /// 发送失败，保留这次更新，按 `retry_policy` 退避后重试
+    Retry(Error),
+    /// 发送失败，放弃这次更新（重试也不会成功，或输出会在恢复后自行补发）
+    Dropped(Error),
+}
+
+/// 活动状态的输出
+///
+/// 输出按 `publish` 的结果决定失败后的处理方式；`rate_limit` 决定分发时的合并与限流参数，
+/// `retry_policy` 决定失败后最多重试几次以及重试前的等待时间
+pub trait PresenceSink: Any {
+    /// 输出名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 发送频率限制
+    fn rate_limit(&self) -> RateLimit {
+        RateLimit::default()
+    }
+
+    /// 发送失败后的重试策略
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// 处理后台事务（如断线重连），每轮主循环调用一次
+    fn poll(&mut self) {}
+
+    /// 发布活动状态，`None` 表示清除
+    fn publish(&mut self, presence: Option<&Presence>) -> SinkResult;
+
+    /// 应用重新加载的配置
+    fn reload(&mut self, _config: &Config) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// 清除活动状态并释放资源，最多等待 `timeout`
+    fn shutdown(self: Box<Self>, _timeout: Duration) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct Output {
+    sink: Box<dyn PresenceSink>,
+    scheduler: UpdateScheduler,
+}
+
+/// 把活动状态分发给多个输出
+#[derive(Default)]
+pub struct Fanout {
+    outputs: Vec<Output>,
+}
+
+impl Fanout {
+    /// 创建没有输出的分发器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加输出
+    pub fn add(&mut self, sink: impl PresenceSink) {
+        self.add_boxed(Box::new(sink));
+    }
+
+    /// 添加已装箱的输出
+    pub fn add_boxed(&mut self, sink: Box<dyn PresenceSink>) {
+        let mut scheduler = UpdateScheduler::new(sink.rate_limit());
+        scheduler.set_retry_policy(sink.retry_policy());
+        self.outputs.push(Output { sink, scheduler });
+    }
+
+    /// 输出数量
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// 是否没有任何输出
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// 所有输出的名称
+    pub fn names(&self) -> Vec<&str> {
+        self.outputs.iter().map(|output| output.sink.name()).collect()
+    }
+
+    /// 取得指定类型的输出
+    pub fn get<T: PresenceSink>(&self) -> Option<&T> {
+        self.outputs
+            .iter()
+            .find_map(|output| (output.sink.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// 取得指定类型的输出（可变）
+    pub fn get_mut<T: PresenceSink>(&mut self) -> Option<&mut T> {
+        self.outputs
+            .iter_mut()
+            .find_map(|output| (output.sink.as_mut() as &mut dyn Any).downcast_mut::<T>())
+    }
+
+    /// 向所有输出提交新的活动状态
+    pub fn submit(&mut self, presence: &Presence) {
+        for output in &mut self.outputs {
+            output.scheduler.submit(presence.clone());
+        }
+    }
+
+    /// 向所有输出提交清除活动状态的请求
+    pub fn submit_clear(&mut self) {
+        for output in &mut self.outputs {
+            output.scheduler.submit_clear();
+        }
+    }
+
+    /// 让每个输出处理后台事务
+    pub fn poll(&mut self) {
+        for output in &mut self.outputs {
+            output.sink.poll();
+        }
+    }
+
+    /// 发送各输出待发送的更新，返回没有空闲的输出的结果
+    pub fn flush(&mut self) -> Vec<(String, FlushOutcome)> {
+        self.outputs
+            .iter_mut()
+            .filter_map(|output| match output.scheduler.flush(output.sink.as_mut()) {
+                FlushOutcome::Idle => None,
+                outcome => Some((output.sink.name().to_string(), outcome)),
+            })
+            .collect()
+    }
+
+    /// 距离最早一个输出可以发送待发送更新还需等待的时间，没有待发送更新时返回 `None`
+    pub fn next_flush_in(&mut self, now: Instant) -> Option<Duration> {
+        self.outputs
+            .iter_mut()
+            .filter_map(|output| output.scheduler.next_flush_in(now))
+            .min()
+    }
+
+    /// 各输出的调度计数
+    pub fn stats(&self) -> Vec<(&str, SchedulerStats)> {
+        self.outputs
+            .iter()
+            .map(|output| (output.sink.name(), output.scheduler.stats()))
+            .collect()
+    }
+
+    /// 让每个输出应用重新加载的配置，返回失败的输出及错误
+    ///
+    /// 一个输出失败不影响其他输出；失败的输出继续使用原来的配置
+    pub fn reload(&mut self, config: &Config) -> Vec<(String, Error)> {
+        let mut failures = Vec::new();
+        for output in &mut self.outputs {
+            match output.sink.reload(config) {
+                Ok(()) => {
+                    output.scheduler.set_rate_limit(output.sink.rate_limit());
+                    output.scheduler.set_retry_policy(output.sink.retry_policy());
+                },
+                Err(e) => failures.push((output.sink.name().to_string(), e)),
+            }
+        }
+        failures
+    }
+
+    /// 依次关闭所有输出，总共最多等待 `timeout`，返回失败的输出及错误
+    ///
+    /// 尚未发送的更新不再发送
+    pub fn shutdown(self, timeout: Duration) -> Vec<(String, Error)> {
+        let deadline = Instant::now() + timeout;
+        self.outputs
+            .into_iter()
+            .filter_map(|output| {
+                let name = output.sink.name().to_string();
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                output.sink.shutdown(remaining).err().map(|e| (name, e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord::DiscordError;
+
+    /// 记录收到的状态，并按预设结果返回
+    struct Recorder {
+        name: &'static str,
+        published: Vec<Option<String>>,
+        results: Vec<SinkResult>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, results: Vec<SinkResult>) -> Self {
+            Self { name, published: Vec::new(), results }
+        }
+    }
+
+    impl PresenceSink for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn retry_policy(&self) -> RetryPolicy {
+            RetryPolicy { max_retries: 1, backoff: Duration::from_millis(10) }
+        }
+
+        fn publish(&mut self, presence: Option<&Presence>) -> SinkResult {
+            self.published.push(presence.and_then(|p| p.state.clone()));
+            if self.results.is_empty() {
+                SinkResult::Sent
+            } else {
+                self.results.remove(0)
+            }
+        }
+
+        fn shutdown(self: Box<Self>, _timeout: Duration) -> Result<(), Error> {
+            match self.name {
+                "broken" => Err(DiscordError::NotConnected.into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn presence(state: &str) -> Presence {
+        Presence::new("App", state, 0)
+    }
+
+    fn not_connected() -> Error {
+        DiscordError::NotConnected.into()
+    }
+
+    #[test]
+    fn test_fans_out_to_every_sink() {
+        let mut fanout = Fanout::new();
+        fanout.add(Recorder::new("a", Vec::new()));
+        fanout.add(Recorder::new("b", Vec::new()));
+        assert_eq!(fanout.names(), vec!["a", "b"]);
+
+        fanout.submit(&presence("hello"));
+        let outcomes = fanout.flush();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|(_, outcome)| matches!(outcome, FlushOutcome::Sent)));
+        fanout.submit_clear();
+        fanout.flush();
+
+        let recorder = fanout.get::<Recorder>().unwrap();
+        assert_eq!(recorder.published, vec![Some("hello".to_string()), None]);
+        assert!(fanout.flush().is_empty());
+    }
+
+    #[test]
+    fn test_each_sink_handles_its_own_failures() {
+        let mut fanout = Fanout::new();
+        fanout.add(Recorder::new("retry", vec![SinkResult::Retry(not_connected())]));
+        fanout.submit(&presence("a"));
+
+        let outcomes = fanout.flush();
+        assert!(matches!(outcomes[0], (ref name, FlushOutcome::Failed(_)) if name == "retry"));
+        // 保留的更新在退避结束后重试
+        assert!(matches!(fanout.flush()[0].1, FlushOutcome::Deferred(_)));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(matches!(fanout.flush()[0].1, FlushOutcome::Sent));
+        let recorder = fanout.get::<Recorder>().unwrap();
+        assert_eq!(recorder.published, vec![Some("a".to_string()), Some("a".to_string())]);
+
+        let mut fanout = Fanout::new();
+        fanout.add(Recorder::new("dropped", vec![SinkResult::Dropped(not_connected())]));
+        fanout.add(Recorder::new("healthy", Vec::new()));
+        fanout.submit(&presence("a"));
+        let outcomes = fanout.flush();
+        assert!(matches!(outcomes[0].1, FlushOutcome::Failed(_)));
+        assert!(matches!(outcomes[1].1, FlushOutcome::Sent));
+        // 放弃的更新不再重试
+        assert!(fanout.flush().is_empty());
+        assert_eq!(fanout.stats()[0].1.failed, 1);
+        assert_eq!(fanout.next_flush_in(Instant::now()), None);
+    }
+
+    #[test]
+    fn test_shutdown_reports_failed_sinks() {
+        let mut fanout = Fanout::new();
+        fanout.add(Recorder::new("ok", Vec::new()));
+        fanout.add(Recorder::new("broken", Vec::new()));
+        let failures = fanout.shutdown(Duration::from_secs(1));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "broken");
+    }
+}
@@ -0,0 +1,518 @@
+//! 模板模块
+//!
+//! `details`、`state`、图标悬停文字和按钮文字都可以用模板配置。模板语法：
+//!
+//! * `{app}` - 占位符，没有值时为空
+//! * `{details|truncate:20|upper}` - 过滤器，按顺序应用
+//! * `{if project}...{else}...{end}` - 条件，占位符有值时输出第一段；`{if !project}` 表示取反
+//! * `{{` / `}}` - 字面量 `{` / `}`
+//!
+//! 可用的占位符见 [`PLACEHOLDERS`]，过滤器有：
+//!
+//! * `upper` / `lower` - 转为大写/小写
+//! * `truncate:N` - 截断到N个字符（按字素计算），超出时以 `…` 结尾
+//! * `default:文字` - 值为空时使用的文字
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::buttons::extract_domain;
+use crate::i18n::Msg;
+use crate::presence::Presence;
+use crate::project::{detect_language, find_project, workspace_name};
+use crate::rules::{RuleSet, Scoped, WindowContext};
+use crate::tr;
+
+/// 支持的占位符
+pub const PLACEHOLDERS: &[&str] = &[
+    "app", "title", "details", "category", "workspace", "project", "language", "repo_url",
+    "domain", "elapsed",
+];
+
+/// 由窗口标题得出的占位符，启用加密时只能用于 `state`
+pub const TITLE_PLACEHOLDERS: &[&str] =
+    &["title", "details", "workspace", "project", "language", "repo_url", "domain"];
+
+/// 模板解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// 标签没有闭合（标签原文）
+    Unclosed(String),
+    /// `{else}` 或 `{end}` 没有对应的 `{if}`
+    UnexpectedTag(&'static str),
+    /// 未知的占位符
+    UnknownPlaceholder(String),
+    /// 未知的过滤器
+    UnknownFilter(String),
+    /// 过滤器缺少参数或参数无效
+    BadArgument(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TemplateError::Unclosed(tag) => tr!(Msg::TemplateUnclosed, tag),
+            TemplateError::UnexpectedTag(tag) => tr!(Msg::TemplateUnexpectedTag, tag),
+            TemplateError::UnknownPlaceholder(name) => {
+                tr!(Msg::TemplateUnknownPlaceholder, format!("{{{}}}", name))
+            },
+            TemplateError::UnknownFilter(name) => tr!(Msg::TemplateUnknownFilter, name),
+            TemplateError::BadArgument(spec) => tr!(Msg::TemplateBadArgument, spec),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// 过滤器
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Upper,
+    Lower,
+    Truncate(usize),
+    Default(String),
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Result<Self, TemplateError> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (spec.trim(), None),
+        };
+        match (name, arg) {
+            ("upper", None) => Ok(Filter::Upper),
+            ("lower", None) => Ok(Filter::Lower),
+            ("truncate", Some(n)) => n
+                .trim()
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(Filter::Truncate)
+                .ok_or_else(|| TemplateError::BadArgument(spec.to_string())),
+            ("default", Some(text)) => Ok(Filter::Default(text.to_string())),
+            ("upper" | "lower" | "truncate" | "default", _) => {
+                Err(TemplateError::BadArgument(spec.to_string()))
+            },
+            _ => Err(TemplateError::UnknownFilter(name.to_string())),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Truncate(max) => {
+                let graphemes: Vec<&str> = value.graphemes(true).collect();
+                if graphemes.len() <= *max {
+                    value
+                } else {
+                    let mut truncated = graphemes[..max - 1].concat();
+                    truncated.push('…');
+                    truncated
+                }
+            },
+            Filter::Default(text) if value.is_empty() => text.clone(),
+            Filter::Default(_) => value,
+        }
+    }
+}
+
+/// 模板节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Value { name: String, filters: Vec<Filter> },
+    If { name: String, negate: bool, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+/// 词法单元：普通文本或 `{...}` 标签
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+/// 块的结束方式
+enum BlockEnd {
+    Eof,
+    Else,
+    End,
+}
+
+/// 解析后的模板
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// 解析模板
+    ///
+    /// 标签未闭合、使用了未知的占位符或过滤器、`{else}` / `{end}` 不成对时返回错误
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::template::{Template, TemplateValues};
+    ///
+    /// let template = Template::parse("{if project}In {project}{else}{app|upper}{end}").unwrap();
+    /// let values = TemplateValues { app: "Code".to_string(), ..Default::default() };
+    /// assert_eq!(template.render(&values), "CODE");
+    /// assert!(Template::parse("{app|shout}").is_err());
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut tokens = tokenize(source)?.into_iter();
+        let nodes = match parse_block(&mut tokens)? {
+            (nodes, BlockEnd::Eof) => nodes,
+            (_, BlockEnd::Else) => return Err(TemplateError::UnexpectedTag("{else}")),
+            (_, BlockEnd::End) => return Err(TemplateError::UnexpectedTag("{end}")),
+        };
+        Ok(Self { source: source.to_string(), nodes })
+    }
+
+    /// 模板原文
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// 模板是否含有占位符或条件（不是固定文字）
+    pub fn is_dynamic(&self) -> bool {
+        self.nodes.iter().any(|node| !matches!(node, Node::Text(_)))
+    }
+
+    /// 模板中用到的占位符（包括条件中的）
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect_placeholders(&self.nodes, &mut names);
+        names
+    }
+
+    /// 用占位符的值渲染模板
+    pub fn render(&self, values: &TemplateValues) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, values, &mut output);
+        output
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => tag.push(c),
+                        None => return Err(TemplateError::Unclosed(format!("{{{}", tag))),
+                    }
+                }
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Tag(tag.trim().to_string()));
+            },
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+fn parse_block(
+    tokens: &mut impl Iterator<Item = Token>,
+) -> Result<(Vec<Node>, BlockEnd), TemplateError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            },
+            Token::Tag(tag) => tag,
+        };
+        match tag.as_str() {
+            "else" => return Ok((nodes, BlockEnd::Else)),
+            "end" => return Ok((nodes, BlockEnd::End)),
+            _ => {},
+        }
+        if let Some(condition) = tag.strip_prefix("if ") {
+            let condition = condition.trim();
+            let (negate, name) = match condition.strip_prefix('!') {
+                Some(name) => (true, name.trim()),
+                None => (false, condition),
+            };
+            check_placeholder(name)?;
+            let unclosed = || TemplateError::Unclosed(format!("{{{}}}", tag));
+            let (then, otherwise) = match parse_block(tokens)? {
+                (then, BlockEnd::End) => (then, Vec::new()),
+                (then, BlockEnd::Else) => match parse_block(tokens)? {
+                    (otherwise, BlockEnd::End) => (then, otherwise),
+                    (_, BlockEnd::Else) => {
+                        return Err(TemplateError::UnexpectedTag("{else}"));
+                    },
+                    (_, BlockEnd::Eof) => return Err(unclosed()),
+                },
+                (_, BlockEnd::Eof) => return Err(unclosed()),
+            };
+            nodes.push(Node::If { name: name.to_string(), negate, then, otherwise });
+        } else {
+            let mut parts = tag.split('|');
+            let name = parts.next().unwrap_or_default().trim();
+            check_placeholder(name)?;
+            let filters = parts.map(Filter::parse).collect::<Result<_, _>>()?;
+            nodes.push(Node::Value { name: name.to_string(), filters });
+        }
+    }
+    Ok((nodes, BlockEnd::Eof))
+}
+
+fn check_placeholder(name: &str) -> Result<(), TemplateError> {
+    if PLACEHOLDERS.contains(&name) {
+        Ok(())
+    } else {
+        Err(TemplateError::UnknownPlaceholder(name.to_string()))
+    }
+}
+
+fn render_nodes(nodes: &[Node], values: &TemplateValues, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value { name, filters } => {
+                let value = values.get(name).unwrap_or_default().to_string();
+                output.push_str(&filters.iter().fold(value, |value, filter| filter.apply(value)));
+            },
+            Node::If { name, negate, then, otherwise } => {
+                let branch = if values.get(name).is_some() != *negate { then } else { otherwise };
+                render_nodes(branch, values, output);
+            },
+        }
+    }
+}
+
+fn collect_placeholders<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {},
+            Node::Value { name, .. } => names.push(name),
+            Node::If { name, then, otherwise, .. } => {
+                names.push(name);
+                collect_placeholders(then, names);
+                collect_placeholders(otherwise, names);
+            },
+        }
+    }
+}
+
+/// 占位符的值
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateValues {
+    /// 应用名称
+    pub app: String,
+    /// 完整窗口标题
+    pub title: String,
+    /// 详细信息
+    pub details: String,
+    /// 类别名称（随界面语言）
+    pub category: String,
+    /// 编辑器工作区名称
+    pub workspace: Option<String>,
+    /// 在项目目录中找到的git项目名称
+    pub project: Option<String>,
+    /// 正在编辑的文件的编程语言
+    pub language: Option<String>,
+    /// git仓库的网页地址
+    pub repo_url: Option<String>,
+    /// 标题中出现的网站域名
+    pub domain: Option<String>,
+    /// 从活动开始时间到现在经过的时间，如 `1h 05m`
+    pub elapsed: Option<String>,
+}
+
+impl TemplateValues {
+    /// 根据窗口上下文计算占位符的值
+    ///
+    /// # 参数
+    /// * `context` - 当前窗口
+    /// * `project_dirs` - 查找git仓库的项目目录
+    /// * `elapsed` - 活动已经持续的时间
+    pub fn from_context(
+        context: &WindowContext,
+        project_dirs: &[PathBuf],
+        elapsed: Option<Duration>,
+    ) -> Self {
+        let workspace = workspace_name(&context.details);
+        let project = workspace.as_deref().and_then(|name| find_project(project_dirs, name));
+        Self {
+            app: context.app_name.clone(),
+            title: context.title.clone(),
+            details: context.details.clone(),
+            category: context.category.label().to_string(),
+            workspace,
+            language: detect_language(&context.details).map(str::to_string),
+            repo_url: project.as_ref().and_then(|project| project.remote_url.clone()),
+            project: project.map(|project| project.name),
+            domain: extract_domain(&context.title),
+            elapsed: elapsed.map(format_elapsed),
+        }
+    }
+
+    /// 获取占位符的值，空值视为没有值
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let value = match name {
+            "app" => Some(&self.app),
+            "title" => Some(&self.title),
+            "details" => Some(&self.details),
+            "category" => Some(&self.category),
+            "workspace" => self.workspace.as_ref(),
+            "project" => self.project.as_ref(),
+            "language" => self.language.as_ref(),
+            "repo_url" => self.repo_url.as_ref(),
+            "domain" => self.domain.as_ref(),
+            "elapsed" => self.elapsed.as_ref(),
+            _ => None,
+        };
+        value.map(String::as_str).filter(|value| !value.is_empty())
+    }
+}
+
+/// 将经过的时间格式化为 `45s`、`12m` 或 `1h 05m`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match (secs / 3600, secs / 60 % 60) {
+        (0, 0) => format!("{}s", secs),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// `details` 和 `state` 的模板配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateConfig {
+    /// `details` 模板（默认为应用名称）
+    pub details: Scoped<Template>,
+    /// `state` 模板（默认为完整窗口标题）
+    pub state: Scoped<Template>,
+}
+
+impl TemplateConfig {
+    /// 用生效的模板替换 `details` 和 `state`，渲染结果为空时清除该字段
+    pub fn apply(
+        &self,
+        presence: &mut Presence,
+        context: &WindowContext,
+        rules: &RuleSet,
+        values: &TemplateValues,
+    ) {
+        if let Some(template) = self.details.resolve(context, rules) {
+            presence.details = render_field(template, values);
+        }
+        if let Some(template) = self.state.resolve(context, rules) {
+            presence.state = render_field(template, values);
+        }
+    }
+}
+
+fn render_field(template: &Template, values: &TemplateValues) -> Option<String> {
+    let text = template.render(values);
+    (!text.trim().is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+    use crate::parser::WindowInfo;
+    use crate::rules::Scope;
+
+    fn values() -> TemplateValues {
+        TemplateValues {
+            app: "Visual Studio Code".to_string(),
+            details: "main.rs - my-app".to_string(),
+            workspace: Some("my-app".to_string()),
+            language: Some("Rust".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let render = |source: &str| Template::parse(source).unwrap().render(&values());
+        assert_eq!(render("{language|upper} in {workspace}"), "RUST in my-app");
+        assert_eq!(render("{app|truncate:8}"), "Visual …");
+        assert_eq!(render("{project|default:no project}"), "no project");
+        assert_eq!(render("{{literal}} {app|lower|truncate:6}"), "{literal} visua…");
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let render = |source: &str| Template::parse(source).unwrap().render(&values());
+        assert_eq!(render("{if language}Writing {language}{else}Editing{end}"), "Writing Rust");
+        assert_eq!(render("{if project}{project}{else}{if !domain}local{end}{end}"), "local");
+        assert_eq!(render("{if elapsed} for {elapsed}{end}"), "");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (source, error) in [
+            ("{app", TemplateError::Unclosed("{app".to_string())),
+            ("{unknown}", TemplateError::UnknownPlaceholder("unknown".to_string())),
+            ("{app|shout}", TemplateError::UnknownFilter("shout".to_string())),
+            ("{app|truncate:x}", TemplateError::BadArgument("truncate:x".to_string())),
+            ("{app|default}", TemplateError::BadArgument("default".to_string())),
+            ("{if app}open", TemplateError::Unclosed("{if app}".to_string())),
+            ("text{end}", TemplateError::UnexpectedTag("{end}")),
+            ("{if app}a{else}b{else}c{end}", TemplateError::UnexpectedTag("{else}")),
+        ] {
+            assert_eq!(Template::parse(source), Err(error), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(42)), "42s");
+        assert_eq!(format_elapsed(Duration::from_secs(12 * 60 + 5)), "12m");
+        assert_eq!(format_elapsed(Duration::from_secs(3600 + 5 * 60)), "1h 05m");
+    }
+
+    #[test]
+    fn test_template_config_apply() {
+        let mut config = TemplateConfig::default();
+        config.details.set(Scope::Global, Template::parse("{app}").unwrap());
+        config
+            .details
+            .set(Scope::Category(Category::Coding), Template::parse("{workspace|upper}").unwrap());
+        config.state.set(Scope::Global, Template::parse("{if domain}{domain}{end}").unwrap());
+
+        let title = "main.rs - my-app - Visual Studio Code";
+        let context = WindowContext::new(title, &WindowInfo::parse(title));
+        let values = TemplateValues::from_context(&context, &[], None);
+        let mut presence = Presence::new("Visual Studio Code", title, 0);
+        config.apply(&mut presence, &context, &RuleSet::default(), &values);
+        assert_eq!(presence.details.as_deref(), Some("MY-APP"));
+        assert_eq!(presence.state, None);
+    }
+}
@@ -0,0 +1,319 @@
+//! 时间戳模块
+//!
+//! 决定活动状态中的 `timestamps` 字段。通过 `TIMESTAMP_MODE` 配置，可带作用范围：
+//!
+//! * `session` - 从程序启动开始计时（默认）
+//! * `window` - 从切换到当前窗口（标题变化）开始计时
+//! * `category` - 从进入当前类别开始计时
+//! * `today` - 今天在当前应用上累计的时间
+//! * `countdown:HH:MM` - 倒计时到本地时间 `HH:MM`（已过则为次日）
+//! * `none` - 不显示时间
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+
+use crate::category::Category;
+use crate::i18n::Msg;
+use crate::rules::WindowContext;
+use crate::tr;
+
+/// 时间戳模式解析错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampModeError {
+    /// 未知的模式
+    UnknownMode(String),
+    /// 倒计时结束时间不是 `HH:MM`
+    BadEndTime(String),
+}
+
+impl fmt::Display for TimestampModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TimestampModeError::UnknownMode(value) => tr!(Msg::TimestampUnknownMode, value),
+            TimestampModeError::BadEndTime(time) => tr!(Msg::TimestampBadEndTime, time),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for TimestampModeError {}
+
+/// 时间戳模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampMode {
+    /// 从程序启动开始
+    #[default]
+    Session,
+    /// 从切换到当前窗口开始
+    Window,
+    /// 从进入当前类别开始
+    Category,
+    /// 今天在当前应用上累计的时间
+    Today,
+    /// 倒计时到本地时间
+    Countdown(NaiveTime),
+    /// 不显示时间
+    None,
+}
+
+impl TimestampMode {
+    /// 解析配置值
+    ///
+    /// # 示例
+    /// ```
+    /// use active_window_info_to_lanyard_lib::timestamps::TimestampMode;
+    ///
+    /// assert_eq!(TimestampMode::parse("Window"), Ok(TimestampMode::Window));
+    /// assert!(TimestampMode::parse("countdown:18:30").is_ok());
+    /// assert!(TimestampMode::parse("countdown:25:00").is_err());
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, TimestampModeError> {
+        let value = value.trim();
+        if let Some((name, time)) = value.split_once(':')
+            && name.trim().eq_ignore_ascii_case("countdown")
+        {
+            return NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map(TimestampMode::Countdown)
+                .map_err(|_| TimestampModeError::BadEndTime(time.trim().to_string()));
+        }
+        match value.to_ascii_lowercase().as_str() {
+            "session" => Ok(TimestampMode::Session),
+            "window" => Ok(TimestampMode::Window),
+            "category" => Ok(TimestampMode::Category),
+            "today" => Ok(TimestampMode::Today),
+            "none" => Ok(TimestampMode::None),
+            _ => Err(TimestampModeError::UnknownMode(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TimestampMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampMode::Session => write!(f, "session"),
+            TimestampMode::Window => write!(f, "window"),
+            TimestampMode::Category => write!(f, "category"),
+            TimestampMode::Today => write!(f, "today"),
+            TimestampMode::Countdown(end) => write!(f, "countdown:{}", end.format("%H:%M")),
+            TimestampMode::None => write!(f, "none"),
+        }
+    }
+}
+
+/// 开始和结束时间戳（Unix秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamps {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+/// 记录窗口、类别和应用的切换时间，并统计今天各应用的累计使用时间
+#[derive(Debug, Clone)]
+pub struct ActivityClock {
+    session_start: u64,
+    /// 当前窗口标题及切换到它的时间
+    window: Option<(String, u64)>,
+    /// 当前类别及进入它的时间
+    category: Option<(Category, u64)>,
+    /// 当前应用（小写）及切换到它的时间
+    app: Option<(String, u64)>,
+    /// 累计时间所属的日期
+    day: Option<NaiveDate>,
+    /// 今天各应用（小写）已结束的使用时长（秒），不含当前这一段
+    today: HashMap<String, u64>,
+}
+
+impl ActivityClock {
+    /// 创建计时器
+    ///
+    /// # 参数
+    /// * `session_start` - 程序启动时间（Unix秒）
+    pub fn new(session_start: u64) -> Self {
+        Self {
+            session_start,
+            window: None,
+            category: None,
+            app: None,
+            day: None,
+            today: HashMap::new(),
+        }
+    }
+
+    /// 记录当前窗口，窗口、类别或应用变化时重新计时
+    pub fn observe(&mut self, context: &WindowContext, now: u64) {
+        self.roll_over(now);
+        if self.window.as_ref().is_none_or(|(title, _)| *title != context.title) {
+            self.window = Some((context.title.clone(), now));
+        }
+        if self.category.is_none_or(|(category, _)| category != context.category) {
+            self.category = Some((context.category, now));
+        }
+        let app = context.app_name.to_lowercase();
+        if self.app.as_ref().is_none_or(|(current, _)| *current != app) {
+            self.finish_app(now);
+            self.app = Some((app, now));
+        }
+    }
+
+    /// 没有活动窗口（离开）时调用，停止所有计时
+    pub fn pause(&mut self, now: u64) {
+        self.roll_over(now);
+        self.finish_app(now);
+        self.window = None;
+        self.category = None;
+    }
+
+    /// 今天在某个应用上累计的使用时间（秒），包括正在进行的这一段
+    pub fn today_secs(&self, app_name: &str, now: u64) -> u64 {
+        let app = app_name.to_lowercase();
+        let finished = self.today.get(&app).copied().unwrap_or_default();
+        let current = match &self.app {
+            Some((current, since)) if *current == app => now.saturating_sub(*since),
+            _ => 0,
+        };
+        finished + current
+    }
+
+    /// 按模式计算时间戳
+    pub fn timestamps(&self, mode: TimestampMode, now: u64) -> Timestamps {
+        let start = |since: Option<u64>| Timestamps { start: since.or(Some(now)), end: None };
+        match mode {
+            TimestampMode::Session => start(Some(self.session_start)),
+            TimestampMode::Window => start(self.window.as_ref().map(|(_, since)| *since)),
+            TimestampMode::Category => start(self.category.map(|(_, since)| since)),
+            TimestampMode::Today => {
+                let today = self
+                    .app
+                    .as_ref()
+                    .map(|(app, _)| self.today_secs(app, now))
+                    .unwrap_or_default();
+                start(Some(now.saturating_sub(today)))
+            },
+            TimestampMode::Countdown(end) => Timestamps { start: None, end: next_local(end, now) },
+            TimestampMode::None => Timestamps::default(),
+        }
+    }
+
+    /// 将当前应用这一段的时长计入今天的累计时间
+    fn finish_app(&mut self, now: u64) {
+        if let Some((app, since)) = self.app.take() {
+            *self.today.entry(app).or_default() += now.saturating_sub(since);
+        }
+    }
+
+    /// 跨过本地午夜时清空累计时间，正在进行的一段从午夜重新计算
+    fn roll_over(&mut self, now: u64) {
+        let Some(today) = local_time(now).map(|time| time.date_naive()) else {
+            return;
+        };
+        if self.day.is_some_and(|day| day != today) {
+            self.today.clear();
+            let midnight = local_midnight(today).unwrap_or(now);
+            if let Some((_, since)) = &mut self.app {
+                *since = (*since).max(midnight);
+            }
+        }
+        self.day = Some(today);
+    }
+}
+
+fn local_time(timestamp: u64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(i64::try_from(timestamp).ok()?, 0).earliest()
+}
+
+fn local_midnight(day: NaiveDate) -> Option<u64> {
+    let midnight = Local.from_local_datetime(&day.and_hms_opt(0, 0, 0)?).earliest()?;
+    u64::try_from(midnight.timestamp()).ok()
+}
+
+/// 下一个本地时间 `time` 对应的Unix时间戳
+fn next_local(time: NaiveTime, now: u64) -> Option<u64> {
+    let today = local_time(now)?.date_naive();
+    [today, today.succ_opt()?]
+        .into_iter()
+        .filter_map(|day| Local.from_local_datetime(&day.and_time(time)).earliest())
+        .filter_map(|end| u64::try_from(end.timestamp()).ok())
+        .find(|end| *end > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::WindowInfo;
+
+    fn context(title: &str) -> WindowContext {
+        WindowContext::new(title, &WindowInfo::parse(title))
+    }
+
+    /// 本地时间今天中午，避免测试跨过午夜
+    fn noon() -> u64 {
+        let day = Local::now().date_naive();
+        local_midnight(day).unwrap() + 12 * 3600
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(TimestampMode::parse("today"), Ok(TimestampMode::Today));
+        assert_eq!(TimestampMode::parse(" none "), Ok(TimestampMode::None));
+        assert_eq!(
+            TimestampMode::parse("countdown:9:05").map(|mode| mode.to_string()),
+            Ok("countdown:09:05".to_string())
+        );
+        assert_eq!(
+            TimestampMode::parse("yesterday"),
+            Err(TimestampModeError::UnknownMode("yesterday".to_string()))
+        );
+        assert_eq!(
+            TimestampMode::parse("countdown:soon"),
+            Err(TimestampModeError::BadEndTime("soon".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_window_and_category_clocks() {
+        let t = noon();
+        let mut clock = ActivityClock::new(t - 1000);
+        clock.observe(&context("a.rs - Visual Studio Code"), t);
+        clock.observe(&context("b.rs - Visual Studio Code"), t + 60);
+        clock.observe(&context("Terminal"), t + 120);
+        clock.observe(&context("zsh - Windows Terminal"), t + 180);
+
+        let now = t + 200;
+        assert_eq!(clock.timestamps(TimestampMode::Session, now).start, Some(t - 1000));
+        assert_eq!(clock.timestamps(TimestampMode::Window, now).start, Some(t + 180));
+        // "Terminal" 和 "Windows Terminal" 属于同一类别
+        assert_eq!(clock.timestamps(TimestampMode::Category, now).start, Some(t + 120));
+        assert_eq!(clock.timestamps(TimestampMode::None, now), Timestamps::default());
+    }
+
+    #[test]
+    fn test_today_accumulates_per_app() {
+        let t = noon();
+        let mut clock = ActivityClock::new(t);
+        clock.observe(&context("a.rs - Visual Studio Code"), t);
+        clock.observe(&context("News - Firefox"), t + 100);
+        clock.observe(&context("b.rs - Visual Studio Code"), t + 130);
+        clock.pause(t + 150);
+        clock.observe(&context("c.rs - Visual Studio Code"), t + 500);
+
+        assert_eq!(clock.today_secs("Visual Studio Code", t + 510), 130);
+        assert_eq!(clock.today_secs("firefox", t + 510), 30);
+        assert_eq!(clock.timestamps(TimestampMode::Today, t + 510).start, Some(t + 510 - 130));
+    }
+
+    #[test]
+    fn test_countdown() {
+        let t = noon();
+        let clock = ActivityClock::new(t);
+        let later = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let earlier = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+
+        let end = clock.timestamps(TimestampMode::Countdown(later), t).end.unwrap();
+        assert!(end > t && end - t <= 7 * 3600);
+        // 已经过去的时间指向次日
+        let end = clock.timestamps(TimestampMode::Countdown(earlier), t).end.unwrap();
+        assert!(end - t > 12 * 3600);
+    }
+}
@@ -0,0 +1,425 @@
+//! 查看端解密模块
+//!
+//! 供通过Lanyard查看状态的一方使用：有了密钥，就能从单条密文、Lanyard返回的JSON，
+//! 或直接按用户ID从Lanyard（或兼容的自建接口）取得的状态中找出加密字段并解密。
+//!
+//! 加密字段不需要事先指定：所有能按密文格式解码的字符串都会以所在的键名作为字段名尝试解密，
+//! 只有通过认证的才会返回，因此普通文本不会被误认为密文。单条密文按调用方指定的字段名解密，
+//! 通常为 [`STATE_FIELD`](crate::discord::STATE_FIELD)，KV中的标题为 `activity_title`。
+//! 发布端轮换过密钥时，用 [`decrypt_with`] 传入多个密钥，按密文中的密钥ID选择。
+//! 密文中的加密时间经过认证，可以用 [`check_max_age`] 拒绝过旧（可能是重放的）密文。
+//!
+//! # 示例
+//! ```
+//! use active_window_info_to_lanyard_lib::discord::STATE_FIELD;
+//! use active_window_info_to_lanyard_lib::viewer::{self, DecryptInput};
+//! use active_window_info_to_lanyard_lib::CryptoManager;
+//!
+//! let key = CryptoManager::key_to_hex(&CryptoManager::generate_key());
+//! let crypto = CryptoManager::from_hex(&key).unwrap();
+//! let state = crypto.encrypt_field("main.rs", "state", Some(1_700_000_000)).unwrap();
+//! let payload = format!(r#"{{"data":{{"activities":[{{"details":"Code","state":"{}"}}]}}}}"#, state);
+//!
+//! let input = DecryptInput::parse(&payload).unwrap();
+//! let fields = viewer::decrypt(&key, &input, STATE_FIELD, "https://api.lanyard.rest").unwrap();
+//! assert_eq!(fields[0].path, "data.activities[0].state");
+//! assert_eq!(fields[0].plaintext, "main.rs");
+//! ```
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::crypto::{CryptoError, CryptoManager, Opened};
+use crate::i18n::Msg;
+use crate::keyring::{self, KeyringCrypto};
+use crate::lanyard::{self, LanyardError};
+use crate::tr;
+
+/// 查看端解密错误
+#[derive(Debug)]
+pub enum ViewerError {
+    /// 密钥无效
+    Key(CryptoError),
+    /// 单条密文解密失败
+    Decrypt(CryptoError),
+    /// 输入不是有效的JSON
+    Json(serde_json::Error),
+    /// 获取Lanyard状态失败
+    Lanyard(LanyardError),
+    /// 状态中没有能用该密钥解密的字段
+    NoEncryptedFields,
+    /// 字段的加密时间早于允许的最长时间，或没有加密时间
+    Stale {
+        /// 字段在JSON中的位置，单条密文为空
+        path: String,
+        /// 加密时间（Unix秒）
+        timestamp: Option<u64>,
+    },
+}
+
+impl fmt::Display for ViewerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ViewerError::Key(e) | ViewerError::Decrypt(e) => e.to_string(),
+            ViewerError::Json(e) => tr!(Msg::ViewerBadJson, e),
+            ViewerError::Lanyard(e) => e.to_string(),
+            ViewerError::NoEncryptedFields => tr!(Msg::ViewerNoEncryptedFields),
+            ViewerError::Stale { path, timestamp } => {
+                let path = if path.is_empty() { Msg::ViewerCiphertext.text() } else { path };
+                match timestamp {
+                    Some(timestamp) => tr!(Msg::ViewerStale, path, keyring::format_time(*timestamp)),
+                    None => tr!(Msg::ViewerNoTimestamp, path),
+                }
+            },
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ViewerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ViewerError::Key(e) | ViewerError::Decrypt(e) => Some(e),
+            ViewerError::Json(e) => Some(e),
+            ViewerError::Lanyard(e) => Some(e),
+            ViewerError::NoEncryptedFields | ViewerError::Stale { .. } => None,
+        }
+    }
+}
+
+impl From<LanyardError> for ViewerError {
+    fn from(e: LanyardError) -> Self {
+        ViewerError::Lanyard(e)
+    }
+}
+
+/// 要解密的内容
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecryptInput {
+    /// 单条密文（如复制下来的 `state`）
+    Ciphertext(String),
+    /// Lanyard返回的JSON（完整响应、`data` 对象或WebSocket事件均可）
+    Payload(Value),
+    /// Discord用户ID，从Lanyard接口获取状态
+    UserId(String),
+}
+
+impl DecryptInput {
+    /// 按内容判断输入类型：`{` / `[` 开头为JSON，纯数字为用户ID，其他为密文
+    pub fn parse(input: &str) -> Result<Self, ViewerError> {
+        let input = input.trim();
+        if input.starts_with('{') || input.starts_with('[') {
+            return serde_json::from_str(input)
+                .map(DecryptInput::Payload)
+                .map_err(ViewerError::Json);
+        }
+        // 用户ID解码后远短于最短的密文，不会与密文混淆
+        if !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(DecryptInput::UserId(input.to_string()));
+        }
+        Ok(DecryptInput::Ciphertext(input.to_string()))
+    }
+}
+
+/// 解密出的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedField {
+    /// 字段在JSON中的位置，如 `data.activities[0].state`；单条密文为空
+    pub path: String,
+    /// 明文
+    pub plaintext: String,
+    /// 加密时写入的时间戳（Unix秒），早期格式的密文没有
+    pub timestamp: Option<u64>,
+}
+
+impl DecryptedField {
+    /// 加密后经过的秒数（`now` 为Unix秒），没有时间戳时返回 `None`
+    pub fn age(&self, now: u64) -> Option<u64> {
+        self.timestamp.map(|timestamp| now.saturating_sub(timestamp))
+    }
+}
+
+/// 检查每个字段都在 `max_age` 秒内加密，防止以前的密文被当作当前状态重放
+///
+/// 没有时间戳的字段（早期格式）无法判断，同样视为过旧
+pub fn check_max_age(fields: &[DecryptedField], now: u64, max_age: u64) -> Result<(), ViewerError> {
+    match fields.iter().find(|field| field.age(now).is_none_or(|age| age > max_age)) {
+        Some(field) => Err(ViewerError::Stale { path: field.path.clone(), timestamp: field.timestamp }),
+        None => Ok(()),
+    }
+}
+
+/// 用十六进制密钥解密 `input`
+///
+/// # 参数
+/// * `key` - 64个字符的十六进制密钥（与发布端的 `ENCRYPTION_KEY` 相同）
+/// * `input` - 要解密的内容
+/// * `field` - 输入为单条密文时使用的字段名（其他输入按所在的键名解密）
+/// * `base_url` - 输入为用户ID时使用的Lanyard接口地址
+///
+/// # 错误
+/// 密钥无效、单条密文无法解密、获取状态失败，或状态中没有可解密的字段时返回错误
+pub fn decrypt(
+    key: &str,
+    input: &DecryptInput,
+    field: &str,
+    base_url: &str,
+) -> Result<Vec<DecryptedField>, ViewerError> {
+    let crypto = CryptoManager::from_hex(key).map_err(ViewerError::Key)?;
+    decrypt_with(&KeyringCrypto::new(vec![crypto]), input, field, base_url)
+}
+
+/// 用密钥环解密 `input`，参数和错误与 [`decrypt`] 相同
+pub fn decrypt_with(
+    crypto: &KeyringCrypto,
+    input: &DecryptInput,
+    field: &str,
+    base_url: &str,
+) -> Result<Vec<DecryptedField>, ViewerError> {
+    let fields = match input {
+        DecryptInput::Ciphertext(ciphertext) => {
+            let opened = crypto.open(ciphertext, field).map_err(ViewerError::Decrypt)?;
+            return Ok(vec![DecryptedField {
+                path: String::new(),
+                plaintext: opened.plaintext,
+                timestamp: opened.timestamp,
+            }]);
+        },
+        DecryptInput::Payload(payload) => decrypt_fields_with(crypto, payload),
+        DecryptInput::UserId(user_id) => {
+            decrypt_fields_with(crypto, &lanyard::fetch_presence(base_url, user_id)?)
+        },
+    };
+    if fields.is_empty() {
+        return Err(ViewerError::NoEncryptedFields);
+    }
+    Ok(fields)
+}
+
+/// 找出 `value` 中所有能用 `crypto` 解密的字符串
+pub fn decrypt_fields(crypto: &CryptoManager, value: &Value) -> Vec<DecryptedField> {
+    let mut fields = Vec::new();
+    collect(&|text, field| crypto.open(text, field).ok(), value, String::new(), "", &mut fields);
+    fields
+}
+
+/// 找出 `value` 中所有能用密钥环中的密钥解密的字符串
+pub fn decrypt_fields_with(crypto: &KeyringCrypto, value: &Value) -> Vec<DecryptedField> {
+    let mut fields = Vec::new();
+    collect(&|text, field| crypto.open(text, field).ok(), value, String::new(), "", &mut fields);
+    fields
+}
+
+/// `field` 为值所在的键名（数组元素沿用数组的键名），解密时作为字段名
+fn collect(
+    open: &dyn Fn(&str, &str) -> Option<Opened>,
+    value: &Value,
+    path: String,
+    field: &str,
+    fields: &mut Vec<DecryptedField>,
+) {
+    match value {
+        Value::String(text) => {
+            if let Some(opened) = open(text, field) {
+                fields.push(DecryptedField {
+                    path,
+                    plaintext: opened.plaintext,
+                    timestamp: opened.timestamp,
+                });
+            }
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect(open, item, format!("{}[{}]", path, i), field, fields);
+            }
+        },
+        Value::Object(map) => {
+            for (key, item) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                collect(open, item, path, key, fields);
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discord::STATE_FIELD;
+    use crate::config::Config;
+    use crate::keyring::{KeyEntry, Keyring};
+    use crate::presence::Presence;
+    use crate::server::LanyardServer;
+    use crate::sink::PresenceSink;
+    use serde_json::json;
+
+    const KEY: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    fn encrypt(text: &str, field: &str) -> String {
+        CryptoManager::from_hex(KEY).unwrap().encrypt_field(text, field, Some(1_700_000_000)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(
+            DecryptInput::parse(" 94490510688792576\n").unwrap(),
+            DecryptInput::UserId("94490510688792576".to_string())
+        );
+        assert_eq!(
+            DecryptInput::parse(r#"{"data":{}}"#).unwrap(),
+            DecryptInput::Payload(json!({ "data": {} }))
+        );
+        assert_eq!(
+            DecryptInput::parse("q83vEjRWeJA=").unwrap(),
+            DecryptInput::Ciphertext("q83vEjRWeJA=".to_string())
+        );
+        assert!(matches!(
+            DecryptInput::parse("{not json"),
+            Err(ViewerError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_ciphertext() {
+        let input = DecryptInput::Ciphertext(encrypt("main.rs - Code", "state"));
+        let fields = decrypt(KEY, &input, STATE_FIELD, "").unwrap();
+        assert_eq!(
+            fields,
+            vec![DecryptedField {
+                path: String::new(),
+                plaintext: "main.rs - Code".to_string(),
+                timestamp: Some(1_700_000_000),
+            }]
+        );
+
+        let other = CryptoManager::key_to_hex(&CryptoManager::generate_key());
+        assert!(matches!(
+            decrypt(&other, &input, STATE_FIELD, ""),
+            Err(ViewerError::Decrypt(_))
+        ));
+        assert!(matches!(
+            decrypt("abc", &input, STATE_FIELD, ""),
+            Err(ViewerError::Key(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_ciphertext_field() {
+        // KV中的标题以 `activity_title` 作为字段名加密
+        let input = DecryptInput::Ciphertext(encrypt("lib.rs - project", "activity_title"));
+        let fields = decrypt(KEY, &input, "activity_title", "").unwrap();
+        assert_eq!(fields[0].plaintext, "lib.rs - project");
+        assert_eq!(fields[0].timestamp, Some(1_700_000_000));
+        assert!(matches!(
+            decrypt(KEY, &input, STATE_FIELD, ""),
+            Err(ViewerError::Decrypt(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_payload_finds_encrypted_fields() {
+        let payload = json!({
+            "success": true,
+            "data": {
+                "activities": [
+                    { "name": "Spotify", "details": "Song", "state": "Artist" },
+                    { "details": "Visual Studio Code", "state": encrypt("lib.rs", "state") },
+                    // 密文被挪到其他字段时无法通过认证
+                    { "details": encrypt("lib.rs", "state") },
+                ],
+                "kv": { "activity_app": "Code", "activity_title": encrypt("lib.rs - project", "activity_title") },
+            }
+        });
+        let fields = decrypt(KEY, &DecryptInput::Payload(payload.clone()), STATE_FIELD, "").unwrap();
+        let found: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|f| (f.path.as_str(), f.plaintext.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("data.activities[1].state", "lib.rs"),
+                ("data.kv.activity_title", "lib.rs - project")
+            ]
+        );
+        let crypto = CryptoManager::from_hex(KEY).unwrap();
+        assert_eq!(decrypt_fields(&crypto, &payload), fields);
+
+        let other = CryptoManager::key_to_hex(&CryptoManager::generate_key());
+        assert!(matches!(
+            decrypt(&other, &DecryptInput::Payload(payload), STATE_FIELD, ""),
+            Err(ViewerError::NoEncryptedFields)
+        ));
+    }
+
+    #[test]
+    fn test_check_max_age() {
+        let field = |timestamp| DecryptedField {
+            path: "state".to_string(),
+            plaintext: "main.rs".to_string(),
+            timestamp,
+        };
+        let now = 1_700_000_000;
+        assert_eq!(field(Some(now - 30)).age(now), Some(30));
+        assert!(check_max_age(&[field(Some(now - 30))], now, 60).is_ok());
+        // 时间戳在未来时按0秒计算
+        assert!(check_max_age(&[field(Some(now + 5))], now, 60).is_ok());
+
+        let err = check_max_age(&[field(Some(now - 30)), field(Some(now - 61))], now, 60).unwrap_err();
+        assert!(matches!(err, ViewerError::Stale { timestamp: Some(t), .. } if t == now - 61));
+        assert!(err.to_string().contains("2023-11-14T22:12:19Z"));
+        assert!(matches!(
+            check_max_age(&[field(None)], now, 60),
+            Err(ViewerError::Stale { timestamp: None, .. })
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_with_rotated_keys() {
+        let mut keyring = Keyring::default();
+        keyring.insert(KeyEntry { expires: Some(1), ..KeyEntry::new(1, KEY) });
+        let new_key = CryptoManager::key_to_hex(&CryptoManager::generate_key());
+        keyring.insert(KeyEntry::new(2, new_key.as_str()));
+        let crypto = keyring.crypto().unwrap().unwrap();
+
+        let payload = json!({
+            "old": CryptoManager::from_hex(KEY).unwrap().with_key_id(1).encrypt_field("before", "old", None).unwrap(),
+            "new": crypto.encryptor(2).unwrap().encrypt_field("after", "new", None).unwrap(),
+        });
+        let fields = decrypt_with(&crypto, &DecryptInput::Payload(payload.clone()), STATE_FIELD, "").unwrap();
+        let found: Vec<&str> = fields.iter().map(|f| f.plaintext.as_str()).collect();
+        assert_eq!(found, ["after", "before"]);
+
+        // 只有旧密钥时只能解密旧内容
+        let fields = decrypt(KEY, &DecryptInput::Payload(payload), STATE_FIELD, "").unwrap();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].path, "old");
+    }
+
+    #[test]
+    fn test_decrypt_user_from_api() {
+        let mut config = Config::new(42, 5);
+        config.server_addr = Some("127.0.0.1:0".parse().unwrap());
+        config.lanyard_user_id = Some("1".to_string());
+        config.encryption_key = Some(KEY.to_string());
+        let mut server = LanyardServer::new(&config).unwrap().unwrap();
+        server.publish(Some(&Presence::new("Code", "secret.rs", 1)));
+        let base_url = format!("http://{}/", server.local_addr().unwrap());
+
+        let fields = decrypt(KEY, &DecryptInput::UserId("1".to_string()), STATE_FIELD, &base_url).unwrap();
+        assert_eq!(fields[0].path, "activities[0].state");
+        assert_eq!(fields[0].plaintext, "secret.rs");
+
+        let err = decrypt(KEY, &DecryptInput::UserId("2".to_string()), STATE_FIELD, &base_url).unwrap_err();
+        assert!(matches!(
+            err,
+            ViewerError::Lanyard(LanyardError::Status { status: 404, .. })
+        ));
+    }
+}
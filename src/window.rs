@@ -6,9 +6,13 @@
 // 忽略 objc 宏的 clippy 警告
 #![allow(unexpected_cfgs)]
 
+use std::fmt;
+#[cfg(target_os = "macos")]
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+use crate::i18n::Msg;
+
 #[cfg(windows)]
 use windows::{
     Win32::Foundation::HWND,
@@ -32,6 +36,50 @@ use core_graphics::window::{kCGWindowListOptionOnScreenOnly, kCGNullWindowID};
 #[cfg(target_os = "macos")]
 use std::sync::LazyLock;
 
+/// 获取活动窗口失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowError {
+    /// 当前平台不支持窗口监控
+    UnsupportedPlatform,
+    /// 没有活动窗口（如锁屏、桌面获得焦点）
+    NoActiveWindow,
+    /// 活动窗口没有标题
+    NoTitle,
+    /// 系统窗口列表查询失败
+    QueryFailed,
+    /// 等待窗口查询锁超时
+    LockTimeout,
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            WindowError::UnsupportedPlatform => Msg::WindowUnsupportedPlatform.text(),
+            WindowError::NoActiveWindow => Msg::WindowNoActive.text(),
+            WindowError::NoTitle => Msg::WindowNoTitle.text(),
+            WindowError::QueryFailed => Msg::WindowQueryFailed.text(),
+            WindowError::LockTimeout => Msg::WindowQueryLockTimeout.text(),
+        };
+        f.write_str(text)
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+/// 获取当前活动窗口的标题，无法获取时返回 `None`
+///
+/// 需要区分失败原因时使用 [`active_window_title`]
+pub fn get_active_window_title() -> Option<String> {
+    match active_window_title() {
+        Ok(title) => Some(title),
+        Err(e @ WindowError::UnsupportedPlatform) => {
+            eprintln!("{}", e);
+            None
+        },
+        Err(_) => None,
+    }
+}
+
 /// macOS 窗口查询互斥锁
 /// 用于防止快速切换窗口时的并发访问问题
 #[cfg(target_os = "macos")]
@@ -44,8 +92,8 @@ const WINDOW_QUERY_TIMEOUT_MS: u64 = 100;
 /// 获取当前活动窗口的标题 (Windows版本)
 ///
 /// # 返回值
-/// * `Some(String)` - 窗口标题
-/// * `None` - 无法获取窗口标题或没有活动窗口
+/// * `Ok(String)` - 窗口标题
+/// * `Err(WindowError)` - 没有活动窗口或无法获取窗口标题
 ///
 /// # 平台支持
 /// 仅支持Windows平台
@@ -57,7 +105,7 @@ const WINDOW_QUERY_TIMEOUT_MS: u64 = 100;
 /// # 长时间运行优化
 /// 每次重新获取前台窗口句柄，不缓存任何状态，确保能够检测到所有窗口变化
 #[cfg(windows)]
-pub fn get_active_window_title() -> Option<String> {
+pub fn active_window_title() -> Result<String, WindowError> {
     unsafe {
         // 每次都重新获取前台窗口句柄（不使用缓存）
         // 这确保了即使长时间未切换窗口，后续的切换也能被正确检测到
@@ -65,12 +113,12 @@ pub fn get_active_window_title() -> Option<String> {
         
         // 验证句柄是否有效
         if hwnd.0 == 0 {
-            return None;
+            return Err(WindowError::NoActiveWindow);
         }
         
         // 验证窗口是否仍然存在（长时间运行时可能窗口已关闭）
         if !IsWindow(hwnd).as_bool() {
-            return None;
+            return Err(WindowError::NoActiveWindow);
         }
 
         // 使用较大的缓冲区以支持长标题
@@ -81,7 +129,7 @@ pub fn get_active_window_title() -> Option<String> {
         // 检查是否成功获取文本
         if length == 0 {
             // 窗口可能没有标题，或者获取失败
-            return None;
+            return Err(WindowError::NoTitle);
         }
 
         // 转换为 Rust 字符串
@@ -89,9 +137,9 @@ pub fn get_active_window_title() -> Option<String> {
         
         // 确保标题不为空
         if title.is_empty() {
-            None
+            Err(WindowError::NoTitle)
         } else {
-            Some(title)
+            Ok(title)
         }
     }
 }
@@ -99,8 +147,8 @@ pub fn get_active_window_title() -> Option<String> {
 /// 获取当前活动窗口的标题 (macOS版本)
 ///
 /// # 返回值
-/// * `Some(String)` - 窗口标题
-/// * `None` - 无法获取窗口标题或没有活动窗口
+/// * `Ok(String)` - 窗口标题
+/// * `Err(WindowError)` - 没有活动窗口、查询失败或等待查询锁超时
 ///
 /// # 平台支持
 /// 仅支持macOS平台
@@ -114,7 +162,7 @@ pub fn get_active_window_title() -> Option<String> {
 /// 2. 使用 autorelease pool 确保内存及时释放，防止长时间运行后内存泄漏
 /// 3. 超时机制避免长时间阻塞
 #[cfg(target_os = "macos")]
-pub fn get_active_window_title() -> Option<String> {
+pub fn active_window_title() -> Result<String, WindowError> {
     // 尝试获取锁，使用超时机制避免死锁
     let start = Instant::now();
     let lock_result = loop {
@@ -125,7 +173,7 @@ pub fn get_active_window_title() -> Option<String> {
         // 检查是否超时
         if start.elapsed() > Duration::from_millis(WINDOW_QUERY_TIMEOUT_MS) {
             #[cfg(debug_assertions)]
-            eprintln!("[警告] 获取窗口查询锁超时，跳过此次查询");
+            eprintln!("{}", Msg::WindowLockTimeout.text());
             break None;
         }
         
@@ -133,8 +181,8 @@ pub fn get_active_window_title() -> Option<String> {
         std::thread::sleep(Duration::from_millis(1));
     };
     
-    // 如果无法获取锁，返回 None
-    let _guard = lock_result?;
+    // 如果无法获取锁，放弃此次查询
+    let _guard = lock_result.ok_or(WindowError::LockTimeout)?;
     
     unsafe {
         // 创建 autorelease pool，确保在函数结束时释放所有自动释放的对象
@@ -153,7 +201,7 @@ pub fn get_active_window_title() -> Option<String> {
 /// 内部函数：实际获取窗口标题的逻辑
 /// 这样设计可以确保 autorelease pool 在外层函数统一管理
 #[cfg(target_os = "macos")]
-unsafe fn get_window_title_internal() -> Option<String> {
+unsafe fn get_window_title_internal() -> Result<String, WindowError> {
     use core_graphics::window::CGWindowListCopyWindowInfo;
     use core_foundation::{
         array::CFArray,
@@ -172,7 +220,7 @@ unsafe fn get_window_title_internal() -> Option<String> {
     };
     
     if window_list.is_null() {
-        return None;
+        return Err(WindowError::QueryFailed);
     }
     
     let window_list_ref = window_list as *const _ as *const std::ffi::c_void;
@@ -232,20 +280,19 @@ unsafe fn get_window_title_internal() -> Option<String> {
         
         // 返回第一个找到的 layer=0 窗口
         if !window_title.is_empty() {
-            return Some(format!("{} - {}", window_title, app_name));
+            return Ok(format!("{} - {}", window_title, app_name));
         } else {
-            return Some(app_name);
+            return Ok(app_name);
         }
     }
     
-    None
+    Err(WindowError::NoActiveWindow)
 }
 
 /// 非支持平台的占位实现
 #[cfg(not(any(windows, target_os = "macos")))]
-pub fn get_active_window_title() -> Option<String> {
-    eprintln!("⚠️  窗口监控仅在 Windows 和 macOS 平台上受支持");
-    None
+pub fn active_window_title() -> Result<String, WindowError> {
+    Err(WindowError::UnsupportedPlatform)
 }
 
 /// 窗口监控器
@@ -305,7 +352,7 @@ impl WindowMonitor {
                 {
                     let remaining = self.min_query_interval_ms - elapsed.as_millis() as u64;
                     if remaining > 10 {
-                        println!("[调试] 查询间隔限制，剩余 {}ms", remaining);
+                        println!("{}", crate::tr!(Msg::WindowThrottled, remaining));
                     }
                 }
                 return None;
@@ -324,7 +371,7 @@ impl WindowMonitor {
                 if window_title != self.last_window_title {
                     // 窗口标题发生变化
                     #[cfg(debug_assertions)]
-                    println!("[调试] 检测到窗口变化: {} -> {}", self.last_window_title, window_title);
+                    println!("{}", crate::tr!(Msg::WindowChangeDetected, self.last_window_title, window_title));
                     
                     // 记录上一次标题，失败时可回滚
                     self.previous_window_title = self.last_window_title.clone();
@@ -339,7 +386,7 @@ impl WindowMonitor {
                 // 无法获取窗口标题（可能没有活动窗口或获取失败）
                 #[cfg(debug_assertions)]
                 if !self.last_window_title.is_empty() {
-                    println!("[调试] 无法获取窗口标题，之前的窗口: {}", self.last_window_title);
+                    println!("{}", crate::tr!(Msg::WindowTitleUnavailable, self.last_window_title));
                 }
                 
                 if !self.last_window_title.is_empty() {
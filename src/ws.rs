@@ -0,0 +1,500 @@
+//! WebSocket协议模块
+//!
+//! 实现RFC 6455中用到的部分：客户端和服务端握手、文本/二进制消息、分片、ping/pong和关闭。
+//! 客户端支持 `ws://` 和 `wss://`（通过系统TLS库）。读取时可以指定超时，
+//! 超时不会丢失已读到一半的数据帧，便于在等待消息的同时定时发送心跳。
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::fmt;
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use reqwest::Url;
+use sha1::{Digest, Sha1};
+
+use crate::i18n::Msg;
+use crate::tr;
+
+/// 握手时用于计算 `Sec-WebSocket-Accept` 的固定GUID
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 单条消息的最大长度
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// 握手请求/响应头的最大长度
+const MAX_HEADER_LEN: usize = 16 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// WebSocket错误
+#[derive(Debug)]
+pub enum WsError {
+    /// 地址无效或不是 `ws://` / `wss://`
+    Url(String),
+    /// 读写连接失败
+    Io(io::Error),
+    /// TLS握手失败
+    Tls(String),
+    /// WebSocket握手失败
+    Handshake(String),
+    /// 收到不符合协议的数据
+    Protocol(String),
+    /// 连接已关闭（带对方给出的关闭码）
+    Closed(Option<u16>),
+}
+
+impl fmt::Display for WsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            WsError::Url(url) => tr!(Msg::WsBadUrl, url),
+            WsError::Io(e) => tr!(Msg::WsIo, e),
+            WsError::Tls(message) => tr!(Msg::WsTls, message),
+            WsError::Handshake(message) => tr!(Msg::WsHandshakeFailed, message),
+            WsError::Protocol(message) => tr!(Msg::WsProtocol, message),
+            WsError::Closed(Some(code)) => tr!(Msg::WsClosedWithCode, code),
+            WsError::Closed(None) => tr!(Msg::WsClosed),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for WsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WsError {
+    fn from(e: io::Error) -> Self {
+        WsError::Io(e)
+    }
+}
+
+/// 收到的消息（ping/pong由连接自动处理）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// 文本消息
+    Text(String),
+    /// 二进制消息
+    Binary(Vec<u8>),
+    /// 对方关闭了连接
+    Close(Option<u16>),
+}
+
+/// 可以设置读取超时的双向字节流
+pub trait Transport: Read + Write + Send {
+    /// 设置读取超时，`None` 表示一直等待
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl Transport for native_tls::TlsStream<TcpStream> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.get_ref().set_read_timeout(timeout)
+    }
+}
+
+impl Transport for Box<dyn Transport> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.as_ref().set_read_timeout(timeout)
+    }
+}
+
+/// WebSocket连接
+pub struct WebSocket<S: Transport> {
+    stream: S,
+    /// 客户端发送的帧需要掩码
+    client: bool,
+    /// 已读取但尚未组成完整帧的数据
+    buf: Vec<u8>,
+    /// 正在接收的分片消息（操作码和已收到的数据）
+    fragments: Option<(u8, Vec<u8>)>,
+    /// 已发送关闭帧
+    close_sent: bool,
+}
+
+/// 连接到 `ws://` 或 `wss://` 地址并完成握手
+///
+/// `timeout` 同时用于建立连接和握手
+pub fn connect(url: &str, timeout: Duration) -> Result<WebSocket<Box<dyn Transport>>, WsError> {
+    let parsed = Url::parse(url).map_err(|_| WsError::Url(url.to_string()))?;
+    let secure = match parsed.scheme() {
+        "ws" => false,
+        "wss" => true,
+        _ => return Err(WsError::Url(url.to_string())),
+    };
+    let host = parsed.host_str().ok_or_else(|| WsError::Url(url.to_string()))?;
+    let port = parsed.port_or_known_default().ok_or_else(|| WsError::Url(url.to_string()))?;
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, host.to_string());
+    let mut tcp = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                tcp = Some(stream);
+                break;
+            },
+            Err(e) => last_error = e,
+        }
+    }
+    let tcp = tcp.ok_or(last_error)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+
+    let stream: Box<dyn Transport> = if secure {
+        let connector = native_tls::TlsConnector::new().map_err(|e| WsError::Tls(e.to_string()))?;
+        Box::new(connector.connect(host, tcp).map_err(|e| WsError::Tls(e.to_string()))?)
+    } else {
+        Box::new(tcp)
+    };
+
+    let host_header = match parsed.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let mut resource = parsed.path().to_string();
+    if let Some(query) = parsed.query() {
+        resource.push('?');
+        resource.push_str(query);
+    }
+    WebSocket::handshake(stream, &host_header, &resource)
+}
+
+impl<S: Transport> WebSocket<S> {
+    /// 在已建立的连接上完成客户端握手
+    pub fn handshake(mut stream: S, host: &str, resource: &str) -> Result<Self, WsError> {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let key = general_purpose::STANDARD.encode(nonce);
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            resource, host, key
+        );
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+
+        let head = read_head(&mut stream)?;
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(WsError::Handshake(status.to_string()));
+        }
+        if header(&head, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+            return Err(WsError::Handshake(tr!(Msg::WsBadAccept)));
+        }
+        Ok(Self::new(stream, true))
+    }
+
+    /// 读取客户端的升级请求并完成服务端握手，返回连接和请求的路径
+    pub fn accept(mut stream: S) -> Result<(Self, String), WsError> {
+        let head = read_head(&mut stream)?;
+        let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+        let key = header(&head, "Sec-WebSocket-Key")
+            .filter(|_| {
+                header(&head, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+            })
+            .ok_or_else(|| WsError::Handshake(tr!(Msg::WsNotUpgrade)))?;
+        stream.write_all(upgrade_response(key).as_bytes())?;
+        stream.flush()?;
+        Ok((Self::new(stream, false), path))
+    }
+
+    /// 用已完成服务端握手的连接创建WebSocket
+    pub fn server(stream: S) -> Self {
+        Self::new(stream, false)
+    }
+
+    fn new(stream: S, client: bool) -> Self {
+        Self { stream, client, buf: Vec::new(), fragments: None, close_sent: false }
+    }
+
+    /// 底层连接
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// 发送文本消息
+    pub fn send_text(&mut self, text: &str) -> Result<(), WsError> {
+        self.send_frame(OP_TEXT, text.as_bytes())
+    }
+
+    /// 发送关闭帧
+    pub fn close(&mut self, code: u16) -> Result<(), WsError> {
+        if self.close_sent {
+            return Ok(());
+        }
+        self.close_sent = true;
+        self.send_frame(OP_CLOSE, &code.to_be_bytes())
+    }
+
+    /// 读取下一条消息，`timeout` 内没有收到完整消息时返回 `None`
+    ///
+    /// 收到ping时自动回复pong；收到关闭帧时回复关闭帧并返回 `Message::Close`
+    pub fn read(&mut self, timeout: Option<Duration>) -> Result<Option<Message>, WsError> {
+        self.stream.set_read_timeout(timeout.map(|t| t.max(Duration::from_millis(1))))?;
+        loop {
+            while let Some((fin, opcode, payload)) = self.take_frame()? {
+                if let Some(message) = self.handle_frame(fin, opcode, payload)? {
+                    return Ok(Some(message));
+                }
+            }
+
+            let mut chunk = [0u8; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(WsError::Closed(None)),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if is_timeout(&e) => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn handle_frame(
+        &mut self,
+        fin: bool,
+        opcode: u8,
+        payload: Vec<u8>,
+    ) -> Result<Option<Message>, WsError> {
+        match opcode {
+            OP_PING => {
+                self.send_frame(OP_PONG, &payload)?;
+                Ok(None)
+            },
+            OP_PONG => Ok(None),
+            OP_CLOSE => {
+                let code = payload.get(..2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+                self.close(code.unwrap_or(1000))?;
+                Ok(Some(Message::Close(code)))
+            },
+            OP_TEXT | OP_BINARY if self.fragments.is_none() => {
+                if fin {
+                    message(opcode, payload).map(Some)
+                } else {
+                    self.fragments = Some((opcode, payload));
+                    Ok(None)
+                }
+            },
+            OP_CONTINUATION if self.fragments.is_some() => {
+                let (first, mut data) = self.fragments.take().unwrap_or_default();
+                data.extend_from_slice(&payload);
+                if data.len() > MAX_MESSAGE_LEN {
+                    return Err(WsError::Protocol(tr!(Msg::WsMessageTooLarge, data.len())));
+                }
+                if fin {
+                    message(first, data).map(Some)
+                } else {
+                    self.fragments = Some((first, data));
+                    Ok(None)
+                }
+            },
+            _ => Err(WsError::Protocol(tr!(Msg::WsUnexpectedOpcode, opcode))),
+        }
+    }
+
+    /// 从缓冲区取出一个完整的帧，数据不足时返回 `None`
+    fn take_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, WsError> {
+        let buf = &self.buf;
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let fin = buf[0] & 0x80 != 0;
+        let opcode = buf[0] & 0x0F;
+        let masked = buf[1] & 0x80 != 0;
+        let (len, mut offset) = match buf[1] & 0x7F {
+            126 if buf.len() >= 4 => (usize::from(u16::from_be_bytes([buf[2], buf[3]])), 4),
+            127 if buf.len() >= 10 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&buf[2..10]);
+                (usize::try_from(u64::from_be_bytes(bytes)).unwrap_or(usize::MAX), 10)
+            },
+            126 | 127 => return Ok(None),
+            len => (usize::from(len), 2),
+        };
+        if len > MAX_MESSAGE_LEN {
+            return Err(WsError::Protocol(tr!(Msg::WsMessageTooLarge, len)));
+        }
+        let mask = if masked {
+            if buf.len() < offset + 4 {
+                return Ok(None);
+            }
+            let mask = [buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]];
+            offset += 4;
+            Some(mask)
+        } else {
+            None
+        };
+        if buf.len() < offset + len {
+            return Ok(None);
+        }
+
+        let mut payload: Vec<u8> = self.buf.drain(..offset + len).skip(offset).collect();
+        if let Some(mask) = mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        Ok(Some((fin, opcode, payload)))
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), WsError> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        let mask_bit = if self.client { 0x80 } else { 0 };
+        match payload.len() {
+            len @ 0..=125 => frame.push(mask_bit | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            },
+            len => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            },
+        }
+        if self.client {
+            let mut mask = [0u8; 4];
+            rand::thread_rng().fill_bytes(&mut mask);
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        } else {
+            frame.extend_from_slice(payload);
+        }
+        self.stream.write_all(&frame)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+/// 服务端握手响应
+pub fn upgrade_response(key: &str) -> String {
+    format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )
+}
+
+/// 根据客户端的 `Sec-WebSocket-Key` 计算 `Sec-WebSocket-Accept`
+pub fn accept_key(key: &str) -> String {
+    general_purpose::STANDARD.encode(Sha1::digest(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+/// 在HTTP请求/响应头中查找字段（不区分大小写）
+pub fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// 逐字节读取HTTP头直到空行，不会多读后面的数据
+pub fn read_head(stream: &mut impl Read) -> Result<String, WsError> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > MAX_HEADER_LEN {
+            return Err(WsError::Handshake(tr!(Msg::WsHeaderTooLarge)));
+        }
+        match stream.read(&mut byte) {
+            Ok(0) => return Err(WsError::Closed(None)),
+            Ok(_) => head.push(byte[0]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e.into()),
+        }
+    }
+    String::from_utf8(head).map_err(|e| WsError::Handshake(e.to_string()))
+}
+
+fn message(opcode: u8, payload: Vec<u8>) -> Result<Message, WsError> {
+    if opcode == OP_TEXT {
+        String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|e| WsError::Protocol(e.to_string()))
+    } else {
+        Ok(Message::Binary(payload))
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_accept_key() {
+        // RFC 6455 第1.3节的示例
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_client_and_server_exchange_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/socket?v=1", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (mut ws, path) = WebSocket::accept(stream).unwrap();
+            assert_eq!(path, "/socket?v=1");
+            let Some(Message::Text(text)) = ws.read(None).unwrap() else {
+                panic!("expected a text message");
+            };
+            // 原样返回，再发送一条超过125字节的消息和一个ping
+            ws.send_text(&text).unwrap();
+            ws.send_text(&"x".repeat(300)).unwrap();
+            ws.send_frame(OP_PING, b"hi").unwrap();
+            ws.read(Some(Duration::from_secs(5))).unwrap()
+        });
+
+        let mut ws = connect(&url, Duration::from_secs(5)).unwrap();
+        ws.send_text("hello").unwrap();
+        assert_eq!(ws.read(None).unwrap(), Some(Message::Text("hello".to_string())));
+        assert_eq!(ws.read(None).unwrap(), Some(Message::Text("x".repeat(300))));
+        // ping被自动回复，之后没有消息时超时返回 None
+        assert_eq!(ws.read(Some(Duration::from_millis(50))).unwrap(), None);
+        ws.close(1000).unwrap();
+        assert_eq!(server.join().unwrap(), Some(Message::Close(Some(1000))));
+    }
+
+    #[test]
+    fn test_fragmented_message_is_reassembled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (mut ws, _) = WebSocket::accept(stream).unwrap();
+            // 第一个分片不带FIN
+            ws.stream.write_all(&[OP_TEXT, 3, b'a', b'b', b'c']).unwrap();
+            ws.stream.write_all(&[0x80 | OP_CONTINUATION, 2, b'd', b'e']).unwrap();
+        });
+        let mut ws = connect(&url, Duration::from_secs(5)).unwrap();
+        assert_eq!(ws.read(None).unwrap(), Some(Message::Text("abcde".to_string())));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_bad_urls() {
+        assert!(matches!(connect("http://localhost/", Duration::from_secs(1)), Err(WsError::Url(_))));
+        assert!(matches!(connect("not a url", Duration::from_secs(1)), Err(WsError::Url(_))));
+    }
+}